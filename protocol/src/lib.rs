@@ -0,0 +1,154 @@
+//! Wire types shared by the agentexport CLI and the share server (worker):
+//! the HTTP API and the share payload the viewer renders.
+//!
+//! Both sides build requests, responses, and payloads from these definitions,
+//! so field names and headers can't drift apart. With the `openapi` feature,
+//! [`openapi()`] describes the same API as an OpenAPI 3.1 document.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "openapi")]
+mod openapi;
+mod payload;
+
+#[cfg(feature = "openapi")]
+pub use openapi::openapi;
+pub use payload::{
+    BundlePart, IV_LEN, KEY_LEN, ModelCost, PASSPHRASE_ITERATIONS, PASSPHRASE_PREFIX, Publisher,
+    RenderedMessage, SeriesLink, SessionStatus, SharePayload, TaskItem, TaskProgress, Timeline,
+    ToolStat, ViewerDefaults,
+};
+
+/// Largest body accepted by `POST /upload`
+pub const MAX_BLOB_SIZE: usize = 10 * 1024 * 1024;
+
+/// Secret chosen by the uploader; required to delete the blob later
+pub const DELETE_TOKEN_HEADER: &str = "X-Delete-Token";
+
+/// Length of a delete token (hex-encoded 32 bytes)
+pub const DELETE_TOKEN_LEN: usize = 64;
+
+/// Requested retention in days (30, 60, 90, 180, 365, or 0 for forever)
+pub const TTL_DAYS_HEADER: &str = "X-TTL-Days";
+
+/// Id of an earlier share this upload continues
+pub const RELATES_TO_HEADER: &str = "X-Relates-To";
+
+/// Set to [`ENCRYPTION_NONE`] for plaintext org-mode uploads
+pub const ENCRYPTION_HEADER: &str = "X-Encryption";
+
+/// [`ENCRYPTION_HEADER`] value marking an unencrypted JSON payload
+pub const ENCRYPTION_NONE: &str = "none";
+
+/// Index the appended chunk should get: the number of chunks the uploader
+/// believes are stored. A mismatch means another writer got there first.
+pub const CHUNK_INDEX_HEADER: &str = "X-Chunk-Index";
+
+/// Number of chunks appended to a blob, sent with `GET /blob/{id}`
+pub const CHUNK_COUNT_HEADER: &str = "X-Chunk-Count";
+
+/// Most chunks a blob takes before it has to be replaced whole
+pub const MAX_CHUNKS: u32 = 50;
+
+/// Upload endpoint
+pub const UPLOAD_PATH: &str = "/upload";
+
+/// Blob endpoint for fetching, checking, replacing, and deleting a share
+pub fn blob_path(id: &str) -> String {
+    format!("/blob/{id}")
+}
+
+/// What the server stores for a share: size and expiry, without the content
+pub fn blob_meta_path(id: &str) -> String {
+    format!("/blob/{id}/meta")
+}
+
+/// Chunks appended to a share; `POST` appends one. Each chunk is encoded like
+/// the blob and holds a [`SharePayload`] whose messages follow the ones before it.
+pub fn blob_chunks_path(id: &str) -> String {
+    format!("/blob/{id}/chunks")
+}
+
+/// One appended chunk, numbered from 0 in the order they were appended
+pub fn blob_chunk_path(id: &str, index: u32) -> String {
+    format!("/blob/{id}/chunks/{index}")
+}
+
+/// Retention of a share; `POST` with a [`TtlRequest`] changes it in place
+pub fn blob_ttl_path(id: &str) -> String {
+    format!("/blob/{id}/ttl")
+}
+
+/// Viewer page for a share
+pub fn viewer_path(id: &str) -> String {
+    format!("/v/{id}")
+}
+
+/// Public gallery of listed shares, on servers that enable it
+pub const EXPLORE_PATH: &str = "/explore";
+
+/// Gallery entry for a share; `PUT` lists the share
+pub fn listing_path(id: &str) -> String {
+    format!("{EXPLORE_PATH}/{id}")
+}
+
+/// Request body of `PUT /explore/{id}`: what the gallery shows for a share.
+/// No key is sent, so only plaintext shares can be listed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ListingRequest {
+    pub title: String,
+    /// Tool that produced the session (e.g. `Claude Code`)
+    pub tool: String,
+}
+
+/// Request body of `POST /blob/{id}/ttl`. The share keeps its id; the new
+/// lifetime counts from now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct TtlRequest {
+    /// Days to keep the share (30, 60, 90, 180, or 365), or 0 for forever
+    pub ttl_days: u64,
+}
+
+/// Response body of `GET /blob/{id}/meta`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct BlobMetadata {
+    pub id: String,
+    /// Stored size in bytes, after compression and encryption
+    pub size_bytes: u64,
+    /// Unix seconds of the last upload or replacement
+    pub uploaded_at: u64,
+    /// Unix seconds when the share expires, or 0 if it never does
+    pub expires_at: u64,
+}
+
+/// Response body of `POST /upload`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct UploadResponse {
+    /// Share id: a TTL prefix letter followed by 16 hex characters
+    pub id: String,
+    /// Unix seconds when the share expires, or 0 if it never does
+    pub expires_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upload_response_roundtrip() {
+        let json = r#"{"id":"gabc123def4567890","expires_at":0}"#;
+        let response: UploadResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.id, "gabc123def4567890");
+        assert_eq!(serde_json::to_string(&response).unwrap(), json);
+        assert_eq!(blob_path(&response.id), "/blob/gabc123def4567890");
+        assert_eq!(listing_path(&response.id), "/explore/gabc123def4567890");
+        assert_eq!(
+            blob_chunk_path(&response.id, 3),
+            "/blob/gabc123def4567890/chunks/3"
+        );
+    }
+}
@@ -0,0 +1,358 @@
+//! OpenAPI document for the share server, built from the shared types.
+
+use schemars::generate::SchemaSettings;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    BlobMetadata, CHUNK_COUNT_HEADER, CHUNK_INDEX_HEADER, DELETE_TOKEN_HEADER, ENCRYPTION_HEADER,
+    ENCRYPTION_NONE, EXPLORE_PATH, ListingRequest, MAX_BLOB_SIZE, MAX_CHUNKS, RELATES_TO_HEADER,
+    SharePayload, TTL_DAYS_HEADER, TtlRequest, UPLOAD_PATH, UploadResponse,
+};
+
+/// JSON Schemas for the shared types and everything they reference,
+/// keyed by type name for `components.schemas`
+fn component_schemas() -> Map<String, Value> {
+    let mut generator = SchemaSettings::draft2020_12()
+        .with(|s| s.definitions_path = "/components/schemas".into())
+        .into_generator();
+    generator.subschema_for::<UploadResponse>();
+    generator.subschema_for::<SharePayload>();
+    generator.subschema_for::<ListingRequest>();
+    generator.subschema_for::<BlobMetadata>();
+    generator.subschema_for::<TtlRequest>();
+    generator.take_definitions(true)
+}
+
+fn header(name: &str, description: &str, required: bool, schema: Value) -> Value {
+    json!({
+        "name": name,
+        "in": "header",
+        "required": required,
+        "description": description,
+        "schema": schema,
+    })
+}
+
+fn text_error(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "text/plain": { "schema": { "type": "string" } } },
+    })
+}
+
+/// OpenAPI 3.1 description of the upload, blob, replace, delete, and gallery endpoints
+pub fn openapi() -> Value {
+    let id_param = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "description": "Share id: a TTL prefix letter followed by 16 hex characters",
+        "schema": { "type": "string", "pattern": "^[g-n][0-9a-f]{16}$" },
+    });
+    let blob_body = json!({
+        "application/octet-stream": {
+            "schema": { "type": "string", "contentEncoding": "binary" },
+        },
+        "application/json": {
+            "schema": { "$ref": "#/components/schemas/SharePayload" },
+        },
+    });
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "agentexport share server",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Stores encrypted transcript blobs. Blobs are IV || AES-256-GCM(gzip(JSON)) where the JSON is a SharePayload; the key stays in the share URL fragment.",
+        },
+        "paths": {
+            UPLOAD_PATH: {
+                "post": {
+                    "operationId": "upload",
+                    "summary": "Store a share",
+                    "parameters": [
+                        header(DELETE_TOKEN_HEADER, "Secret required to delete the blob later", true,
+                            json!({ "type": "string", "pattern": "^[0-9a-f]{64}$" })),
+                        header(TTL_DAYS_HEADER, "Retention in days: 30, 60, 90, 180, 365, or 0 for forever", false,
+                            json!({ "type": "integer", "default": 30 })),
+                        header(RELATES_TO_HEADER, "Id of an earlier share this one continues", false,
+                            json!({ "type": "string" })),
+                        header(ENCRYPTION_HEADER, "Marks an unencrypted JSON body (plaintext org mode; needs a bearer token)", false,
+                            json!({ "type": "string", "enum": [ENCRYPTION_NONE] })),
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "description": format!("At most {MAX_BLOB_SIZE} bytes"),
+                        "content": blob_body,
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Stored",
+                            "content": { "application/json": {
+                                "schema": { "$ref": "#/components/schemas/UploadResponse" },
+                            } },
+                        },
+                        "400": text_error("Missing delete token, TTL above the server maximum, or empty body"),
+                        "401": text_error("Wrong plaintext upload token"),
+                        "403": text_error("Plaintext uploads are not enabled"),
+                        "413": text_error("Body too large"),
+                    },
+                },
+            },
+            "/blob/{id}": {
+                "get": {
+                    "operationId": "getBlob",
+                    "summary": "Fetch a stored blob",
+                    "parameters": [id_param],
+                    "responses": {
+                        "200": {
+                            "description": "Blob bytes, or JSON for plaintext shares",
+                            "headers": {
+                                RELATES_TO_HEADER: {
+                                    "description": "Id of the share this one continues",
+                                    "schema": { "type": "string" },
+                                },
+                                CHUNK_COUNT_HEADER: {
+                                    "description": "Number of chunks appended to the blob (fetch each from /blob/{id}/chunks/{index})",
+                                    "schema": { "type": "integer" },
+                                },
+                            },
+                            "content": blob_body,
+                        },
+                        "400": text_error("Invalid id"),
+                        "404": text_error("Not found or expired"),
+                    },
+                },
+                "head": {
+                    "operationId": "checkBlob",
+                    "summary": "Check whether a blob is still stored",
+                    "parameters": [id_param],
+                    "responses": {
+                        "200": { "description": "Stored" },
+                        "404": { "description": "Not found or expired" },
+                    },
+                },
+                "put": {
+                    "operationId": "replaceBlob",
+                    "summary": "Replace a blob's content, keeping its id and dropping appended chunks; retention starts over",
+                    "parameters": [
+                        id_param,
+                        header(DELETE_TOKEN_HEADER, "Token sent with the upload", true,
+                            json!({ "type": "string" })),
+                        header(ENCRYPTION_HEADER, "Required, with a bearer token, when the share is plaintext", false,
+                            json!({ "type": "string", "enum": [ENCRYPTION_NONE] })),
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "description": format!("At most {MAX_BLOB_SIZE} bytes"),
+                        "content": blob_body,
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Replaced",
+                            "content": { "application/json": {
+                                "schema": { "$ref": "#/components/schemas/UploadResponse" },
+                            } },
+                        },
+                        "400": text_error("Invalid id, empty body, or encryption differing from the original"),
+                        "401": text_error("Missing or wrong delete token, or wrong plaintext upload token"),
+                        "403": text_error("Blob predates delete support, or plaintext uploads are not enabled"),
+                        "404": text_error("Not found"),
+                        "413": text_error("Body too large"),
+                    },
+                },
+                "delete": {
+                    "operationId": "deleteBlob",
+                    "summary": "Delete a blob and its appended chunks",
+                    "parameters": [
+                        id_param,
+                        header(DELETE_TOKEN_HEADER, "Token sent with the upload", true,
+                            json!({ "type": "string" })),
+                    ],
+                    "responses": {
+                        "204": { "description": "Deleted" },
+                        "401": text_error("Missing or wrong delete token"),
+                        "403": text_error("Blob predates delete support"),
+                        "404": text_error("Not found"),
+                    },
+                },
+            },
+            "/blob/{id}/meta": {
+                "get": {
+                    "operationId": "getBlobMetadata",
+                    "summary": "Size and expiry of a stored blob, without its content",
+                    "parameters": [id_param],
+                    "responses": {
+                        "200": {
+                            "description": "Blob metadata",
+                            "content": { "application/json": {
+                                "schema": { "$ref": "#/components/schemas/BlobMetadata" },
+                            } },
+                        },
+                        "400": text_error("Invalid id"),
+                        "404": text_error("Not found or expired"),
+                    },
+                },
+            },
+            "/blob/{id}/ttl": {
+                "post": {
+                    "operationId": "setBlobTtl",
+                    "summary": "Change how long a blob is kept, counting from now, without uploading it again",
+                    "parameters": [
+                        id_param,
+                        header(DELETE_TOKEN_HEADER, "Token sent with the upload", true,
+                            json!({ "type": "string" })),
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": {
+                            "schema": { "$ref": "#/components/schemas/TtlRequest" },
+                        } },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Retention changed; the id still works",
+                            "content": { "application/json": {
+                                "schema": { "$ref": "#/components/schemas/UploadResponse" },
+                            } },
+                        },
+                        "400": text_error("Invalid id or body, or TTL exceeds the server maximum"),
+                        "401": text_error("Missing or wrong delete token"),
+                        "403": text_error("Blob predates delete support"),
+                        "404": text_error("Not found"),
+                    },
+                },
+            },
+            "/blob/{id}/chunks": {
+                "post": {
+                    "operationId": "appendChunk",
+                    "summary": "Append messages to a blob without uploading it again",
+                    "parameters": [
+                        id_param,
+                        header(DELETE_TOKEN_HEADER, "Token sent with the upload", true,
+                            json!({ "type": "string" })),
+                        header(CHUNK_INDEX_HEADER, "Number of chunks already appended; the new chunk's index", true,
+                            json!({ "type": "integer", "minimum": 0 })),
+                        header(ENCRYPTION_HEADER, "Required, with a bearer token, when the share is plaintext", false,
+                            json!({ "type": "string", "enum": [ENCRYPTION_NONE] })),
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "description": format!("Encoded like the blob; a SharePayload holding only the new messages. At most {MAX_BLOB_SIZE} bytes"),
+                        "content": blob_body,
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Appended; the blob's expiry is unchanged",
+                            "content": { "application/json": {
+                                "schema": { "$ref": "#/components/schemas/UploadResponse" },
+                            } },
+                        },
+                        "400": text_error("Invalid id or chunk index, empty body, or encryption differing from the original"),
+                        "401": text_error("Missing or wrong delete token, or wrong plaintext upload token"),
+                        "403": text_error("Blob predates delete support, or plaintext uploads are not enabled"),
+                        "404": text_error("Not found"),
+                        "409": text_error(&format!("Chunk index differs from the stored count, or the blob already has {MAX_CHUNKS} chunks; replace it whole")),
+                        "413": text_error("Body too large"),
+                    },
+                },
+            },
+            "/blob/{id}/chunks/{index}": {
+                "get": {
+                    "operationId": "getChunk",
+                    "summary": "Fetch an appended chunk",
+                    "parameters": [
+                        id_param,
+                        {
+                            "name": "index",
+                            "in": "path",
+                            "required": true,
+                            "description": "Chunk number, from 0 in the order they were appended",
+                            "schema": { "type": "integer", "minimum": 0 },
+                        },
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Chunk bytes, or JSON for plaintext shares",
+                            "content": blob_body,
+                        },
+                        "400": text_error("Invalid id or index"),
+                        "404": text_error("Not found or expired"),
+                    },
+                },
+            },
+            EXPLORE_PATH: {
+                "get": {
+                    "operationId": "explore",
+                    "summary": "Public gallery of listed shares, newest first (HTML)",
+                    "responses": {
+                        "200": {
+                            "description": "Gallery page",
+                            "content": { "text/html": { "schema": { "type": "string" } } },
+                        },
+                        "404": text_error("The gallery is not enabled on this server"),
+                    },
+                },
+            },
+            format!("{EXPLORE_PATH}/{{id}}"): {
+                "put": {
+                    "operationId": "listShare",
+                    "summary": "List a plaintext share in the public gallery until it expires or is deleted",
+                    "parameters": [
+                        id_param,
+                        header(DELETE_TOKEN_HEADER, "Token sent with the upload", true,
+                            json!({ "type": "string" })),
+                    ],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": {
+                            "schema": { "$ref": "#/components/schemas/ListingRequest" },
+                        } },
+                    },
+                    "responses": {
+                        "204": { "description": "Listed" },
+                        "400": text_error("Invalid id or body, or the share is encrypted"),
+                        "401": text_error("Missing or wrong delete token"),
+                        "404": text_error("Share not found, or the gallery is not enabled on this server"),
+                    },
+                },
+            },
+        },
+        "components": { "schemas": component_schemas() },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The checked-in spec must match the types; regenerate with
+    /// `UPDATE_OPENAPI=1 cargo test -p agentexport-protocol`
+    #[test]
+    fn checked_in_spec_is_current() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+        let generated = serde_json::to_string_pretty(&openapi()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            checked_in == generated,
+            "protocol/openapi.json is stale; rerun with UPDATE_OPENAPI=1"
+        );
+    }
+
+    #[test]
+    fn schemas_reference_components() {
+        let schemas = component_schemas();
+        assert_eq!(
+            schemas["UploadResponse"]["required"],
+            json!(["id", "expires_at"])
+        );
+        assert_eq!(
+            schemas["SharePayload"]["properties"]["messages"]["items"]["$ref"],
+            "#/components/schemas/RenderedMessage"
+        );
+        assert!(schemas.contains_key("RenderedMessage"));
+    }
+}
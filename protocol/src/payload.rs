@@ -0,0 +1,382 @@
+//! The share payload: the JSON the CLI builds, encrypts, and the viewer renders.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Length of the random IV that prefixes every encrypted blob
+pub const IV_LEN: usize = 12;
+
+/// Length of the AES-256-GCM key carried in the share URL fragment
+pub const KEY_LEN: usize = 32;
+
+/// Fragment prefix of a passphrase-protected share. The rest of the fragment
+/// is a base64url salt; the key is PBKDF2-HMAC-SHA256(passphrase, salt).
+pub const PASSPHRASE_PREFIX: &str = "pw.";
+
+/// PBKDF2 rounds used to derive a passphrase share's key
+pub const PASSPHRASE_ITERATIONS: u32 = 600_000;
+
+/// A rendered message for the share payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RenderedMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// On a conversation branch the user later abandoned (by editing a
+    /// prompt or regenerating a reply)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub abandoned: bool,
+    /// Index into the payload's `parts`, on bundle shares
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<usize>,
+    /// Position in the parsed transcript, counting from 1. It survives
+    /// filtering, so `#msg-<id>` links to the same message in any share of
+    /// the session; bundles number on from the previous session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    /// Marked by the publisher with `--highlight`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub highlighted: bool,
+    /// Images as `data:` URIs, on shares published with `--include-images`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+}
+
+/// How a session ended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    #[default]
+    Completed,
+    /// The user aborted the last turn
+    Interrupted,
+    /// The last turn failed with an API or runtime error
+    Errored,
+}
+
+impl SessionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionStatus::Completed => "completed",
+            SessionStatus::Interrupted => "interrupted",
+            SessionStatus::Errored => "errored",
+        }
+    }
+}
+
+/// A task from Claude's TodoWrite list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct TaskItem {
+    pub content: String,
+    /// pending, in_progress, or completed
+    pub status: String,
+}
+
+/// Task list state built from successive TodoWrite calls
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct TaskProgress {
+    /// Latest state of the list
+    pub items: Vec<TaskItem>,
+    /// Items added or changed by each TodoWrite call, in order
+    pub updates: Vec<Vec<TaskItem>>,
+}
+
+impl TaskProgress {
+    /// Record a new TodoWrite state, returning the items that changed
+    pub fn update(&mut self, items: Vec<TaskItem>) -> Vec<TaskItem> {
+        let changes: Vec<TaskItem> = items
+            .iter()
+            .filter(|item| !self.items.contains(item))
+            .cloned()
+            .collect();
+        self.updates.push(changes.clone());
+        self.items = items;
+        changes
+    }
+
+    pub fn completed(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.status == "completed")
+            .count()
+    }
+}
+
+/// Link to the previous share in a multi-part series
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct SeriesLink {
+    /// 1-based position of this share in the series
+    pub part: u32,
+    pub previous_url: String,
+}
+
+/// Who published a share (only included when attribution is enabled)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Publisher {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+/// Publisher-chosen initial visibility for the viewer toggles.
+/// Unset fields keep the viewer's own defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ViewerDefaults {
+    /// Show tool calls and results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<bool>,
+    /// Show thinking blocks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<bool>,
+    /// Show system messages (otherwise they follow the tool calls toggle)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<bool>,
+}
+
+impl FromStr for ViewerDefaults {
+    type Err = String;
+
+    /// Parse `tools=show,thinking=hide,system=hide`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut defaults = ViewerDefaults::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((name, value)) = entry.split_once('=') else {
+                return Err(format!("expected ROLE=show|hide, got '{entry}'"));
+            };
+            let show = match value.trim() {
+                "show" => true,
+                "hide" => false,
+                other => return Err(format!("expected show or hide for {name}, got '{other}'")),
+            };
+            let slot = match name.trim() {
+                "tools" => &mut defaults.tools,
+                "thinking" => &mut defaults.thinking,
+                "system" => &mut defaults.system,
+                other => {
+                    return Err(format!(
+                        "unknown role '{other}' (expected tools, thinking, or system)"
+                    ));
+                }
+            };
+            *slot = Some(show);
+        }
+        Ok(defaults)
+    }
+}
+
+/// When a session ran and how busy it was over time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Timeline {
+    /// Unix seconds of the first timestamped event
+    pub started_at: i64,
+    /// Unix seconds of the last timestamped event
+    pub ended_at: i64,
+    /// Width of each bucket in minutes
+    pub bucket_minutes: u32,
+    /// Messages per bucket, starting at `started_at`
+    pub buckets: Vec<u32>,
+}
+
+fn is_zero(val: &u64) -> bool {
+    *val == 0
+}
+
+/// Token usage and estimated cost for one model in the session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ModelCost {
+    pub model: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub input_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub output_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_read_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_creation_tokens: u64,
+    /// Cached tokens are counted in `input_tokens` too (Codex)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub input_includes_cache: bool,
+    /// None when the model's pricing is unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_usd: Option<f64>,
+}
+
+/// How often the agent called one tool and how long the calls took
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ToolStat {
+    /// Tool name as the agent called it (e.g. `Bash`, `shell`)
+    pub name: String,
+    pub calls: u32,
+    /// Milliseconds from call to result, summed over the calls whose both
+    /// ends are timestamped
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub duration_ms: u64,
+}
+
+/// One transcript of a bundle share
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct BundlePart {
+    /// Tool that produced this transcript (e.g. `Codex`)
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Most used model in this transcript
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Payload sent to the viewer (encrypted JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct SharePayload {
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub shared_at: String,
+    /// Primary model (most used), shown in header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// All models used, for "model1 + model2" display if multiple
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    pub messages: Vec<RenderedMessage>,
+    /// Token usage totals (if available)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub total_input_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub total_output_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub total_cache_read_tokens: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub total_cache_creation_tokens: u64,
+    /// Usage and estimated cost per model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_costs: Vec<ModelCost>,
+    /// Sum of the per-model estimates, over models with known pricing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_usd: Option<f64>,
+    /// Publisher provenance, opt-in via the `attribution` config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<Publisher>,
+    /// Previous share this one continues (set via `--relates-to`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<SeriesLink>,
+    #[serde(default)]
+    pub status: SessionStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<TaskProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<Timeline>,
+    /// Initial toggle state chosen by the publisher (set via `--viewer-defaults`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub viewer: Option<ViewerDefaults>,
+    /// Dominant language of the conversation as a BCP 47 tag (e.g. `en`, `es`),
+    /// when it could be detected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Stats without conversation content (`publish --metadata-only`):
+    /// `messages` is empty and `message_counts` says what was left out
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub metadata_only: bool,
+    /// Messages per role, set on metadata-only shares
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub message_counts: BTreeMap<String, usize>,
+    /// Calls per tool, most called first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_stats: Vec<ToolStat>,
+    /// Transcripts combined into this share, in order (`publish --transcript a
+    /// --transcript b`); each message names its part
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<BundlePart>,
+}
+
+impl SharePayload {
+    /// Add a chunk appended to this share since it was uploaded: the chunk's
+    /// messages follow this payload's, and its other fields (the latest title,
+    /// usage, status) replace these.
+    pub fn append_chunk(&mut self, chunk: SharePayload) {
+        let mut messages = std::mem::take(&mut self.messages);
+        messages.extend(chunk.messages);
+        *self = SharePayload { messages, ..chunk };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_payload_roundtrips_and_skips_empty_fields() {
+        let json = r#"{"tool":"Codex","shared_at":"Jan 1","messages":[{"role":"user","content":"hi"}],"total_input_tokens":5,"status":"interrupted"}"#;
+        let payload: SharePayload = serde_json::from_str(json).unwrap();
+        assert_eq!(payload.status, SessionStatus::Interrupted);
+        assert!(payload.models.is_empty() && payload.messages[0].raw.is_none());
+        assert_eq!(serde_json::to_string(&payload).unwrap(), json);
+    }
+
+    #[test]
+    fn chunks_append_messages_and_update_the_rest() {
+        let mut payload: SharePayload = serde_json::from_str(
+            r#"{"tool":"Claude","title":"Old","shared_at":"1","messages":[{"role":"user","content":"a"}],"errors":["x"]}"#,
+        )
+        .unwrap();
+        let chunk: SharePayload = serde_json::from_str(
+            r#"{"tool":"Claude","title":"New","shared_at":"2","messages":[{"role":"assistant","content":"b"}],"total_output_tokens":9}"#,
+        )
+        .unwrap();
+        payload.append_chunk(chunk);
+        let contents: Vec<_> = payload
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, ["a", "b"]);
+        assert_eq!(payload.title.as_deref(), Some("New"));
+        assert_eq!(payload.total_output_tokens, 9);
+        assert!(payload.errors.is_empty());
+    }
+
+    #[test]
+    fn viewer_defaults_parse() {
+        let defaults: ViewerDefaults = "tools=show, thinking=hide".parse().unwrap();
+        assert_eq!(defaults.tools, Some(true));
+        assert_eq!(defaults.thinking, Some(false));
+        assert_eq!(defaults.system, None);
+        assert_eq!(
+            serde_json::to_string(&defaults).unwrap(),
+            r#"{"tools":true,"thinking":false}"#
+        );
+        assert!("tools".parse::<ViewerDefaults>().is_err());
+        assert!("tools=maybe".parse::<ViewerDefaults>().is_err());
+        assert!("images=show".parse::<ViewerDefaults>().is_err());
+    }
+}
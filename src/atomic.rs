@@ -0,0 +1,93 @@
+//! Crash-safe file writes for state the tool keeps (config, shares, Claude
+//! state, backups, exports).
+//!
+//! Contents go to a temp file in the same folder, which is fsynced and then
+//! renamed over the target, so an interrupted write leaves the old file whole
+//! rather than a truncated one.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace `path` with `contents` atomically, like `fs::write`. An existing
+/// file keeps its permissions, and a symlink is followed rather than replaced.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = target(path.as_ref())?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut tmp = tempfile::Builder::new()
+        .prefix(".agentexport-")
+        .suffix(".tmp")
+        .tempfile_in(dir)?;
+    tmp.write_all(contents.as_ref())?;
+    if let Ok(meta) = fs::metadata(&path) {
+        tmp.as_file().set_permissions(meta.permissions())?;
+    }
+    tmp.as_file().sync_all()?;
+    tmp.persist(&path).map_err(|err| err.error)?;
+    // The rename only survives a crash once the folder is synced too
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// The file a write to `path` should replace: symlinks are resolved so that
+/// the link itself survives (dotfile managers link config files)
+fn target(path: &Path) -> io::Result<PathBuf> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => match fs::canonicalize(path) {
+            Ok(resolved) => Ok(resolved),
+            // A dangling link: write where it points
+            Err(_) => {
+                let link = fs::read_link(path)?;
+                Ok(path.parent().map_or(link.clone(), |dir| dir.join(link)))
+            }
+        },
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_contents_and_leaves_no_temp_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("shares.json");
+        write(&path, "first").unwrap();
+        write(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["shares.json"]);
+        assert!(write(dir.path().join("missing/x.json"), "x").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+        let dir = tempfile::TempDir::new().unwrap();
+        let real = dir.path().join("dotfiles-config.toml");
+        fs::write(&real, "old").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.path().join("config.toml");
+        symlink(&real, &link).unwrap();
+
+        write(&link, "new").unwrap();
+        assert!(
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new");
+        let mode = fs::metadata(&real).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
@@ -0,0 +1,115 @@
+//! Encrypted local backups of published share payloads.
+//!
+//! Every upload keeps a copy of the exact payload JSON under
+//! `~/.agentexport/backups/<share-id>.bin`, encrypted with a per-machine key
+//! in `~/.agentexport/backup.key`, so a share can be recovered after it
+//! expires on the server.
+
+use anyhow::{Context, Result, bail};
+use rand::RngCore;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::atomic;
+use crate::crypto;
+
+fn agentexport_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME not set")?;
+    Ok(PathBuf::from(home).join(".agentexport"))
+}
+
+fn backups_dir() -> Result<PathBuf> {
+    Ok(agentexport_dir()?.join("backups"))
+}
+
+/// Path of the backup file for a share id
+pub fn backup_path(share_id: &str) -> Result<PathBuf> {
+    if share_id.is_empty()
+        || !share_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Invalid share id: {share_id}");
+    }
+    Ok(backups_dir()?.join(format!("{share_id}.bin")))
+}
+
+/// Load the backup key, creating it on first use
+fn backup_key() -> Result<[u8; 32]> {
+    let path = agentexport_dir()?.join("backup.key");
+    if path.exists() {
+        let hex_key = fs::read_to_string(&path).context("Failed to read backup key")?;
+        let bytes = hex::decode(hex_key.trim()).context("Backup key is not valid hex")?;
+        return bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Backup key must be 32 bytes"));
+    }
+
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    fs::create_dir_all(path.parent().unwrap())?;
+    atomic::write(&path, format!("{}\n", hex::encode(key)))
+        .context("Failed to write backup key")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(key)
+}
+
+/// Encrypt and store the payload JSON for a share
+pub fn save_backup(share_id: &str, payload_json: &str) -> Result<PathBuf> {
+    let path = backup_path(share_id)?;
+    let blob = crypto::encrypt_with_key(payload_json, &backup_key()?)?;
+    fs::create_dir_all(backups_dir()?)?;
+    atomic::write(&path, blob).context("Failed to write backup")?;
+    Ok(path)
+}
+
+/// Decrypt the stored payload JSON for a share
+pub fn load_backup(share_id: &str) -> Result<String> {
+    let path = backup_path(share_id)?;
+    if !path.exists() {
+        bail!("No local backup for share {share_id}");
+    }
+    let blob = fs::read(&path).context("Failed to read backup")?;
+    crypto::decrypt_with_key(&blob, &backup_key()?)
+}
+
+/// Delete the backup for a share, if any
+pub fn remove_backup(share_id: &str) -> Result<()> {
+    let path = backup_path(share_id)?;
+    if path.exists() {
+        fs::remove_file(&path).context("Failed to remove backup")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use tempfile::TempDir;
+
+    #[test]
+    fn backup_roundtrip() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard = EnvGuard::set("HOME", tmp.path().to_str().unwrap());
+
+        let path = save_backup("abc123", "{\"title\":\"hi\"}").unwrap();
+        assert!(path.exists());
+        assert!(!fs::read(&path).unwrap().starts_with(b"{"));
+        assert_eq!(load_backup("abc123").unwrap(), "{\"title\":\"hi\"}");
+
+        remove_backup("abc123").unwrap();
+        assert!(load_backup("abc123").is_err());
+    }
+
+    #[test]
+    fn backup_path_rejects_traversal() {
+        assert!(backup_path("../etc").is_err());
+        assert!(backup_path("").is_err());
+    }
+}
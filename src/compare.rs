@@ -0,0 +1,169 @@
+//! Cross-session comparison reports (`agentexport compare`).
+
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::monitor::format_tokens;
+use crate::transcript::{ParseResult, SessionStatus, parse_transcript};
+
+/// How sessions are bucketed in a comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// Dominant model of each session
+    Model,
+}
+
+/// Numbers pulled from one transcript
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    pub model: String,
+    pub tokens: u64,
+    pub tool_calls: usize,
+    pub duration: Option<time::Duration>,
+    /// Heuristic: the session completed and ended on an assistant reply rather than a tool call
+    pub succeeded: bool,
+}
+
+impl SessionStats {
+    pub fn from_parsed(parsed: &ParseResult) -> Self {
+        let last_role = parsed
+            .messages
+            .iter()
+            .rev()
+            .map(|m| m.role.as_str())
+            .find(|role| *role != "thinking");
+        Self {
+            model: parsed
+                .dominant_model()
+                .unwrap_or_else(|| "unknown".to_string()),
+            tokens: parsed.total_input_tokens() + parsed.total_output_tokens(),
+            tool_calls: parsed.tool_call_count,
+            duration: parsed.duration(),
+            succeeded: parsed.status == SessionStatus::Completed && last_role == Some("assistant"),
+        }
+    }
+}
+
+/// Expand files and directories into the `.jsonl` transcripts they contain
+pub fn collect_transcripts(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(
+                walkdir::WalkDir::new(path)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .map(|e| e.into_path())
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl")),
+            );
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files.sort();
+    files
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, (seconds % 3600) / 60),
+    }
+}
+
+/// Markdown table of per-group averages
+pub fn compare_report(stats: &[SessionStats], group_by: GroupBy) -> String {
+    let mut groups: BTreeMap<&str, Vec<&SessionStats>> = BTreeMap::new();
+    for s in stats {
+        let key = match group_by {
+            GroupBy::Model => s.model.as_str(),
+        };
+        groups.entry(key).or_default().push(s);
+    }
+
+    let mut out = String::from("# Session comparison\n\n");
+    out.push_str("| Model | Sessions | Avg tokens | Avg tool calls | Avg duration | Success |\n");
+    out.push_str("|---|---:|---:|---:|---:|---:|\n");
+    for (key, sessions) in &groups {
+        let n = sessions.len() as f64;
+        let tokens = sessions.iter().map(|s| s.tokens).sum::<u64>() as f64 / n;
+        let tool_calls = sessions.iter().map(|s| s.tool_calls).sum::<usize>() as f64 / n;
+        let durations: Vec<f64> = sessions
+            .iter()
+            .filter_map(|s| s.duration)
+            .map(|d| d.as_seconds_f64())
+            .collect();
+        let duration = if durations.is_empty() {
+            "-".to_string()
+        } else {
+            format_duration(durations.iter().sum::<f64>() / durations.len() as f64)
+        };
+        let succeeded = sessions.iter().filter(|s| s.succeeded).count() as f64;
+        out.push_str(&format!(
+            "| {key} | {} | {} | {tool_calls:.1} | {duration} | {:.0}% |\n",
+            sessions.len(),
+            format_tokens(tokens.round() as u64),
+            succeeded / n * 100.0,
+        ));
+    }
+    out.push_str(&format!(
+        "\n_{} sessions. Tokens are input + output; success means the session was not interrupted or errored and ended on an assistant reply._\n",
+        stats.len()
+    ));
+    out
+}
+
+/// Parse every transcript under `paths` and build the report
+pub fn compare(paths: &[PathBuf], group_by: GroupBy) -> Result<String> {
+    let files = collect_transcripts(paths);
+    let mut stats = Vec::new();
+    for file in &files {
+        match parse_transcript(Path::new(file)) {
+            Ok(parsed) if !parsed.messages.is_empty() => {
+                stats.push(SessionStats::from_parsed(&parsed))
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("warning: skipping {}: {err}", file.display()),
+        }
+    }
+    if stats.is_empty() {
+        bail!("No transcripts with messages found");
+    }
+    Ok(compare_report(&stats, group_by))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn report_averages_per_model() {
+        let tmp = TempDir::new().unwrap();
+        let sonnet = [
+            r#"{"type":"user","timestamp":"2025-01-01T10:00:00Z","message":{"content":"Fix it"}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:02:00Z","message":{"id":"m1","model":"claude-sonnet-4-5","usage":{"input_tokens":100,"output_tokens":50},"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{}}]}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:05:00Z","message":{"id":"m2","model":"claude-sonnet-4-5","usage":{"input_tokens":200,"output_tokens":50},"content":[{"type":"text","text":"Done"}]}}"#,
+        ]
+        .join("\n");
+        let haiku = [
+            r#"{"type":"user","message":{"content":"Try"}}"#,
+            r#"{"type":"assistant","message":{"id":"m1","model":"claude-haiku-4-5","usage":{"input_tokens":10,"output_tokens":10},"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{}}]}}"#,
+        ]
+        .join("\n");
+        fs::create_dir_all(tmp.path().join("runs")).unwrap();
+        fs::write(tmp.path().join("runs/a.jsonl"), sonnet).unwrap();
+        fs::write(tmp.path().join("runs/b.jsonl"), haiku).unwrap();
+        fs::write(tmp.path().join("runs/notes.txt"), "ignored").unwrap();
+
+        let report = compare(&[tmp.path().to_path_buf()], GroupBy::Model).unwrap();
+        assert!(report.contains("| claude-sonnet-4-5 | 1 | 400 | 1.0 | 5m 0s | 100% |"));
+        assert!(report.contains("| claude-haiku-4-5 | 1 | 20 | 1.0 | - | 0% |"));
+        assert!(report.contains("_2 sessions."));
+    }
+}
@@ -1,18 +1,26 @@
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::atomic;
+use crate::transcript::{PricingTable, TitleSource};
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[derive(Default)]
 pub enum StorageType {
+    #[default]
     Agentexport,
     Gist,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[derive(Default)]
 pub enum GistFormat {
+    #[default]
     Markdown,
     Json,
 }
@@ -27,12 +35,6 @@ impl GistFormat {
     }
 }
 
-impl Default for GistFormat {
-    fn default() -> Self {
-        GistFormat::Markdown
-    }
-}
-
 impl std::fmt::Display for GistFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let value = match self {
@@ -43,6 +45,75 @@ impl std::fmt::Display for GistFormat {
     }
 }
 
+/// Whether gist shares also carry the original transcript file
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GistRaw {
+    #[default]
+    Off,
+    /// The transcript as is (redacted like the share)
+    Jsonl,
+    /// The transcript gzipped and base64-encoded, for long sessions
+    Gzip,
+}
+
+impl GistRaw {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "false" | "no" | "none" => Ok(Self::Off),
+            "jsonl" | "on" | "true" | "yes" => Ok(Self::Jsonl),
+            "gzip" => Ok(Self::Gzip),
+            _ => bail!("invalid gist_include_raw: must be off, jsonl, or gzip"),
+        }
+    }
+}
+
+impl std::fmt::Display for GistRaw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            GistRaw::Off => "off",
+            GistRaw::Jsonl => "jsonl",
+            GistRaw::Gzip => "gzip",
+        };
+        write!(f, "{value}")
+    }
+}
+
+/// How much of each message's raw tool input/output a share keeps
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RawContent {
+    /// Drop raw content; only the rendered text is shared
+    None,
+    /// Keep the first few thousand characters of each
+    Truncated,
+    /// Keep raw content as parsed
+    #[default]
+    Full,
+}
+
+impl RawContent {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "none" | "off" => Ok(Self::None),
+            "truncated" => Ok(Self::Truncated),
+            "full" => Ok(Self::Full),
+            _ => bail!("invalid raw: must be none, truncated, or full"),
+        }
+    }
+}
+
+impl std::fmt::Display for RawContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            RawContent::None => "none",
+            RawContent::Truncated => "truncated",
+            RawContent::Full => "full",
+        };
+        write!(f, "{value}")
+    }
+}
+
 impl StorageType {
     pub fn parse(value: &str) -> Result<Self> {
         match value.trim().to_lowercase().as_str() {
@@ -53,12 +124,6 @@ impl StorageType {
     }
 }
 
-impl Default for StorageType {
-    fn default() -> Self {
-        StorageType::Agentexport
-    }
-}
-
 impl std::fmt::Display for StorageType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let value = match self {
@@ -86,6 +151,166 @@ pub struct Config {
     /// Format for gist storage (html or json)
     #[serde(default = "default_gist_format")]
     pub gist_format: GistFormat,
+
+    /// Attach the original transcript to gist shares (off, jsonl, or gzip)
+    #[serde(default)]
+    pub gist_include_raw: GistRaw,
+
+    /// Raw tool content kept in shares (none, truncated, or full)
+    #[serde(default)]
+    pub raw: RawContent,
+
+    /// Embed publisher name/email and hostname in shares (off by default)
+    #[serde(default)]
+    pub attribution: bool,
+
+    /// Display name used for attribution instead of git user.name/email
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+
+    /// Token for plaintext (`--no-e2e`) uploads to a self-hosted server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_token: Option<String>,
+
+    /// Atlassian account email for `export --post-to` (Confluence Cloud).
+    /// Without it the token is sent as a personal access token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confluence_email: Option<String>,
+
+    /// Confluence API token for `export --post-to`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confluence_token: Option<String>,
+
+    /// Only publish transcripts whose models all match one of these (empty = any)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_models: Vec<String>,
+
+    /// Never publish transcripts with messages from models matching these
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_models: Vec<String>,
+
+    /// Extra regexes whose matches are redacted before upload
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact_patterns: Vec<String>,
+
+    /// Shell command that scans the payload text on stdin for secrets and
+    /// prints JSON findings (e.g. gitleaks or trufflehog); they are masked too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_scanner: Option<String>,
+
+    /// Where `shares sync` keeps a copy of shares.json for other machines: a
+    /// file in a git checkout, or an `s3://bucket/key` object
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shares_sync: Option<String>,
+
+    /// Wipe the keys and delete tokens of shares once they expire, keeping
+    /// only their metadata in shares.json
+    #[serde(default)]
+    pub scrub_expired_keys: bool,
+
+    /// Age in days past which `shares prune` deletes live shares from their
+    /// server (unset: it only drops expired ones)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prune_after_days: Option<u64>,
+
+    /// Named sets of publish options, applied with `publish --preset <name>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, PublishPreset>,
+
+    /// Per-model prices (USD per million tokens) for cost estimates, taking
+    /// precedence over the built-in ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pricing: PricingTable,
+}
+
+/// Publish options saved under `[presets.<name>]`. Flags given on the command
+/// line take precedence; tags are combined.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PublishPreset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Title built from transcript metadata, e.g. `"Demo: {first_message:60}"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_from: Option<TitleSource>,
+    /// Leave thinking blocks out of the share
+    #[serde(default)]
+    pub no_thinking: bool,
+    /// Set to false to skip secret redaction (like `--no-redact`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redact: Option<bool>,
+    /// Same values as `--raw`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<RawContent>,
+    /// Same syntax as `--viewer-defaults`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewer_defaults: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Set to false to leave the publisher out even with `attribution = true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<bool>,
+    #[serde(default)]
+    pub include_abandoned: bool,
+    #[serde(default)]
+    pub no_e2e: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_url: Option<String>,
+    /// Keys this version doesn't understand, reported rather than ignored
+    #[serde(flatten)]
+    pub unsupported: BTreeMap<String, toml::Value>,
+}
+
+/// Which models' transcripts may be published.
+/// Patterns match a model exactly, or by prefix when they end in `*`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelPolicy {
+    pub allowed: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+fn model_matches(pattern: &str, model: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => model.starts_with(prefix),
+        None => model == pattern,
+    }
+}
+
+impl ModelPolicy {
+    pub fn permits(&self, model: &str) -> bool {
+        let listed = |patterns: &[String]| patterns.iter().any(|p| model_matches(p, model));
+        !listed(&self.denied) && (self.allowed.is_empty() || listed(&self.allowed))
+    }
+
+    /// Fail, naming the offending models, if any of `models` is not permitted
+    pub fn check<'a>(&self, models: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        let mut offending: Vec<&str> = models
+            .into_iter()
+            .map(String::as_str)
+            .filter(|model| !self.permits(model))
+            .collect();
+        if !offending.is_empty() {
+            offending.sort_unstable();
+            bail!(
+                "refusing to publish: transcript has messages from models blocked by the allowed_models/denied_models config: {}",
+                offending.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parse a comma-separated list of model patterns
+pub fn parse_model_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
 fn default_ttl() -> u64 {
@@ -123,11 +348,43 @@ impl Config {
         Ok(config)
     }
 
+    /// The preset named `name`, failing if it is missing or has options this
+    /// version can't apply
+    pub fn preset(&self, name: &str) -> Result<&PublishPreset> {
+        let Some(preset) = self.presets.get(name) else {
+            if self.presets.is_empty() {
+                bail!("unknown preset '{name}': add one under [presets.{name}] in the config");
+            }
+            let known: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+            bail!("unknown preset '{name}' (available: {})", known.join(", "));
+        };
+        if !preset.unsupported.is_empty() {
+            let keys: Vec<&str> = preset.unsupported.keys().map(String::as_str).collect();
+            bail!(
+                "preset '{name}' has unsupported options: {}",
+                keys.join(", ")
+            );
+        }
+        Ok(preset)
+    }
+
+    pub fn model_policy(&self) -> ModelPolicy {
+        ModelPolicy {
+            allowed: self.allowed_models.clone(),
+            denied: self.denied_models.clone(),
+        }
+    }
+
     /// Save config to ~/.agentexport/config.toml
     pub fn save(&self) -> Result<PathBuf> {
         let path = config_path()?;
         let content = toml::to_string_pretty(self).context("failed to serialize config")?;
-        fs::write(&path, content).with_context(|| format!("failed to write {}", path.display()))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        atomic::write(&path, content)
+            .with_context(|| format!("failed to write {}", path.display()))?;
         Ok(path)
     }
 }
@@ -139,6 +396,22 @@ impl Default for Config {
             storage_type: default_storage_type(),
             upload_url: default_upload_url(),
             gist_format: default_gist_format(),
+            gist_include_raw: GistRaw::Off,
+            raw: RawContent::default(),
+            attribution: false,
+            handle: None,
+            upload_token: None,
+            confluence_email: None,
+            confluence_token: None,
+            allowed_models: Vec::new(),
+            denied_models: Vec::new(),
+            redact_patterns: Vec::new(),
+            secret_scanner: None,
+            shares_sync: None,
+            scrub_expired_keys: false,
+            prune_after_days: None,
+            presets: BTreeMap::new(),
+            pricing: PricingTable::new(),
         }
     }
 }
@@ -158,6 +431,32 @@ mod tests {
             storage_type: StorageType::Gist,
             upload_url: "https://example.com".to_string(),
             gist_format: GistFormat::Json,
+            gist_include_raw: GistRaw::Gzip,
+            raw: RawContent::None,
+            attribution: true,
+            handle: Some("nico".to_string()),
+            upload_token: Some("secret".to_string()),
+            confluence_email: None,
+            confluence_token: Some("atl".to_string()),
+            allowed_models: Vec::new(),
+            denied_models: vec!["ft:*".to_string()],
+            redact_patterns: vec![r"acme_[0-9a-f]{32}".to_string()],
+            secret_scanner: Some(
+                "gitleaks stdin --no-banner --report-format json --report-path /dev/stdout"
+                    .to_string(),
+            ),
+            shares_sync: Some("s3://team-bucket/agentexport/shares.json".to_string()),
+            scrub_expired_keys: true,
+            prune_after_days: Some(180),
+            presets: BTreeMap::new(),
+            pricing: PricingTable::from([(
+                "in-house-model".to_string(),
+                crate::transcript::ModelPrice {
+                    input: 1.0,
+                    output: 2.0,
+                    ..Default::default()
+                },
+            )]),
         };
 
         let content = toml::to_string_pretty(&config).unwrap();
@@ -166,7 +465,39 @@ mod tests {
         let loaded: Config = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
         assert_eq!(loaded.default_ttl, 90);
         assert_eq!(loaded.storage_type, StorageType::Gist);
+        assert_eq!(loaded.raw, RawContent::None);
+        assert_eq!(loaded.gist_include_raw, GistRaw::Gzip);
         assert_eq!(loaded.upload_url, "https://example.com");
+        assert!(loaded.attribution);
+        assert_eq!(loaded.handle.as_deref(), Some("nico"));
+        assert_eq!(loaded.upload_token.as_deref(), Some("secret"));
+        assert_eq!(loaded.confluence_token.as_deref(), Some("atl"));
+        assert_eq!(loaded.denied_models, ["ft:*"]);
+        assert_eq!(loaded.redact_patterns, [r"acme_[0-9a-f]{32}"]);
+        assert!(loaded.secret_scanner.unwrap().starts_with("gitleaks stdin"));
+        assert!(loaded.allowed_models.is_empty());
+        assert_eq!(loaded.pricing["in-house-model"].output, 2.0);
+    }
+
+    #[test]
+    fn model_policy_checks_allow_and_deny_lists() {
+        let models = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(ModelPolicy::default().check(&models(&["anything"])).is_ok());
+
+        let policy = ModelPolicy {
+            allowed: parse_model_list("claude-*, gpt-5"),
+            denied: parse_model_list("claude-internal-*"),
+        };
+        assert!(policy.permits("claude-sonnet-4"));
+        assert!(policy.permits("gpt-5"));
+        assert!(!policy.permits("gpt-5-mini"));
+        assert!(!policy.permits("claude-internal-ft"));
+
+        let err = policy
+            .check(&models(&["gpt-5", "ft:acme", "claude-internal-ft"]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with(": claude-internal-ft, ft:acme"), "{err}");
     }
 
     #[test]
@@ -176,6 +507,8 @@ mod tests {
         assert_eq!(config.storage_type, StorageType::Agentexport);
         assert_eq!(config.upload_url, "https://agentexports.com");
         assert_eq!(config.gist_format, GistFormat::Markdown);
+        assert!(!config.attribution);
+        assert_eq!(config.handle, None);
     }
 
     #[test]
@@ -187,6 +520,36 @@ mod tests {
         assert_eq!(config.upload_url, "https://agentexports.com");
     }
 
+    #[test]
+    fn presets_parse_and_report_unsupported_options() {
+        let config: Config = toml::from_str(
+            r#"
+[presets.demo]
+no_thinking = true
+redact = true
+attribution = false
+ttl = 7
+title_template = "Demo: {first_message:60}"
+title_from = "first-message"
+
+[presets.later]
+tags = ["team"]
+shiny = true
+"#,
+        )
+        .unwrap();
+        let demo = config.preset("demo").unwrap();
+        assert!(demo.no_thinking);
+        assert_eq!(demo.redact, Some(true));
+        assert_eq!(demo.attribution, Some(false));
+        assert_eq!(demo.ttl, Some(7));
+        assert_eq!(demo.title_from, Some(TitleSource::FirstMessage));
+        let err = config.preset("later").unwrap_err().to_string();
+        assert!(err.ends_with("unsupported options: shiny"), "{err}");
+        let err = config.preset("nope").unwrap_err().to_string();
+        assert!(err.contains("available: demo, later"), "{err}");
+    }
+
     #[test]
     fn config_storage_type_parse() {
         let content = "storage_type = \"gist\"\n";
@@ -201,6 +564,15 @@ mod tests {
         assert_eq!(config.gist_format, GistFormat::Json);
     }
 
+    #[test]
+    fn raw_content_defaults_to_full() {
+        assert_eq!(Config::default().raw, RawContent::Full);
+        let config: Config = toml::from_str("raw = \"truncated\"\n").unwrap();
+        assert_eq!(config.raw, RawContent::Truncated);
+        assert_eq!(RawContent::parse("None").unwrap(), RawContent::None);
+        assert!(RawContent::parse("some").is_err());
+    }
+
     #[test]
     fn gist_format_parse_variants() {
         assert_eq!(GistFormat::parse("markdown").unwrap(), GistFormat::Markdown);
@@ -208,5 +580,8 @@ mod tests {
         assert_eq!(GistFormat::parse("json").unwrap(), GistFormat::Json);
         assert_eq!(GistFormat::parse("MARKDOWN").unwrap(), GistFormat::Markdown);
         assert!(GistFormat::parse("invalid").is_err());
+        assert_eq!(GistRaw::parse("true").unwrap(), GistRaw::Jsonl);
+        assert_eq!(GistRaw::parse("gzip").unwrap(), GistRaw::Gzip);
+        assert!(GistRaw::parse("zip").is_err());
     }
 }
@@ -0,0 +1,297 @@
+//! Confluence export: render a share payload in Confluence storage format
+//! (XHTML with `ac:` macros) and optionally create it as a page.
+//!
+//! Tool calls go in code macros and thinking in collapsed expand macros, so
+//! archived pages read like the viewer with its default toggles.
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use maud::{Markup, PreEscaped, html};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::test_mode;
+use crate::transcript::SharePayload;
+
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Text as a CDATA section, splitting any `]]>` that would end it early
+fn cdata(text: &str) -> PreEscaped<String> {
+    PreEscaped(format!(
+        "<![CDATA[{}]]>",
+        text.replace("]]>", "]]]]><![CDATA[>")
+    ))
+}
+
+/// Prose as paragraphs, keeping single line breaks
+fn paragraphs(text: &str) -> Markup {
+    html! {
+        @for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+            p {
+                @for (i, line) in paragraph.lines().enumerate() {
+                    @if i > 0 { (PreEscaped("<br />")) }
+                    (line)
+                }
+            }
+        }
+    }
+}
+
+fn code_macro(text: &str) -> Markup {
+    html! {
+        ac:structured-macro ac:name="code" {
+            ac:parameter ac:name="linenumbers" { "false" }
+            ac:plain-text-body { (cdata(text)) }
+        }
+    }
+}
+
+/// The transcript as a Confluence storage-format page body
+pub fn render_storage(payload: &SharePayload) -> String {
+    html! {
+        p {
+            strong { (payload.tool) }
+            @if let Some(model) = &payload.model { " · " (model) }
+            @if !payload.shared_at.is_empty() { " · " (payload.shared_at) }
+        }
+        @for message in &payload.messages {
+            @match message.role.as_str() {
+                "thinking" => {
+                    ac:structured-macro ac:name="expand" {
+                        ac:parameter ac:name="title" { "Thinking" }
+                        ac:rich-text-body { (paragraphs(&message.content)) }
+                    }
+                }
+                "tool" | "command" => {
+                    h3 { (role_label(&message.role)) }
+                    (code_macro(&message.content))
+                    @if let Some(raw) = &message.raw {
+                        ac:structured-macro ac:name="expand" {
+                            ac:parameter ac:name="title" {
+                                (message.raw_label.as_deref().unwrap_or("Output"))
+                            }
+                            ac:rich-text-body { (code_macro(raw)) }
+                        }
+                    }
+                }
+                role => {
+                    h3 { (role_label(role)) }
+                    (paragraphs(&message.content))
+                }
+            }
+        }
+        (PreEscaped("<hr />"))
+        p { em { "Exported with agentexport" } }
+    }
+    .into_string()
+}
+
+/// A Confluence page, as identified by its URL
+#[derive(Debug, PartialEq, Eq)]
+struct PageRef {
+    /// Site base that the REST API hangs off (e.g. `https://acme.atlassian.net/wiki`)
+    base: String,
+    id: String,
+}
+
+/// Parse `.../spaces/KEY/pages/123/Title` or `.../viewpage.action?pageId=123`
+fn parse_page_url(url: &str) -> Result<PageRef> {
+    let url = url.trim().trim_end_matches('/');
+    let id = if let Some((_, query)) = url.split_once("pageId=") {
+        query.split('&').next().unwrap_or_default()
+    } else if let Some((_, rest)) = url.split_once("/pages/") {
+        rest.split(['/', '?', '#']).next().unwrap_or_default()
+    } else {
+        ""
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        bail!("Cannot find a page id in {url}; copy the link from the page's address bar");
+    }
+    let base = ["/spaces/", "/pages/", "/display/"]
+        .iter()
+        .filter_map(|marker| url.find(marker))
+        .min()
+        .map(|end| &url[..end])
+        .context("Not a Confluence page URL")?;
+    Ok(PageRef {
+        base: base.to_string(),
+        id: id.to_string(),
+    })
+}
+
+/// Authorization header: basic auth for Atlassian Cloud (email + API token),
+/// a bearer personal access token otherwise
+fn authorization(config: &Config) -> Result<String> {
+    let Some(token) = config.confluence_token.as_deref() else {
+        bail!(
+            "--post-to needs a Confluence API token: agentexport config set confluence_token <TOKEN>"
+        );
+    };
+    Ok(match config.confluence_email.as_deref() {
+        Some(email) => format!("Basic {}", STANDARD.encode(format!("{email}:{token}"))),
+        None => format!("Bearer {token}"),
+    })
+}
+
+fn confluence_api(method: &str, url: &str, auth: &str, body: Option<&Value>) -> Result<Value> {
+    let request = ureq::request(method, url)
+        .set("Authorization", auth)
+        .set("Accept", "application/json");
+    let response = match body {
+        Some(body) => request.send_json(body),
+        None => request.call(),
+    };
+    match response {
+        Ok(response) => response
+            .into_json()
+            .context("Failed to parse Confluence response"),
+        Err(ureq::Error::Status(status, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            bail!("Confluence returned {status}: {}", text.trim())
+        }
+        Err(err) => Err(err).context("Failed to reach Confluence"),
+    }
+}
+
+/// Create the payload as a child page of `parent_url`, returning the new page's URL
+pub fn post_page(payload: &SharePayload, title: &str, parent_url: &str) -> Result<String> {
+    let parent = parse_page_url(parent_url)?;
+    let auth = authorization(&Config::load()?)?;
+    let api = format!(
+        "{}/rest/api/content",
+        test_mode::endpoints().unwrap_or(parent.base)
+    );
+
+    let page = confluence_api(
+        "GET",
+        &format!("{api}/{}?expand=space", parent.id),
+        &auth,
+        None,
+    )?;
+    let space = page
+        .pointer("/space/key")
+        .and_then(Value::as_str)
+        .context("Missing space in Confluence page response")?;
+
+    let body = serde_json::json!({
+        "type": "page",
+        "title": title,
+        "space": { "key": space },
+        "ancestors": [{ "id": parent.id }],
+        "body": {
+            "storage": {
+                "value": render_storage(payload),
+                "representation": "storage"
+            }
+        }
+    });
+    let created = confluence_api("POST", &api, &auth, Some(&body))?;
+    let link = |name: &str| {
+        created
+            .pointer(&format!("/_links/{name}"))
+            .and_then(Value::as_str)
+    };
+    match (link("base"), link("webui")) {
+        (Some(base), Some(webui)) => Ok(format!("{base}{webui}")),
+        _ => {
+            let id = created
+                .get("id")
+                .and_then(Value::as_str)
+                .context("Missing id in Confluence response")?;
+            Ok(format!("{api}/{id}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mode::TEST_ENDPOINTS_ENV;
+    use crate::test_utils::{EnvGuard, env_lock, serve};
+
+    #[test]
+    fn storage_format_uses_macros_and_escapes_text() {
+        let payload: SharePayload = serde_json::from_str(
+            r#"{"tool":"Codex","shared_at":"Jan 1","messages":[
+                {"role":"user","content":"Fix <b>\n\nplease"},
+                {"role":"thinking","content":"hmm"},
+                {"role":"tool","content":"cat x ]]> y","raw":"out","raw_label":"Result"}]}"#,
+        )
+        .unwrap();
+        let storage = render_storage(&payload);
+        assert!(storage.contains("<p>Fix &lt;b&gt;</p><p>please</p>"));
+        assert!(storage.contains(r#"<ac:structured-macro ac:name="expand"><ac:parameter ac:name="title">Thinking</ac:parameter>"#));
+        assert!(storage.contains("<![CDATA[cat x ]]]]><![CDATA[> y]]>"));
+        assert!(storage.contains(r#"<ac:parameter ac:name="title">Result</ac:parameter>"#));
+        assert!(!storage.contains("<br>") && !storage.contains("<hr>"));
+    }
+
+    #[test]
+    fn page_urls_parse() {
+        assert_eq!(
+            parse_page_url("https://acme.atlassian.net/wiki/spaces/ENG/pages/123/Agent+Logs")
+                .unwrap(),
+            PageRef {
+                base: "https://acme.atlassian.net/wiki".to_string(),
+                id: "123".to_string()
+            }
+        );
+        assert_eq!(
+            parse_page_url("https://wiki.acme.dev/pages/viewpage.action?pageId=77&x=1")
+                .unwrap()
+                .base,
+            "https://wiki.acme.dev"
+        );
+        assert!(parse_page_url("https://acme.atlassian.net/wiki/spaces/ENG/overview").is_err());
+    }
+
+    #[test]
+    fn confluence_post_creates_child_page() {
+        let _lock = env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", home.path().to_str().unwrap());
+        crate::config::Config {
+            confluence_email: Some("me@acme.dev".to_string()),
+            confluence_token: Some("tok".to_string()),
+            ..Default::default()
+        }
+        .save()
+        .unwrap();
+        let (base, server) = serve(vec![
+            br#"{"id":"123","space":{"key":"ENG"}}"#.to_vec(),
+            br#"{"id":"456","_links":{"base":"https://acme.atlassian.net/wiki","webui":"/spaces/ENG/pages/456"}}"#.to_vec(),
+        ]);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let payload: crate::transcript::SharePayload = serde_json::from_str(
+            r#"{"tool":"Codex","shared_at":"","messages":[{"role":"user","content":"hi"}]}"#,
+        )
+        .unwrap();
+        let url = post_page(
+            &payload,
+            "Agent log",
+            "https://acme.atlassian.net/wiki/spaces/ENG/pages/123/Agent+Logs",
+        )
+        .unwrap();
+        assert_eq!(url, "https://acme.atlassian.net/wiki/spaces/ENG/pages/456");
+
+        let requests = server.join().unwrap();
+        assert!(
+            requests[0]
+                .0
+                .starts_with("GET /rest/api/content/123?expand=space ")
+        );
+        assert!(requests[1].0.starts_with("POST /rest/api/content "));
+        assert!(requests[1].0.contains("Basic bWVAYWNtZS5kZXY6dG9r"));
+        let body: serde_json::Value = serde_json::from_str(&requests[1].1).unwrap();
+        assert_eq!(body["space"]["key"], "ENG");
+        assert_eq!(body["ancestors"][0]["id"], "123");
+        assert_eq!(body["body"]["storage"]["representation"], "storage");
+    }
+}
@@ -2,51 +2,166 @@ use aes_gcm::{
     Aes256Gcm, Nonce,
     aead::{Aead, KeyInit},
 };
-use anyhow::{Context, Result};
+use agentexport_protocol::{IV_LEN, KEY_LEN, PASSPHRASE_ITERATIONS, PASSPHRASE_PREFIX};
+use anyhow::{Context, Result, bail};
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
-use flate2::{Compression, write::GzEncoder};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use rand::RngCore;
-use std::io::Write;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
 
 /// Result of encrypting content
 pub struct EncryptionResult {
     /// IV (12 bytes) || ciphertext (includes auth tag)
     pub blob: Vec<u8>,
     /// 32-byte key, base64url encoded for URL fragment
+    /// (`pw.<salt>` when the key comes from a passphrase)
     pub key_b64: String,
 }
 
 /// Compress and encrypt HTML content with AES-256-GCM
-/// Returns blob (IV + ciphertext) and base64url-encoded key
-pub fn encrypt_html(html: &str) -> Result<EncryptionResult> {
-    // Compress with gzip
-    let compressed = gzip_compress(html.as_bytes())?;
+/// Returns blob (IV + ciphertext) and base64url-encoded key.
+/// With a passphrase, the key is derived from it and a random salt that goes
+/// in the fragment instead, so opening the share needs both link and passphrase.
+pub fn encrypt_html(html: &str, passphrase: Option<&str>) -> Result<EncryptionResult> {
+    if let Some(passphrase) = passphrase {
+        return encrypt_with_passphrase(html, passphrase, PASSPHRASE_ITERATIONS);
+    }
 
     // Generate random 256-bit key
-    let mut key_bytes = [0u8; 32];
+    let mut key_bytes = [0u8; KEY_LEN];
     rand::thread_rng().fill_bytes(&mut key_bytes);
 
+    let blob = encrypt_with_key(html, &key_bytes)?;
+
+    // Encode key as base64url (no padding)
+    let key_b64 = URL_SAFE_NO_PAD.encode(key_bytes);
+
+    Ok(EncryptionResult { blob, key_b64 })
+}
+
+fn encrypt_with_passphrase(
+    content: &str,
+    passphrase: &str,
+    iterations: u32,
+) -> Result<EncryptionResult> {
+    if passphrase.is_empty() {
+        bail!("Passphrase is empty");
+    }
+    let mut salt = [0u8; KEY_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = pbkdf2_sha256(passphrase.as_bytes(), &salt, iterations);
+    Ok(EncryptionResult {
+        blob: encrypt_with_key(content, &key)?,
+        key_b64: format!("{PASSPHRASE_PREFIX}{}", URL_SAFE_NO_PAD.encode(salt)),
+    })
+}
+
+/// Whether a share URL fragment is a passphrase salt rather than a key
+pub fn is_passphrase_fragment(fragment: &str) -> bool {
+    fragment.starts_with(PASSPHRASE_PREFIX)
+}
+
+/// PBKDF2-HMAC-SHA256 producing one 32-byte block, the same derivation as
+/// WebCrypto's `deriveBits({ name: "PBKDF2", hash: "SHA-256" }, key, 256)`
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; KEY_LEN] {
+    const BLOCK_LEN: usize = 64;
+    let mut block = [0u8; BLOCK_LEN];
+    if password.len() > BLOCK_LEN {
+        block[..KEY_LEN].copy_from_slice(&Sha256::digest(password));
+    } else {
+        block[..password.len()].copy_from_slice(password);
+    }
+    // Hash the padded keys once and clone the states for every round
+    let inner = Sha256::new().chain_update(block.map(|b| b ^ 0x36));
+    let outer = Sha256::new().chain_update(block.map(|b| b ^ 0x5c));
+    let hmac = |parts: &[&[u8]]| -> [u8; KEY_LEN] {
+        let mut hash = inner.clone();
+        for part in parts {
+            hash.update(part);
+        }
+        outer
+            .clone()
+            .chain_update(hash.finalize())
+            .finalize()
+            .into()
+    };
+
+    let mut round = hmac(&[salt, &1u32.to_be_bytes()]);
+    let mut key = round;
+    for _ in 1..iterations {
+        round = hmac(&[&round]);
+        for (k, r) in key.iter_mut().zip(round) {
+            *k ^= r;
+        }
+    }
+    key
+}
+
+/// Compress and encrypt content with a caller-provided 256-bit key
+/// Returns IV (12 bytes) || ciphertext
+pub fn encrypt_with_key(content: &str, key_bytes: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
+    // Compress with gzip
+    let compressed = gzip_compress(content.as_bytes())?;
+
     // Generate random 96-bit IV/nonce
-    let mut iv_bytes = [0u8; 12];
+    let mut iv_bytes = [0u8; IV_LEN];
     rand::thread_rng().fill_bytes(&mut iv_bytes);
     let nonce = Nonce::from_slice(&iv_bytes);
 
     // Create cipher and encrypt
-    let cipher = Aes256Gcm::new_from_slice(&key_bytes).context("Failed to create cipher")?;
+    let cipher = Aes256Gcm::new_from_slice(key_bytes).context("Failed to create cipher")?;
 
     let ciphertext = cipher
         .encrypt(nonce, compressed.as_slice())
         .map_err(|e| anyhow::anyhow!("Encryption failed: {e}"))?;
 
     // Combine IV + ciphertext
-    let mut blob = Vec::with_capacity(12 + ciphertext.len());
+    let mut blob = Vec::with_capacity(IV_LEN + ciphertext.len());
     blob.extend_from_slice(&iv_bytes);
     blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
 
-    // Encode key as base64url (no padding)
-    let key_b64 = URL_SAFE_NO_PAD.encode(key_bytes);
+/// Decrypt and decompress a blob produced by `encrypt_with_key`
+pub fn decrypt_with_key(blob: &[u8], key_bytes: &[u8; KEY_LEN]) -> Result<String> {
+    if blob.len() < IV_LEN {
+        bail!("Encrypted blob is too short");
+    }
+    let (iv, ciphertext) = blob.split_at(IV_LEN);
+    let cipher = Aes256Gcm::new_from_slice(key_bytes).context("Failed to create cipher")?;
+    let compressed = cipher
+        .decrypt(Nonce::from_slice(iv), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Decryption failed (wrong key or corrupted data)"))?;
 
-    Ok(EncryptionResult { blob, key_b64 })
+    let mut decoder = GzDecoder::new(compressed.as_slice());
+    let mut content = String::new();
+    decoder
+        .read_to_string(&mut content)
+        .context("Failed to decompress decrypted content")?;
+    Ok(content)
+}
+
+/// Key bytes from the base64url key in a share URL fragment
+fn decode_key_b64(key_b64: &str) -> Result<[u8; KEY_LEN]> {
+    if is_passphrase_fragment(key_b64) {
+        bail!("Share is passphrase-protected; open it in the browser to enter the passphrase");
+    }
+    let key = URL_SAFE_NO_PAD
+        .decode(key_b64.trim_end_matches('='))
+        .context("Share key is not valid base64url")?;
+    key.try_into()
+        .map_err(|_| anyhow::anyhow!("Share key must be {KEY_LEN} bytes"))
+}
+
+/// Decrypt a blob with the base64url key from a share URL fragment
+pub fn decrypt_with_key_b64(blob: &[u8], key_b64: &str) -> Result<String> {
+    decrypt_with_key(blob, &decode_key_b64(key_b64)?)
+}
+
+/// Encrypt content with an existing share's key, so its link opens the result
+pub fn encrypt_with_key_b64(content: &str, key_b64: &str) -> Result<Vec<u8>> {
+    encrypt_with_key(content, &decode_key_b64(key_b64)?)
 }
 
 fn gzip_compress(data: &[u8]) -> Result<Vec<u8>> {
@@ -59,13 +174,11 @@ fn gzip_compress(data: &[u8]) -> Result<Vec<u8>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use flate2::read::GzDecoder;
-    use std::io::Read;
 
     #[test]
     fn test_encrypt_produces_valid_blob() {
         let html = "<html><body>Hello, World!</body></html>";
-        let result = encrypt_html(html).unwrap();
+        let result = encrypt_html(html, None).unwrap();
 
         // Verify blob structure: 12 bytes IV + ciphertext
         assert!(result.blob.len() > 12);
@@ -78,7 +191,7 @@ mod tests {
     #[test]
     fn test_encrypt_decrypt_roundtrip() {
         let html = "<html><body>Hello, World!</body></html>";
-        let result = encrypt_html(html).unwrap();
+        let result = encrypt_html(html, None).unwrap();
 
         // Decode key
         let key_bytes = URL_SAFE_NO_PAD.decode(&result.key_b64).unwrap();
@@ -104,9 +217,161 @@ mod tests {
     fn test_compression_reduces_size() {
         // Repetitive content compresses well
         let html = "<html><body>".to_string() + &"Hello ".repeat(1000) + "</body></html>";
-        let result = encrypt_html(&html).unwrap();
+        let result = encrypt_html(&html, None).unwrap();
 
         // Blob should be smaller than original (minus some overhead)
         assert!(result.blob.len() < html.len());
     }
+
+    #[test]
+    fn test_decrypt_with_key_roundtrip() {
+        let key = [7u8; 32];
+        let blob = encrypt_with_key("{\"title\":\"hi\"}", &key).unwrap();
+        assert_eq!(decrypt_with_key(&blob, &key).unwrap(), "{\"title\":\"hi\"}");
+        assert!(decrypt_with_key(&blob, &[8u8; 32]).is_err());
+
+        // Re-encrypting with a share's fragment keeps its link working
+        let share = encrypt_html("old", None).unwrap();
+        let updated = encrypt_with_key_b64("new", &share.key_b64).unwrap();
+        assert_eq!(
+            decrypt_with_key_b64(&updated, &share.key_b64).unwrap(),
+            "new"
+        );
+        assert!(encrypt_with_key_b64("new", "pw.c2FsdA").is_err());
+    }
+
+    /// Native mirror of the browser viewer: pad and translate the base64url
+    /// fragment, reject short keys and blobs, then AES-GCM decrypt and gunzip
+    fn viewer_decrypt(blob: &[u8], fragment: &str) -> Result<String> {
+        let mut b64 = fragment.replace('-', "+").replace('_', "/");
+        let pad = b64.len() % 4;
+        if pad > 0 {
+            b64.push_str(&"=".repeat(4 - pad));
+        }
+        let key = base64::engine::general_purpose::STANDARD.decode(b64)?;
+        if key.len() != KEY_LEN {
+            bail!("Invalid key length");
+        }
+        if blob.len() <= IV_LEN {
+            bail!("Invalid blob");
+        }
+        let (iv, ciphertext) = blob.split_at(IV_LEN);
+        let compressed = Aes256Gcm::new_from_slice(&key)?
+            .decrypt(Nonce::from_slice(iv), ciphertext)
+            .map_err(|_| anyhow::anyhow!("decrypt failed"))?;
+        let mut json = String::new();
+        GzDecoder::new(compressed.as_slice()).read_to_string(&mut json)?;
+        Ok(json)
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_viewer_decrypts_any_payload(payload in ".*") {
+            let result = encrypt_html(&payload, None).unwrap();
+            proptest::prop_assert_eq!(viewer_decrypt(&result.blob, &result.key_b64).unwrap(), payload);
+        }
+
+        #[test]
+        fn prop_fixed_key_roundtrip(payload in proptest::collection::vec(proptest::num::u8::ANY, 0..4096), key in proptest::array::uniform32(proptest::num::u8::ANY)) {
+            // Arbitrary bytes, made valid UTF-8 the way the viewer's TextDecoder would see them
+            let payload = String::from_utf8_lossy(&payload).into_owned();
+            let blob = encrypt_with_key(&payload, &key).unwrap();
+            proptest::prop_assert_eq!(decrypt_with_key(&blob, &key).unwrap(), payload.clone());
+            let fragment = URL_SAFE_NO_PAD.encode(key);
+            proptest::prop_assert_eq!(viewer_decrypt(&blob, &fragment).unwrap(), payload);
+        }
+
+        #[test]
+        fn prop_tampered_blob_is_rejected(payload in ".{0,256}", index in proptest::num::usize::ANY, bit in 0u8..8) {
+            let result = encrypt_html(&payload, None).unwrap();
+            let mut blob = result.blob;
+            // Flipping any bit, IV or ciphertext, must fail authentication
+            let index = index % blob.len();
+            blob[index] ^= 1 << bit;
+            proptest::prop_assert!(viewer_decrypt(&blob, &result.key_b64).is_err());
+        }
+
+        #[test]
+        fn prop_truncated_blob_is_rejected(payload in ".{0,64}", keep in 0usize..64) {
+            let result = encrypt_html(&payload, None).unwrap();
+            let keep = keep.min(result.blob.len() - 1);
+            proptest::prop_assert!(viewer_decrypt(&result.blob[..keep], &result.key_b64).is_err());
+            proptest::prop_assert!(decrypt_with_key(&result.blob[..keep], &[0u8; KEY_LEN]).is_err());
+        }
+    }
+
+    #[test]
+    fn empty_payload_roundtrips() {
+        let result = encrypt_html("", None).unwrap();
+        assert!(result.blob.len() > IV_LEN);
+        assert_eq!(viewer_decrypt(&result.blob, &result.key_b64).unwrap(), "");
+    }
+
+    #[test]
+    fn each_encryption_uses_a_fresh_iv() {
+        let key = [3u8; KEY_LEN];
+        let a = encrypt_with_key("same", &key).unwrap();
+        let b = encrypt_with_key("same", &key).unwrap();
+        assert_ne!(a[..IV_LEN], b[..IV_LEN]);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn short_or_padded_fragments_match_the_viewer() {
+        let result = encrypt_html("{}", None).unwrap();
+        let padded = format!("{}=", result.key_b64);
+        assert_eq!(viewer_decrypt(&result.blob, &padded).unwrap(), "{}");
+        assert!(viewer_decrypt(&result.blob, &result.key_b64[..20]).is_err());
+    }
+
+    #[test]
+    fn pbkdf2_matches_reference_vectors() {
+        // RFC 7914 section 11 and the widely used PBKDF2-HMAC-SHA256 vectors
+        let hex = |key: [u8; KEY_LEN]| hex::encode(key);
+        assert_eq!(
+            hex(pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex(pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+        assert_eq!(
+            hex(pbkdf2_sha256(b"passwd", b"salt", 1)),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+    }
+
+    #[test]
+    fn passphrase_shares_need_link_and_passphrase() {
+        let result = encrypt_with_passphrase("{}", "correct horse", 10).unwrap();
+        assert!(is_passphrase_fragment(&result.key_b64));
+        // What the viewer does after prompting: derive from the fragment's salt
+        let salt = URL_SAFE_NO_PAD
+            .decode(result.key_b64.strip_prefix(PASSPHRASE_PREFIX).unwrap())
+            .unwrap();
+        let key = pbkdf2_sha256(b"correct horse", &salt, 10);
+        assert_eq!(decrypt_with_key(&result.blob, &key).unwrap(), "{}");
+        let wrong = pbkdf2_sha256(b"correct horse!", &salt, 10);
+        assert!(decrypt_with_key(&result.blob, &wrong).is_err());
+
+        let err = decrypt_with_key_b64(&result.blob, &result.key_b64).unwrap_err();
+        assert!(err.to_string().contains("passphrase-protected"));
+        assert!(encrypt_with_passphrase("{}", "", 10).is_err());
+    }
+
+    #[test]
+    #[ignore = "encrypts ~10MB; slow in debug builds (run with --ignored)"]
+    fn max_size_blob_roundtrips() {
+        // Incompressible text just under the server's upload limit
+        let mut bytes = vec![0u8; agentexport_protocol::MAX_BLOB_SIZE / 2 - 1024];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let payload = hex::encode(bytes);
+        let result = encrypt_html(&payload, None).unwrap();
+        assert!(result.blob.len() <= agentexport_protocol::MAX_BLOB_SIZE);
+        assert_eq!(
+            viewer_decrypt(&result.blob, &result.key_b64).unwrap(),
+            payload
+        );
+    }
 }
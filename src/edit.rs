@@ -0,0 +1,91 @@
+//! `publish --edit`: hand the share payload to the user's editor before upload.
+//!
+//! The payload is written as pretty JSON, so individual messages can be cut
+//! out as whole objects. The edited file is parsed back into a payload, and
+//! emptying it cancels the publish.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::process::Command;
+
+use crate::transcript::SharePayload;
+
+/// `$VISUAL`, then `$EDITOR`, then `vi`
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Parse an edited payload, rejecting anything that can't be shared
+fn parse_edited(text: &str) -> Result<SharePayload> {
+    if text.trim().is_empty() {
+        bail!("Edited payload is empty; publish cancelled");
+    }
+    let payload: SharePayload =
+        serde_json::from_str(text).context("Edited payload is not a valid share payload")?;
+    if payload.messages.is_empty() {
+        bail!("Edited payload has no messages left");
+    }
+    Ok(payload)
+}
+
+/// Open `payload` in the user's editor and return the edited version
+pub fn edit_payload(payload: &SharePayload) -> Result<SharePayload> {
+    let dir = tempfile::tempdir().context("Failed to create temp dir for editing")?;
+    let path = dir.path().join("agentexport-share.json");
+    fs::write(&path, serde_json::to_string_pretty(payload)? + "\n")
+        .context("Failed to write payload for editing")?;
+
+    // Through the shell, so editors configured with arguments (`code -w`) work
+    let editor = editor_command();
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run editor {editor:?}"))?;
+    if !status.success() {
+        bail!("Editor exited with {status}; publish cancelled");
+    }
+    parse_edited(&fs::read_to_string(&path).context("Failed to read edited payload")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+
+    #[test]
+    fn edited_payload_is_validated() {
+        let payload: SharePayload = serde_json::from_str(
+            r#"{"tool":"Codex","shared_at":"","messages":[
+                {"role":"user","content":"hi"},{"role":"assistant","content":"oops"}]}"#,
+        )
+        .unwrap();
+        let mut edited = serde_json::to_value(&payload).unwrap();
+        edited["messages"].as_array_mut().unwrap().pop();
+        let parsed = parse_edited(&edited.to_string()).unwrap();
+        assert_eq!(parsed.messages.len(), 1);
+
+        assert!(
+            parse_edited("  \n")
+                .unwrap_err()
+                .to_string()
+                .contains("cancelled")
+        );
+        assert!(parse_edited(r#"{"tool":"Codex","messages":[{"role":"user"}]}"#).is_err());
+        assert!(parse_edited(r#"{"tool":"Codex","shared_at":"","messages":[]}"#).is_err());
+
+        let _lock = env_lock();
+        let _visual = EnvGuard::set("VISUAL", "");
+        let _editor = EnvGuard::set("EDITOR", "true");
+        let unchanged = edit_payload(&payload).unwrap();
+        assert_eq!(unchanged.messages.len(), 2);
+        let _editor = EnvGuard::set("EDITOR", "false");
+        assert!(edit_payload(&payload).is_err());
+    }
+}
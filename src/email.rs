@@ -0,0 +1,173 @@
+//! Email rendering: package a share payload as a self-contained `.eml` message.
+//!
+//! The message is `multipart/alternative` with the gist markdown as the plain
+//! text part and an HTML part whose styles are all inline, since mail clients
+//! drop `<style>` blocks and never run scripts. Marked `X-Unsent` so Outlook
+//! opens it as a draft ready to address and send.
+
+use anyhow::{Context, Result};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use maud::{Markup, html};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc2822;
+
+use crate::gist::render_gist_markdown;
+use crate::transcript::SharePayload;
+
+const BODY_STYLE: &str =
+    "font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;max-width:760px;color:#222;";
+const LABEL_STYLE: &str =
+    "font-size:12px;font-weight:600;text-transform:uppercase;letter-spacing:0.04em;margin:0 0 4px;";
+const TEXT_STYLE: &str = "white-space:pre-wrap;word-wrap:break-word;font-family:inherit;font-size:14px;line-height:1.5;margin:0;";
+const CODE_STYLE: &str = "white-space:pre-wrap;word-wrap:break-word;font-family:Menlo,Consolas,monospace;font-size:12px;line-height:1.4;margin:0;color:#444;background:#f6f8fa;padding:8px;";
+
+/// Label color and whether the content is shown as code, by role
+fn role_style(role: &str) -> (&'static str, bool) {
+    match role {
+        "user" => ("#1a7f37", false),
+        "assistant" => ("#0969da", false),
+        "tool" | "command" => ("#6e7781", true),
+        "thinking" => ("#8250df", false),
+        _ => ("#6e7781", false),
+    }
+}
+
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Title of the document, falling back when the payload has none
+fn document_title(payload: &SharePayload) -> &str {
+    payload.title.as_deref().unwrap_or("Agent transcript")
+}
+
+/// The transcript as a standalone HTML document with inline styles
+pub(crate) fn render_html(payload: &SharePayload) -> String {
+    let title = document_title(payload);
+    let markup: Markup = html! {
+        (maud::DOCTYPE)
+        html {
+            head {
+                meta charset="utf-8";
+                title { (title) }
+            }
+            body style=(BODY_STYLE) {
+                h1 style="font-size:20px;margin:0 0 4px;" { (title) }
+                p style="color:#666;font-size:13px;margin:0 0 16px;" {
+                    (payload.tool)
+                    @if let Some(model) = &payload.model { " · " (model) }
+                    @if !payload.shared_at.is_empty() { " · " (payload.shared_at) }
+                }
+                @for message in &payload.messages {
+                    @let (color, code) = role_style(&message.role);
+                    div style="border-top:1px solid #e5e5e5;padding:10px 0;" {
+                        p style=(format!("{LABEL_STYLE}color:{color};")) { (role_label(&message.role)) }
+                        pre style=(if code { CODE_STYLE } else { TEXT_STYLE }) { (message.content) }
+                    }
+                }
+                p style="color:#999;font-size:12px;margin-top:24px;" { "Exported with agentexport" }
+            }
+        }
+    };
+    markup.into_string()
+}
+
+/// Base64 wrapped at 76 characters, as MIME requires
+fn base64_lines(data: &str) -> String {
+    let encoded = STANDARD.encode(data.as_bytes());
+    encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).expect("base64 is ASCII"))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Header value, as an RFC 2047 encoded word when it isn't plain ASCII
+fn header_text(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value.as_bytes()))
+    }
+}
+
+/// Render a share payload as an RFC 822 message with text and HTML parts
+pub fn render_eml(payload: &SharePayload) -> Result<String> {
+    let json = serde_json::to_string(payload)?;
+    let text = render_gist_markdown(&json).context("Failed to render the plain text part")?;
+    let title = document_title(payload);
+    let html = render_html(payload);
+    let boundary = format!(
+        "agentexport-{}",
+        &hex::encode(Sha256::digest(json.as_bytes()))[..24]
+    );
+    let date = OffsetDateTime::now_utc()
+        .format(&Rfc2822)
+        .context("Failed to format the message date")?;
+
+    let mut eml = String::new();
+    for (name, value) in [
+        ("MIME-Version", "1.0".to_string()),
+        ("Date", date),
+        ("Subject", header_text(title)),
+        ("X-Unsent", "1".to_string()),
+        (
+            "Content-Type",
+            format!("multipart/alternative; boundary=\"{boundary}\""),
+        ),
+    ] {
+        eml.push_str(&format!("{name}: {value}\r\n"));
+    }
+    eml.push_str("\r\n");
+    for (content_type, body) in [("text/plain", text), ("text/html", html)] {
+        eml.push_str(&format!(
+            "--{boundary}\r\nContent-Type: {content_type}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            base64_lines(&body)
+        ));
+    }
+    eml.push_str(&format!("--{boundary}--\r\n"));
+    Ok(eml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eml_has_text_and_inline_styled_html_parts() {
+        let payload: SharePayload = serde_json::from_str(
+            r#"{"tool":"Claude Code","title":"Fix <parser> ✓","shared_at":"Jan 1","messages":[{"role":"user","content":"hi"},{"role":"tool","content":"Read a.rs"}]}"#,
+        )
+        .unwrap();
+        let eml = render_eml(&payload).unwrap();
+        let (headers, body) = eml.split_once("\r\n\r\n").unwrap();
+        assert!(headers.contains("Subject: =?UTF-8?B?"));
+        assert!(headers.contains("X-Unsent: 1"));
+        let boundary = headers
+            .split("boundary=\"")
+            .nth(1)
+            .unwrap()
+            .trim_end_matches('"');
+        assert!(body.ends_with(&format!("--{boundary}--\r\n")));
+        assert!(body.lines().all(|line| line.len() <= 998));
+
+        let html_part = body.split("Content-Type: text/html").nth(1).unwrap();
+        let encoded: String = html_part
+            .split("\r\n\r\n")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .take_while(|line| !line.starts_with("--"))
+            .collect();
+        let html = String::from_utf8(STANDARD.decode(encoded).unwrap()).unwrap();
+        assert!(html.contains("Fix &lt;parser&gt; ✓"));
+        assert!(html.contains(CODE_STYLE) && !html.contains("<style"));
+    }
+}
@@ -0,0 +1,114 @@
+//! Local automation hooks driven by the shares store.
+//!
+//! `agentexport emit --on publish --exec <cmd>` polls `shares.json` and runs
+//! each command once per newly recorded share, so publish itself never has to
+//! know about the automations hanging off it.
+
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::shares::{self, Share};
+
+/// Store event a hook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmitEvent {
+    /// A new share was recorded
+    Publish,
+}
+
+/// Shares whose ids are not in `seen`, oldest first
+pub fn new_shares<'a>(seen: &HashSet<String>, shares: &'a [Share]) -> Vec<&'a Share> {
+    let mut fresh: Vec<&Share> = shares.iter().filter(|s| !seen.contains(&s.id)).collect();
+    fresh.sort_by_key(|s| s.created_at);
+    fresh
+}
+
+/// Run `cmd` through the shell with the share exposed as `AGENTEXPORT_*`
+/// environment variables and as JSON on stdin
+pub fn run_hook(cmd: &str, event: EmitEvent, share: &Share) -> Result<()> {
+    let event = match event {
+        EmitEvent::Publish => "publish",
+    };
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .env("AGENTEXPORT_EVENT", event)
+        .env("AGENTEXPORT_SHARE_ID", &share.id)
+        .env("AGENTEXPORT_URL", share.url())
+        .env("AGENTEXPORT_TOOL", &share.tool)
+        .env("AGENTEXPORT_TRANSCRIPT", &share.transcript_path)
+        .env("AGENTEXPORT_TAGS", share.tags.join(","))
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run hook: {cmd}"))?;
+
+    let json = serde_json::to_string(share)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Hooks that ignore stdin may exit before reading it
+        let _ = stdin.write_all(json.as_bytes());
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("hook exited with {status}: {cmd}");
+    }
+    Ok(())
+}
+
+/// Watch the shares store and run every command for each new share.
+/// Shares that exist when the watcher starts are not replayed.
+pub fn watch(event: EmitEvent, commands: &[String], interval: Duration) -> Result<()> {
+    let mut seen: HashSet<String> = shares::load_shares()?.into_iter().map(|s| s.id).collect();
+    loop {
+        std::thread::sleep(interval);
+        let current = match shares::load_shares() {
+            Ok(current) => current,
+            // shares.json may be mid-write; try again next tick
+            Err(_) => continue,
+        };
+        for share in new_shares(&seen, &current) {
+            for cmd in commands {
+                if let Err(err) = run_hook(cmd, event, share) {
+                    eprintln!("warning: {err}");
+                }
+            }
+        }
+        seen = current.into_iter().map(|s| s.id).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shares::tests::make_test_share;
+    use tempfile::TempDir;
+
+    #[test]
+    fn new_shares_skips_seen_ids() {
+        let shares = vec![make_test_share("old"), make_test_share("new")];
+        let seen: HashSet<String> = ["old".to_string()].into_iter().collect();
+        let fresh = new_shares(&seen, &shares);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].id, "new");
+    }
+
+    #[test]
+    fn run_hook_exposes_share_to_command() {
+        let tmp = TempDir::new().unwrap();
+        let out = tmp.path().join("out.txt");
+        let share = make_test_share("abc123");
+        let cmd = format!(
+            "printf '%s %s ' \"$AGENTEXPORT_EVENT\" \"$AGENTEXPORT_SHARE_ID\" > '{0}' && cat >> '{0}'",
+            out.display()
+        );
+        run_hook(&cmd, EmitEvent::Publish, &share).unwrap();
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert!(written.starts_with("publish abc123 {"));
+        assert!(written.contains("\"id\":\"abc123\""));
+
+        assert!(run_hook("exit 3", EmitEvent::Publish, &share).is_err());
+    }
+}
@@ -0,0 +1,537 @@
+//! Local export of sessions (`agentexport export`). Nothing is uploaded.
+//!
+//! A single session can be written as markdown, standalone HTML, payload JSON,
+//! an email, or Confluence storage format, and can be posted straight to
+//! Confluence as a new page. In batch mode each session is written as markdown and payload JSON under
+//! `<out>/<repo>/<date>/`, with an `index.md` linking everything exported so
+//! far. A manifest in the output directory records what was written, so
+//! `--incremental` runs only redo sessions whose transcript changed. Secrets
+//! are masked as for `publish`, unless `--no-redact`.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use time::OffsetDateTime;
+
+use crate::atomic;
+use crate::confluence;
+use crate::email;
+use crate::gist::render_gist_markdown;
+use crate::publish::{create_share_payload, format_generated_at_nice};
+use crate::redact::redact_share_payload;
+use crate::report::{parse_since, recent_transcripts};
+use crate::transcript::{
+    DiscoverOptions, Discovered, Tool, TranscriptSource, build_share_payload, claude_projects_dir,
+    codex_sessions_dir, extract_transcript_meta, parse_transcript,
+};
+
+/// Manifest of exported sessions, kept in the output directory
+const MANIFEST_FILE: &str = ".agentexport-export.json";
+
+/// Longest title slug used in file names
+const MAX_SLUG_CHARS: usize = 48;
+
+/// Output format for a single-session export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// The gist markdown rendering
+    Markdown,
+    /// A standalone HTML page with inline styles
+    Html,
+    /// The share payload JSON
+    Json,
+    /// An email with HTML and plain text parts
+    Eml,
+    /// Confluence storage format (XHTML with Confluence macros)
+    Confluence,
+}
+
+impl ExportFormat {
+    /// Format implied by the output file extension, defaulting to markdown
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("html" | "htm") => ExportFormat::Html,
+            Some("json") => ExportFormat::Json,
+            Some("eml") => ExportFormat::Eml,
+            Some("xhtml") => ExportFormat::Confluence,
+            _ => ExportFormat::Markdown,
+        }
+    }
+}
+
+/// Options for a single-session `agentexport export`
+#[derive(Debug, Clone)]
+pub struct SessionExportOptions {
+    pub tool: Tool,
+    pub transcript: Option<PathBuf>,
+    pub max_age_minutes: u64,
+    pub title: Option<String>,
+    /// Inferred from the `out` extension when unset
+    pub format: Option<ExportFormat>,
+    pub out: Option<PathBuf>,
+    /// Confluence page to create the export under, as a child page
+    pub post_to: Option<String>,
+    /// Secret redaction patterns (None: `--no-redact`)
+    pub redact_patterns: Option<Vec<String>>,
+    /// External secret scanner run along with redaction
+    pub secret_scanner: Option<String>,
+}
+
+/// Outcome of a single-session `agentexport export`
+#[derive(Debug, Clone)]
+pub struct SessionExport {
+    /// Format of the file written to `path`
+    pub format: Option<ExportFormat>,
+    pub path: Option<String>,
+    /// URL of the Confluence page created with `post_to`
+    pub page_url: Option<String>,
+    /// Secrets masked
+    pub redacted: usize,
+}
+
+/// Options for a batch `agentexport export`
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub out: PathBuf,
+    /// Only sessions touched within this lookback (e.g. "30d"); all sessions when unset
+    pub since: Option<String>,
+    /// Skip sessions whose transcript hasn't changed since the last export
+    pub incremental: bool,
+    /// Secret redaction patterns (None: `--no-redact`)
+    pub redact_patterns: Option<Vec<String>>,
+    /// External secret scanner run along with redaction
+    pub secret_scanner: Option<String>,
+}
+
+/// Outcome of `agentexport export`
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportResult {
+    pub exported: usize,
+    pub unchanged: usize,
+    /// Secrets masked across the exported sessions
+    pub redacted: usize,
+    pub index_path: String,
+}
+
+/// One exported session, keyed by transcript path in the manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportedSession {
+    /// Transcript mtime (unix seconds) when it was exported
+    modified: u64,
+    tool: String,
+    title: String,
+    repo: String,
+    /// YYYY-MM-DD the session started
+    date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    messages: usize,
+    /// Paths relative to the output directory
+    markdown: String,
+    json: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    sessions: BTreeMap<String, ExportedSession>,
+}
+
+impl Manifest {
+    fn load(out: &Path) -> Result<Self> {
+        let path = out.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).context("Failed to read export manifest")?;
+        serde_json::from_str(&content).context("Failed to parse export manifest")
+    }
+
+    fn save(&self, out: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        atomic::write(out.join(MANIFEST_FILE), format!("{content}\n"))
+            .context("Failed to write export manifest")
+    }
+}
+
+/// Lowercase ASCII words joined by dashes, for file names
+fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for word in title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if slug.len() + word.len() + 1 > MAX_SLUG_CHARS {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    if slug.is_empty() {
+        "session".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Repo directory for a session: the last component of its working directory
+pub(crate) fn repo_name(cwd: Option<&str>) -> String {
+    cwd.and_then(|cwd| Path::new(cwd).file_name())
+        .map(|name| name.to_string_lossy().replace(['/', '\\'], "-"))
+        .filter(|name| !name.is_empty() && !name.starts_with('.'))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Render one transcript into the tree, returning its manifest entry and how
+/// many secrets were masked
+fn export_session(
+    tool: Tool,
+    transcript: &Path,
+    options: &ExportOptions,
+) -> Result<Option<(ExportedSession, usize)>> {
+    let out = options.out.as_path();
+    let mut parsed = parse_transcript(transcript)?;
+    if let Some(note) = parsed.recovery.describe() {
+        eprintln!("{}: {note}", transcript.display());
+    }
+    parsed.drop_abandoned();
+    if parsed.messages.is_empty() {
+        return Ok(None);
+    }
+    let meta = extract_transcript_meta(transcript);
+    let repo = repo_name(meta.cwd.as_deref());
+    let modified = modified_secs(transcript);
+    let started = parsed.started_at.unwrap_or_else(|| {
+        OffsetDateTime::from_unix_timestamp(modified as i64).unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    });
+    let date = started.date().to_string();
+    let messages = parsed.messages.len();
+    let mut payload = build_share_payload(
+        tool.display_name(),
+        parsed,
+        meta,
+        None,
+        None,
+        format_generated_at_nice(),
+    );
+    let redacted = redact_share_payload(
+        &mut payload,
+        options.redact_patterns.as_deref(),
+        options.secret_scanner.as_deref(),
+    )?;
+    let title = payload
+        .title
+        .clone()
+        .unwrap_or_else(|| "Untitled session".to_string());
+
+    // The path hash keeps names unique and stable when titles collide
+    let hash = hex::encode(Sha256::digest(transcript.display().to_string().as_bytes()));
+    let stem = format!("{repo}/{date}/{}-{}", slugify(&title), &hash[..8]);
+    let json = serde_json::to_string_pretty(&payload)?;
+    let markdown = render_gist_markdown(&json)?;
+    fs::create_dir_all(out.join(&repo).join(&date))?;
+    let entry = ExportedSession {
+        modified,
+        tool: payload.tool.clone(),
+        title,
+        repo,
+        date,
+        model: payload.model.clone(),
+        messages,
+        markdown: format!("{stem}.md"),
+        json: format!("{stem}.json"),
+    };
+    atomic::write(out.join(&entry.markdown), markdown)?;
+    atomic::write(out.join(&entry.json), format!("{json}\n"))?;
+    Ok(Some((entry, redacted)))
+}
+
+/// Markdown index of every exported session, grouped by repo, newest first
+fn render_index(manifest: &Manifest) -> String {
+    let mut repos: BTreeMap<&str, Vec<&ExportedSession>> = BTreeMap::new();
+    for session in manifest.sessions.values() {
+        repos.entry(&session.repo).or_default().push(session);
+    }
+    let mut md = String::from("# Agent sessions\n");
+    for (repo, mut sessions) in repos {
+        sessions.sort_by(|a, b| b.date.cmp(&a.date).then(a.title.cmp(&b.title)));
+        md.push_str(&format!("\n## {repo}\n\n"));
+        for session in sessions {
+            let title = session.title.replace(['[', ']'], "");
+            let mut details = vec![session.tool.clone()];
+            details.extend(session.model.clone());
+            details.push(format!("{} messages", session.messages));
+            md.push_str(&format!(
+                "- {} · [{title}]({}) · {}\n",
+                session.date,
+                session.markdown,
+                details.join(" · ")
+            ));
+        }
+    }
+    md
+}
+
+/// Export one session to a local file and/or a new Confluence page
+pub fn export_one(options: SessionExportOptions) -> Result<SessionExport> {
+    if options.post_to.is_some()
+        && options
+            .format
+            .is_some_and(|format| format != ExportFormat::Confluence)
+    {
+        bail!(
+            "--post-to writes Confluence storage format; drop --format or use --format confluence"
+        );
+    }
+    let Discovered {
+        path: transcript,
+        session_id,
+        thread_id,
+        warnings,
+    } = options.tool.discover(&DiscoverOptions {
+        transcript: options.transcript,
+        max_age_minutes: options.max_age_minutes,
+        ..DiscoverOptions::default()
+    })?;
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+    let mut payload = create_share_payload(
+        &options.tool,
+        &transcript,
+        session_id.as_deref(),
+        thread_id.as_deref(),
+        options.title.as_deref(),
+        None,
+        false,
+    )?;
+    // The file may be mailed or attached anywhere, and a page is posted to Confluence
+    let redacted = redact_share_payload(
+        &mut payload,
+        options.redact_patterns.as_deref(),
+        options.secret_scanner.as_deref(),
+    )?;
+
+    let mut result = SessionExport {
+        format: None,
+        path: None,
+        page_url: None,
+        redacted,
+    };
+    if let Some(out) = &options.out {
+        let format = match (options.format, &options.post_to) {
+            (Some(format), _) => format,
+            (None, Some(_)) => ExportFormat::Confluence,
+            (None, None) => ExportFormat::from_path(out),
+        };
+        let rendered = match format {
+            ExportFormat::Markdown => render_gist_markdown(&serde_json::to_string(&payload)?)?,
+            ExportFormat::Html => email::render_html(&payload),
+            ExportFormat::Json => format!("{}\n", serde_json::to_string_pretty(&payload)?),
+            ExportFormat::Eml => email::render_eml(&payload)?,
+            ExportFormat::Confluence => confluence::render_storage(&payload),
+        };
+        if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        atomic::write(out, rendered)
+            .with_context(|| format!("Failed to write {}", out.display()))?;
+        result.format = Some(format);
+        result.path = Some(out.display().to_string());
+    }
+    if let Some(parent) = &options.post_to {
+        let title = payload
+            .title
+            .clone()
+            .unwrap_or_else(|| "Agent session".to_string());
+        result.page_url = Some(confluence::post_page(&payload, &title, parent)?);
+    }
+    Ok(result)
+}
+
+/// Export every Claude and Codex session (or those touched within `since`) to `out`
+pub fn export(options: ExportOptions) -> Result<ExportResult> {
+    let cutoff = match &options.since {
+        Some(since) => SystemTime::now()
+            .checked_sub(parse_since(since)?)
+            .unwrap_or(UNIX_EPOCH),
+        None => UNIX_EPOCH,
+    };
+    fs::create_dir_all(&options.out)
+        .with_context(|| format!("Failed to create {}", options.out.display()))?;
+    let mut manifest = Manifest::load(&options.out)?;
+
+    let mut found = 0;
+    let mut exported = 0;
+    let mut unchanged = 0;
+    let mut redacted = 0;
+    for (tool, dir) in [
+        (Tool::Claude, claude_projects_dir()?),
+        (Tool::Codex, codex_sessions_dir()?),
+    ] {
+        for path in recent_transcripts(&dir, cutoff) {
+            found += 1;
+            let key = path.display().to_string();
+            let previous = manifest.sessions.get(&key);
+            if options.incremental
+                && let Some(previous) = previous
+                && previous.modified == modified_secs(&path)
+                && options.out.join(&previous.markdown).exists()
+            {
+                unchanged += 1;
+                continue;
+            }
+            match export_session(tool, &path, &options) {
+                Ok(Some((entry, masked))) => {
+                    // A retitled session gets a new name; drop the old files
+                    if let Some(previous) = previous
+                        && previous.markdown != entry.markdown
+                    {
+                        let _ = fs::remove_file(options.out.join(&previous.markdown));
+                        let _ = fs::remove_file(options.out.join(&previous.json));
+                    }
+                    manifest.sessions.insert(key, entry);
+                    exported += 1;
+                    redacted += masked;
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("warning: skipping {}: {err}", path.display()),
+            }
+        }
+    }
+    if found == 0 {
+        match &options.since {
+            Some(since) => bail!("No sessions found in the last {since}"),
+            None => bail!("No sessions found"),
+        }
+    }
+
+    manifest.save(&options.out)?;
+    let index_path = options.out.join("index.md");
+    atomic::write(&index_path, render_index(&manifest)).context("Failed to write index.md")?;
+    Ok(ExportResult {
+        exported,
+        unchanged,
+        redacted,
+        index_path: index_path.display().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use tempfile::TempDir;
+
+    #[test]
+    fn slugify_and_repo_names() {
+        assert_eq!(slugify("Fix the parser: v2!"), "fix-the-parser-v2");
+        assert_eq!(slugify("¿¡"), "session");
+        assert!(slugify(&"word ".repeat(40)).len() <= MAX_SLUG_CHARS);
+        assert_eq!(repo_name(Some("/home/me/agentexport")), "agentexport");
+        assert_eq!(repo_name(None), "unknown");
+    }
+
+    #[test]
+    fn export_one_renders_format_from_extension() {
+        let tmp = TempDir::new().unwrap();
+        let transcript = tmp.path().join("sess.jsonl");
+        fs::write(
+            &transcript,
+            [
+                r#"{"type":"user","message":{"content":"Rename <Cart>"}}"#,
+                r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Renamed."}]}}"#,
+                r#"{"type":"user","message":{"content":"Deploy with sk-live0123456789abcdefghij"}}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let export_to = |name: &str, format: Option<ExportFormat>| {
+            let out = tmp.path().join("out").join(name);
+            let written = export_one(SessionExportOptions {
+                tool: Tool::Claude,
+                transcript: Some(transcript.clone()),
+                max_age_minutes: 0,
+                title: Some("Cart rename".to_string()),
+                format,
+                out: Some(out.clone()),
+                post_to: None,
+                redact_patterns: Some(Vec::new()),
+                secret_scanner: None,
+            })
+            .unwrap();
+            assert_eq!(written.redacted, 1);
+            (written.format.unwrap(), fs::read_to_string(out).unwrap())
+        };
+
+        let (format, html) = export_to("session.html", None);
+        assert_eq!(format, ExportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>") && html.contains("Rename &lt;Cart&gt;"));
+        let (format, markdown) = export_to("session.txt", None);
+        assert_eq!(format, ExportFormat::Markdown);
+        assert!(markdown.contains("# Cart rename"));
+        let (_, json) = export_to("session.md", Some(ExportFormat::Json));
+        let payload: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(payload["messages"][1]["content"], "Renamed.");
+        assert_eq!(payload["messages"][2]["content"], "Deploy with [REDACTED]");
+    }
+
+    #[test]
+    fn export_writes_tree_and_skips_unchanged_sessions() {
+        let _lock = env_lock();
+        let home = TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", home.path().to_str().unwrap());
+        let _codex = EnvGuard::set(
+            "AGENTEXPORT_CODEX_SESSIONS_DIR",
+            home.path().join("codex").to_str().unwrap(),
+        );
+        let project = claude_projects_dir().unwrap().join("-work-shop");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            project.join("sess-1.jsonl"),
+            [
+                r#"{"type":"user","cwd":"/work/shop","slug":"tidy-cart","timestamp":"2026-03-04T10:00:00Z","message":{"content":"Tidy the cart"}}"#,
+                r#"{"type":"assistant","timestamp":"2026-03-04T10:01:00Z","message":{"id":"msg_1","model":"claude-sonnet-4","content":[{"type":"text","text":"Done."}]}}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        let out = home.path().join("backup");
+        let options = ExportOptions {
+            out: out.clone(),
+            since: None,
+            incremental: true,
+            redact_patterns: Some(Vec::new()),
+            secret_scanner: None,
+        };
+
+        let first = export(options.clone()).unwrap();
+        assert_eq!((first.exported, first.unchanged), (1, 0));
+        let day = out.join("shop").join("2026-03-04");
+        let files: Vec<String> = fs::read_dir(&day)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|f| f.starts_with("tidy-cart-")));
+        let index = fs::read_to_string(out.join("index.md")).unwrap();
+        assert!(index.contains("## shop"));
+        assert!(index.contains("2026-03-04 · [tidy cart](shop/2026-03-04/tidy-cart-"));
+
+        let second = export(options).unwrap();
+        assert_eq!((second.exported, second.unchanged), (0, 1));
+    }
+}
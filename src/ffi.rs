@@ -0,0 +1,187 @@
+//! C ABI over the transcript parser and payload builder.
+//!
+//! Everything takes transcript bytes in and hands JSON back, so the same
+//! parsing logic as the CLI can run in editors and web tools without a
+//! filesystem. Build a shared library or wasm module with:
+//!
+//! ```text
+//! cargo rustc --lib --release --no-default-features --features ffi --crate-type cdylib
+//! cargo rustc --lib --release --no-default-features --features wasm --crate-type cdylib \
+//!     --target wasm32-unknown-unknown
+//! ```
+//!
+//! Returned strings are NUL-terminated UTF-8 owned by the library; release
+//! them with `agentexport_string_free`. Failures come back as
+//! `{"error": "..."}` rather than null.
+
+use anyhow::{Result, bail};
+use std::ffi::{CStr, CString, c_char};
+
+use crate::transcript::{
+    Tool, build_share_payload, extract_transcript_meta_from_reader, parse_transcript_from_reader,
+};
+
+fn tool_from_code(code: u32) -> Result<Tool> {
+    match code {
+        0 => Ok(Tool::Claude),
+        1 => Ok(Tool::Codex),
+        2 => Ok(Tool::Cursor),
+        3 => Ok(Tool::Aider),
+        4 => Ok(Tool::ChatGpt),
+        5 => Ok(Tool::ClaudeAi),
+        other => bail!(
+            "unknown tool code {other} (expected 0 = claude, 1 = codex, 2 = cursor, 3 = aider, 4 = chatgpt, 5 = claude-ai)"
+        ),
+    }
+}
+
+/// Parse JSONL transcript bytes into a JSON array of rendered messages.
+/// Messages on abandoned branches are kept and flagged `abandoned`.
+pub fn parse_messages_json(data: &[u8]) -> Result<String> {
+    let parsed = parse_transcript_from_reader(data)?;
+    Ok(serde_json::to_string(&parsed.messages)?)
+}
+
+/// Build a share payload JSON from JSONL transcript bytes.
+/// `shared_at` is left empty since wasm32 has no clock; callers fill it in.
+/// Abandoned branches and image data are left out.
+pub fn build_payload_json(tool: Tool, data: &[u8], title: Option<&str>) -> Result<String> {
+    let mut parsed = parse_transcript_from_reader(data)?;
+    parsed.drop_abandoned();
+    for message in &mut parsed.messages {
+        message.images.clear();
+    }
+    let meta = extract_transcript_meta_from_reader(data);
+    let payload = build_share_payload(
+        tool.display_name(),
+        parsed,
+        meta,
+        None,
+        title,
+        String::new(),
+    );
+    Ok(serde_json::to_string(&payload)?)
+}
+
+fn into_c_string(result: Result<String>) -> *mut c_char {
+    let json =
+        result.unwrap_or_else(|err| serde_json::json!({ "error": format!("{err:#}") }).to_string());
+    // serde_json escapes control characters, so the JSON never contains NUL
+    CString::new(json).unwrap_or_default().into_raw()
+}
+
+/// # Safety
+/// `data` must point to `len` readable bytes (or be null with `len == 0`).
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if data.is_null() || len == 0 {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(data, len) }
+}
+
+/// Allocate `len` bytes for the caller to copy transcript data into.
+/// Release with `agentexport_dealloc`.
+#[unsafe(no_mangle)]
+pub extern "C" fn agentexport_alloc(len: usize) -> *mut u8 {
+    let mut buf = vec![0u8; len].into_boxed_slice();
+    let ptr = buf.as_mut_ptr();
+    std::mem::forget(buf);
+    ptr
+}
+
+/// Free a buffer returned by `agentexport_alloc`.
+///
+/// # Safety
+/// `ptr` and `len` must come from a single `agentexport_alloc` call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn agentexport_dealloc(ptr: *mut u8, len: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+}
+
+/// Parse transcript bytes into a JSON array of messages.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn agentexport_parse_transcript(data: *const u8, len: usize) -> *mut c_char {
+    into_c_string(parse_messages_json(unsafe { bytes(data, len) }))
+}
+
+/// Build a share payload JSON from transcript bytes.
+/// `tool` is 0 for Claude, 1 for Codex; `title` may be null.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes and `title` must be null or a
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn agentexport_build_payload(
+    tool: u32,
+    data: *const u8,
+    len: usize,
+    title: *const c_char,
+) -> *mut c_char {
+    let title = if title.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(title) }.to_str().ok()
+    };
+    let data = unsafe { bytes(data, len) };
+    into_c_string(tool_from_code(tool).and_then(|tool| build_payload_json(tool, data, title)))
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+/// `ptr` must be null or a pointer returned by an `agentexport_*` function.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn agentexport_string_free(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        unsafe { drop(CString::from_raw(ptr)) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &[u8] = concat!(
+        "{\"type\":\"user\",\"message\":{\"content\":\"Fix the build\"}}\n",
+        "{\"type\":\"assistant\",\"message\":{\"model\":\"claude-sonnet-4\",\"content\":[{\"type\":\"text\",\"text\":\"Done\"}]}}\n"
+    )
+    .as_bytes();
+
+    #[test]
+    fn build_payload_from_bytes() {
+        let json = build_payload_json(Tool::Claude, TRANSCRIPT, None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["tool"], "Claude Code");
+        assert_eq!(value["title"], "Fix the build");
+        assert_eq!(value["messages"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn c_abi_roundtrip() {
+        let title = CString::new("My title").unwrap();
+        unsafe {
+            let buf = agentexport_alloc(TRANSCRIPT.len());
+            std::ptr::copy_nonoverlapping(TRANSCRIPT.as_ptr(), buf, TRANSCRIPT.len());
+
+            let out = agentexport_build_payload(0, buf, TRANSCRIPT.len(), title.as_ptr());
+            let json = CStr::from_ptr(out).to_str().unwrap().to_string();
+            agentexport_string_free(out);
+            assert!(json.contains("\"title\":\"My title\""));
+
+            let out = agentexport_build_payload(9, buf, TRANSCRIPT.len(), std::ptr::null());
+            let json = CStr::from_ptr(out).to_str().unwrap().to_string();
+            agentexport_string_free(out);
+            assert!(json.contains("\"error\""));
+
+            agentexport_dealloc(buf, TRANSCRIPT.len());
+        }
+    }
+}
@@ -2,8 +2,103 @@
 
 use anyhow::{Context, Result};
 
+/// Markdown gists larger than this are split into part files. GitHub stops
+/// rendering big markdown files, and its API truncates files past 1 MB.
+pub const GIST_PART_BYTES: usize = 512 * 1024;
+
+/// A rendered document in pieces: everything above the first rule, one block
+/// per message, and the closing stats
+struct Sections {
+    title: String,
+    header: String,
+    messages: Vec<String>,
+    footer: String,
+}
+
+impl Sections {
+    fn document(&self) -> String {
+        format!(
+            "{}---\n\n{}{}",
+            self.header,
+            self.messages.concat(),
+            self.footer
+        )
+    }
+}
+
 /// Render payload JSON into a markdown document for GitHub Gist
 pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
+    Ok(render_sections(payload_json)?.document())
+}
+
+/// Render payload JSON as gist files: `transcript.md`, or when that would be
+/// over `limit` bytes, `part-01.md`, `part-02.md`, … split between messages,
+/// each linking to its neighbours. The gist viewer joins them back together.
+pub fn render_gist_markdown_parts(
+    payload_json: &str,
+    limit: usize,
+) -> Result<Vec<(String, String)>> {
+    let sections = render_sections(payload_json)?;
+    let total = sections.header.len()
+        + sections.messages.iter().map(String::len).sum::<usize>()
+        + sections.footer.len();
+    if total <= limit {
+        return Ok(vec![("transcript.md".to_string(), sections.document())]);
+    }
+
+    // Leave room for the title and navigation lines each part adds
+    let budget = limit.saturating_sub(sections.title.len() + 256);
+    let mut groups: Vec<String> = vec![String::new()];
+    let mut used = sections.header.len();
+    for block in &sections.messages {
+        // A message bigger than a whole part still gets a part of its own
+        if used + block.len() > budget && !groups.last().is_some_and(String::is_empty) {
+            groups.push(String::new());
+            used = 0;
+        }
+        used += block.len();
+        if let Some(group) = groups.last_mut() {
+            group.push_str(block);
+        }
+    }
+
+    let count = groups.len();
+    let name = |n: usize| format!("part-{n:02}.md");
+    let anchor = |n: usize| format!("#file-part-{n:02}-md");
+    let files = groups
+        .into_iter()
+        .enumerate()
+        .map(|(index, messages)| {
+            let n = index + 1;
+            let mut nav = vec![format!("Part {n} of {count}")];
+            if n > 1 {
+                nav.push(format!("[previous part]({})", anchor(n - 1)));
+            }
+            if n < count {
+                nav.push(format!("[next part]({})", anchor(n + 1)));
+            }
+            let mut md = if n == 1 {
+                sections.header.clone()
+            } else {
+                format!("# {}\n\n", sections.title)
+            };
+            md.push_str(&format!("> {}\n\n---\n\n{messages}", nav.join(" · ")));
+            if n < count {
+                md.push_str(&format!(
+                    "---\n\n> Continued in [part {}]({})\n",
+                    n + 1,
+                    anchor(n + 1)
+                ));
+            } else {
+                md.push_str(&sections.footer);
+            }
+            (name(n), md)
+        })
+        .collect();
+    Ok(files)
+}
+
+fn render_sections(payload_json: &str) -> Result<Sections> {
     let payload: serde_json::Value =
         serde_json::from_str(payload_json).context("Failed to parse payload JSON")?;
 
@@ -15,6 +110,7 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
         .and_then(|v| v.as_str())
         .unwrap_or("Agent Export");
     md.push_str(&format!("# {}\n\n", title));
+    let title = title.to_string();
 
     // Metadata
     let tool = payload.get("tool").and_then(|v| v.as_str()).unwrap_or("");
@@ -50,11 +146,59 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
         md.push_str(&format!("*{}*\n\n", meta_parts.join(" · ")));
     }
 
-    md.push_str("---\n\n");
+    // Session outcome, only when something went wrong
+    let status = payload
+        .get("status")
+        .and_then(|v| v.as_str())
+        .unwrap_or("completed");
+    if status != "completed" {
+        let errors: Vec<&str> = payload
+            .get("errors")
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        if errors.is_empty() {
+            md.push_str(&format!("*Session {status}*\n\n"));
+        } else {
+            md.push_str(&format!("*Session {status}* — {}\n\n", errors.join("; ")));
+        }
+    }
+
+    // Series navigation
+    if let Some(series) = payload.get("series") {
+        let part = series.get("part").and_then(|v| v.as_u64()).unwrap_or(1);
+        if let Some(url) = series.get("previous_url").and_then(|v| v.as_str()) {
+            md.push_str(&format!("*Part {} · [previous part]({})*\n\n", part, url));
+        }
+    }
+
+    let header = md;
 
-    // Messages
+    // Messages, under a heading per session on bundle shares
+    let parts = payload.get("parts").and_then(|v| v.as_array());
+    let mut current_part = None;
+    let mut blocks = Vec::new();
     if let Some(messages) = payload.get("messages").and_then(|v| v.as_array()) {
         for msg in messages {
+            let mut md = String::new();
+            let part = msg.get("part").and_then(|v| v.as_u64());
+            if let (Some(index), Some(parts)) = (part, parts)
+                && current_part != Some(index)
+            {
+                current_part = Some(index);
+                let info = parts.get(index as usize);
+                let field = |key: &str| info.and_then(|p| p.get(key)).and_then(|v| v.as_str());
+                let mut heading = format!("## {}. {}", index + 1, field("tool").unwrap_or(""));
+                if let Some(title) = field("title") {
+                    heading.push_str(&format!(" · {title}"));
+                }
+                md.push_str(&format!(
+                    "{}
+
+",
+                    heading.trim_end()
+                ));
+            }
             let role = msg
                 .get("role")
                 .and_then(|v| v.as_str())
@@ -69,6 +213,8 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
                 "tool" => "Tool",
                 "thinking" => "Thinking",
                 "system" => "System",
+                "command" => "Command",
+                "plan" => "Plan",
                 _ => role,
             };
 
@@ -76,18 +222,23 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
             md.push_str(&format!("### {}{}\n\n", role_display, model_suffix));
 
             // Content - for tool messages, wrap in code block if not already
-            if role == "tool" && !content.trim().starts_with("```") {
+            if role == "command" {
+                md.push_str(&format!("{}\n\n", inline_code(content)));
+            } else if role == "tool" && !content.trim().starts_with("```") {
                 // Check if it looks like JSON or code
                 let trimmed = content.trim();
                 if trimmed.starts_with('{') || trimmed.starts_with('[') || trimmed.contains('\n') {
-                    md.push_str("```\n");
+                    let fence = backtick_fence(content, 3);
+                    md.push_str(&fence);
+                    md.push('\n');
                     md.push_str(content);
                     if !content.ends_with('\n') {
                         md.push('\n');
                     }
-                    md.push_str("```\n\n");
+                    md.push_str(&fence);
+                    md.push_str("\n\n");
                 } else {
-                    md.push_str(&format!("`{}`\n\n", content));
+                    md.push_str(&format!("{}\n\n", inline_code(content)));
                 }
             } else {
                 md.push_str(content);
@@ -108,7 +259,37 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
                     label, raw
                 ));
             }
+            blocks.push(md);
+        }
+    }
+
+    let mut md = String::new();
+    // Metadata-only shares have counts in place of messages
+    if payload.get("metadata_only").and_then(|v| v.as_bool()) == Some(true) {
+        md.push_str("*Metadata only; the conversation itself was not shared.*\n\n");
+        if let Some(counts) = payload.get("message_counts").and_then(|v| v.as_object()) {
+            let counts: Vec<String> = counts
+                .iter()
+                .map(|(role, count)| format!("{} {}", count, role))
+                .collect();
+            md.push_str(&format!("- Messages: {}\n", counts.join(" · ")));
+        }
+        if let Some(timeline) = payload.get("timeline") {
+            let start = timeline.get("started_at").and_then(|v| v.as_i64());
+            let end = timeline.get("ended_at").and_then(|v| v.as_i64());
+            if let (Some(start), Some(end)) = (start, end) {
+                let minutes = (end - start).max(0) / 60;
+                md.push_str(&format!(
+                    "- Duration: {}h {}m\n",
+                    minutes / 60,
+                    minutes % 60
+                ));
+            }
+        }
+        if let Some(cost) = payload.get("estimated_cost_usd").and_then(|v| v.as_f64()) {
+            md.push_str(&format!("- Estimated cost: ${:.2}\n", cost));
         }
+        md.push('\n');
     }
 
     // Token stats
@@ -129,8 +310,12 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
         .and_then(|v| v.as_u64())
         .unwrap_or(0);
 
-    if input_tokens > 0 || output_tokens > 0 {
+    let publisher = payload.get("publisher").and_then(format_publisher);
+
+    if input_tokens > 0 || output_tokens > 0 || publisher.is_some() {
         md.push_str("---\n\n");
+    }
+    if input_tokens > 0 || output_tokens > 0 {
         let mut stats = Vec::new();
         if input_tokens > 0 {
             stats.push(format!("Input: {} tokens", input_tokens));
@@ -146,8 +331,57 @@ pub fn render_gist_markdown(payload_json: &str) -> Result<String> {
         }
         md.push_str(&format!("*{}*\n", stats.join(" · ")));
     }
+    if let Some(publisher) = publisher {
+        md.push_str(&format!("\n*Shared by {}*\n", publisher));
+    }
+
+    Ok(Sections {
+        title,
+        header,
+        messages: blocks,
+        footer: md,
+    })
+}
+
+/// A run of at least `min` backticks, longer than any run in `content`, so
+/// the content can't close its code span or block early
+fn backtick_fence(content: &str, min: usize) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(min.max(longest + 1))
+}
+
+/// `content` as an inline code span. Markdown drops one space from each end
+/// of a span, so content starting or ending with a backtick gets padded.
+fn inline_code(content: &str) -> String {
+    let fence = backtick_fence(content, 1);
+    let pad = content.starts_with('`')
+        || content.ends_with('`')
+        || (content.starts_with(' ') && content.ends_with(' ') && !content.trim().is_empty());
+    let pad = if pad { " " } else { "" };
+    format!("{fence}{pad}{content}{pad}{fence}")
+}
 
-    Ok(md)
+/// Format publisher info as "name (email) from host"
+fn format_publisher(publisher: &serde_json::Value) -> Option<String> {
+    let field = |key: &str| {
+        publisher
+            .get(key)
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+    };
+    let name = field("name");
+    let email = field("email");
+    let host = field("host");
+    let who = match (name, email) {
+        (Some(name), Some(email)) => format!("{} ({})", name, email),
+        (Some(name), None) => name.to_string(),
+        (None, Some(email)) => email.to_string(),
+        (None, None) => return host.map(|host| format!("unknown from {}", host)),
+    };
+    Some(match host {
+        Some(host) => format!("{} from {}", who, host),
+        None => who,
+    })
 }
 
 #[cfg(test)]
@@ -176,6 +410,60 @@ mod tests {
         assert!(md.contains("Hi there!"));
     }
 
+    #[test]
+    fn test_render_gist_markdown_parts_split_long_sessions() {
+        let messages: Vec<serde_json::Value> = (0..12)
+            .map(|i| serde_json::json!({"role": "user", "content": format!("Message {i} {}", "x".repeat(200))}))
+            .collect();
+        let payload = serde_json::json!({
+            "title": "Long Session",
+            "tool": "Claude Code",
+            "messages": messages,
+            "total_input_tokens": 1000,
+        })
+        .to_string();
+
+        let whole = render_gist_markdown_parts(&payload, 1 << 20).unwrap();
+        assert_eq!(
+            whole,
+            [(
+                "transcript.md".to_string(),
+                render_gist_markdown(&payload).unwrap()
+            )]
+        );
+
+        let parts = render_gist_markdown_parts(&payload, 1200).unwrap();
+        let names: Vec<&str> = parts.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["part-01.md", "part-02.md", "part-03.md"]);
+        for (_, md) in &parts {
+            assert!(md.starts_with("# Long Session\n\n"));
+            assert!(md.len() <= 1200);
+        }
+        assert!(parts[0].1.contains("*Claude Code*"));
+        assert!(
+            parts[0]
+                .1
+                .contains("> Part 1 of 3 · [next part](#file-part-02-md)")
+        );
+        assert!(parts[1].1.contains(
+            "> Part 2 of 3 · [previous part](#file-part-01-md) · [next part](#file-part-03-md)"
+        ));
+        assert!(
+            parts[1]
+                .1
+                .ends_with("> Continued in [part 3](#file-part-03-md)\n")
+        );
+        assert!(!parts[1].1.contains("Input: 1000 tokens"));
+        assert!(parts[2].1.contains("Input: 1000 tokens"));
+        // Every message lands in exactly one part, in order
+        let joined: String = parts.iter().map(|(_, md)| md.as_str()).collect();
+        let found: Vec<usize> = (0..12)
+            .map(|i| joined.matches(&format!("Message {i} ")).count())
+            .collect();
+        assert_eq!(found, [1; 12]);
+        assert!(joined.find("Message 3 ").unwrap() < joined.find("Message 4 ").unwrap());
+    }
+
     #[test]
     fn test_render_gist_markdown_all_roles() {
         let payload = serde_json::json!({
@@ -225,6 +513,23 @@ mod tests {
         assert!(md.contains("```\nline1\nline2\nline3\n```"));
     }
 
+    #[test]
+    fn test_render_gist_markdown_code_fences_outlast_backticks() {
+        let payload = serde_json::json!({
+            "title": "Backticks",
+            "messages": [
+                {"role": "command", "content": "echo `date` >> log"},
+                {"role": "command", "content": "`pwd`"},
+                {"role": "tool", "content": "Wrote:\n```rust\nfn main() {}\n```"}
+            ]
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+
+        assert!(md.contains("``echo `date` >> log``\n"));
+        assert!(md.contains("`` `pwd` ``\n"));
+        assert!(md.contains("````\nWrote:\n```rust\nfn main() {}\n```\n````\n"));
+    }
+
     #[test]
     fn test_render_gist_markdown_tool_simple_inline() {
         // Simple tool output without JSON/multiline should be inline code
@@ -279,6 +584,42 @@ mod tests {
         assert!(md.contains("Cache write: 100 tokens"));
     }
 
+    #[test]
+    fn test_render_gist_markdown_bundle_parts() {
+        let payload = serde_json::json!({
+            "tool": "Claude Code + Codex",
+            "parts": [{"tool": "Claude Code", "title": "Plan"}, {"tool": "Codex"}],
+            "messages": [
+                {"role": "user", "content": "Plan it", "part": 0},
+                {"role": "assistant", "content": "Planned", "part": 0},
+                {"role": "user", "content": "Build it", "part": 1}
+            ]
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+        assert!(md.contains("*Claude Code + Codex*"));
+        assert_eq!(md.matches("## 1. Claude Code · Plan\n").count(), 1);
+        let second = md.find("## 2. Codex\n").unwrap();
+        assert!(md.find("Planned").unwrap() < second && second < md.find("Build it").unwrap());
+    }
+
+    #[test]
+    fn test_render_gist_markdown_metadata_only() {
+        let payload = serde_json::json!({
+            "title": "Expensive run",
+            "messages": [],
+            "metadata_only": true,
+            "message_counts": {"assistant": 12, "user": 3},
+            "timeline": {"started_at": 0, "ended_at": 5400, "bucket_minutes": 10, "buckets": []},
+            "estimated_cost_usd": 4.5
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+
+        assert!(md.contains("conversation itself was not shared"));
+        assert!(md.contains("- Messages: 12 assistant · 3 user"));
+        assert!(md.contains("- Duration: 1h 30m"));
+        assert!(md.contains("- Estimated cost: $4.50"));
+    }
+
     #[test]
     fn test_render_gist_markdown_no_stats_when_zero() {
         let payload = serde_json::json!({
@@ -340,4 +681,75 @@ mod tests {
         // Uses default title
         assert!(md.contains("# Agent Export"));
     }
+
+    #[test]
+    fn test_render_gist_markdown_command() {
+        let payload = serde_json::json!({
+            "title": "Command Test",
+            "messages": [
+                {
+                    "role": "command",
+                    "content": "/review src/lib.rs",
+                    "raw": "Review the file",
+                    "raw_label": "Command prompt"
+                }
+            ]
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+
+        assert!(md.contains("### Command"));
+        assert!(md.contains("`/review src/lib.rs`"));
+        assert!(md.contains("<summary>Command prompt</summary>"));
+    }
+
+    #[test]
+    fn test_render_gist_markdown_publisher_footer() {
+        let payload = serde_json::json!({
+            "title": "Attributed",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "publisher": {"name": "Nico", "email": "nico@example.com", "host": "devbox"}
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+
+        assert!(md.contains("---\n\n"));
+        assert!(md.contains("*Shared by Nico (nico@example.com) from devbox*"));
+    }
+
+    #[test]
+    fn test_render_gist_markdown_series_link() {
+        let payload = serde_json::json!({
+            "title": "Part Two",
+            "tool": "Claude Code",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "series": {"part": 2, "previous_url": "https://agentexports.com/v/abc#key"}
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+
+        assert!(md.contains("*Part 2 · [previous part](https://agentexports.com/v/abc#key)*"));
+    }
+
+    #[test]
+    fn test_render_gist_markdown_plan() {
+        let payload = serde_json::json!({
+            "messages": [{"role": "plan", "content": "- [ ] Add parser\n- [ ] Ship"}]
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+        assert!(md.contains("### Plan\n\n- [ ] Add parser\n- [ ] Ship"));
+    }
+
+    #[test]
+    fn test_render_gist_markdown_session_status() {
+        let payload = serde_json::json!({
+            "title": "Broken",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "status": "errored",
+            "errors": ["API Error: 529 overloaded"]
+        });
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+        assert!(md.contains("*Session errored* — API Error: 529 overloaded\n"));
+
+        let payload = serde_json::json!({"messages": [], "status": "completed"});
+        let md = render_gist_markdown(&payload.to_string()).unwrap();
+        assert!(!md.contains("*Session"));
+    }
 }
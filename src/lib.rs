@@ -1,33 +1,136 @@
 //! agentexport: CLI tool for sharing Claude Code and Codex transcripts.
 //!
-//! This is the public API for the agentexport library.
+//! This is the public API for the agentexport library. The default `cli`
+//! feature enables discovery, upload, and share management; without it only
+//! the transcript parser and payload builder are compiled.
 
+#[cfg(feature = "cli")]
+mod atomic;
+#[cfg(feature = "cli")]
+pub mod backup;
+#[cfg(feature = "cli")]
+pub mod compare;
+#[cfg(feature = "cli")]
 pub mod config;
+#[cfg(feature = "cli")]
+mod confluence;
+#[cfg(feature = "cli")]
 mod crypto;
+#[cfg(feature = "cli")]
+mod edit;
+#[cfg(feature = "cli")]
+mod email;
+#[cfg(feature = "cli")]
+pub mod emit;
+#[cfg(feature = "cli")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "cli")]
 mod gist;
+#[cfg(feature = "cli")]
+pub mod lint;
+#[cfg(feature = "cli")]
+mod locate;
+#[cfg(feature = "cli")]
+pub mod manpages;
+#[cfg(feature = "cli")]
+pub mod monitor;
+#[cfg(feature = "cli")]
+pub mod output;
+#[cfg(feature = "cli")]
+pub mod pretty;
+#[cfg(feature = "cli")]
+pub mod prune;
+#[cfg(feature = "cli")]
 mod publish;
+#[cfg(feature = "cli")]
+pub mod purge;
+#[cfg(feature = "cli")]
+mod redact;
+#[cfg(feature = "cli")]
+pub mod report;
+#[cfg(feature = "cli")]
+mod select;
+#[cfg(feature = "cli")]
+pub mod sessions;
+#[cfg(feature = "cli")]
 mod setup;
+#[cfg(feature = "cli")]
 pub mod shares;
+#[cfg(feature = "cli")]
+pub mod store;
+#[cfg(feature = "cli")]
+pub mod sync;
+#[cfg(feature = "cli")]
 mod terminal;
-#[cfg(test)]
+#[cfg(feature = "cli")]
+pub mod test_mode;
+#[cfg(all(test, feature = "cli"))]
 pub mod test_utils;
 mod transcript;
+#[cfg(feature = "cli")]
 mod upload;
+#[cfg(feature = "cli")]
+pub mod usage;
+#[cfg(feature = "cli")]
+pub mod watch;
 
 // Re-export public types from config
-pub use config::{Config, GistFormat, StorageType};
+#[cfg(feature = "cli")]
+pub use config::{
+    Config, GistFormat, GistRaw, ModelPolicy, PublishPreset, RawContent, StorageType,
+    parse_model_list,
+};
 
 // Re-export public types from transcript
-pub use transcript::Tool;
+pub use transcript::{
+    BundlePart, LineRecovery, ModelCost, ModelPrice, ModelUsage, ParseResult, PricingTable,
+    Publisher, RenderedMessage, SeriesLink, SessionStatus, SharePayload, TaskItem, TaskProgress,
+    Timeline, TitleSource, Tool, TranscriptMeta, ViewerDefaults,
+};
+#[cfg(feature = "cli")]
+pub use transcript::{CodexDiscovery, SessionPreference};
 
 // Re-export public types and functions from publish
+#[cfg(feature = "cli")]
 pub use publish::{
-    ClaudeState, PublishOptions, PublishResult, claude_state_path, handle_claude_sessionstart,
-    publish, read_claude_state, write_claude_state,
+    ClaudeState, PublishOptions, PublishPlan, PublishResult, RenderFormat, STATE_MAX_AGE_DAYS,
+    claude_state_path, claude_state_stale_reason, cleanup_env_file, extend,
+    handle_claude_sessionstart, list_claude_states, migrate, prune_claude_states, publish,
+    publish_all, read_claude_state, rekey, render_from_gzip, republish, retitle,
+    write_claude_state,
 };
 
+// Re-export share deletion for the shares command
+#[cfg(feature = "cli")]
+pub use upload::{delete_blob, delete_gist};
+
+// Re-export session lookup
+#[cfg(feature = "cli")]
+pub use locate::{SessionLocation, locate};
+
 // Re-export setup
+#[cfg(feature = "cli")]
 pub use setup::run as run_setup;
 
 // Re-export transcript utilities needed by external code
-pub use transcript::{cache_dir, codex_home_dir, codex_sessions_dir};
+
+// Transcript parsing and payload building (available without the `cli` feature)
+#[cfg(feature = "cli")]
+pub use transcript::archive;
+pub use transcript::{
+    build_share_payload, bundle_payloads, extract_transcript_meta,
+    extract_transcript_meta_from_reader, parse_transcript, parse_transcript_from_reader,
+    price_payload, sample_turns, select_around,
+};
+
+// Transcript sources, for adding agent formats
+pub use transcript::{
+    DiscoverOptions, Discovered, TranscriptSource, named_source, register_source, source,
+    source_for_path, sources,
+};
+#[cfg(feature = "cli")]
+pub use transcript::{
+    cache_dir, codex_home_dir, codex_sessions_dir, compaction_chain, find_session, transcript_tool,
+};
@@ -0,0 +1,343 @@
+//! Content lint: flag risky content in a share before it is published
+//! (`agentexport lint`).
+//!
+//! Nothing is changed, unlike redaction. Each finding names the message it was
+//! found in and a severity, so CI can fail a publish step with `--fail-on`.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::publish::create_share_payload;
+use crate::redact::redact_share_payload;
+use crate::transcript::{
+    CodexDiscovery, DiscoverOptions, Discovered, SharePayload, Tool, TranscriptSource,
+};
+
+/// Lines above which a message counts as an inline dump
+const DUMP_LINES: usize = 300;
+
+/// Bytes above which a message counts as an inline dump
+const DUMP_BYTES: usize = 30_000;
+
+/// Shortest run of token characters checked for credentials
+const CREDENTIAL_MIN_LEN: usize = 32;
+
+/// Bits per character above which a token looks random rather than a word
+const CREDENTIAL_MIN_ENTROPY: f64 = 4.0;
+
+/// How bad a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// One piece of risky content
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    /// Index into the payload's messages; None for the title
+    pub message: Option<usize>,
+    pub role: String,
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// What matched, shortened (and masked for secrets and personal data)
+    pub excerpt: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let place = match self.message {
+            Some(index) => format!("#{index} {}", self.role),
+            None => "title".to_string(),
+        };
+        write!(
+            f,
+            "{place}: {} [{}] {}",
+            self.severity, self.rule, self.excerpt
+        )
+    }
+}
+
+struct Rules {
+    email: Regex,
+    host: Regex,
+    private_ip: Regex,
+    token: Regex,
+    card: Regex,
+    ssn: Regex,
+    phone: Regex,
+}
+
+impl Rules {
+    fn new() -> Self {
+        let re = |pattern: &str| Regex::new(pattern).expect("built-in lint pattern is valid");
+        Self {
+            email: re(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b"),
+            host: re(
+                r"(?i)\b[a-z0-9-]+(?:\.[a-z0-9-]+)*\.(?:internal|intranet|corp|lan|local|localdomain)\b",
+            ),
+            private_ip: re(
+                r"\b(?:10\.\d{1,3}|192\.168|172\.(?:1[6-9]|2\d|3[01]))\.\d{1,3}\.\d{1,3}\b",
+            ),
+            token: re(&format!(r"[A-Za-z0-9_+-]{{{CREDENTIAL_MIN_LEN},}}")),
+            card: re(r"\b\d(?:[ -]?\d){12,18}\b"),
+            ssn: re(r"\b\d{3}-\d{2}-\d{4}\b"),
+            phone: re(r"\+\d{1,3}[ .-]?(?:\(\d{1,4}\)[ .-]?)?\d{2,4}(?:[ .-]?\d{2,4}){1,3}\b"),
+        }
+    }
+
+    /// Findings in one piece of text as (rule, severity, excerpt)
+    fn check(&self, text: &str) -> Vec<(&'static str, Severity, String)> {
+        let mut found = Vec::new();
+        let lines = text.lines().count();
+        if lines > DUMP_LINES || text.len() > DUMP_BYTES {
+            found.push((
+                "large-dump",
+                Severity::Warning,
+                format!("{lines} lines, {} bytes inline", text.len()),
+            ));
+        }
+        for m in self.email.find_iter(text) {
+            let (local, domain) = m.as_str().split_once('@').unwrap_or_default();
+            let domain = domain.to_ascii_lowercase();
+            if local == "git" || domain.starts_with("example.") || domain.contains("noreply") {
+                continue;
+            }
+            found.push(("email", Severity::Warning, m.as_str().to_string()));
+        }
+        for m in self
+            .host
+            .find_iter(text)
+            .chain(self.private_ip.find_iter(text))
+        {
+            found.push(("internal-host", Severity::Warning, m.as_str().to_string()));
+        }
+        for m in self.token.find_iter(text) {
+            if looks_random(m.as_str()) {
+                found.push(("credential", Severity::Error, mask(m.as_str())));
+            }
+        }
+        for m in self.card.find_iter(text) {
+            let digits: Vec<u32> = m.as_str().chars().filter_map(|c| c.to_digit(10)).collect();
+            if luhn_valid(&digits) {
+                found.push(("card-number", Severity::Error, mask(m.as_str())));
+            }
+        }
+        for m in self.ssn.find_iter(text) {
+            found.push(("national-id", Severity::Error, mask(m.as_str())));
+        }
+        for m in self.phone.find_iter(text) {
+            found.push(("phone-number", Severity::Warning, mask(m.as_str())));
+        }
+        found
+    }
+}
+
+/// Mixed-case, high-entropy tokens with digits sprinkled through. Hex digests
+/// and UUIDs are single-case, and identifiers rarely carry several digits.
+fn looks_random(token: &str) -> bool {
+    let count = |f: fn(&char) -> bool| token.chars().filter(f).count();
+    if count(char::is_ascii_uppercase) == 0
+        || count(char::is_ascii_lowercase) == 0
+        || count(char::is_ascii_digit) < 3
+    {
+        return false;
+    }
+    let mut counts = [0usize; 128];
+    for byte in token.bytes() {
+        counts[byte as usize & 127] += 1;
+    }
+    let len = token.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    entropy >= CREDENTIAL_MIN_ENTROPY
+}
+
+fn luhn_valid(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Keep the first four characters so a finding can be located, not copied
+fn mask(value: &str) -> String {
+    let shown: String = value.chars().take(4).collect();
+    format!("{shown}…")
+}
+
+/// Check every message (content and raw details) of `payload`, then the title.
+/// Titles usually quote the first message, so only what's new in them is reported.
+pub fn lint_payload(payload: &SharePayload) -> Vec<Finding> {
+    let rules = Rules::new();
+    let mut findings = Vec::new();
+    let mut seen = HashSet::new();
+    let texts = payload
+        .messages
+        .iter()
+        .enumerate()
+        .flat_map(|(index, m)| {
+            let raw = m.raw.as_deref().map(|raw| (Some(index), &m.role, raw));
+            std::iter::once((Some(index), &m.role, m.content.as_str())).chain(raw)
+        })
+        .collect::<Vec<_>>();
+    let title_role = "title".to_string();
+    let title = payload.title.as_deref().map(|t| (None, &title_role, t));
+    for (message, role, text) in texts.into_iter().chain(title) {
+        for (rule, severity, excerpt) in rules.check(text) {
+            let in_messages = seen.iter().any(|(_, r, e)| *r == rule && *e == excerpt);
+            if message.is_none() && in_messages {
+                continue;
+            }
+            if seen.insert((message, rule, excerpt.clone())) {
+                findings.push(Finding {
+                    message,
+                    role: role.clone(),
+                    rule,
+                    severity,
+                    excerpt,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// Options for `agentexport lint`
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    pub tool: Tool,
+    pub transcript: Option<PathBuf>,
+    pub max_age_minutes: u64,
+    pub discovery: CodexDiscovery,
+    /// Lint what would be uploaded, after secret redaction (None: lint as is)
+    pub redact_patterns: Option<Vec<String>>,
+    /// External secret scanner run along with redaction
+    pub secret_scanner: Option<String>,
+}
+
+/// Build the session's share payload as `publish` would and lint it
+pub fn lint(options: LintOptions) -> Result<(PathBuf, Vec<Finding>)> {
+    let Discovered {
+        path,
+        session_id,
+        thread_id,
+        warnings,
+    } = options.tool.discover(&DiscoverOptions {
+        transcript: options.transcript,
+        max_age_minutes: options.max_age_minutes,
+        branch: None,
+        codex: options.discovery,
+    })?;
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+    let mut payload = create_share_payload(
+        &options.tool,
+        &path,
+        session_id.as_deref(),
+        thread_id.as_deref(),
+        None,
+        None,
+        false,
+    )?;
+    redact_share_payload(
+        &mut payload,
+        options.redact_patterns.as_deref(),
+        options.secret_scanner.as_deref(),
+    )?;
+    Ok((path, lint_payload(&payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_hit(text: &str) -> Vec<&'static str> {
+        Rules::new().check(text).into_iter().map(|f| f.0).collect()
+    }
+
+    #[test]
+    fn rules_flag_risky_content_only() {
+        assert_eq!(rules_hit("mail jane.doe@acme-customer.io"), ["email"]);
+        assert!(rules_hit("git@github.com:me/repo and a@example.com").is_empty());
+        assert_eq!(
+            rules_hit("ssh build01.corp then 10.2.3.4"),
+            ["internal-host", "internal-host"]
+        );
+        assert_eq!(
+            rules_hit("token=Zx8Qv2LmP9rT4sWk7Yb1Nc5Hd3Jf6Ga0"),
+            ["credential"]
+        );
+        // Digests, uuids, and identifiers aren't credentials
+        assert!(
+            rules_hit("sha 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")
+                .is_empty()
+        );
+        assert!(rules_hit("id 0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b").is_empty());
+        assert!(rules_hit("call parseTranscriptFromReaderWithOptions2").is_empty());
+        assert_eq!(rules_hit("card 4111 1111 1111 1111"), ["card-number"]);
+        assert!(rules_hit("order 4111 1111 1111 1112").is_empty());
+        assert_eq!(rules_hit("ssn 078-05-1120"), ["national-id"]);
+        assert_eq!(rules_hit("call +44 20 7946 0958"), ["phone-number"]);
+        assert_eq!(rules_hit(&"line\n".repeat(DUMP_LINES + 1)), ["large-dump"]);
+    }
+
+    #[test]
+    fn payload_findings_name_the_message() {
+        let payload: SharePayload = serde_json::from_str(
+            r#"{"tool":"Claude","title":"Email bob@client.com","shared_at":"","messages":[
+                {"role":"user","content":"hi"},
+                {"role":"tool","content":"ok","raw":"card 4111-1111-1111-1111, again 4111-1111-1111-1111"}]}"#,
+        )
+        .unwrap();
+        let findings = lint_payload(&payload);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].message, Some(1));
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(
+            findings[0].to_string(),
+            "#1 tool: error [card-number] 4111…"
+        );
+        assert_eq!(findings[1].message, None);
+        assert_eq!(
+            findings[1].to_string(),
+            "title: warning [email] bob@client.com"
+        );
+
+        // A title taken from the first message isn't reported twice
+        let payload: SharePayload = serde_json::from_str(
+            r#"{"tool":"Claude","title":"Email bob@client.com","shared_at":"","messages":[
+                {"role":"user","content":"Email bob@client.com"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(lint_payload(&payload).len(), 1);
+    }
+}
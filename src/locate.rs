@@ -0,0 +1,115 @@
+//! Session lookup for editor integrations (`agentexport where`).
+
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::shares;
+use crate::store::{ObjectKind, Store};
+use crate::transcript::{CodexDiscovery, Tool, resolve_transcript};
+
+/// Everything an editor needs to know about the current session
+#[derive(Debug, Serialize)]
+pub struct SessionLocation {
+    pub tool: String,
+    pub cwd: String,
+    pub transcript_path: String,
+    pub session_id: Option<String>,
+    pub thread_id: Option<String>,
+    /// Latest rendered payload for this tool in the artifact store
+    pub render_path: Option<String>,
+    /// Most recent share of this transcript
+    pub last_share_url: Option<String>,
+    pub last_share_id: Option<String>,
+    /// How the session was picked when several matched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Resolve the current session for `tool` without publishing anything
+pub fn locate(
+    tool: Tool,
+    transcript: Option<PathBuf>,
+    max_age_minutes: u64,
+    discovery: &CodexDiscovery,
+    branch: Option<&str>,
+) -> Result<SessionLocation> {
+    let cwd = std::env::current_dir()?;
+    let found = resolve_transcript(tool, transcript, max_age_minutes, discovery, branch)?;
+    let transcript_str = found.path.display().to_string();
+
+    let store = Store::open()?;
+    let render_path = store
+        .latest(ObjectKind::Render, &format!("{}-", tool.as_str()))?
+        .map(|hash| store.object_path(&hash).display().to_string());
+
+    let last_share = shares::load_shares()?
+        .into_iter()
+        .filter(|s| s.transcript_path == transcript_str)
+        .max_by_key(|s| s.created_at);
+
+    Ok(SessionLocation {
+        tool: tool.as_str().to_string(),
+        cwd: cwd.display().to_string(),
+        transcript_path: transcript_str,
+        session_id: found.session_id,
+        thread_id: found.thread_id,
+        render_path,
+        last_share_url: last_share.as_ref().map(|s| s.url()),
+        last_share_id: last_share.map(|s| s.id),
+        warnings: found.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shares::tests::make_test_share;
+    use crate::test_utils::{DirGuard, EnvGuard, env_lock};
+    use crate::transcript::cwd_to_project_folder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn locate_reports_transcript_and_last_share() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard = EnvGuard::set("AGENTEXPORT_CACHE_DIR", tmp.path().to_str().unwrap());
+        let _guard_home = EnvGuard::set("HOME", tmp.path().to_str().unwrap());
+        let _guard_session = EnvGuard::set("AGENTEXPORT_CLAUDE_SESSION_ID", "");
+
+        let cwd = tmp.path().join("work");
+        fs::create_dir_all(&cwd).unwrap();
+        let cwd = fs::canonicalize(&cwd).unwrap();
+        let project_dir = tmp
+            .path()
+            .join(".claude")
+            .join("projects")
+            .join(cwd_to_project_folder(cwd.to_str().unwrap()));
+        fs::create_dir_all(&project_dir).unwrap();
+        let transcript = project_dir.join("sess-abc.jsonl");
+        fs::write(
+            &transcript,
+            "{\"sessionId\":\"sess-abc\",\"type\":\"user\",\"message\":{\"content\":\"Hello\"}}\n",
+        )
+        .unwrap();
+        let _dir_guard = DirGuard::set(&cwd).unwrap();
+
+        let location = locate(Tool::Claude, None, 0, &CodexDiscovery::default(), None).unwrap();
+        assert_eq!(location.session_id.as_deref(), Some("sess-abc"));
+        assert_eq!(PathBuf::from(&location.transcript_path), transcript);
+        assert_eq!(location.last_share_url, None);
+
+        shares::save_share(&shares::Share {
+            id: "abc".to_string(),
+            transcript_path: transcript.display().to_string(),
+            ..make_test_share("abc")
+        })
+        .unwrap();
+        let location = locate(Tool::Claude, None, 0, &CodexDiscovery::default(), None).unwrap();
+        assert_eq!(
+            location.last_share_url.as_deref(),
+            Some("https://example.com/v/abc#key123")
+        );
+    }
+}
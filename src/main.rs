@@ -1,36 +1,138 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::io::Read;
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::collections::HashSet;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
 
 use agentexport::{
-    Config, GistFormat, PublishOptions, StorageType, Tool, handle_claude_sessionstart, publish,
+    CodexDiscovery, Config, GistFormat, GistRaw, PublishOptions, PublishPlan, PublishPreset,
+    PublishResult, RawContent, RenderFormat, STATE_MAX_AGE_DAYS, SessionPreference, StorageType,
+    TitleSource, Tool, ViewerDefaults, archive, claude_state_stale_reason, cleanup_env_file,
+    compare::{self, GroupBy},
+    emit::{self, EmitEvent},
+    export::{self, ExportFormat, ExportOptions, SessionExportOptions},
+    find_session, handle_claude_sessionstart,
+    lint::{self, LintOptions, Severity},
+    list_claude_states, locate, manpages,
+    monitor::{self, MonitorOptions},
+    named_source,
+    output::{ColorChoice, Output, Table},
+    parse_model_list,
+    pretty::{self, CatOptions},
+    prune_claude_states, publish, publish_all, purge, render_from_gzip,
+    report::{self, ReportOptions},
     run_setup,
+    sessions::{self, SessionSummary},
+    shares::{self, ShareSort},
+    source_for_path,
+    store::{self, Store},
+    test_mode, transcript_tool,
+    usage::{self, UsageOptions},
+    watch::{self, WatchOptions},
 };
 
 mod shares_cmd;
 
+const PUBLISH_EXAMPLES: &str = "\
+Examples:
+  # Share the current Claude session (what /agentexport runs)
+  agentexport publish --tool claude
+
+  # See what would be shared, then publish it
+  agentexport publish --tool codex --plan --yes
+
+  # A past session by id, titled, tagged, and kept for a week
+  agentexport publish --session-id 0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b \\
+      --title \"Parser rewrite\" --tag demo --ttl 7
+
+  # Bundle two sessions (any tools) into one share
+  agentexport publish --transcript plan.jsonl --transcript build.jsonl
+
+  # Just the part of a long session around a match
+  agentexport publish --tool claude --around \"flaky test\" --context 10
+
+  # Replace an earlier share's content, keeping its link
+  agentexport publish --tool claude --update <SHARE_ID>";
+
+const SHARES_EXAMPLES: &str = "\
+Examples:
+  # Pick a share interactively
+  agentexport shares
+
+  # Shares expiring within 3 days, checked against the server
+  agentexport shares list --expiring 3 --check
+
+  # Only Codex shares tagged demo, largest first
+  agentexport shares list --filter tool=codex --filter tag=demo --sort size --reverse
+
+  # Take a share down, or bring one back from the local backup
+  agentexport shares unshare <SHARE_ID>
+  agentexport shares restore <SHARE_ID> --republish --ttl 30
+
+  # Forget expired shares, and take down any older than a year
+  agentexport shares prune --older-than 365 --dry-run";
+
+const EXPORT_EXAMPLES: &str = "\
+Examples:
+  # The current Claude session as markdown
+  agentexport export --tool claude --out session.md
+
+  # As a standalone HTML page
+  agentexport export --tool codex --out session.html
+
+  # Back up every session touched this week, skipping unchanged ones
+  agentexport export --since 7d --out ~/agent-sessions --incremental
+
+  # Everything, into <repo>/<date>/ folders with an index.md
+  agentexport export --all --out ~/agent-sessions";
+
 #[derive(Parser)]
 #[command(name = "agentexport", version, about = "Local agent export helper")]
 struct Cli {
+    /// Print results as JSON on stdout, and errors as {"error": ...}
+    #[arg(long, global = true)]
+    json: bool,
+    /// When to use ANSI colors (auto: on a terminal, unless NO_COLOR is set)
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
     #[command(subcommand)]
     command: Commands,
 }
 
+// Parsed once per run, so the size of the big variants doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Internal: called by Claude hook
     #[command(name = "claude-sessionstart", hide = true)]
     ClaudeSessionstart,
 
-    #[command(name = "publish")]
-    Publish {
+    /// Remove the variables `claude-sessionstart` exported from Claude's env file
+    #[command(name = "cleanup-env")]
+    CleanupEnv {
+        /// Env file to clean (default: $CLAUDE_ENV_FILE)
         #[arg(long)]
-        tool: Tool,
+        env_file: Option<PathBuf>,
+    },
+
+    /// Publish a session as a share link
+    #[command(name = "publish", after_long_help = PUBLISH_EXAMPLES)]
+    Publish {
+        /// Tool to publish from: claude, codex, cursor, aider, chatgpt,
+        /// claude-ai, or a registered source. `all` publishes Claude and Codex
+        /// concurrently
+        #[arg(long, value_parser = parse_publish_tool, required_unless_present_any = ["session_id", "transcript"])]
+        tool: Option<String>,
         #[arg(long, hide = true)]
         term_key: Option<String>,
+        /// Transcript to publish; repeat to bundle several sessions, from any
+        /// tool, into one share
         #[arg(long)]
-        transcript: Option<PathBuf>,
+        transcript: Vec<PathBuf>,
+        /// Publish this past session, found by id in the Claude projects and
+        /// Codex sessions folders (narrowed by --tool), from any directory
+        #[arg(long, conflicts_with = "transcript")]
+        session_id: Option<String>,
         #[arg(long, default_value_t = 10)]
         max_age_minutes: u64,
         #[arg(long)]
@@ -51,12 +153,386 @@ enum Commands {
         /// Title for the share (overrides auto-detected title)
         #[arg(long)]
         title: Option<String>,
+        /// Where to take the auto-detected title from (default: slug, then first message)
+        #[arg(long, value_enum, conflicts_with = "title")]
+        title_from: Option<TitleSource>,
+        /// Build the title from session fields, e.g. "Demo: {first_message:60}"
+        /// (fields: slug, first_message, summary, branch, repo; :N truncates)
+        #[arg(long, conflicts_with_all = ["title", "title_from"])]
+        title_template: Option<String>,
+        /// Only share messages matching this keyword, plus surrounding context
+        #[arg(long)]
+        around: Option<String>,
+        /// Messages of context to keep on either side of an --around match
+        #[arg(long, default_value_t = 3, requires = "around")]
+        context: usize,
+        /// For very long sessions: share the first and last N turns plus N
+        /// turns sampled from the middle, marking what was skipped
+        #[arg(long, value_name = "N", conflicts_with = "metadata_only",
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        preview_sample: Option<usize>,
+        /// Also consider headless `codex exec` sessions when locating the transcript
+        #[arg(long)]
+        include_headless: bool,
+        /// How to choose between multiple Codex sessions for the current directory
+        #[arg(long, value_enum, default_value_t = SessionPreference::NewestHistory)]
+        prefer: SessionPreference,
+        /// Prefer the Claude session on this git branch (default: the current branch)
+        #[arg(long)]
+        branch: Option<String>,
+        /// Include publisher name and hostname in the share (default from the
+        /// preset, then config attribution)
+        #[arg(long, overrides_with = "no_attribution")]
+        attribution: bool,
+        /// Leave publisher name and hostname out, whatever the preset or config say
+        #[arg(long, overrides_with = "attribution")]
+        no_attribution: bool,
+        /// Link this share to an earlier one (shown as "Part N" in the viewer)
+        #[arg(long, value_name = "SHARE_ID")]
+        relates_to: Option<String>,
+        /// Tag the share for filtering in `shares list` (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Replace the content of this existing share, keeping its link
+        #[arg(long, value_name = "SHARE_ID", conflicts_with_all = ["no_upload", "dry_run"])]
+        update: Option<String>,
+        /// Upload even if this transcript was already published with the same options
+        #[arg(long)]
+        force: bool,
+        /// Upload unencrypted to a server running plaintext org mode (uses config upload_token)
+        #[arg(long, conflicts_with = "no_upload")]
+        no_e2e: bool,
+        /// Require a passphrase to open the share, on top of the link
+        /// (prompted for, or read from AGENTEXPORT_PASSPHRASE)
+        #[arg(long, conflicts_with_all = ["no_e2e", "no_upload"])]
+        passphrase: bool,
+        /// Initial viewer visibility, e.g. `tools=show,thinking=hide,system=hide`
+        #[arg(long, value_name = "ROLE=show|hide,...")]
+        viewer_defaults: Option<ViewerDefaults>,
+        /// Include conversation branches abandoned by editing a prompt or regenerating a reply
+        #[arg(long)]
+        include_abandoned: bool,
+        /// Also share the earlier Claude session files this one was compacted
+        /// or resumed from, stitched into one history
+        #[arg(long)]
+        follow_compaction: bool,
+        /// Mark messages by their number (the `#msg-<n>` anchor in the viewer,
+        /// or the `#<n>` in `agentexport cat`); repeatable or comma-separated
+        #[arg(
+            long,
+            value_name = "N",
+            value_delimiter = ',',
+            conflicts_with = "metadata_only"
+        )]
+        highlight: Vec<u32>,
+        /// Leave thinking blocks out of the share
+        #[arg(long)]
+        no_thinking: bool,
+        /// Raw tool input/output to keep: none, truncated, or full (default: config `raw`)
+        #[arg(long, value_enum)]
+        raw: Option<RawContent>,
+        /// Upload without masking API keys, tokens, and private keys
+        #[arg(long)]
+        no_redact: bool,
+        /// Share only the title, models, token and cost stats, duration, and
+        /// message counts, without any conversation text
+        #[arg(long, conflicts_with_all = ["select", "edit"])]
+        metadata_only: bool,
+        /// List the share in the server's public gallery (/explore) until it
+        /// expires. Needs --no-e2e, since the gallery never gets a key.
+        #[arg(long)]
+        public_listing: bool,
+        /// Share pasted and tool-produced images inline, instead of "[Image]"
+        /// placeholders (up to 1 MB each and 6 MB per share)
+        #[arg(long, conflicts_with = "metadata_only")]
+        include_images: bool,
+        /// What to print once uploaded: the bare link, a one-line summary
+        /// (link, expiry, size, messages), or the full result as JSON
+        #[arg(long, value_enum, default_value = "url")]
+        output: PublishOutput,
+        /// Apply a named set of options from [presets.<name>] in the config
+        #[arg(long, value_name = "NAME")]
+        preset: Option<String>,
+        /// Pick the messages to share from a checklist before uploading
+        #[arg(long)]
+        select: bool,
+        /// Review and edit the payload in $EDITOR before uploading
+        #[arg(long)]
+        edit: bool,
+        /// Show what would be published (transcript, messages, redactions, size, destination, TTL) and exit
+        #[arg(long)]
+        plan: bool,
+        /// With --plan, go ahead and publish after showing the plan
+        #[arg(short = 'y', long, requires = "plan")]
+        yes: bool,
+        /// Don't reuse the filters, title, and redaction choices of this session's last publish
+        #[arg(long)]
+        fresh: bool,
     },
+    /// Interactive setup: storage, TTL, slash commands, hook, and a test upload
     #[command(name = "setup")]
     Setup,
 
+    /// Write man pages for agentexport and each subcommand
+    #[command(name = "man")]
+    Man {
+        /// Directory to write into (default: ~/.local/share/man/man1)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Print the current session's transcript, render, and last share as JSON (for editors)
+    #[command(name = "where")]
+    Where {
+        #[arg(long)]
+        tool: Tool,
+        #[arg(long)]
+        transcript: Option<PathBuf>,
+        /// Only consider transcripts modified within this many minutes (0 = any age)
+        #[arg(long, default_value_t = 0)]
+        max_age_minutes: u64,
+        /// Also consider headless `codex exec` sessions
+        #[arg(long)]
+        include_headless: bool,
+        /// How to choose between multiple Codex sessions for the current directory
+        #[arg(long, value_enum, default_value_t = SessionPreference::NewestHistory)]
+        prefer: SessionPreference,
+        /// Prefer the Claude session on this git branch (default: the current branch)
+        #[arg(long)]
+        branch: Option<String>,
+    },
+
+    /// Print the current session's conversation to the terminal
+    #[command(name = "cat")]
+    Cat {
+        /// Tool whose session to print (not needed with --transcript)
+        #[arg(long, required_unless_present = "transcript")]
+        tool: Option<Tool>,
+        #[arg(long)]
+        transcript: Option<PathBuf>,
+        /// Only print the last N messages
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+        /// Only consider transcripts modified within this many minutes (0 = any age)
+        #[arg(long, default_value_t = 0)]
+        max_age_minutes: u64,
+        /// Also consider headless `codex exec` sessions
+        #[arg(long)]
+        include_headless: bool,
+        /// How to choose between multiple Codex sessions for the current directory
+        #[arg(long, value_enum, default_value_t = SessionPreference::NewestHistory)]
+        prefer: SessionPreference,
+        /// Disable ANSI colors (same as --color never)
+        #[arg(long)]
+        no_color: bool,
+        /// Keep printing new messages as the session appends them
+        #[arg(short = 'f', long)]
+        follow: bool,
+    },
+
+    /// Flag risky content (emails, internal hosts, credentials, personal data,
+    /// large dumps) in what publish would upload
+    #[command(name = "lint")]
+    Lint {
+        /// Tool whose session to check (not needed with --transcript)
+        #[arg(long, required_unless_present = "transcript")]
+        tool: Option<Tool>,
+        #[arg(long)]
+        transcript: Option<PathBuf>,
+        /// Only consider transcripts modified within this many minutes (0 = any age)
+        #[arg(long, default_value_t = 0)]
+        max_age_minutes: u64,
+        /// Also consider headless `codex exec` sessions
+        #[arg(long)]
+        include_headless: bool,
+        /// How to choose between multiple Codex sessions for the current directory
+        #[arg(long, value_enum, default_value_t = SessionPreference::NewestHistory)]
+        prefer: SessionPreference,
+        /// Check the content before secret redaction rather than after
+        #[arg(long)]
+        no_redact: bool,
+        /// Exit with an error if any finding is at least this severe
+        #[arg(long, value_enum)]
+        fail_on: Option<Severity>,
+    },
+
+    /// Show live token usage and estimated cost for the current session
+    #[command(name = "monitor")]
+    Monitor {
+        /// Tool whose session to watch (not needed with --transcript)
+        #[arg(long, required_unless_present = "transcript")]
+        tool: Option<Tool>,
+        #[arg(long)]
+        transcript: Option<PathBuf>,
+        /// Only consider transcripts modified within this many minutes (0 = any age)
+        #[arg(long, default_value_t = 0)]
+        max_age_minutes: u64,
+        /// Also consider headless `codex exec` sessions
+        #[arg(long)]
+        include_headless: bool,
+        /// How to choose between multiple Codex sessions for the current directory
+        #[arg(long, value_enum, default_value_t = SessionPreference::NewestHistory)]
+        prefer: SessionPreference,
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+
+    /// Publish the current session and keep the share updated as the session goes on
+    #[command(name = "watch")]
+    Watch {
+        /// Tool whose session to share (not needed with --transcript)
+        #[arg(long, value_parser = parse_source_name, required_unless_present = "transcript")]
+        tool: Option<String>,
+        #[arg(long)]
+        transcript: Option<PathBuf>,
+        #[arg(long, default_value_t = 10)]
+        max_age_minutes: u64,
+        /// Upload URL (default from ~/.agentexport/config.toml or https://agentexports.com)
+        #[arg(long)]
+        upload_url: Option<String>,
+        /// TTL for the share: 30, 60, 90, 180, 365, or 0 for forever (default from config)
+        #[arg(long)]
+        ttl: Option<u64>,
+        /// Title for the share (overrides auto-detected title)
+        #[arg(long)]
+        title: Option<String>,
+        /// Also consider headless `codex exec` sessions
+        #[arg(long)]
+        include_headless: bool,
+        /// How to choose between multiple Codex sessions for the current directory
+        #[arg(long, value_enum, default_value_t = SessionPreference::NewestHistory)]
+        prefer: SessionPreference,
+        /// Prefer the Claude session on this git branch (default: the current branch)
+        #[arg(long)]
+        branch: Option<String>,
+        /// Leave thinking blocks out of the share
+        #[arg(long)]
+        no_thinking: bool,
+        /// Raw tool input/output to keep: none, truncated, or full (default: config `raw`)
+        #[arg(long, value_enum)]
+        raw: Option<RawContent>,
+        /// Upload without masking API keys, tokens, and private keys
+        #[arg(long)]
+        no_redact: bool,
+        /// Seconds between checks for changes
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+
+    /// Compare sessions across models as a markdown report
+    #[command(name = "compare")]
+    Compare {
+        /// Transcript files or directories to scan for .jsonl transcripts
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// How to group sessions
+        #[arg(long, value_enum, default_value_t = GroupBy::Model)]
+        group_by: GroupBy,
+        /// Write the report here instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Summarize recent sessions into one report (uploaded, or written locally with --no-upload)
+    #[command(name = "report")]
+    Report {
+        /// How far back to look: e.g. 24h, 7d, 2w
+        #[arg(long, default_value = "7d")]
+        since: String,
+        /// Title for the report
+        #[arg(long)]
+        title: Option<String>,
+        /// Write the report payload here (implies --no-upload)
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Upload URL (default from ~/.agentexport/config.toml or https://agentexports.com)
+        #[arg(long)]
+        upload_url: Option<String>,
+        /// Skip uploading; keep the payload in the local store
+        #[arg(long)]
+        no_upload: bool,
+        /// TTL for the share: 30, 60, 90, 180, 365, or 0 for forever (default from ~/.agentexport/config.toml or 30)
+        #[arg(long)]
+        ttl: Option<u64>,
+        /// Report without masking API keys, tokens, and private keys
+        #[arg(long)]
+        no_redact: bool,
+    },
+
+    /// Write a session to a local file, or back up sessions into a directory tree
+    #[command(name = "export", after_long_help = EXPORT_EXAMPLES)]
+    Export {
+        /// Tool whose current session to export to a single file
+        #[arg(long, conflicts_with_all = ["all", "since", "incremental"])]
+        tool: Option<Tool>,
+        /// Transcript to export to a single file
+        #[arg(long, conflicts_with_all = ["all", "since", "incremental"])]
+        transcript: Option<PathBuf>,
+        /// Only consider transcripts modified within this many minutes (0 = any age)
+        #[arg(long, default_value_t = 0)]
+        max_age_minutes: u64,
+        /// Single-file format (inferred from the --out extension, default markdown)
+        #[arg(long, value_enum, conflicts_with_all = ["all", "since"])]
+        format: Option<ExportFormat>,
+        /// Create the session as a child of this Confluence page (uses config confluence_token)
+        #[arg(long, value_name = "PAGE_URL", conflicts_with_all = ["all", "since"])]
+        post_to: Option<String>,
+        /// Title for a single-file export
+        #[arg(long, conflicts_with_all = ["all", "since"])]
+        title: Option<String>,
+        /// Export every discovered Claude and Codex session
+        #[arg(long, required_unless_present_any = ["since", "tool", "transcript"])]
+        all: bool,
+        /// Only export sessions touched within this lookback: e.g. 24h, 7d, 2w
+        #[arg(long, conflicts_with = "all")]
+        since: Option<String>,
+        /// File to write, or with --all/--since the directory to export into
+        /// (sessions go under <repo>/<date>/, plus index.md)
+        #[arg(long, required_unless_present = "post_to")]
+        out: Option<PathBuf>,
+        /// Skip sessions unchanged since the last export into this directory
+        #[arg(long)]
+        incremental: bool,
+        /// Export without masking API keys, tokens, and private keys
+        #[arg(long)]
+        no_redact: bool,
+    },
+
+    /// Render a share payload from an archived transcript
+    #[command(name = "render")]
+    Render {
+        /// Archived .jsonl.gz transcript (as written by `publish`)
+        #[arg(long, value_name = "PATH")]
+        from_gzip: PathBuf,
+        /// Tool that produced the transcript (inferred from the file name if omitted)
+        #[arg(long)]
+        tool: Option<Tool>,
+        /// Title for the rendered share
+        #[arg(long)]
+        title: Option<String>,
+        /// Output format: the share payload JSON, or an email (.eml) with inline-styled HTML
+        #[arg(long, value_enum, default_value_t = RenderFormat::Json)]
+        format: RenderFormat,
+        /// Where to write the output
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Delete a session everywhere: its transcript, local artifacts, and shares
+    #[command(name = "purge-session")]
+    PurgeSession {
+        /// Claude session id or Codex thread id
+        id: String,
+        #[arg(long)]
+        tool: Tool,
+        /// Skip confirmation prompt
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
     /// Manage shared transcripts
-    #[command(name = "shares")]
+    #[command(name = "shares", after_long_help = SHARES_EXAMPLES)]
     Shares {
         #[command(subcommand)]
         action: Option<SharesAction>,
@@ -69,6 +545,73 @@ enum Commands {
         action: Option<ConfigAction>,
     },
 
+    /// Run commands when shares are published (runs until interrupted)
+    #[command(name = "emit")]
+    Emit {
+        /// Event to react to
+        #[arg(long, value_enum, default_value_t = EmitEvent::Publish)]
+        on: EmitEvent,
+        /// Shell command to run per event; the share is passed as AGENTEXPORT_* env vars and JSON on stdin (repeatable)
+        #[arg(long, value_name = "CMD", required = true)]
+        exec: Vec<String>,
+        /// Seconds between checks of the shares store
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+
+    /// Browse local Claude and Codex transcripts
+    #[command(name = "sessions")]
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+
+    /// Import a conversation exported from ChatGPT or Claude.ai (a data
+    /// export's conversations.json, a saved ChatGPT share page, or a chat
+    /// saved by a Claude sharing tool) so it can be published like a session
+    #[command(name = "import")]
+    Import {
+        /// The exported file: conversations.json, a share page's .html, or a
+        /// chat's .json
+        file: PathBuf,
+        /// Conversation to import: its id, or part of its title (default:
+        /// pick one interactively)
+        #[arg(long)]
+        conversation: Option<String>,
+        /// List the export's conversations instead of importing one
+        #[arg(long, conflicts_with = "conversation")]
+        list: bool,
+    },
+
+    /// How you've used agentexport: shares per week, sizes, and repos, from
+    /// local history only (nothing is sent anywhere)
+    #[command(name = "usage")]
+    Usage {
+        /// How far back to look: e.g. 4w, 90d (default: all history)
+        #[arg(long)]
+        since: Option<String>,
+        /// Repos to list
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect the local artifact store (transcript gzips and renders)
+    #[command(name = "store")]
+    Store {
+        #[command(subcommand)]
+        action: StoreAction,
+    },
+
+    /// Inspect or clean up Claude session state left by the SessionStart hook
+    #[command(name = "state")]
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+
     /// Update agentexport to the latest version
     #[command(name = "update")]
     Update {
@@ -81,12 +624,193 @@ enum Commands {
 #[derive(Subcommand)]
 enum SharesAction {
     /// List all shares
-    List,
+    List {
+        /// Column to sort by
+        #[arg(long, value_enum, default_value_t = ShareSort::Created)]
+        sort: ShareSort,
+        /// Sort descending
+        #[arg(long)]
+        reverse: bool,
+        /// Only show shares matching key=value (tool, storage, tag, status, session, lang; repeatable)
+        #[arg(long, value_name = "KEY=VALUE")]
+        filter: Vec<String>,
+        /// Only show active shares expiring within DAYS (default 7)
+        #[arg(long, value_name = "DAYS", num_args = 0..=1, default_missing_value = "7")]
+        expiring: Option<i64>,
+        /// Ask the server whether each share is still live, and record the answer
+        /// (without it, only shares not checked in the last hour are asked)
+        #[arg(long)]
+        check: bool,
+        /// Show the recorded status without asking any server
+        #[arg(long, conflicts_with = "check")]
+        offline: bool,
+    },
+    /// Recover a share's payload from the local backup
+    Restore {
+        /// Share ID to restore
+        id: String,
+        /// Write the payload JSON here instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Upload the backed-up payload again as a new share
+        #[arg(long, conflicts_with = "out")]
+        republish: bool,
+        /// TTL in days for the republished share (default from config)
+        #[arg(long, requires = "republish")]
+        ttl: Option<u64>,
+    },
     /// Delete a share from the server
     Unshare {
         /// Share ID to delete
         id: String,
     },
+    /// Download a share and check it decrypts with the stored key
+    Verify {
+        /// Share ID to verify
+        id: String,
+    },
+    /// Change a share's title (server shares are re-uploaded and get a new link)
+    Retitle {
+        /// Share ID to retitle
+        id: String,
+        /// New title
+        title: String,
+    },
+    /// Change how long a server share is kept, counting from now (the link stays the same)
+    Extend {
+        /// Share ID to extend
+        id: String,
+        /// New TTL in days: 30, 60, 90, 180, 365, or 0 for forever
+        #[arg(long)]
+        ttl: u64,
+    },
+    /// Re-encrypt shares under fresh keys and print their new links
+    /// (for when shares.json may have leaked; the old links stop working)
+    Rekey {
+        /// Share ID to rekey
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id: Option<String>,
+        /// Rekey every live end-to-end encrypted share
+        #[arg(long)]
+        all: bool,
+    },
+    /// Move a share to another storage backend (it gets a new link)
+    Migrate {
+        /// Share ID to move
+        id: String,
+        /// Backend to move to: agentexport or gist
+        #[arg(long, value_name = "STORAGE")]
+        to: String,
+        /// Server for agentexport storage (default from config)
+        #[arg(long)]
+        upload_url: Option<String>,
+        /// Delete the original share once the copy is uploaded
+        #[arg(long)]
+        delete_old: bool,
+    },
+    /// Sync the shares list with other machines through a file in a git repo
+    /// or an S3 object (config `shares_sync`)
+    Sync {
+        /// push: merge these shares into the synced copy; pull: merge the
+        /// synced copy into these shares
+        direction: SyncDirection,
+        /// Sync target instead of the configured one: a file in a git
+        /// checkout, or s3://bucket/key
+        #[arg(long)]
+        target: Option<String>,
+        /// push: also upload each share's key and delete token, so the
+        /// other machines can open and delete the shares
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Wipe the keys and delete tokens of expired shares, keeping their
+    /// metadata (config `scrub_expired_keys` does this automatically)
+    Scrub,
+    /// Drop expired shares from the list and remove store artifacts no share
+    /// refers to
+    Prune {
+        /// Also delete live shares older than DAYS from their server
+        /// (default: config `prune_after_days`; 0 to skip)
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation before deleting shares from their server
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum SyncDirection {
+    Push,
+    Pull,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum PublishOutput {
+    Url,
+    Summary,
+    Json,
+}
+
+/// `--tool` naming a transcript source, built-in or registered
+fn parse_source_name(value: &str) -> Result<String, String> {
+    named_source(value)
+        .map(|source| source.name().to_string())
+        .map_err(|err| err.to_string())
+}
+
+/// Publish's `--tool`: a transcript source, or `all`
+fn parse_publish_tool(value: &str) -> Result<String, String> {
+    if value == "all" {
+        return Ok(value.to_string());
+    }
+    parse_source_name(value)
+}
+
+#[derive(Subcommand)]
+enum SessionsAction {
+    /// List sessions, newest first
+    List {
+        /// Only list this tool's sessions (claude or codex; default: both)
+        #[arg(long)]
+        tool: Option<Tool>,
+        /// List every session instead of the most recent ones
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StoreAction {
+    /// Re-hash stored artifacts and report missing or corrupt objects
+    Verify,
+    /// Remove artifacts no longer referenced by any share
+    Gc {
+        /// Only collect unreferenced artifacts older than this many days
+        #[arg(long, default_value_t = 7)]
+        older_than: i64,
+        /// Show what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StateAction {
+    /// List state entries, newest first
+    List,
+    /// Remove entries that are old, unreadable, or whose transcript is gone
+    Clean {
+        /// Remove entries not updated for this many days
+        #[arg(long, default_value_t = STATE_MAX_AGE_DAYS)]
+        older_than: u64,
+        /// Show what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -95,7 +819,7 @@ enum ConfigAction {
     Show,
     /// Set a config value
     Set {
-        /// Key to set (default_ttl, storage_type, upload_url, gist_format)
+        /// Key to set (default_ttl, storage_type, upload_url, gist_format, raw, attribution, handle, upload_token, confluence_email, confluence_token)
         key: String,
         /// Value to set
         value: String,
@@ -105,24 +829,41 @@ enum ConfigAction {
 }
 
 fn main() {
-    check_for_update_async();
-    if let Err(err) = run() {
-        eprintln!("error: {err}");
+    let cli = Cli::parse();
+    check_for_update_async(cli.color.enabled(&std::io::stderr()));
+    let json = cli.json;
+    if let Err(err) = run(cli) {
+        if json {
+            println!("{}", serde_json::json!({ "error": format!("{err:#}") }));
+        } else {
+            eprintln!("error: {err}");
+        }
         std::process::exit(1);
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+fn run(cli: Cli) -> Result<()> {
+    let json = cli.json;
+    let color = cli.color;
     match cli.command {
         Commands::ClaudeSessionstart => {
             let input = read_stdin()?;
             handle_claude_sessionstart(&input)?;
         }
+        Commands::CleanupEnv { env_file } => {
+            let Some(path) =
+                env_file.or_else(|| std::env::var_os("CLAUDE_ENV_FILE").map(PathBuf::from))
+            else {
+                anyhow::bail!("CLAUDE_ENV_FILE not set; pass --env-file");
+            };
+            let removed = cleanup_env_file(&path)?;
+            println!("removed {removed} line(s) from {}", path.display());
+        }
         Commands::Publish {
             tool,
             term_key,
             transcript,
+            session_id,
             max_age_minutes,
             out,
             dry_run,
@@ -131,23 +872,163 @@ fn run() -> Result<()> {
             render,
             ttl,
             title,
+            title_from,
+            title_template,
+            around,
+            context,
+            preview_sample,
+            include_headless,
+            prefer,
+            branch,
+            attribution,
+            no_attribution,
+            relates_to,
+            mut tags,
+            update,
+            force,
+            no_e2e,
+            passphrase,
+            viewer_defaults,
+            include_abandoned,
+            follow_compaction,
+            highlight,
+            no_thinking,
+            raw,
+            no_redact,
+            metadata_only,
+            public_listing,
+            include_images,
+            output,
+            preset,
+            select,
+            edit,
+            plan,
+            yes,
+            fresh,
         } => {
-            let config = Config::load().unwrap_or_default();
-            let effective_ttl = ttl.unwrap_or(config.default_ttl);
+            let output = if json { PublishOutput::Json } else { output };
+            // Choices given now replace the ones remembered for the session
+            let reuse_prefs = !fresh
+                && preset.is_none()
+                && title.is_none()
+                && title_from.is_none()
+                && title_template.is_none()
+                && around.is_none()
+                && preview_sample.is_none()
+                && viewer_defaults.is_none()
+                && !include_abandoned
+                && !no_thinking
+                && raw.is_none()
+                && !no_redact;
+            let config = match &preset {
+                // A broken config shouldn't silently drop the requested preset
+                Some(_) => Config::load()?,
+                None => Config::load().unwrap_or_default(),
+            };
+            let preset = match &preset {
+                Some(name) => config.preset(name)?.clone(),
+                None => PublishPreset::default(),
+            };
+            // Explicit title flags replace the preset's title choice entirely
+            let explicit_title =
+                title.is_some() || title_from.is_some() || title_template.is_some();
+            let (title, title_from, title_template) = if explicit_title {
+                (title, title_from, title_template)
+            } else {
+                (preset.title, preset.title_from, preset.title_template)
+            };
+            let viewer_defaults = match (viewer_defaults, &preset.viewer_defaults) {
+                (Some(defaults), _) => Some(defaults),
+                (None, Some(value)) => Some(
+                    value
+                        .parse::<ViewerDefaults>()
+                        .map_err(|err| anyhow::anyhow!("preset viewer_defaults: {err}"))?,
+                ),
+                (None, None) => None,
+            };
+            for tag in preset.tags {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            let no_e2e = no_e2e || (preset.no_e2e && !no_upload);
+            let upload_url = upload_url.or(preset.upload_url);
+            let effective_ttl = ttl.or(preset.ttl).unwrap_or(config.default_ttl);
             let effective_storage_type = config.storage_type;
             let effective_gist_format = config.gist_format;
+            let plaintext_token = if no_e2e {
+                if effective_storage_type == StorageType::Gist {
+                    anyhow::bail!("--no-e2e only applies to agentexport storage");
+                }
+                let Some(token) = config.upload_token.clone() else {
+                    anyhow::bail!(
+                        "--no-e2e needs an upload token: agentexport config set upload_token <TOKEN>"
+                    );
+                };
+                Some(token)
+            } else {
+                None
+            };
             let effective_upload_url = if no_upload {
                 None
             } else if effective_storage_type == StorageType::Gist {
                 Some("gist".to_string())
             } else {
-                Some(upload_url.unwrap_or(config.upload_url))
+                Some(upload_url.unwrap_or(config.upload_url.clone()))
             };
             let has_upload_target = effective_upload_url.is_some();
-            let result = publish(PublishOptions {
-                tool,
+            let passphrase = if passphrase {
+                if effective_storage_type == StorageType::Gist || plaintext_token.is_some() {
+                    anyhow::bail!(
+                        "--passphrase only applies to end-to-end encrypted agentexport shares"
+                    );
+                }
+                Some(read_passphrase()?)
+            } else {
+                None
+            };
+            let mut transcripts = transcript.into_iter();
+            let transcript = transcripts.next();
+            let bundle: Vec<PathBuf> = transcripts.collect();
+            // A session id stands in for --transcript, found wherever it lives.
+            // Past sessions are the point, so they're never too old to publish.
+            let (tool, transcript, max_age_minutes) = match session_id {
+                Some(id) => {
+                    let tools = match tool.as_deref() {
+                        Some("all") | None => vec![Tool::Claude, Tool::Codex],
+                        Some(name) => match <Tool as clap::ValueEnum>::from_str(name, false) {
+                            Ok(tool) => vec![tool],
+                            Err(_) => anyhow::bail!(
+                                "--session-id only finds sessions of the built-in tools; pass --transcript"
+                            ),
+                        },
+                    };
+                    let (found, path) = find_session(&tools, &id)?;
+                    (found.as_str().to_string(), Some(path), 0)
+                }
+                None => {
+                    let tool = match (tool, &transcript) {
+                        (Some(tool), _) => tool,
+                        (None, Some(path)) => match source_for_path(path) {
+                            Some(source) => source.name().to_string(),
+                            // Not a transcript: report why
+                            None => transcript_tool(path)?.as_str().to_string(),
+                        },
+                        (None, None) => Tool::Claude.as_str().to_string(),
+                    };
+                    (tool, transcript, max_age_minutes)
+                }
+            };
+            let publish_all_tools = tool == "all";
+            let options = PublishOptions {
+                tool: if publish_all_tools {
+                    Tool::Claude.as_str().to_string()
+                } else {
+                    tool
+                },
                 term_key,
                 transcript,
+                bundle,
                 max_age_minutes,
                 out,
                 dry_run,
@@ -156,14 +1037,71 @@ fn run() -> Result<()> {
                 ttl_days: effective_ttl,
                 storage_type: effective_storage_type,
                 gist_format: effective_gist_format,
+                gist_include_raw: config.gist_include_raw,
                 title,
-            })?;
+                title_from,
+                around,
+                context,
+                preview_sample,
+                include_headless,
+                prefer,
+                branch,
+                attribution: match (attribution, no_attribution) {
+                    (true, _) => true,
+                    (_, true) => false,
+                    _ => preset.attribution.unwrap_or(config.attribution),
+                },
+                handle: config.handle.clone(),
+                relates_to,
+                tags,
+                force,
+                plaintext_token,
+                passphrase,
+                viewer_defaults,
+                include_abandoned: include_abandoned || preset.include_abandoned,
+                follow_compaction,
+                highlight,
+                title_template,
+                no_thinking: no_thinking || preset.no_thinking,
+                raw: raw.or(preset.raw).unwrap_or(config.raw),
+                redact: !no_redact && preset.redact.unwrap_or(true),
+                redact_patterns: config.redact_patterns.clone(),
+                secret_scanner: config.secret_scanner.clone(),
+                include_images,
+                pricing: config.pricing.clone(),
+                metadata_only,
+                public_listing,
+                model_policy: config.model_policy(),
+                select,
+                edit,
+                plan_only: plan && !yes,
+                on_plan: (plan && yes).then_some(print_plan as fn(&PublishPlan)),
+                reuse_prefs,
+                replaces: update,
+            };
+
+            if config.scrub_expired_keys
+                && let Err(err) = agentexport::shares::scrub_expired()
+            {
+                eprintln!("warning: could not scrub expired share keys: {err:#}");
+            }
+            if publish_all_tools {
+                return print_publish_all(publish_all(options)?, has_upload_target, output);
+            }
+            let result = publish(options)?;
+            if let Some(plan) = &result.plan
+                && output != PublishOutput::Json
+            {
+                println!("{plan}");
+                return Ok(());
+            }
+            report_publish_notes(&result);
 
             // When uploading, print just the share URL to stdout (for piping)
-            // Otherwise, print full JSON result
+            // unless asked for more. Otherwise, print full JSON result
             if has_upload_target {
-                if let Some(url) = &result.share_url {
-                    println!("{url}");
+                if let Some(line) = publish_output_line(&result, output)? {
+                    println!("{line}");
                 } else {
                     // No URL returned (dry-run or error), print JSON for debugging
                     eprintln!("{}", serde_json::to_string_pretty(&result)?);
@@ -173,13 +1111,340 @@ fn run() -> Result<()> {
             }
         }
         Commands::Setup => {
-            run_setup()?;
+            run_setup(Cli::command())?;
+        }
+        Commands::Man { out } => {
+            let dir = match out {
+                Some(dir) => dir,
+                None => manpages::user_man_dir()?,
+            };
+            let written = manpages::write_man_pages(Cli::command(), &dir)?;
+            println!("Wrote {} man pages to {}", written.len(), dir.display());
+        }
+        Commands::Where {
+            tool,
+            transcript,
+            max_age_minutes,
+            include_headless,
+            prefer,
+            branch,
+        } => {
+            let location = locate(
+                tool,
+                transcript,
+                max_age_minutes,
+                &CodexDiscovery {
+                    include_headless,
+                    prefer,
+                },
+                branch.as_deref(),
+            )?;
+            println!("{}", serde_json::to_string_pretty(&location)?);
+        }
+        Commands::Cat {
+            tool,
+            transcript,
+            tail,
+            max_age_minutes,
+            include_headless,
+            prefer,
+            no_color,
+            follow,
+        } => {
+            pretty::cat(CatOptions {
+                tool: tool.unwrap_or(Tool::Claude),
+                transcript,
+                max_age_minutes,
+                discovery: CodexDiscovery {
+                    include_headless,
+                    prefer,
+                },
+                tail,
+                color: if no_color { ColorChoice::Never } else { color },
+                follow,
+            })?;
+        }
+        Commands::Lint {
+            tool,
+            transcript,
+            max_age_minutes,
+            include_headless,
+            prefer,
+            no_redact,
+            fail_on,
+        } => {
+            let config = Config::load().unwrap_or_default();
+            let (path, findings) = lint::lint(LintOptions {
+                tool: tool.unwrap_or(Tool::Claude),
+                transcript,
+                max_age_minutes,
+                discovery: CodexDiscovery {
+                    include_headless,
+                    prefer,
+                },
+                redact_patterns: (!no_redact).then(|| config.redact_patterns.clone()),
+                secret_scanner: config.secret_scanner.clone(),
+            })?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&findings)?);
+            } else {
+                for finding in &findings {
+                    println!("{finding}");
+                }
+            }
+            eprintln!("{}: {} finding(s)", path.display(), findings.len());
+            if let Some(threshold) = fail_on {
+                let failing = findings.iter().filter(|f| f.severity >= threshold).count();
+                if failing > 0 {
+                    anyhow::bail!("{failing} finding(s) at {threshold} or above");
+                }
+            }
+        }
+        Commands::Import {
+            file,
+            conversation,
+            list,
+        } => handle_import(&file, conversation.as_deref(), list, json, color)?,
+        Commands::Usage { since, top, json } => {
+            let report = usage::usage(UsageOptions {
+                since: since.as_deref().map(report::parse_since).transpose()?,
+                top,
+            })?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", usage::format_usage(&report, &Output::detect(color)));
+            }
+        }
+        Commands::Monitor {
+            tool,
+            transcript,
+            max_age_minutes,
+            include_headless,
+            prefer,
+            interval,
+        } => {
+            monitor::monitor(MonitorOptions {
+                tool: tool.unwrap_or(Tool::Claude),
+                transcript,
+                max_age_minutes,
+                discovery: CodexDiscovery {
+                    include_headless,
+                    prefer,
+                },
+                interval: std::time::Duration::from_secs(interval.max(1)),
+                color,
+            })?;
+        }
+        Commands::Watch {
+            tool,
+            transcript,
+            max_age_minutes,
+            upload_url,
+            ttl,
+            title,
+            include_headless,
+            prefer,
+            branch,
+            no_thinking,
+            raw,
+            no_redact,
+            interval,
+        } => {
+            let config = Config::load().unwrap_or_default();
+            let upload_url = if config.storage_type == StorageType::Gist {
+                "gist".to_string()
+            } else {
+                upload_url.unwrap_or(config.upload_url.clone())
+            };
+            let options = PublishOptions {
+                tool: tool.unwrap_or_else(|| Tool::Claude.as_str().to_string()),
+                term_key: None,
+                transcript,
+                bundle: Vec::new(),
+                max_age_minutes,
+                out: None,
+                dry_run: false,
+                upload_url: Some(upload_url),
+                render: false,
+                ttl_days: ttl.unwrap_or(config.default_ttl),
+                storage_type: config.storage_type,
+                gist_format: config.gist_format,
+                gist_include_raw: config.gist_include_raw,
+                title,
+                title_from: None,
+                around: None,
+                context: 0,
+                preview_sample: None,
+                include_headless,
+                prefer,
+                branch,
+                attribution: config.attribution,
+                handle: config.handle.clone(),
+                relates_to: None,
+                tags: Vec::new(),
+                force: false,
+                plaintext_token: None,
+                passphrase: None,
+                viewer_defaults: None,
+                include_abandoned: false,
+                follow_compaction: false,
+                highlight: Vec::new(),
+                title_template: None,
+                no_thinking,
+                raw: raw.unwrap_or(config.raw),
+                redact: !no_redact,
+                redact_patterns: config.redact_patterns.clone(),
+                secret_scanner: config.secret_scanner.clone(),
+                include_images: false,
+                pricing: config.pricing.clone(),
+                metadata_only: false,
+                public_listing: false,
+                model_policy: config.model_policy(),
+                select: false,
+                edit: false,
+                plan_only: false,
+                on_plan: None,
+                reuse_prefs: false,
+                replaces: None,
+            };
+            let mut last_url: Option<String> = None;
+            watch::watch(
+                WatchOptions {
+                    publish: options,
+                    interval: std::time::Duration::from_secs(interval.max(1)),
+                },
+                |result| {
+                    match &last_url {
+                        None => eprintln!("Watching {} (Ctrl-C to stop)", result.transcript_path),
+                        Some(_) => eprintln!("{}", result.note),
+                    }
+                    // Print the link again only when an update moved the share
+                    if result.share_url != last_url {
+                        last_url = result.share_url.clone();
+                        println!("{}", last_url.as_deref().unwrap_or_default());
+                    }
+                },
+            )?;
+        }
+        Commands::Compare {
+            paths,
+            group_by,
+            out,
+        } => {
+            let report = compare::compare(&paths, group_by)?;
+            match out {
+                Some(path) => std::fs::write(&path, report)?,
+                None => print!("{report}"),
+            }
+        }
+        Commands::Report {
+            since,
+            title,
+            out,
+            upload_url,
+            no_upload,
+            ttl,
+            no_redact,
+        } => {
+            let config = Config::load().unwrap_or_default();
+            let upload_url = if no_upload || out.is_some() {
+                None
+            } else {
+                Some(upload_url.unwrap_or(config.upload_url))
+            };
+            let result = report::report(ReportOptions {
+                since,
+                title,
+                out,
+                upload_url,
+                ttl_days: ttl.unwrap_or(config.default_ttl),
+                storage_type: config.storage_type,
+                redact_patterns: (!no_redact).then(|| config.redact_patterns.clone()),
+                secret_scanner: config.secret_scanner.clone(),
+            })?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        Commands::Export {
+            tool,
+            transcript,
+            max_age_minutes,
+            format,
+            post_to,
+            title,
+            out,
+            no_redact,
+            ..
+        } if tool.is_some() || transcript.is_some() => {
+            let config = Config::load().unwrap_or_default();
+            let result = export::export_one(SessionExportOptions {
+                tool: tool.unwrap_or(Tool::Claude),
+                transcript,
+                max_age_minutes,
+                title,
+                format,
+                out,
+                post_to,
+                redact_patterns: (!no_redact).then(|| config.redact_patterns.clone()),
+                secret_scanner: config.secret_scanner.clone(),
+            })?;
+            if result.redacted > 0 {
+                eprintln!(
+                    "Redacted {} secret(s) (use --no-redact to keep them)",
+                    result.redacted
+                );
+            }
+            for line in [result.path, result.page_url].into_iter().flatten() {
+                println!("{line}");
+            }
+        }
+        Commands::Export {
+            since,
+            out,
+            incremental,
+            no_redact,
+            ..
+        } => {
+            let config = Config::load().unwrap_or_default();
+            let result = export::export(ExportOptions {
+                out: out.ok_or_else(|| anyhow::anyhow!("--out is required"))?,
+                since,
+                incremental,
+                redact_patterns: (!no_redact).then(|| config.redact_patterns.clone()),
+                secret_scanner: config.secret_scanner.clone(),
+            })?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        Commands::Render {
+            from_gzip,
+            tool,
+            title,
+            format,
+            out,
+        } => {
+            let path = render_from_gzip(&from_gzip, tool, title.as_deref(), format, out)?;
+            println!("{}", path.display());
+        }
+        Commands::PurgeSession { id, tool, yes } => {
+            purge_session(tool, &id, yes)?;
         }
         Commands::Shares { action } => {
-            shares_cmd::run(action)?;
+            shares_cmd::run(action, json, Output::detect(color))?;
+        }
+        Commands::Emit { on, exec, interval } => {
+            emit::watch(on, &exec, std::time::Duration::from_secs(interval.max(1)))?;
+        }
+        Commands::Sessions { action } => {
+            handle_sessions(action, json, color)?;
+        }
+        Commands::Store { action } => {
+            handle_store(action)?;
+        }
+        Commands::State { action } => {
+            handle_state(action)?;
         }
         Commands::Config { action } => {
-            handle_config(action)?;
+            handle_config(action, json)?;
         }
         Commands::Update { yes } => {
             run_update(yes)?;
@@ -188,14 +1453,466 @@ fn run() -> Result<()> {
     Ok(())
 }
 
-fn handle_config(action: Option<ConfigAction>) -> Result<()> {
+/// Tell the user on stderr when secrets were masked
+fn report_publish_notes(result: &PublishResult) {
+    if result.reused_prefs {
+        eprintln!("Reused the options from this session's last publish (--fresh ignores them)");
+    }
+    if result.redacted > 0 {
+        eprintln!(
+            "Redacted {} secret(s) before sharing (use --no-redact to keep them)",
+            result.redacted
+        );
+    }
+}
+
+/// Passphrase for `publish --passphrase`, from the environment or a prompt
+fn read_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var("AGENTEXPORT_PASSPHRASE")
+        && !passphrase.is_empty()
+    {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("--passphrase needs an interactive terminal or AGENTEXPORT_PASSPHRASE");
+    }
+    let passphrase = dialoguer::Password::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Share passphrase")
+        .with_confirmation("Repeat passphrase", "Passphrases don't match")
+        .interact()?;
+    Ok(passphrase)
+}
+
+/// Show the plan on stderr before `--plan --yes` uploads
+fn print_plan(plan: &PublishPlan) {
+    eprintln!("{plan}\n");
+}
+
+/// What `publish` prints for an uploaded share; None when there's no link
+fn publish_output_line(result: &PublishResult, output: PublishOutput) -> Result<Option<String>> {
+    if result.share_url.is_none() {
+        return Ok(None);
+    }
+    Ok(match output {
+        PublishOutput::Url => result.share_url.clone(),
+        PublishOutput::Summary => result.summary(),
+        PublishOutput::Json => Some(serde_json::to_string_pretty(result)?),
+    })
+}
+
+/// Print one line per tool for `publish --tool all`; fails if any tool failed
+fn print_publish_all(
+    results: Vec<(Tool, Result<PublishResult>)>,
+    has_upload_target: bool,
+    output: PublishOutput,
+) -> Result<()> {
+    let mut failed = 0;
+    for (tool, result) in results {
+        match result {
+            Ok(PublishResult {
+                share_url: None,
+                plan: Some(plan),
+                ..
+            }) if output != PublishOutput::Json => println!("{}:\n{plan}\n", tool.as_str()),
+            Ok(result) if has_upload_target && result.share_url.is_some() => {
+                report_publish_notes(&result);
+                let line = publish_output_line(&result, output)?.unwrap_or_default();
+                // JSON already names the tool
+                if output == PublishOutput::Json {
+                    println!("{line}");
+                } else {
+                    println!("{}: {line}", tool.as_str());
+                }
+            }
+            Ok(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+            Err(err) => {
+                failed += 1;
+                eprintln!("{}: error: {err}", tool.as_str());
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of 2 publishes failed");
+    }
+    Ok(())
+}
+
+fn purge_session(tool: Tool, id: &str, skip_confirm: bool) -> Result<()> {
+    let plan = purge::plan(tool, id)?;
+    if plan.is_empty() {
+        anyhow::bail!("Nothing found for {} session {id}", tool.as_str());
+    }
+    println!("This will permanently delete:");
+    for path in &plan.transcripts {
+        println!("  transcript  {}", path.display());
+    }
+    let store = Store::open()?;
+    for hash in &plan.objects {
+        println!("  artifact    {}", store.object_path(hash).display());
+    }
+    for path in &plan.files {
+        println!("  gzip        {}", path.display());
+    }
+    if plan.prefs.is_some() {
+        println!("  options     remembered publish options");
+    }
+    for share in &plan.shares {
+        println!(
+            "  share       {} (server copy and local backup)",
+            share.url()
+        );
+    }
+    println!();
+
+    if !skip_confirm {
+        use dialoguer::{Confirm, theme::ColorfulTheme};
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("Refusing to purge without confirmation; pass --yes");
+        }
+        let confirm = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Delete all of this?")
+            .default(false)
+            .interact()?;
+        if !confirm {
+            println!("Purge cancelled.");
+            return Ok(());
+        }
+    }
+
+    let report = purge::execute(&plan)?;
+    println!(
+        "Deleted {} file(s) and {} share(s).",
+        report.files_removed, report.shares_removed
+    );
+    for (id, err) in &report.failed_shares {
+        eprintln!("warning: share {id} was not deleted from the server: {err}");
+        eprintln!("  retry with: agentexport shares unshare {id}");
+    }
+    if !report.failed_shares.is_empty() {
+        anyhow::bail!(
+            "{} share(s) could not be deleted",
+            report.failed_shares.len()
+        );
+    }
+    Ok(())
+}
+
+fn handle_store(action: StoreAction) -> Result<()> {
+    let store = Store::open()?;
+    match action {
+        StoreAction::Verify => {
+            let issues = store.verify()?;
+            if issues.is_empty() {
+                println!("All artifacts verified.");
+                return Ok(());
+            }
+            for issue in &issues {
+                println!("{issue}");
+            }
+            anyhow::bail!("{} artifact(s) failed verification", issues.len());
+        }
+        StoreAction::Gc {
+            older_than,
+            dry_run,
+        } => {
+            let keep: HashSet<String> = shares::load_shares()?
+                .iter()
+                .filter_map(|s| s.gzip_path.as_deref())
+                .filter_map(|p| store::hash_from_path(Path::new(p)))
+                .collect();
+            let removed = store.gc(&keep, time::Duration::days(older_than), dry_run)?;
+            let verb = if dry_run { "Would remove" } else { "Removed" };
+            for hash in &removed {
+                println!("{verb} {hash}");
+            }
+            println!("{verb} {} artifact(s).", removed.len());
+        }
+    }
+    Ok(())
+}
+
+fn handle_sessions(action: SessionsAction, json: bool, color: ColorChoice) -> Result<()> {
+    match action {
+        SessionsAction::List { tool, all } => {
+            let tools = match tool {
+                Some(tool) => vec![tool],
+                None => vec![Tool::Claude, Tool::Codex],
+            };
+            let limit = (!all).then_some(sessions::DEFAULT_LIMIT);
+            let found = sessions::list_sessions(&tools, limit)?;
+            if json {
+                let rows: Vec<serde_json::Value> = found
+                    .iter()
+                    .map(|s| {
+                        serde_json::json!({
+                            "id": s.id,
+                            "tool": s.tool.as_str(),
+                            "path": s.path,
+                            "cwd": s.cwd,
+                            "title": s.title,
+                            "modified": s.modified.format(&time::format_description::well_known::Rfc3339).unwrap_or_default(),
+                            "messages": s.messages,
+                            "input_tokens": s.input_tokens,
+                            "output_tokens": s.output_tokens,
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+                return Ok(());
+            }
+            if found.is_empty() {
+                println!("No sessions found.");
+                return Ok(());
+            }
+            print!("{}", sessions_table(&found).render(&Output::detect(color)));
+            if found.len() == sessions::DEFAULT_LIMIT && !all {
+                eprintln!(
+                    "Showing the {} most recent; use --all for every session.",
+                    found.len()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Longest title shown in `sessions list`, in characters
+const SESSION_TITLE_CHARS: usize = 60;
+
+fn sessions_table(found: &[SessionSummary]) -> Table {
+    let format = time::format_description::parse("[year]-[month]-[day] [hour]:[minute]")
+        .expect("valid format");
+    let home = std::env::var("HOME").ok().filter(|h| !h.is_empty());
+    let mut table = Table::new(&[
+        "ID", "TOOL", "MODIFIED", "MSGS", "IN", "OUT", "CWD", "TITLE",
+    ])
+    .right_align(&[3, 4, 5])
+    .shrink(&[6, 7]);
+    for s in found {
+        let cwd = s.cwd.clone().unwrap_or_default();
+        let cwd = match home.as_deref().and_then(|h| cwd.strip_prefix(h)) {
+            Some(rest) => format!("~{rest}"),
+            None => cwd,
+        };
+        let title = s.title.as_deref().unwrap_or("").replace('\n', " ");
+        let title = if title.chars().count() > SESSION_TITLE_CHARS {
+            let cut: String = title.chars().take(SESSION_TITLE_CHARS - 1).collect();
+            format!("{}…", cut.trim_end())
+        } else {
+            title
+        };
+        table.push(vec![
+            s.id.clone(),
+            s.tool.as_str().to_string(),
+            s.modified.format(&format).unwrap_or_default(),
+            s.messages.to_string(),
+            monitor::format_tokens(s.input_tokens),
+            monitor::format_tokens(s.output_tokens),
+            cwd,
+            title,
+        ]);
+    }
+    table
+}
+
+fn handle_import(
+    file: &Path,
+    conversation: Option<&str>,
+    list: bool,
+    json: bool,
+    color: ColorChoice,
+) -> Result<()> {
+    let archive = archive::Archive::read(file)?;
+    let conversations = archive.list();
+    let rfc3339 = &time::format_description::well_known::Rfc3339;
+    if list {
+        if json {
+            let rows: Vec<serde_json::Value> = conversations
+                .iter()
+                .map(|c| {
+                    serde_json::json!({
+                        "id": c.id,
+                        "title": c.title,
+                        "updated": c.updated_at.and_then(|t| t.format(rfc3339).ok()),
+                        "messages": c.messages,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+            return Ok(());
+        }
+        if conversations.is_empty() {
+            println!("No conversations in {}.", file.display());
+            return Ok(());
+        }
+        let format = time::format_description::parse("[year]-[month]-[day] [hour]:[minute]")
+            .expect("valid format");
+        let mut table = Table::new(&["ID", "UPDATED", "MSGS", "TITLE"])
+            .right_align(&[2])
+            .shrink(&[3]);
+        for c in &conversations {
+            table.push(vec![
+                c.id.clone(),
+                c.updated_at
+                    .and_then(|t| t.format(&format).ok())
+                    .unwrap_or_default(),
+                c.messages.to_string(),
+                c.title.clone(),
+            ]);
+        }
+        print!("{}", table.render(&Output::detect(color)));
+        return Ok(());
+    }
+
+    let picked = match conversation {
+        Some(query) => archive.find(query)?,
+        None => {
+            if conversations.is_empty() {
+                anyhow::bail!("No conversations in {}", file.display());
+            }
+            if !std::io::stdin().is_terminal() {
+                anyhow::bail!(
+                    "{} has {} conversations; pick one with --conversation <id or title>",
+                    file.display(),
+                    conversations.len()
+                );
+            }
+            let labels: Vec<String> = conversations
+                .iter()
+                .map(|c| format!("{} ({} messages)", c.title, c.messages))
+                .collect();
+            let index = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                .with_prompt("Conversation to import")
+                .items(&labels)
+                .default(0)
+                .interact()?;
+            archive.find(&conversations[index].id)?
+        }
+    };
+    let (path, id) = archive.import(picked)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(
+                &serde_json::json!({ "id": id, "tool": archive.tool.as_str(), "path": path })
+            )?
+        );
+    } else {
+        println!("Imported {id}");
+        eprintln!(
+            "Publish it with: agentexport publish --tool {} --session-id {id}",
+            archive.tool.as_str()
+        );
+    }
+    Ok(())
+}
+
+fn handle_state(action: StateAction) -> Result<()> {
     match action {
+        StateAction::List => {
+            let states = list_claude_states()?;
+            if states.is_empty() {
+                println!("No state entries.");
+                return Ok(());
+            }
+            let now = time::OffsetDateTime::now_utc().unix_timestamp().max(0) as u64;
+            for (path, state) in states {
+                let Some(state) = state else {
+                    println!("{}  (unreadable)", path.display());
+                    continue;
+                };
+                let age_hours = now.saturating_sub(state.updated_at) / 3600;
+                let stale = claude_state_stale_reason(&state, now, STATE_MAX_AGE_DAYS)
+                    .map(|reason| format!("  ({reason})"))
+                    .unwrap_or_default();
+                println!(
+                    "{}  {}  {}h ago  {}{stale}",
+                    state.term_key, state.session_id, age_hours, state.transcript_path
+                );
+            }
+        }
+        StateAction::Clean {
+            older_than,
+            dry_run,
+        } => {
+            let removed = prune_claude_states(older_than, dry_run)?;
+            let verb = if dry_run { "Would remove" } else { "Removed" };
+            for (path, reason) in &removed {
+                println!("{verb} {} ({reason})", path.display());
+            }
+            println!("{verb} {} state file(s).", removed.len());
+        }
+    }
+    Ok(())
+}
+
+fn handle_config(action: Option<ConfigAction>, json: bool) -> Result<()> {
+    match action {
+        None | Some(ConfigAction::Show) if json => {
+            let config = Config::load().unwrap_or_default();
+            let mut shown = serde_json::to_value(&config)?;
+            // Like the text listing, say whether tokens are set without printing them
+            for key in ["upload_token", "confluence_token"] {
+                if let Some(token) = shown.get_mut(key) {
+                    *token = "(set)".into();
+                }
+            }
+            println!("{}", serde_json::to_string_pretty(&shown)?);
+        }
         None | Some(ConfigAction::Show) => {
             let config = Config::load().unwrap_or_default();
             println!("default_ttl = {}", config.default_ttl);
             println!("storage_type = \"{}\"", config.storage_type);
             println!("upload_url = \"{}\"", config.upload_url);
             println!("gist_format = \"{}\"", config.gist_format);
+            if config.gist_include_raw != GistRaw::Off {
+                println!("gist_include_raw = \"{}\"", config.gist_include_raw);
+            }
+            println!("raw = \"{}\"", config.raw);
+            println!("attribution = {}", config.attribution);
+            if let Some(handle) = &config.handle {
+                println!("handle = \"{handle}\"");
+            }
+            if config.upload_token.is_some() {
+                println!("upload_token = (set)");
+            }
+            if let Some(email) = &config.confluence_email {
+                println!("confluence_email = \"{email}\"");
+            }
+            if config.confluence_token.is_some() {
+                println!("confluence_token = (set)");
+            }
+            if !config.allowed_models.is_empty() {
+                println!("allowed_models = {:?}", config.allowed_models);
+            }
+            if !config.denied_models.is_empty() {
+                println!("denied_models = {:?}", config.denied_models);
+            }
+            if !config.redact_patterns.is_empty() {
+                println!("redact_patterns = {:?}", config.redact_patterns);
+            }
+            if let Some(scanner) = &config.secret_scanner {
+                println!("secret_scanner = {scanner:?}");
+            }
+            if let Some(target) = &config.shares_sync {
+                println!("shares_sync = \"{target}\"");
+            }
+            if config.scrub_expired_keys {
+                println!("scrub_expired_keys = true");
+            }
+            if let Some(days) = config.prune_after_days {
+                println!("prune_after_days = {days}");
+            }
+            if !config.presets.is_empty() {
+                let names: Vec<&String> = config.presets.keys().collect();
+                println!("presets = {names:?}");
+            }
+            for (model, price) in &config.pricing {
+                println!(
+                    "pricing.\"{model}\" = {{ input = {}, output = {}, cache_read = {}, cache_write = {} }}",
+                    price.input, price.output, price.cache_read, price.cache_write
+                );
+            }
         }
         Some(ConfigAction::Set { key, value }) => {
             let mut config = Config::load().unwrap_or_default();
@@ -218,17 +1935,86 @@ fn handle_config(action: Option<ConfigAction>) -> Result<()> {
                 "gist_format" | "format" => {
                     config.gist_format = GistFormat::parse(&value)?;
                 }
+                "gist_include_raw" => {
+                    config.gist_include_raw = GistRaw::parse(&value)?;
+                }
+                "raw" => {
+                    config.raw = RawContent::parse(&value)?;
+                }
+                "attribution" => {
+                    config.attribution = match value.trim().to_lowercase().as_str() {
+                        "true" | "on" | "yes" | "1" => true,
+                        "false" | "off" | "no" | "0" => false,
+                        _ => anyhow::bail!("invalid attribution: must be true or false"),
+                    };
+                }
+                "handle" => {
+                    let handle = value.trim();
+                    config.handle = (!handle.is_empty()).then(|| handle.to_string());
+                }
+                "upload_token" => {
+                    let token = value.trim();
+                    config.upload_token = (!token.is_empty()).then(|| token.to_string());
+                }
+                "confluence_email" => {
+                    let email = value.trim();
+                    config.confluence_email = (!email.is_empty()).then(|| email.to_string());
+                }
+                "confluence_token" => {
+                    let token = value.trim();
+                    config.confluence_token = (!token.is_empty()).then(|| token.to_string());
+                }
+                "allowed_models" => {
+                    config.allowed_models = parse_model_list(&value);
+                }
+                "denied_models" => {
+                    config.denied_models = parse_model_list(&value);
+                }
+                "secret_scanner" => {
+                    let command = value.trim();
+                    config.secret_scanner = (!command.is_empty()).then(|| command.to_string());
+                }
+                "shares_sync" => {
+                    let target = value.trim();
+                    config.shares_sync = if target.is_empty() {
+                        None
+                    } else {
+                        agentexport::sync::SyncBackend::parse(target)?;
+                        Some(target.to_string())
+                    };
+                }
+                "scrub_expired_keys" => {
+                    config.scrub_expired_keys = match value.trim().to_lowercase().as_str() {
+                        "true" | "on" | "yes" | "1" => true,
+                        "false" | "off" | "no" | "0" => false,
+                        _ => anyhow::bail!("invalid scrub_expired_keys: must be true or false"),
+                    };
+                }
+                "prune_after_days" => {
+                    let days: u64 = value.trim().parse().map_err(|_| {
+                        anyhow::anyhow!("invalid prune_after_days: must be a number of days")
+                    })?;
+                    config.prune_after_days = (days > 0).then_some(days);
+                }
                 _ => {
                     anyhow::bail!("unknown config key: {key}");
                 }
             }
             let path = config.save()?;
-            println!("saved to {}", path.display());
+            if json {
+                println!("{}", serde_json::json!({ "saved": path }));
+            } else {
+                println!("saved to {}", path.display());
+            }
         }
         Some(ConfigAction::Reset) => {
             let config = Config::default();
             let path = config.save()?;
-            println!("reset to defaults at {}", path.display());
+            if json {
+                println!("{}", serde_json::json!({ "reset": path }));
+            } else {
+                println!("reset to defaults at {}", path.display());
+            }
         }
     }
     Ok(())
@@ -285,7 +2071,8 @@ fn run_update(skip_confirm: bool) -> Result<()> {
 
     let (os, arch) = detect_platform()?;
     let url = format!(
-        "https://github.com/{REPO}/releases/download/v{latest}/agentexport-{latest}-{os}-{arch}.tar.gz"
+        "{}/{REPO}/releases/download/v{latest}/agentexport-{latest}-{os}-{arch}.tar.gz",
+        test_mode::github_downloads()
     );
 
     println!("Downloading {url}...");
@@ -361,7 +2148,7 @@ fn fetch_latest_version() -> Result<String> {
     let output = std::process::Command::new("curl")
         .args([
             "-fsSL",
-            &format!("https://api.github.com/repos/{REPO}/releases/latest"),
+            &format!("{}/repos/{REPO}/releases/latest", test_mode::github_api()),
         ])
         .output()?;
 
@@ -398,7 +2185,7 @@ fn detect_platform() -> Result<(&'static str, &'static str)> {
 }
 
 /// Check for updates in the background and print a warning if outdated.
-fn check_for_update_async() {
+fn check_for_update_async(color: bool) {
     let is_brew = is_homebrew_install();
     std::thread::spawn(move || {
         if let Ok(latest) = fetch_latest_version() {
@@ -409,10 +2196,15 @@ fn check_for_update_async() {
                 } else {
                     "agentexport update"
                 };
+                let (yellow, reset) = if color {
+                    ("\x1b[33m", "\x1b[0m")
+                } else {
+                    ("", "")
+                };
                 eprintln!(
-                    "\x1b[33mA new version of agentexport is available: v{latest} (current: v{current})\x1b[0m"
+                    "{yellow}A new version of agentexport is available: v{latest} (current: v{current}){reset}"
                 );
-                eprintln!("\x1b[33mRun '{upgrade_cmd}' to upgrade.\x1b[0m");
+                eprintln!("{yellow}Run '{upgrade_cmd}' to upgrade.{reset}");
             }
         }
     });
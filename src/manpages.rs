@@ -0,0 +1,77 @@
+//! Man pages for `agentexport` and each of its subcommands, rendered from the
+//! clap definition (`agentexport man`, and offered by `setup`).
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where per-user man pages go: `$XDG_DATA_HOME/man/man1`, or
+/// `~/.local/share/man/man1`, which `man` searches next to `~/.local/bin`
+pub fn user_man_dir() -> Result<PathBuf> {
+    let data = match std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var("HOME").context("HOME not set")?).join(".local/share"),
+    };
+    Ok(data.join("man").join("man1"))
+}
+
+/// Write `agentexport.1` plus an `agentexport-<subcommand>.1` page for every
+/// visible subcommand, nested ones included. Returns the files written.
+pub fn write_man_pages(cmd: clap::Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    fn write(cmd: clap::Command, dir: &Path, written: &mut Vec<PathBuf>) -> Result<()> {
+        for sub in cmd.get_subcommands().filter(|s| !s.is_hide_set()).cloned() {
+            write(sub, dir, written)?;
+        }
+        let man = clap_mangen::Man::new(cmd);
+        let path = dir.join(man.get_filename());
+        let mut file =
+            File::create(&path).with_context(|| format!("Failed to write {}", path.display()))?;
+        man.render(&mut file)?;
+        file.flush()?;
+        written.push(path);
+        Ok(())
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut cmd = cmd.disable_help_subcommand(true);
+    cmd.build();
+    let mut written = Vec::new();
+    write(cmd, dir, &mut written)?;
+    written.sort();
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_for_every_visible_subcommand() {
+        let cmd = clap::Command::new("agentexport")
+            .subcommand(
+                clap::Command::new("shares")
+                    .about("Manage shares")
+                    .after_long_help("Examples:\n  agentexport shares list")
+                    .subcommand(clap::Command::new("list")),
+            )
+            .subcommand(clap::Command::new("internal").hide(true));
+        let dir = tempfile::TempDir::new().unwrap();
+        let written = write_man_pages(cmd, dir.path()).unwrap();
+        let names: Vec<_> = written
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "agentexport-shares-list.1",
+                "agentexport-shares.1",
+                "agentexport.1"
+            ]
+        );
+        let shares = fs::read_to_string(&written[1]).unwrap();
+        assert!(shares.contains("Manage shares"));
+        assert!(shares.contains("agentexport shares list"));
+    }
+}
@@ -0,0 +1,170 @@
+//! Live token and cost counter for the active session (`agentexport monitor`).
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::output::{ColorChoice, Output, Table};
+use crate::pretty::file_stamp;
+use crate::transcript::{CodexDiscovery, ModelUsage, Tool, parse_transcript, resolve_transcript};
+
+/// Options for `agentexport monitor`
+#[derive(Debug, Clone)]
+pub struct MonitorOptions {
+    pub tool: Tool,
+    pub transcript: Option<PathBuf>,
+    pub max_age_minutes: u64,
+    pub discovery: CodexDiscovery,
+    pub interval: Duration,
+    pub color: ColorChoice,
+}
+
+/// Compact token count: 950, 12.3K, 4.1M
+pub fn format_tokens(n: u64) -> String {
+    if n >= 1_000_000 {
+        format!("{:.1}M", n as f64 / 1_000_000.0)
+    } else if n >= 1000 {
+        format!("{:.1}K", n as f64 / 1000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+fn format_cost(cost: Option<f64>) -> String {
+    match cost {
+        Some(c) if c < 0.01 => format!("${c:.4}"),
+        Some(c) => format!("${c:.2}"),
+        None => "-".to_string(),
+    }
+}
+
+/// Total estimated cost across models with known pricing
+pub fn total_cost(by_model: &BTreeMap<String, ModelUsage>) -> f64 {
+    by_model
+        .iter()
+        .filter_map(|(model, usage)| usage.cost(model))
+        .fold(0.0, |total, cost| total + cost)
+}
+
+/// Per-model usage table with a total row and optional burn rate (USD/hour)
+pub fn format_usage(
+    by_model: &BTreeMap<String, ModelUsage>,
+    burn_rate: Option<f64>,
+    output: &Output,
+) -> String {
+    let mut table = Table::new(&["MODEL", "IN", "OUT", "CACHE R", "CACHE W", "HIT", "COST"])
+        .right_align(&[1, 2, 3, 4, 5, 6])
+        .shrink(&[0]);
+    for (model, usage) in by_model {
+        table.push(vec![
+            model.clone(),
+            format_tokens(usage.input_tokens),
+            format_tokens(usage.output_tokens),
+            format_tokens(usage.cache_read_tokens),
+            format_tokens(usage.cache_creation_tokens),
+            format!("{:.0}%", usage.cache_hit_ratio() * 100.0),
+            format_cost(usage.cost(model)),
+        ]);
+    }
+    let mut out = table.render(output);
+    if by_model.is_empty() {
+        out.push_str("(no usage recorded yet)\n");
+    }
+    out.push_str(&format!(
+        "\ntotal {}",
+        format_cost(Some(total_cost(by_model)))
+    ));
+    if let Some(rate) = burn_rate {
+        out.push_str(&format!(" · burning {}/h", format_cost(Some(rate))));
+    }
+    out.push('\n');
+    out
+}
+
+/// Follow the session transcript and redraw usage whenever it changes
+pub fn monitor(options: MonitorOptions) -> Result<()> {
+    let found = resolve_transcript(
+        options.tool,
+        options.transcript,
+        options.max_age_minutes,
+        &options.discovery,
+        None,
+    )?;
+    for warning in &found.warnings {
+        eprintln!("{warning}");
+    }
+    let path = found.path;
+    let redraw = std::io::stdout().is_terminal();
+    let started = Instant::now();
+    let mut start_cost = None;
+    let mut stamp = None;
+
+    loop {
+        let current = file_stamp(&path);
+        if current.is_some()
+            && current != stamp
+            && let Ok(parsed) = parse_transcript(&path)
+        {
+            stamp = current;
+            let by_model = parsed.usage_by_model();
+            let cost = total_cost(&by_model);
+            let baseline = *start_cost.get_or_insert(cost);
+            let hours = started.elapsed().as_secs_f64() / 3600.0;
+            // Rate only covers spend observed while monitoring
+            let burn_rate = (cost > baseline && hours > 0.0).then(|| (cost - baseline) / hours);
+
+            let mut stdout = std::io::stdout().lock();
+            if redraw {
+                write!(stdout, "\x1b[H\x1b[2J")?;
+            }
+            let now = time::OffsetDateTime::now_local()
+                .unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+            writeln!(
+                stdout,
+                "{} · updated {:02}:{:02}:{:02}\n",
+                path.display(),
+                now.hour(),
+                now.minute(),
+                now.second()
+            )?;
+            let output = Output::detect(options.color);
+            write!(stdout, "{}", format_usage(&by_model, burn_rate, &output))?;
+            stdout.flush()?;
+        }
+        std::thread::sleep(options.interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_table_lists_models_and_total() {
+        let mut by_model = BTreeMap::new();
+        by_model.insert(
+            "claude-haiku-4-5".to_string(),
+            ModelUsage {
+                input_tokens: 1_000_000,
+                output_tokens: 2_500,
+                cache_read_tokens: 1_000_000,
+                cache_creation_tokens: 0,
+                input_includes_cache: false,
+            },
+        );
+        by_model.insert("local-model".to_string(), ModelUsage::default());
+
+        let table = format_usage(&by_model, Some(1.5), &Output::PLAIN);
+        let haiku = table
+            .lines()
+            .find(|l| l.starts_with("claude-haiku-4-5"))
+            .unwrap();
+        assert!(haiku.contains("1.0M") && haiku.contains("2.5K"));
+        assert!(haiku.contains("50%"));
+        assert!(haiku.contains("$1.11"));
+        assert!(table.contains("local-model") && table.contains("  -\n"));
+        assert!(table.contains("total $1.11 · burning $1.50/h"));
+    }
+}
@@ -0,0 +1,239 @@
+//! Shared terminal output: when to use color, how wide the terminal is, and
+//! tables that fit it (`sessions list`, `shares list`, `monitor`, `cat`).
+//!
+//! Piped output stays plain and full width, so it reads the same in pagers,
+//! CI logs, and `grep`.
+
+use std::io::IsTerminal;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+
+/// Narrowest a shrinkable column gets before the table is left to wrap
+const MIN_SHRUNK_WIDTH: usize = 12;
+
+/// `--color`: whether to write ANSI colors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and NO_COLOR isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color what's written to `stream`
+    pub fn enabled(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && stream.is_terminal()
+            }
+        }
+    }
+}
+
+/// Width of the terminal on stdout, falling back to $COLUMNS; None when
+/// neither is known (output is piped)
+pub fn terminal_width() -> Option<usize> {
+    #[cfg(unix)]
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&width| width > 0)
+}
+
+/// How stdout is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Output {
+    pub color: bool,
+    /// Width to fit tables to (None: don't cut anything)
+    pub width: Option<usize>,
+}
+
+impl Output {
+    pub fn detect(choice: ColorChoice) -> Self {
+        Self {
+            color: choice.enabled(&std::io::stdout()),
+            width: terminal_width(),
+        }
+    }
+
+    pub const PLAIN: Output = Output {
+        color: false,
+        width: None,
+    };
+}
+
+/// Columns of text padded to line up. Shrinkable columns are cut (with `…`)
+/// when the table is wider than the terminal.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    right: Vec<usize>,
+    shrink: Vec<usize>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Self {
+            header: header.iter().map(|h| h.to_string()).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Right-align these columns (numbers)
+    pub fn right_align(mut self, columns: &[usize]) -> Self {
+        self.right = columns.to_vec();
+        self
+    }
+
+    /// Columns that may be cut to fit the width, like titles and paths
+    pub fn shrink(mut self, columns: &[usize]) -> Self {
+        self.shrink = columns.to_vec();
+        self
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// The table, one line per row after a (bold, with color) header
+    pub fn render(&self, output: &Output) -> String {
+        let mut widths: Vec<usize> = self.header.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        if let Some(limit) = output.width {
+            self.fit(&mut widths, limit);
+        }
+
+        let mut out = String::new();
+        for (index, row) in std::iter::once(&self.header).chain(&self.rows).enumerate() {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, &width))| {
+                    let cell = cut(cell, width);
+                    if self.right.contains(&column) {
+                        format!("{cell:>width$}")
+                    } else {
+                        format!("{cell:<width$}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            let line = line.trim_end();
+            if index == 0 && output.color {
+                out.push_str(&format!("{BOLD}{line}{RESET}\n"));
+            } else {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Narrow the widest shrinkable columns until the table fits in `limit`
+    fn fit(&self, widths: &mut [usize], limit: usize) {
+        let total =
+            |widths: &[usize]| widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+        while total(widths) > limit {
+            let Some(&column) = self
+                .shrink
+                .iter()
+                .filter(|&&c| c < widths.len() && widths[c] > MIN_SHRUNK_WIDTH)
+                .max_by_key(|&&c| widths[c])
+            else {
+                break;
+            };
+            let excess = total(widths) - limit;
+            widths[column] -= excess.min(widths[column] - MIN_SHRUNK_WIDTH);
+        }
+    }
+}
+
+/// `text` cut to `width` characters, ending in `…` when shortened
+fn cut(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(width.saturating_sub(1)).collect();
+    format!("{}…", kept.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["ID", "COUNT", "TITLE"])
+            .right_align(&[1])
+            .shrink(&[2]);
+        table.push(vec![
+            "a1".into(),
+            "7".into(),
+            "Fix the flaky upload retry test".into(),
+        ]);
+        table.push(vec!["b22".into(), "1200".into(), "Short".into()]);
+        table
+    }
+
+    #[test]
+    fn tables_align_and_fit_the_width() {
+        assert_eq!(
+            table().render(&Output::PLAIN),
+            "ID   COUNT  TITLE\n\
+             a1       7  Fix the flaky upload retry test\n\
+             b22   1200  Short\n"
+        );
+        let narrow = Output {
+            color: false,
+            width: Some(33),
+        };
+        assert_eq!(
+            table().render(&narrow),
+            "ID   COUNT  TITLE\n\
+             a1       7  Fix the flaky upload…\n\
+             b22   1200  Short\n"
+        );
+        // Never below the minimum; the terminal wraps the rest
+        let tiny = Output {
+            color: false,
+            width: Some(5),
+        };
+        assert!(table().render(&tiny).contains("Fix the fla…"));
+
+        let color = Output {
+            color: true,
+            width: None,
+        };
+        assert!(
+            table()
+                .render(&color)
+                .starts_with("\x1b[1mID   COUNT  TITLE\x1b[0m\n")
+        );
+    }
+
+    #[test]
+    fn color_choice_honors_no_color() {
+        let _lock = crate::test_utils::env_lock();
+        let _no_color = crate::test_utils::EnvGuard::set("NO_COLOR", "1");
+        let stdout = std::io::stdout();
+        assert!(!ColorChoice::Auto.enabled(&stdout));
+        assert!(ColorChoice::Always.enabled(&stdout));
+        assert!(!ColorChoice::Never.enabled(&stdout));
+    }
+}
@@ -0,0 +1,331 @@
+//! Terminal rendering of parsed transcripts (`agentexport cat`).
+
+use anyhow::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::output::{ColorChoice, Output};
+use crate::transcript::{
+    CodexDiscovery, RenderedMessage, Tool, parse_transcript, resolve_transcript,
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const CYAN: &str = "\x1b[36m";
+
+/// How messages are laid out on the terminal
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub color: bool,
+    pub width: usize,
+}
+
+impl Style {
+    /// Color as `choice` says; wrap to the terminal width (100 when piped)
+    pub fn detect(choice: ColorChoice) -> Self {
+        let output = Output::detect(choice);
+        Self {
+            color: output.color,
+            width: output.width.unwrap_or(100),
+        }
+    }
+
+    fn paint(&self, codes: &str, text: &str) -> String {
+        if self.color && !text.is_empty() {
+            format!("{codes}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Label and ANSI color for a message role
+fn role_style(role: &str) -> (&str, &'static str) {
+    match role {
+        "user" => ("User", "\x1b[32m"),
+        "assistant" => ("Assistant", "\x1b[34m"),
+        "tool" => ("Tool", "\x1b[33m"),
+        "thinking" => ("Thinking", "\x1b[35m"),
+        "system" => ("System", "\x1b[90m"),
+        "command" => ("Command", "\x1b[36m"),
+        "plan" => ("Plan", "\x1b[96m"),
+        other => (other, "\x1b[37m"),
+    }
+}
+
+/// Greedy word wrap to `width` characters; `indent` prefixes continuation lines
+fn wrap(line: &str, width: usize, indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut len = 0;
+    for word in line.split(' ') {
+        let word_len = word.chars().count();
+        if len > 0 && len + 1 + word_len > width {
+            lines.push(std::mem::take(&mut current));
+            current.push_str(indent);
+            len = indent.len();
+        } else if len > 0 {
+            current.push(' ');
+            len += 1;
+        }
+        current.push_str(word);
+        len += word_len;
+    }
+    lines.push(current);
+    lines
+}
+
+/// Highlight `inline code` spans within a single rendered line
+fn style_inline(line: &str, style: &Style) -> String {
+    if !style.color || line.matches('`').count() < 2 {
+        return line.to_string();
+    }
+    let mut out = String::new();
+    let parts: Vec<&str> = line.split('`').collect();
+    let last = parts.len() - 1;
+    for (i, part) in parts.iter().enumerate() {
+        // An unpaired trailing backtick stays literal
+        if i % 2 == 1 && !(i == last && last % 2 == 1) {
+            out.push_str(&style.paint(CYAN, part));
+        } else {
+            if i > 0 && i == last && last % 2 == 1 {
+                out.push('`');
+            }
+            out.push_str(part);
+        }
+    }
+    out
+}
+
+/// Render markdown-ish content into styled, wrapped lines
+fn content_lines(content: &str, width: usize, style: &Style) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    for line in content.trim_end().lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            out.push(style.paint(DIM, line));
+            continue;
+        }
+        if in_fence {
+            // Code is shown verbatim; wrapping would change its meaning
+            out.push(style.paint(DIM, line));
+            continue;
+        }
+        if line.starts_with('#') {
+            for wrapped in wrap(line, width, "") {
+                out.push(style.paint(BOLD, &wrapped));
+            }
+            continue;
+        }
+        let trimmed = line.trim_start();
+        let marker_len = ["- ", "* ", "+ "]
+            .iter()
+            .find(|m| trimmed.starts_with(*m))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let indent = " ".repeat(line.len() - trimmed.len() + marker_len);
+        for wrapped in wrap(line, width, &indent) {
+            out.push(style_inline(&wrapped, style));
+        }
+    }
+    out
+}
+
+/// Format one message with a colored role header and a gutter on each line
+pub fn format_message(message: &RenderedMessage, style: &Style) -> String {
+    let (label, color) = role_style(&message.role);
+    let mut header = style.paint(&format!("{BOLD}{color}"), label);
+    if let Some(id) = message.id {
+        header.push(' ');
+        header.push_str(&style.paint(DIM, &format!("#{id}")));
+    }
+    if let Some(model) = &message.model {
+        header.push(' ');
+        header.push_str(&style.paint(DIM, &format!("({model})")));
+    }
+    let gutter = style.paint(color, "│");
+    let width = style.width.saturating_sub(2).max(20);
+
+    let mut out = format!("{header}\n");
+    for line in content_lines(&message.content, width, style) {
+        if line.is_empty() {
+            out.push_str(&gutter);
+        } else {
+            out.push_str(&format!("{gutter} {line}"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Format messages separated by blank lines, keeping only the last `tail` if set
+pub fn format_messages(messages: &[RenderedMessage], tail: Option<usize>, style: &Style) -> String {
+    let start = tail.map_or(0, |n| messages.len().saturating_sub(n));
+    messages[start..]
+        .iter()
+        .map(|m| format_message(m, style))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Options for `agentexport cat`
+#[derive(Debug, Clone)]
+pub struct CatOptions {
+    pub tool: Tool,
+    pub transcript: Option<PathBuf>,
+    pub max_age_minutes: u64,
+    pub discovery: CodexDiscovery,
+    pub tail: Option<usize>,
+    pub color: ColorChoice,
+    /// Keep running and print messages as they are appended
+    pub follow: bool,
+}
+
+/// Print the parsed transcript for the current session to stdout
+pub fn cat(options: CatOptions) -> Result<()> {
+    let found = resolve_transcript(
+        options.tool,
+        options.transcript,
+        options.max_age_minutes,
+        &options.discovery,
+        None,
+    )?;
+    for warning in &found.warnings {
+        eprintln!("{warning}");
+    }
+    let path = found.path;
+    let mut parsed = parse_transcript(&path)?;
+    let printed = parsed.messages.len();
+    parsed.drop_abandoned();
+    let style = Style::detect(options.color);
+    {
+        let mut stdout = std::io::stdout().lock();
+        write!(
+            stdout,
+            "{}",
+            format_messages(&parsed.messages, options.tail, &style)
+        )?;
+        stdout.flush()?;
+    }
+    if options.follow {
+        follow(&path, printed, &style)?;
+    }
+    Ok(())
+}
+
+/// Size and mtime, used to notice appends without re-parsing on every tick
+pub(crate) fn file_stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Messages in `messages` not yet printed, given how many were printed before.
+/// A shorter transcript means it was rewritten, so everything is new again.
+fn unseen(messages: &[RenderedMessage], printed: usize) -> &[RenderedMessage] {
+    if messages.len() < printed {
+        messages
+    } else {
+        &messages[printed..]
+    }
+}
+
+/// Poll the transcript and print messages appended after the first `printed`.
+/// The whole file is re-parsed on change so tool results stay paired with their calls.
+fn follow(path: &Path, mut printed: usize, style: &Style) -> Result<()> {
+    let mut stamp = file_stamp(path);
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let current = file_stamp(path);
+        if current.is_none() || current == stamp {
+            continue;
+        }
+        stamp = current;
+        // A partially written line fails to parse; pick it up on the next change
+        let Ok(parsed) = parse_transcript(path) else {
+            continue;
+        };
+        let fresh = unseen(&parsed.messages, printed);
+        if !fresh.is_empty() {
+            let mut stdout = std::io::stdout().lock();
+            for message in fresh.iter().filter(|m| !m.abandoned) {
+                write!(stdout, "\n{}", format_message(message, style))?;
+            }
+            stdout.flush()?;
+        }
+        printed = parsed.messages.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> RenderedMessage {
+        RenderedMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            raw: None,
+            raw_label: None,
+            tool_use_id: None,
+            model: None,
+            abandoned: false,
+            part: None,
+            id: None,
+            highlighted: false,
+            images: Vec::new(),
+        }
+    }
+
+    const PLAIN: Style = Style {
+        color: false,
+        width: 40,
+    };
+
+    #[test]
+    fn wraps_prose_but_not_code() {
+        let long = ["word"; 20].join(" ");
+        let content = format!("{long}\n```\n{long}\n```\n- {long}");
+        let out = format_message(&message("assistant", &content), &PLAIN);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "Assistant");
+        assert!(lines.iter().filter(|l| l.starts_with("│ word")).count() > 1);
+        assert!(lines.contains(&format!("│ {long}").as_str()));
+        assert!(lines.iter().any(|l| l.starts_with("│   word")));
+    }
+
+    #[test]
+    fn tail_keeps_last_messages() {
+        let messages = vec![
+            message("user", "one"),
+            message("assistant", "two"),
+            message("user", "three"),
+        ];
+        let out = format_messages(&messages, Some(2), &PLAIN);
+        assert!(!out.contains("one"));
+        assert!(out.contains("two") && out.contains("three"));
+    }
+
+    #[test]
+    fn unseen_returns_appended_messages() {
+        let messages = vec![message("user", "one"), message("assistant", "two")];
+        assert_eq!(unseen(&messages, 1).len(), 1);
+        assert_eq!(unseen(&messages, 1)[0].content, "two");
+        assert!(unseen(&messages, 2).is_empty());
+        // Rewritten transcript: print it all again
+        assert_eq!(unseen(&messages, 5).len(), 2);
+    }
+
+    #[test]
+    fn color_paints_roles_and_inline_code() {
+        let style = Style {
+            color: true,
+            width: 80,
+        };
+        let out = format_message(&message("user", "run `cargo test` now"), &style);
+        assert!(out.starts_with("\x1b[1m\x1b[32mUser\x1b[0m"));
+        assert!(out.contains("\x1b[36mcargo test\x1b[0m"));
+    }
+}
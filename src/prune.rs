@@ -0,0 +1,241 @@
+//! Shares pruning (`shares prune`): drop expired shares from the local list
+//! and collect the artifacts nothing refers to any more.
+//!
+//! Shares past a chosen age are deleted from their server first, so a team
+//! can keep old links from lingering. Expired shares keep their local backup,
+//! which `shares restore` can still read; shares deleted here lose it, as
+//! with `shares unshare`.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::{Duration, OffsetDateTime};
+
+use crate::backup;
+use crate::purge;
+use crate::shares::{self, Share};
+use crate::store::{self, Store};
+use crate::transcript::cache_dir;
+
+/// Store objects younger than this are left alone, in case a publish is
+/// still writing them
+pub const ARTIFACT_MIN_AGE: Duration = Duration::days(1);
+
+/// Everything a prune will remove
+#[derive(Debug, Default)]
+pub struct PrunePlan {
+    /// Expired shares, and shares their server reported gone
+    pub expired: Vec<Share>,
+    /// Live shares past the age limit, deleted from their server first
+    pub aged: Vec<Share>,
+    /// Store objects (gzips and renders) no kept share refers to
+    pub objects: Vec<String>,
+    /// Retained gzips under the cache dir that only pruned shares refer to
+    pub files: Vec<PathBuf>,
+}
+
+impl PrunePlan {
+    pub fn is_empty(&self) -> bool {
+        self.expired.is_empty()
+            && self.aged.is_empty()
+            && self.objects.is_empty()
+            && self.files.is_empty()
+    }
+}
+
+/// What a prune removed
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub shares_removed: usize,
+    /// Aged shares deleted from their server
+    pub shares_deleted: usize,
+    pub artifacts_removed: usize,
+    /// Aged shares whose server copy could not be deleted; they stay in the list
+    pub failed_shares: Vec<(String, String)>,
+}
+
+/// Store hashes of the gzips `shares` refer to
+fn referenced_objects<'a>(shares: impl Iterator<Item = &'a Share>) -> HashSet<String> {
+    shares
+        .filter_map(|s| s.gzip_path.as_deref())
+        .filter_map(|p| store::hash_from_path(Path::new(p)))
+        .collect()
+}
+
+/// Gzips outside the store, under the cache dir, that `pruned` refer to and
+/// `kept` don't
+fn orphaned_files(pruned: &[&Share], kept: &[&Share]) -> Result<Vec<PathBuf>> {
+    let cache = cache_dir()?;
+    let in_use: HashSet<&str> = kept.iter().filter_map(|s| s.gzip_path.as_deref()).collect();
+    let mut files: Vec<PathBuf> = pruned
+        .iter()
+        .filter_map(|s| s.gzip_path.as_deref())
+        .filter(|p| !in_use.contains(p))
+        .map(PathBuf::from)
+        .filter(|p| p.starts_with(&cache) && store::hash_from_path(p).is_none() && p.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Find what to prune: expired shares, live shares created more than
+/// `delete_after` ago (when given), and artifacts older than `artifact_min_age`
+/// that no remaining share refers to
+pub fn plan(delete_after: Option<Duration>, artifact_min_age: Duration) -> Result<PrunePlan> {
+    let now = OffsetDateTime::now_utc();
+    let mut plan = PrunePlan::default();
+    let mut kept = Vec::new();
+    for share in shares::load_shares()? {
+        if share.is_expired() || share.is_gone() {
+            plan.expired.push(share);
+        } else if delete_after.is_some_and(|age| share.created_at <= now - age) {
+            plan.aged.push(share);
+        } else {
+            kept.push(share);
+        }
+    }
+    let keep = referenced_objects(kept.iter());
+    plan.objects = Store::open()?.gc(&keep, artifact_min_age, true)?;
+    let pruned: Vec<&Share> = plan.expired.iter().chain(&plan.aged).collect();
+    plan.files = orphaned_files(&pruned, &kept.iter().collect::<Vec<_>>())?;
+    Ok(plan)
+}
+
+/// Carry out `plan`. An aged share whose server delete fails keeps its record
+/// and its artifacts, so the prune can be retried.
+pub fn execute(plan: &PrunePlan, artifact_min_age: Duration) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    for share in &plan.expired {
+        if shares::remove_share(&share.id)?.is_some() {
+            report.shares_removed += 1;
+        }
+    }
+    for share in &plan.aged {
+        if let Err(err) = purge::delete_remote(share) {
+            report
+                .failed_shares
+                .push((share.id.clone(), err.to_string()));
+            continue;
+        }
+        shares::remove_share(&share.id)?;
+        backup::remove_backup(&share.id)?;
+        report.shares_removed += 1;
+        report.shares_deleted += 1;
+    }
+
+    let remaining = shares::load_shares()?;
+    let keep = referenced_objects(remaining.iter());
+    report.artifacts_removed += Store::open()?.gc(&keep, artifact_min_age, false)?.len();
+    // Failed deletes are still in the list, so their gzips are kept too
+    let in_use: HashSet<&str> = remaining
+        .iter()
+        .filter_map(|s| s.gzip_path.as_deref())
+        .collect();
+    for path in &plan.files {
+        if !in_use.contains(path.to_string_lossy().as_ref()) && path.is_file() {
+            fs::remove_file(path)?;
+            report.artifacts_removed += 1;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mode::TEST_ENDPOINTS_ENV;
+    use crate::test_utils::{EnvGuard, env_lock, serve};
+
+    #[test]
+    fn prune_forgets_expired_shares_and_deletes_old_ones() {
+        use crate::store::{ObjectKind, Store};
+        use time::{Duration, OffsetDateTime};
+        let _lock = env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", home.path().to_str().unwrap());
+        let cache = home.path().join("cache");
+        let _cache = EnvGuard::set("AGENTEXPORT_CACHE_DIR", cache.to_str().unwrap());
+        let (base, server) = serve(vec![Vec::new()]);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let store = Store::open().unwrap();
+        let old_gzip = store
+            .put_bytes(b"old", ObjectKind::Gzip, None, None)
+            .unwrap();
+        let new_gzip = store
+            .put_bytes(b"new", ObjectKind::Gzip, None, None)
+            .unwrap();
+        let render = store
+            .put_bytes(b"render", ObjectKind::Render, None, None)
+            .unwrap();
+        let retained = cache.join("retained/expired.jsonl.gz");
+        std::fs::create_dir_all(retained.parent().unwrap()).unwrap();
+        std::fs::write(&retained, b"gzip").unwrap();
+
+        let now = OffsetDateTime::now_utc();
+        let gzip_of = |hash: &str| Some(store.object_path(hash).display().to_string());
+        for share in [
+            crate::shares::Share {
+                expires_at: now - Duration::days(1),
+                gzip_path: Some(retained.display().to_string()),
+                ..crate::shares::tests::make_test_share("gexp000000000001")
+            },
+            crate::shares::Share {
+                created_at: now - Duration::days(400),
+                expires_at: now + Duration::days(30),
+                gzip_path: gzip_of(&old_gzip),
+                ..crate::shares::tests::make_test_share("gold0000000000004")
+            },
+            crate::shares::Share {
+                expires_at: now + Duration::days(30),
+                gzip_path: gzip_of(&new_gzip),
+                ..crate::shares::tests::make_test_share("gnew000000000001")
+            },
+        ] {
+            crate::shares::save_share(&share).unwrap();
+            crate::backup::save_backup(&share.id, "{}").unwrap();
+        }
+
+        let found = plan(None, Duration::ZERO).unwrap();
+        assert_eq!(found.expired.len(), 1);
+        assert!(found.aged.is_empty());
+        let found = plan(Some(Duration::days(365)), Duration::ZERO).unwrap();
+        assert_eq!(found.expired[0].id, "gexp000000000001");
+        assert_eq!(found.aged[0].id, "gold0000000000004");
+        let mut expected = vec![old_gzip.clone(), render.clone()];
+        expected.sort();
+        assert_eq!(found.objects, expected);
+        assert_eq!(found.files, std::slice::from_ref(&retained));
+
+        let report = execute(&found, Duration::ZERO).unwrap();
+        assert_eq!(
+            (
+                report.shares_removed,
+                report.shares_deleted,
+                report.artifacts_removed
+            ),
+            (2, 1, 3)
+        );
+        let requests = server.join().unwrap();
+        assert!(requests[0].0.starts_with("DELETE /blob/gold0000000000004 "));
+
+        let ids: Vec<String> = crate::shares::load_shares()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["gnew000000000001"]);
+        assert!(store.object_path(&new_gzip).exists());
+        assert!(!store.object_path(&old_gzip).exists() && !retained.exists());
+        // Expired shares can still be restored; deleted ones can't
+        assert!(crate::backup::load_backup("gexp000000000001").is_ok());
+        assert!(crate::backup::load_backup("gold0000000000004").is_err());
+        assert!(
+            plan(Some(Duration::days(365)), Duration::ZERO)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Session purge: remove one session from everywhere agentexport copied it.
+//!
+//! That is the source transcript (and Claude's per-session sidecar folder),
+//! store gzips and renders made from it, retained gzips written elsewhere,
+//! local backups, the publish options remembered for it, and every share
+//! published from it, which is deleted from the server too. Files are overwritten with zeros before they are unlinked.
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::backup;
+use crate::config::StorageType;
+use crate::shares::{self, Share};
+use crate::store::{self, Store};
+use crate::transcript::{Tool, find_session_transcripts};
+use crate::upload;
+
+/// Everything `purge-session` will delete
+#[derive(Debug)]
+pub struct PurgePlan {
+    /// Transcript files and sidecar folders of the session
+    pub transcripts: Vec<PathBuf>,
+    /// Store objects (gzips and renders) made from the transcript
+    pub objects: Vec<String>,
+    /// Retained gzips written outside the store
+    pub files: Vec<PathBuf>,
+    /// Shares published from the transcript
+    pub shares: Vec<Share>,
+    /// Session whose remembered publish options are dropped
+    pub prefs: Option<String>,
+}
+
+impl PurgePlan {
+    pub fn is_empty(&self) -> bool {
+        self.transcripts.is_empty()
+            && self.objects.is_empty()
+            && self.files.is_empty()
+            && self.shares.is_empty()
+            && self.prefs.is_none()
+    }
+}
+
+/// What a purge removed
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    pub files_removed: usize,
+    pub shares_removed: usize,
+    /// Shares whose server copy could not be deleted; they stay in the local list
+    pub failed_shares: Vec<(String, String)>,
+}
+
+/// Whether a transcript file name belongs to session `id`
+/// (`<id>.jsonl` for Claude, `rollout-<time>-<id>.jsonl` for Codex)
+fn names_session(path: &str, id: &str) -> bool {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| stem == id || stem.ends_with(&format!("-{id}")))
+}
+
+/// Find everything derived from session `id`. Shares are matched by their
+/// transcript path, so they are found even if the transcript is already gone.
+pub fn plan(tool: Tool, id: &str) -> Result<PurgePlan> {
+    let found = find_session_transcripts(tool, id)?;
+    let mut sources: HashSet<String> = found.iter().map(|p| p.display().to_string()).collect();
+
+    let shares: Vec<Share> = shares::load_shares()?
+        .into_iter()
+        .filter(|s| {
+            s.tool == tool.as_str()
+                && (sources.contains(&s.transcript_path) || names_session(&s.transcript_path, id))
+        })
+        .collect();
+    sources.extend(shares.iter().map(|s| s.transcript_path.clone()));
+
+    // A publish labels its gzip and render alike, which links renders to shares
+    // made before the store recorded sources
+    let store = Store::open()?;
+    let mut labels = HashSet::new();
+    let mut gzips = Vec::new();
+    let mut files = Vec::new();
+    for gzip in shares.iter().filter_map(|s| s.gzip_path.as_deref()) {
+        let path = PathBuf::from(gzip);
+        let tracked =
+            store::hash_from_path(&path).and_then(|hash| Some((store.entry(&hash).ok()??, hash)));
+        match tracked {
+            Some((entry, hash)) => {
+                labels.extend(entry.label);
+                gzips.push(hash);
+            }
+            None if path.exists() => files.push(path),
+            None => {}
+        }
+    }
+    let mut objects = store.derived_from(&sources, &labels)?;
+    objects.extend(gzips);
+    objects.sort();
+    objects.dedup();
+    files.sort();
+    files.dedup();
+
+    let mut transcripts = Vec::new();
+    for path in found {
+        let sidecar = path.with_extension("");
+        transcripts.push(path);
+        if matches!(tool, Tool::Claude) && sidecar.is_dir() {
+            transcripts.push(sidecar);
+        }
+    }
+
+    let prefs = store
+        .session_prefs::<serde_json::Value>(id)?
+        .map(|_| id.to_string());
+
+    Ok(PurgePlan {
+        transcripts,
+        objects,
+        files,
+        shares,
+        prefs,
+    })
+}
+
+/// Overwrite a file with zeros and unlink it; folders are shredded file by file
+fn shred(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        for entry in WalkDir::new(path).contents_first(true) {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                fs::remove_dir(entry.path())
+                    .with_context(|| format!("Failed to remove {}", entry.path().display()))?;
+            } else {
+                shred(entry.path())?;
+            }
+        }
+        return Ok(());
+    }
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let zeros = [0u8; 64 * 1024];
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Delete a share's server copy: its gist, or its blob
+pub(crate) fn delete_remote(share: &Share) -> Result<()> {
+    if share.storage_type == StorageType::Gist {
+        upload::delete_gist(&share.id)
+    } else {
+        upload::delete_blob(&share.upload_url, &share.id, &share.delete_token)
+    }
+}
+
+/// Delete everything in `plan`. A share whose server delete fails keeps its
+/// local record and backup, so it can be retried with `shares unshare`.
+pub fn execute(plan: &PurgePlan) -> Result<PurgeReport> {
+    let mut report = PurgeReport::default();
+    for share in &plan.shares {
+        if let Err(err) = delete_remote(share) {
+            report
+                .failed_shares
+                .push((share.id.clone(), err.to_string()));
+            continue;
+        }
+        shares::remove_share(&share.id)?;
+        backup::remove_backup(&share.id)?;
+        report.shares_removed += 1;
+    }
+
+    let store = Store::open()?;
+    for hash in &plan.objects {
+        let path = store.object_path(hash);
+        if path.exists() {
+            shred(&path)?;
+            report.files_removed += 1;
+        }
+        store.remove(hash)?;
+    }
+    if let Some(session) = &plan.prefs {
+        store.forget_session_prefs(session)?;
+    }
+    for path in plan.files.iter().chain(&plan.transcripts) {
+        if path.exists() {
+            shred(path)?;
+            report.files_removed += 1;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mode::TEST_ENDPOINTS_ENV;
+    use crate::test_utils::{EnvGuard, env_lock, serve};
+
+    #[test]
+    fn purge_session_deletes_transcript_artifacts_and_shares() {
+        use crate::store::{ObjectKind, Store};
+        let _lock = env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", home.path().to_str().unwrap());
+        let cache = home.path().join("cache");
+        let _cache = EnvGuard::set("AGENTEXPORT_CACHE_DIR", cache.to_str().unwrap());
+        let (base, server) = serve(vec![Vec::new()]);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let project = home.path().join(".claude/projects/-work");
+        std::fs::create_dir_all(project.join("sess-1/subagents")).unwrap();
+        let transcript = project.join("sess-1.jsonl");
+        std::fs::write(&transcript, "{\"sessionId\":\"sess-1\"}\n").unwrap();
+        std::fs::write(project.join("sess-1/subagents/agent-a.jsonl"), "{}\n").unwrap();
+        std::fs::write(project.join("sess-2.jsonl"), "{}\n").unwrap();
+
+        let store = Store::open().unwrap();
+        let label = Some("claude-term-1");
+        let gzip = store
+            .put_bytes(b"gzip", ObjectKind::Gzip, label, Some(&transcript))
+            .unwrap();
+        let render = store
+            .put_bytes(b"render", ObjectKind::Render, label, None)
+            .unwrap();
+        let other = store
+            .put_bytes(b"other", ObjectKind::Render, Some("claude-term-2"), None)
+            .unwrap();
+        let share = crate::shares::Share {
+            transcript_path: transcript.display().to_string(),
+            gzip_path: Some(store.object_path(&gzip).display().to_string()),
+            ..crate::shares::tests::make_test_share("gold0000000000002")
+        };
+        crate::shares::save_share(&share).unwrap();
+        crate::backup::save_backup(&share.id, "{}").unwrap();
+
+        let found = plan(crate::Tool::Claude, "sess-1").unwrap();
+        assert_eq!(
+            found.transcripts,
+            [transcript.clone(), project.join("sess-1")]
+        );
+        assert_eq!(found.objects.len(), 2);
+        assert!(found.objects.contains(&render));
+        assert_eq!(found.shares.len(), 1);
+
+        let report = execute(&found).unwrap();
+        assert_eq!((report.files_removed, report.shares_removed), (4, 1));
+        assert!(report.failed_shares.is_empty());
+        let requests = server.join().unwrap();
+        assert!(requests[0].0.starts_with("DELETE /blob/gold0000000000002 "));
+
+        assert!(!transcript.exists() && !project.join("sess-1").exists());
+        assert!(project.join("sess-2.jsonl").exists());
+        assert!(!store.object_path(&gzip).exists());
+        assert!(store.entry(&render).unwrap().is_none());
+        assert!(store.entry(&other).unwrap().is_some());
+        assert!(crate::shares::get_share(&share.id).unwrap().is_none());
+        assert!(crate::backup::load_backup(&share.id).is_err());
+        assert!(plan(crate::Tool::Claude, "sess-1").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,561 @@
+//! Multi-session summaries (`agentexport report`).
+//!
+//! A report is its own payload kind: the viewer switches to a sectioned
+//! layout when it sees `"kind": "report"` instead of a message list.
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::atomic;
+use crate::compare::collect_transcripts;
+use crate::config::StorageType;
+use crate::monitor::total_cost;
+use crate::publish::{UploadTarget, format_generated_at_nice, upload_payload};
+use crate::redact::Redactor;
+use crate::shares::{self, Share};
+use crate::store::{ObjectKind, Store};
+use crate::transcript::{
+    FileDiff, ModelUsage, ParseResult, SessionStatus, Tool, claude_projects_dir,
+    codex_sessions_dir, extract_transcript_meta, parse_transcript,
+};
+
+/// Most-edited files listed in a report
+const TOP_FILES: usize = 15;
+/// Largest diffs shown per session
+const TOP_DIFFS: usize = 3;
+/// Lines of each diff shown
+const DIFF_EXCERPT_LINES: usize = 20;
+
+/// Parse a lookback like "7d", "24h", "2w" or "30m"
+pub fn parse_since(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let Ok(number) = number.parse::<u64>() else {
+        bail!("invalid --since {value:?}: expected e.g. 7d, 24h, 2w");
+    };
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => bail!("invalid --since {value:?}: unit must be m, h, d or w"),
+    };
+    Ok(Duration::from_secs(number.saturating_mul(unit_secs)))
+}
+
+/// One session in a report
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportSession {
+    pub tool: String,
+    pub title: Option<String>,
+    pub model: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub started_at: Option<time::OffsetDateTime>,
+    /// Seconds from first to last timestamped entry
+    pub duration_secs: Option<u64>,
+    pub messages: usize,
+    pub tool_calls: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+    pub status: SessionStatus,
+    /// Files the session edited, most edits first
+    pub files: Vec<String>,
+    /// The session's largest edits, biggest first
+    pub diffs: Vec<ReportDiff>,
+    /// Share of this transcript, if one was published and is still live
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_url: Option<String>,
+}
+
+/// One edit, with the start of its diff
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportDiff {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+    /// First `DIFF_EXCERPT_LINES` lines of the diff
+    pub excerpt: String,
+}
+
+impl ReportDiff {
+    fn new(diff: &FileDiff) -> Self {
+        let (added, removed) = diff.line_counts();
+        let lines: Vec<&str> = diff.diff.lines().collect();
+        let mut excerpt = lines
+            .iter()
+            .take(DIFF_EXCERPT_LINES)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if lines.len() > DIFF_EXCERPT_LINES {
+            let more = lines.len() - DIFF_EXCERPT_LINES;
+            excerpt.push_str(&format!("\n… {more} more lines"));
+        }
+        Self {
+            path: diff.path.clone(),
+            added,
+            removed,
+            excerpt,
+        }
+    }
+}
+
+/// Usage for one model across all sessions
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportModel {
+    pub model: String,
+    pub sessions: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: Option<f64>,
+}
+
+/// A file edited during the period
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportFile {
+    pub path: String,
+    pub edits: usize,
+    pub sessions: usize,
+}
+
+/// Sums across every session in the period
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportTotals {
+    pub sessions: usize,
+    pub messages: usize,
+    pub tool_calls: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+    pub interrupted: usize,
+    pub errored: usize,
+}
+
+/// Payload for a multi-session report
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportPayload {
+    /// Always "report"; tells the viewer which layout to use
+    pub kind: &'static str,
+    pub tool: String,
+    pub title: String,
+    pub shared_at: String,
+    /// Human-readable lookback, e.g. "7d"
+    pub since: String,
+    pub totals: ReportTotals,
+    pub models: Vec<ReportModel>,
+    pub files: Vec<ReportFile>,
+    pub sessions: Vec<ReportSession>,
+}
+
+impl ReportPayload {
+    /// The report's text taken from sessions: titles, file paths, and diffs
+    fn text_fields(&mut self) -> Vec<&mut String> {
+        let mut fields = vec![&mut self.title];
+        fields.extend(self.files.iter_mut().map(|f| &mut f.path));
+        for session in &mut self.sessions {
+            fields.extend(session.title.as_mut());
+            fields.extend(session.files.iter_mut());
+            for diff in &mut session.diffs {
+                fields.push(&mut diff.path);
+                fields.push(&mut diff.excerpt);
+            }
+        }
+        fields
+    }
+
+    /// Mask secrets as `publish` does: built-in and `patterns` regexes plus
+    /// what `scanner` reports. `patterns` is None with `--no-redact`.
+    /// Returns how many were masked.
+    pub fn redact(&mut self, patterns: Option<&[String]>, scanner: Option<&str>) -> Result<usize> {
+        let Some(patterns) = patterns else {
+            return Ok(0);
+        };
+        let mut redactor = Redactor::new(patterns)?;
+        if let Some(command) = scanner {
+            let fields: Vec<String> = self.text_fields().into_iter().map(|f| f.clone()).collect();
+            redactor.scan_fields(command, &fields)?;
+        }
+        Ok(self
+            .text_fields()
+            .into_iter()
+            .map(|field| redactor.redact(field))
+            .sum())
+    }
+}
+
+/// Summarize one parsed transcript
+fn summarize(
+    tool: Tool,
+    parsed: &ParseResult,
+    title: Option<String>,
+    share_url: Option<String>,
+) -> ReportSession {
+    let mut files: Vec<(&String, &usize)> = parsed.edited_files.iter().collect();
+    files.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let mut diffs: Vec<ReportDiff> = parsed.diffs.iter().map(ReportDiff::new).collect();
+    // Stable sort: equal-sized edits stay in session order
+    diffs.sort_by_key(|d| std::cmp::Reverse(d.added + d.removed));
+    diffs.retain(|d| d.added + d.removed > 0);
+    diffs.truncate(TOP_DIFFS);
+    ReportSession {
+        tool: tool.display_name().to_string(),
+        title,
+        model: parsed.dominant_model(),
+        started_at: parsed.started_at,
+        duration_secs: parsed.duration().map(|d| d.whole_seconds().max(0) as u64),
+        messages: parsed.messages.len(),
+        tool_calls: parsed.tool_call_count,
+        input_tokens: parsed.total_input_tokens(),
+        output_tokens: parsed.total_output_tokens(),
+        cost: total_cost(&parsed.usage_by_model()),
+        status: parsed.status,
+        files: files.into_iter().map(|(path, _)| path.clone()).collect(),
+        diffs,
+        share_url,
+    }
+}
+
+/// Assemble the report from already-parsed sessions
+pub fn build_report(
+    sessions: Vec<(Tool, ParseResult, Option<String>, Option<String>)>,
+    since: &str,
+    title: Option<&str>,
+    shared_at: String,
+) -> ReportPayload {
+    let mut totals = ReportTotals::default();
+    let mut models: BTreeMap<String, (usize, ModelUsage)> = BTreeMap::new();
+    let mut files: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut summaries = Vec::new();
+
+    for (tool, parsed, session_title, share_url) in sessions {
+        let summary = summarize(tool, &parsed, session_title, share_url);
+        totals.sessions += 1;
+        totals.messages += summary.messages;
+        totals.tool_calls += summary.tool_calls;
+        totals.input_tokens += summary.input_tokens;
+        totals.output_tokens += summary.output_tokens;
+        totals.cost += summary.cost;
+        match summary.status {
+            SessionStatus::Completed => {}
+            SessionStatus::Interrupted => totals.interrupted += 1,
+            SessionStatus::Errored => totals.errored += 1,
+        }
+        for (model, usage) in parsed.usage_by_model() {
+            let entry = models.entry(model).or_default();
+            entry.0 += 1;
+            entry.1.input_tokens += usage.input_tokens;
+            entry.1.output_tokens += usage.output_tokens;
+            entry.1.cache_read_tokens += usage.cache_read_tokens;
+            entry.1.cache_creation_tokens += usage.cache_creation_tokens;
+            entry.1.input_includes_cache = usage.input_includes_cache;
+        }
+        for (path, edits) in &parsed.edited_files {
+            let entry = files.entry(path.clone()).or_default();
+            entry.0 += edits;
+            entry.1 += 1;
+        }
+        summaries.push(summary);
+    }
+    summaries.sort_by_key(|s| std::cmp::Reverse(s.started_at));
+
+    let mut files: Vec<ReportFile> = files
+        .into_iter()
+        .map(|(path, (edits, sessions))| ReportFile {
+            path,
+            edits,
+            sessions,
+        })
+        .collect();
+    files.sort_by(|a, b| b.edits.cmp(&a.edits).then(a.path.cmp(&b.path)));
+    files.truncate(TOP_FILES);
+
+    ReportPayload {
+        kind: "report",
+        tool: "Agent report".to_string(),
+        title: title
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| format!("Agent report · last {since}")),
+        shared_at,
+        since: since.to_string(),
+        totals,
+        models: models
+            .into_iter()
+            .map(|(model, (sessions, usage))| ReportModel {
+                cost: usage.cost(&model),
+                model,
+                sessions,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+            })
+            .collect(),
+        files,
+        sessions: summaries,
+    }
+}
+
+/// Transcripts under `dir` modified after `cutoff`
+pub(crate) fn recent_transcripts(dir: &Path, cutoff: SystemTime) -> Vec<PathBuf> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    collect_transcripts(&[dir.to_path_buf()])
+        .into_iter()
+        .filter(|path| {
+            std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified >= cutoff)
+        })
+        .collect()
+}
+
+/// Latest live share of a transcript
+fn share_for(shares: &[Share], transcript: &Path) -> Option<String> {
+    let transcript = transcript.display().to_string();
+    shares
+        .iter()
+        .filter(|s| s.transcript_path == transcript && !s.is_expired())
+        .max_by_key(|s| s.created_at)
+        .map(|s| s.url())
+}
+
+/// Options for `agentexport report`
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Lookback as given on the command line, e.g. "7d"
+    pub since: String,
+    pub title: Option<String>,
+    pub out: Option<PathBuf>,
+    pub upload_url: Option<String>,
+    pub ttl_days: u64,
+    pub storage_type: StorageType,
+    /// Secret redaction patterns (None: `--no-redact`)
+    pub redact_patterns: Option<Vec<String>>,
+    /// External secret scanner run along with redaction
+    pub secret_scanner: Option<String>,
+}
+
+/// Outcome of `agentexport report`: where the payload went
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportResult {
+    pub sessions: usize,
+    /// Secrets masked
+    pub redacted: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub render_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_url: Option<String>,
+}
+
+/// Parse every Claude and Codex session touched since the cutoff and write
+/// (or upload) a report payload
+pub fn report(options: ReportOptions) -> Result<ReportResult> {
+    let lookback = parse_since(&options.since)?;
+    let cutoff = SystemTime::now()
+        .checked_sub(lookback)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let shares = shares::load_shares().unwrap_or_default();
+
+    let mut sessions = Vec::new();
+    for (tool, dir) in [
+        (Tool::Claude, claude_projects_dir()?),
+        (Tool::Codex, codex_sessions_dir()?),
+    ] {
+        for path in recent_transcripts(&dir, cutoff) {
+            match parse_transcript(&path) {
+                Ok(parsed) if !parsed.messages.is_empty() => {
+                    let meta = extract_transcript_meta(&path);
+                    let title = meta.title(None);
+                    let share_url = share_for(&shares, &path);
+                    sessions.push((tool, parsed, title, share_url));
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("warning: skipping {}: {err}", path.display()),
+            }
+        }
+    }
+    if sessions.is_empty() {
+        bail!("No sessions found in the last {}", options.since);
+    }
+
+    let mut payload = build_report(
+        sessions,
+        &options.since,
+        options.title.as_deref(),
+        format_generated_at_nice(),
+    );
+    let redacted = payload.redact(
+        options.redact_patterns.as_deref(),
+        options.secret_scanner.as_deref(),
+    )?;
+    let json = serde_json::to_string(&payload)?;
+    let count = payload.totals.sessions;
+
+    if let Some(upload_url) = &options.upload_url {
+        if options.storage_type == StorageType::Gist {
+            bail!("reports can't be published as gists (set storage_type = \"agentexport\")");
+        }
+        let share = upload_payload(
+            &json,
+            UploadTarget {
+                tool: "report",
+                transcript_path: String::new(),
+                storage_type: options.storage_type,
+                gist_format: Default::default(),
+                upload_url,
+                ttl_days: options.ttl_days,
+                relates_to: None,
+                tags: vec!["report".to_string()],
+                gzip_path: None,
+                fingerprint: None,
+                session_status: None,
+                errors: Vec::new(),
+                title: Some(payload.title.clone()),
+                cwd: None,
+                lang: None,
+                plaintext_token: None,
+                passphrase: None,
+                gist_attachments: Vec::new(),
+            },
+        )?;
+        return Ok(ReportResult {
+            sessions: count,
+            redacted,
+            render_path: None,
+            share_url: Some(share.url()),
+        });
+    }
+
+    let render_path = match &options.out {
+        Some(path) => {
+            atomic::write(path, &json)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            path.clone()
+        }
+        None => {
+            let store = Store::open()?;
+            let hash =
+                store.put_bytes(json.as_bytes(), ObjectKind::Render, Some("report"), None)?;
+            store.object_path(&hash)
+        }
+    };
+    Ok(ReportResult {
+        sessions: count,
+        redacted,
+        render_path: Some(render_path.display().to_string()),
+        share_url: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shares::tests::make_test_share;
+    use crate::transcript::parse_transcript_from_reader;
+
+    #[test]
+    fn parse_since_units() {
+        assert_eq!(parse_since("7d").unwrap(), Duration::from_secs(7 * 86_400));
+        assert_eq!(parse_since("24h").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_since("2w").unwrap(), Duration::from_secs(14 * 86_400));
+        assert_eq!(parse_since("30m").unwrap(), Duration::from_secs(1800));
+        assert!(parse_since("7").is_err());
+        assert!(parse_since("d").is_err());
+        assert!(parse_since("7y").is_err());
+    }
+
+    #[test]
+    fn report_aggregates_sessions_and_files() {
+        let first = [
+            r#"{"type":"user","timestamp":"2025-01-01T10:00:00Z","message":{"content":"Fix it"}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:01:00Z","message":{"id":"m1","model":"claude-haiku-4-5","usage":{"input_tokens":1000,"output_tokens":100},"content":[{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/a.rs"}}]}}"#,
+        ]
+        .join("\n");
+        let second = [
+            r#"{"type":"user","timestamp":"2025-01-02T10:00:00Z","message":{"content":"More"}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-02T10:00:30Z","message":{"id":"m1","model":"claude-haiku-4-5","usage":{"input_tokens":500,"output_tokens":50},"content":[{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/a.rs","old_string":"let key = 1;","new_string":"let key = \"sk-live0123456789abcdefghij\";"}},{"type":"tool_use","id":"t2","name":"Write","input":{"file_path":"src/b.rs","content":"fn b() {}"}}]}}"#,
+        ]
+        .join("\n");
+        let sessions = vec![
+            (
+                Tool::Claude,
+                parse_transcript_from_reader(first.as_bytes()).unwrap(),
+                Some("Fix it".to_string()),
+                None,
+            ),
+            (
+                Tool::Claude,
+                parse_transcript_from_reader(second.as_bytes()).unwrap(),
+                None,
+                Some("https://example.com/v/x#k".to_string()),
+            ),
+        ];
+        let mut report = build_report(sessions, "7d", None, "now".to_string());
+
+        assert_eq!(report.kind, "report");
+        assert_eq!(report.title, "Agent report · last 7d");
+        assert_eq!(report.totals.sessions, 2);
+        assert_eq!(report.totals.tool_calls, 3);
+        assert_eq!(report.totals.input_tokens, 1500);
+        assert_eq!(report.models.len(), 1);
+        assert_eq!(report.models[0].sessions, 2);
+        assert!(report.models[0].cost.unwrap() > 0.0);
+        assert_eq!(report.files[0].path, "src/a.rs");
+        assert_eq!((report.files[0].edits, report.files[0].sessions), (2, 2));
+        // Newest session first
+        assert_eq!(report.sessions[0].duration_secs, Some(30));
+        assert_eq!(report.sessions[0].files, vec!["src/a.rs", "src/b.rs"]);
+        assert!(report.sessions[0].share_url.is_some());
+        assert_eq!(report.sessions[1].title.as_deref(), Some("Fix it"));
+        // Largest edit first; the first session's Edit had no text to show
+        let diffs: Vec<(&str, usize, usize)> = report.sessions[0]
+            .diffs
+            .iter()
+            .map(|d| (d.path.as_str(), d.added, d.removed))
+            .collect();
+        assert_eq!(diffs, [("src/a.rs", 1, 1), ("src/b.rs", 1, 0)]);
+        assert!(report.sessions[1].diffs.is_empty());
+
+        report.sessions[1].title = Some("Rotate sk-live0123456789abcdefghij".to_string());
+        assert_eq!(report.redact(None, None).unwrap(), 0);
+        assert_eq!(report.redact(Some(&[]), None).unwrap(), 2);
+        assert_eq!(
+            report.sessions[1].title.as_deref(),
+            Some("Rotate [REDACTED]")
+        );
+        assert_eq!(
+            report.sessions[0].diffs[0].excerpt,
+            "-let key = 1;\n+let key = \"[REDACTED]\";"
+        );
+    }
+
+    #[test]
+    fn diff_excerpts_are_cut_to_a_few_lines() {
+        let diff = FileDiff {
+            path: "src/big.rs".to_string(),
+            diff: (0..25).map(|i| format!("+line {i}\n")).collect(),
+        };
+        let excerpt = ReportDiff::new(&diff);
+        assert_eq!((excerpt.added, excerpt.removed), (25, 0));
+        assert_eq!(excerpt.excerpt.lines().count(), DIFF_EXCERPT_LINES + 1);
+        assert!(excerpt.excerpt.ends_with("… 5 more lines"));
+    }
+
+    #[test]
+    fn share_for_matches_transcript_path() {
+        let share = Share {
+            transcript_path: "/tmp/session.jsonl".to_string(),
+            expires_at: time::OffsetDateTime::now_utc() + time::Duration::days(1),
+            ..make_test_share("abc")
+        };
+        let shares = vec![share];
+        assert!(share_for(&shares, Path::new("/tmp/session.jsonl")).is_some());
+        assert!(share_for(&shares, Path::new("/tmp/other.jsonl")).is_none());
+    }
+}
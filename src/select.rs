@@ -0,0 +1,115 @@
+//! `publish --select`: pick which messages go into a share from a checklist.
+//!
+//! Messages are grouped into turns, each starting at a user message, and
+//! shown one line each with a short preview. Everything starts checked.
+
+use anyhow::{Result, bail};
+use dialoguer::{MultiSelect, theme::ColorfulTheme};
+use std::io::IsTerminal;
+
+use crate::transcript::{RenderedMessage, SharePayload};
+
+/// Longest preview shown per message, in characters
+const PREVIEW_CHARS: usize = 72;
+
+/// First words of a message on one line
+fn preview(content: &str) -> String {
+    let text = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= PREVIEW_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(PREVIEW_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// One checklist line per message; a user message opens a numbered turn
+fn message_labels(messages: &[RenderedMessage]) -> Vec<String> {
+    let mut turn = 0;
+    messages
+        .iter()
+        .map(|message| {
+            let prefix = if message.role == "user" {
+                turn += 1;
+                format!("Turn {turn}")
+            } else {
+                String::new()
+            };
+            format!(
+                "{prefix:<8} {}: {}",
+                message.role,
+                preview(&message.content)
+            )
+        })
+        .collect()
+}
+
+/// Keep only the messages at `selected` (indices in ascending order)
+fn retain_selected(messages: &mut Vec<RenderedMessage>, selected: &[usize]) {
+    let mut index = 0;
+    messages.retain(|_| {
+        let keep = selected.binary_search(&index).is_ok();
+        index += 1;
+        keep
+    });
+}
+
+/// Let the user uncheck messages to leave out of `payload`
+pub fn select_messages(payload: &mut SharePayload) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        bail!("--select needs an interactive terminal");
+    }
+    let labels = message_labels(&payload.messages);
+    let defaults = vec![true; labels.len()];
+    let mut selected = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Messages to share (space toggles, enter confirms)")
+        .items(&labels)
+        .defaults(&defaults)
+        .interact()?;
+    if selected.is_empty() {
+        bail!("No messages selected; publish cancelled");
+    }
+    selected.sort_unstable();
+    retain_selected(&mut payload.messages, &selected);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> RenderedMessage {
+        RenderedMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            raw: None,
+            raw_label: None,
+            tool_use_id: None,
+            model: None,
+            abandoned: false,
+            part: None,
+            id: None,
+            highlighted: false,
+            images: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn labels_group_by_turn_and_selection_filters() {
+        let mut messages = vec![
+            message("user", "Fix the\nparser"),
+            message("tool", "Read src/parser.rs"),
+            message("assistant", &"word ".repeat(40)),
+            message("user", "Thanks"),
+        ];
+        let labels = message_labels(&messages);
+        assert_eq!(labels[0], "Turn 1   user: Fix the parser");
+        assert_eq!(labels[1], "         tool: Read src/parser.rs");
+        let preview = labels[2].split_once(": ").unwrap().1;
+        assert!(preview.ends_with('…') && preview.chars().count() <= PREVIEW_CHARS);
+        assert!(labels[3].starts_with("Turn 2"));
+
+        retain_selected(&mut messages, &[0, 2]);
+        let roles: Vec<_> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant"]);
+    }
+}
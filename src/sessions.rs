@@ -0,0 +1,182 @@
+//! `agentexport sessions list`: browse local transcripts.
+//!
+//! Walks Claude's projects folder and Codex's sessions folder, newest first,
+//! so old sessions can be found without digging through either by hand.
+
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use time::OffsetDateTime;
+use walkdir::WalkDir;
+
+use crate::transcript::{
+    Tool, claude_projects_dir, codex_sessions_dir, extract_transcript_meta, parse_transcript,
+};
+
+/// Sessions shown by `sessions list` without `--all`
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// One local transcript
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub tool: Tool,
+    pub id: String,
+    pub path: PathBuf,
+    pub cwd: Option<String>,
+    pub title: Option<String>,
+    pub modified: OffsetDateTime,
+    pub messages: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Session id from a transcript file name: `<id>.jsonl` for Claude,
+/// `rollout-<time>-<uuid>.jsonl` for Codex
+fn session_id(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    const UUID_LEN: usize = 36;
+    match stem.strip_prefix("rollout-") {
+        Some(rest) if rest.len() > UUID_LEN && rest.is_char_boundary(rest.len() - UUID_LEN) => {
+            rest[rest.len() - UUID_LEN..].to_string()
+        }
+        Some(rest) => rest.to_string(),
+        None => stem,
+    }
+}
+
+/// Transcripts of `tool` with their modification times. Claude keeps one file
+/// per session directly under each project folder; anything deeper belongs to
+/// a subagent.
+fn transcripts(tool: Tool) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let (dir, depth) = match tool {
+        Tool::Claude => (claude_projects_dir()?, Some(2)),
+        Tool::Codex => (codex_sessions_dir()?, None),
+        Tool::Cursor | Tool::Aider | Tool::ChatGpt | Tool::ClaudeAi => {
+            bail!("sessions list supports claude and codex")
+        }
+    };
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut walk = WalkDir::new(dir);
+    if let Some(depth) = depth {
+        walk = walk.min_depth(depth).max_depth(depth);
+    }
+    Ok(walk
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|e| {
+            let modified = e.metadata().ok()?.modified().ok()?;
+            Some((e.into_path(), modified))
+        })
+        .collect())
+}
+
+/// Newest sessions of `tools` first, at most `limit` of them (all with None).
+/// Only the listed sessions are parsed.
+pub fn list_sessions(tools: &[Tool], limit: Option<usize>) -> Result<Vec<SessionSummary>> {
+    let mut found = Vec::new();
+    for &tool in tools {
+        found.extend(
+            transcripts(tool)?
+                .into_iter()
+                .map(|(path, modified)| (tool, path, modified)),
+        );
+    }
+    found.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(&b.1)));
+    if let Some(limit) = limit {
+        found.truncate(limit);
+    }
+
+    let mut sessions = Vec::new();
+    for (tool, path, modified) in found {
+        let parsed = match parse_transcript(&path) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                eprintln!("warning: skipping {}: {err}", path.display());
+                continue;
+            }
+        };
+        let meta = extract_transcript_meta(&path);
+        sessions.push(SessionSummary {
+            tool,
+            id: session_id(&path),
+            title: meta.title(None),
+            cwd: meta.cwd,
+            modified: modified.into(),
+            messages: parsed.messages.len(),
+            input_tokens: parsed.total_input_tokens(),
+            output_tokens: parsed.total_output_tokens(),
+            path,
+        });
+    }
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use std::fs;
+
+    #[test]
+    fn session_ids_from_file_names() {
+        assert_eq!(session_id(Path::new("/p/abc-123.jsonl")), "abc-123");
+        assert_eq!(
+            session_id(Path::new(
+                "/s/rollout-2025-01-02T10-11-12-0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b.jsonl"
+            )),
+            "0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b"
+        );
+        assert_eq!(session_id(Path::new("/s/rollout-sess-1.jsonl")), "sess-1");
+    }
+
+    #[test]
+    fn lists_newest_sessions_and_skips_subagents() {
+        let _lock = env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", home.path().to_str().unwrap());
+        let codex = home.path().join(".codex/sessions");
+        let _codex = EnvGuard::set("AGENTEXPORT_CODEX_SESSIONS_DIR", codex.to_str().unwrap());
+
+        let project = home.path().join(".claude/projects/-work-app");
+        fs::create_dir_all(project.join("old/subagents")).unwrap();
+        let line = |text: &str| {
+            format!(
+                r#"{{"type":"user","cwd":"/work/app","message":{{"role":"user","content":"{text}"}}}}"#
+            )
+        };
+        fs::write(project.join("old.jsonl"), line("first")).unwrap();
+        fs::write(project.join("old/subagents/agent-1.jsonl"), line("sub")).unwrap();
+        let assistant = r#"{"type":"assistant","message":{"id":"m","model":"claude-sonnet-4-5","usage":{"input_tokens":12,"output_tokens":3},"content":[{"type":"text","text":"ok"}]}}"#;
+        fs::write(
+            project.join("new.jsonl"),
+            format!("{}\n{assistant}", line("second")),
+        )
+        .unwrap();
+        let past = SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(project.join("old.jsonl"))
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+
+        let sessions = list_sessions(&[Tool::Claude, Tool::Codex], None).unwrap();
+        let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["new", "old"]);
+        assert_eq!(sessions[0].cwd.as_deref(), Some("/work/app"));
+        assert_eq!(sessions[0].messages, 2);
+        assert_eq!(sessions[0].input_tokens, 12);
+        assert_eq!(sessions[0].output_tokens, 3);
+
+        let newest = list_sessions(&[Tool::Claude], Some(1)).unwrap();
+        assert_eq!(newest.len(), 1);
+        assert_eq!(newest[0].id, "new");
+    }
+}
@@ -1,25 +1,130 @@
 use anyhow::{Context, Result, bail};
-use dialoguer::{MultiSelect, theme::ColorfulTheme};
+use dialoguer::{Confirm, MultiSelect, Select, theme::ColorfulTheme};
+use serde_json::{Value, json};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::atomic;
+use crate::config::{Config, GistFormat, StorageType};
+use crate::crypto;
+use crate::manpages;
 use crate::transcript::{Tool, codex_home_dir};
+use crate::upload;
 
 // Embed files at compile time
 const CLAUDE_COMMAND: &str = include_str!("../commands/claude/agentexport.md");
 const CODEX_PROMPT: &str = include_str!("../skills/codex/agentexport.md");
 
-pub fn run() -> Result<()> {
+/// TTL choices offered by the wizard (days; 0 is forever)
+const TTL_CHOICES: [u64; 6] = [30, 60, 90, 180, 365, 0];
+
+/// Command the SessionStart hook runs
+const SESSION_START_COMMAND: &str = "agentexport claude-sessionstart";
+
+/// Interactive first-run setup: storage, TTL, slash commands, hook, man pages
+/// (rendered from `command`), and a live check
+pub fn run(command: clap::Command) -> Result<()> {
     let theme = ColorfulTheme::default();
+    let mut config = Config::load().unwrap_or_default();
+
+    let storage = Select::with_theme(&theme)
+        .with_prompt("Where should shares be stored?")
+        .items(&[
+            "agentexports.com (end-to-end encrypted, expires)",
+            "GitHub gist (secret gist via gh)",
+        ])
+        .default(match config.storage_type {
+            StorageType::Agentexport => 0,
+            StorageType::Gist => 1,
+        })
+        .interact()?;
+    config.storage_type = if storage == 0 {
+        StorageType::Agentexport
+    } else {
+        StorageType::Gist
+    };
+
+    if config.storage_type == StorageType::Agentexport {
+        let labels: Vec<String> = TTL_CHOICES
+            .iter()
+            .map(|&days| match days {
+                0 => "forever".to_string(),
+                days => format!("{days} days"),
+            })
+            .collect();
+        let ttl = Select::with_theme(&theme)
+            .with_prompt("How long should shares live?")
+            .items(&labels)
+            .default(
+                TTL_CHOICES
+                    .iter()
+                    .position(|&d| d == config.default_ttl)
+                    .unwrap_or(0),
+            )
+            .interact()?;
+        config.default_ttl = TTL_CHOICES[ttl];
+    }
+    let path = config.save()?;
+    println!("Saved {}.", path.display());
+    println!();
 
     // Detect installed tools
     let claude_path = find_in_path("claude");
     let codex_path = find_in_path("codex");
 
     if claude_path.is_none() && codex_path.is_none() {
-        bail!("Neither claude nor codex found in PATH");
+        println!("Neither claude nor codex found in PATH; skipping slash commands.");
+    } else {
+        install_commands(&theme, claude_path.as_deref(), codex_path.as_deref())?;
+    }
+
+    if claude_path.is_some()
+        && Confirm::with_theme(&theme)
+            .with_prompt(
+                "Install the Claude SessionStart hook (lets publish find the exact session)?",
+            )
+            .default(false)
+            .interact()?
+    {
+        install_claude_hook()?;
     }
 
+    if Confirm::with_theme(&theme)
+        .with_prompt("Install man pages (`man agentexport`)?")
+        .default(true)
+        .interact()?
+    {
+        let dir = manpages::user_man_dir()?;
+        let written = manpages::write_man_pages(command, &dir)?;
+        println!(
+            "Installed {} man pages in {}.",
+            written.len(),
+            dir.display()
+        );
+    }
+
+    if Confirm::with_theme(&theme)
+        .with_prompt("Run a test upload and delete it now?")
+        .default(true)
+        .interact()?
+    {
+        match round_trip(&config) {
+            Ok(detail) => println!("✓ {detail}"),
+            Err(err) => println!("✗ Test upload failed: {err:#}"),
+        }
+    }
+
+    println!();
+    println!("Done! Restart Claude Code / Codex to pick up changes.");
+
+    Ok(())
+}
+
+fn install_commands(
+    theme: &ColorfulTheme,
+    claude_path: Option<&Path>,
+    codex_path: Option<&Path>,
+) -> Result<()> {
     // Show what will be installed
     println!("This will install:");
     if claude_path.is_some() {
@@ -34,25 +139,25 @@ pub fn run() -> Result<()> {
     let mut items: Vec<(Tool, String)> = Vec::new();
     let mut defaults = Vec::new();
 
-    if let Some(path) = &claude_path {
+    if let Some(path) = claude_path {
         items.push((Tool::Claude, format!("Claude Code ({})", path.display())));
         defaults.push(true);
     }
-    if let Some(path) = &codex_path {
+    if let Some(path) = codex_path {
         items.push((Tool::Codex, format!("Codex ({})", path.display())));
         defaults.push(true);
     }
 
     let labels: Vec<&str> = items.iter().map(|(_, label)| label.as_str()).collect();
 
-    let selected = MultiSelect::with_theme(&theme)
+    let selected = MultiSelect::with_theme(theme)
         .with_prompt("Select tools to configure")
         .items(&labels)
         .defaults(&defaults)
         .interact()?;
 
     if selected.is_empty() {
-        println!("Nothing selected.");
+        println!("No slash commands selected.");
         return Ok(());
     }
 
@@ -67,15 +172,115 @@ pub fn run() -> Result<()> {
             Tool::Codex => {
                 install_codex_prompt()?;
             }
+            // Cursor, Aider, and imported chats have no slash command to install
+            Tool::Cursor | Tool::Aider | Tool::ChatGpt | Tool::ClaudeAi => {}
         }
     }
-
     println!();
-    println!("Done! Restart Claude Code / Codex to pick up changes.");
+    Ok(())
+}
 
+/// Add the SessionStart hook to Claude settings; false if it was already there
+fn add_session_start_hook(settings: &mut Value) -> Result<bool> {
+    let Some(root) = settings.as_object_mut() else {
+        bail!("Claude settings are not a JSON object");
+    };
+    let hooks = root
+        .entry("hooks")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("Claude settings `hooks` is not an object")?;
+    let session_start = hooks
+        .entry("SessionStart")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .context("Claude settings `hooks.SessionStart` is not an array")?;
+
+    let installed = session_start.iter().any(|group| {
+        group
+            .get("hooks")
+            .and_then(|h| h.as_array())
+            .is_some_and(|h| {
+                h.iter().any(|hook| {
+                    hook.get("command").and_then(|c| c.as_str()) == Some(SESSION_START_COMMAND)
+                })
+            })
+    });
+    if installed {
+        return Ok(false);
+    }
+    session_start.push(json!({
+        "hooks": [{ "type": "command", "command": SESSION_START_COMMAND }]
+    }));
+    Ok(true)
+}
+
+fn install_claude_hook() -> Result<()> {
+    let path = claude_home_dir()?.join("settings.json");
+    let mut settings: Value = match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse {}", path.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => json!({}),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", path.display())),
+    };
+    if !add_session_start_hook(&mut settings)? {
+        println!(
+            "Skipping SessionStart hook (already in {}).",
+            path.display()
+        );
+        return Ok(());
+    }
+    fs::create_dir_all(path.parent().unwrap())?;
+    atomic::write(&path, serde_json::to_string_pretty(&settings)? + "\n")?;
+    println!("Installed SessionStart hook in {}.", path.display());
     Ok(())
 }
 
+/// Upload an empty payload to the configured backend, confirm it exists, and delete it
+fn round_trip(config: &Config) -> Result<String> {
+    let payload = json!({
+        "tool": "agentexport setup check",
+        "shared_at": "",
+        "messages": [],
+    })
+    .to_string();
+    match config.storage_type {
+        StorageType::Gist => {
+            let result = upload::upload_gist(
+                "gist",
+                &payload,
+                "agentexport setup check",
+                GistFormat::Json,
+                &[],
+            )?;
+            upload::delete_gist(&result.id)?;
+            Ok(format!("Created and deleted a test gist ({}).", result.id))
+        }
+        StorageType::Agentexport => {
+            let encrypted = crypto::encrypt_html(&payload, None)?;
+            let result = upload::upload_blob(
+                &config.upload_url,
+                &encrypted.blob,
+                &encrypted.key_b64,
+                30,
+                None,
+            )?;
+            let status = upload::check_blob_status(&result.upload_url, &result.id)?;
+            upload::delete_blob(&result.upload_url, &result.id, &result.delete_token)?;
+            if status != upload::BlobStatus::Active {
+                bail!(
+                    "uploaded {} but the server reports it as {status:?}",
+                    result.id
+                );
+            }
+            Ok(format!(
+                "Uploaded and deleted a test share on {}.",
+                result.upload_url
+            ))
+        }
+    }
+}
+
 fn install_claude_command() -> Result<()> {
     let dest_dir = ensure_claude_commands_dir()?;
     let dest = dest_dir.join("agentexport.md");
@@ -148,3 +353,26 @@ fn is_executable(path: &Path) -> bool {
 fn is_executable(path: &Path) -> bool {
     path.is_file()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_start_hook_added_once() {
+        let mut settings = json!({
+            "model": "opus",
+            "hooks": { "SessionStart": [{ "hooks": [{ "type": "command", "command": "other" }] }] }
+        });
+        assert!(add_session_start_hook(&mut settings).unwrap());
+        assert!(!add_session_start_hook(&mut settings).unwrap());
+        let groups = settings["hooks"]["SessionStart"].as_array().unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1]["hooks"][0]["command"], SESSION_START_COMMAND);
+        assert_eq!(settings["model"], "opus");
+
+        let mut empty = json!({});
+        assert!(add_session_start_hook(&mut empty).unwrap());
+        assert!(add_session_start_hook(&mut json!([])).is_err());
+    }
+}
@@ -1,12 +1,26 @@
 //! Local shares storage for managing uploaded transcripts.
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use time::OffsetDateTime;
 
 use crate::StorageType;
+use crate::atomic;
+use crate::transcript::{SessionStatus, SharePayload};
+use crate::upload::{self, BlobStatus};
+
+/// Server-side state of a share, as found by `shares list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteStatus {
+    Live,
+    Expired,
+    Deleted,
+}
 
 /// A shared transcript record
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,10 +36,72 @@ pub struct Share {
     pub storage_type: StorageType,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
+    /// When the record was last saved: republished, retitled, extended, or
+    /// tagged. Missing on shares saved before this was tracked.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub updated_at: Option<OffsetDateTime>,
     #[serde(with = "time::serde::rfc3339")]
     pub expires_at: OffsetDateTime,
     pub tool: String,
     pub transcript_path: String,
+    /// Id of the share this one continues
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relates_to: Option<String>,
+    /// Uploaded size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Retained gzip of the raw transcript (restorable with `render --from-gzip`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gzip_path: Option<String>,
+    /// Hash of the transcript and publish options, used to skip duplicate uploads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// How the shared session ended (unknown for shares made before this was tracked)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_status: Option<SessionStatus>,
+    /// Error summaries from the shared session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Title shown on the share page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Working directory of the shared session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Detected conversation language (BCP 47 tag)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// What the server reported at the last check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_status: Option<RemoteStatus>,
+    /// When the server was last asked about this share
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub checked_at: Option<OffsetDateTime>,
+    /// Chunks of new messages appended to the server blob since it was last
+    /// uploaded whole
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chunks: u32,
+    /// The key and delete token were wiped once the share expired
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub scrubbed: bool,
+    /// The key was left out when this record was synced here, so the share
+    /// can't be opened from this machine
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub key_withheld: bool,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
 }
 
 impl Share {
@@ -34,18 +110,226 @@ impl Share {
         if let Some(url) = &self.share_url {
             return url.to_string();
         }
+        if self.key.is_empty() {
+            // Plaintext org-mode shares have no key to carry in the fragment,
+            // and scrubbed or withheld keys aren't here to add
+            return format!("{}/v/{}", self.upload_url, self.id);
+        }
         format!("{}/v/{}#{}", self.upload_url, self.id, self.key)
     }
 
+    /// The share URL without its `#key` fragment, for places anyone may read.
+    /// It only opens plaintext shares and gists.
+    pub fn url_without_key(&self) -> String {
+        let url = self.url();
+        match url.split_once('#') {
+            Some((base, _)) => base.to_string(),
+            None => url,
+        }
+    }
+
+    /// Uploaded without E2E, rather than missing a key it had
+    pub fn is_plaintext(&self) -> bool {
+        self.key.is_empty() && !self.scrubbed && !self.key_withheld
+    }
+
+    /// Fail unless this machine has the key the share was encrypted with
+    /// (plaintext shares need none)
+    pub fn require_key(&self) -> Result<()> {
+        if self.scrubbed {
+            bail!("share {} expired and its key was scrubbed", self.id);
+        }
+        if self.key_withheld {
+            bail!(
+                "the key of share {} is not on this machine; it was synced without --include-secrets",
+                self.id
+            );
+        }
+        Ok(())
+    }
+
+    /// Drop the `#key` fragment from the stored link
+    fn strip_url_key(&mut self) {
+        if let Some(url) = &mut self.share_url
+            && let Some(at) = url.find('#')
+        {
+            url.truncate(at);
+        }
+    }
+
     /// Check if this share has expired (based on local time)
     pub fn is_expired(&self) -> bool {
-        OffsetDateTime::now_utc() > self.expires_at
+        !self.never_expires() && OffsetDateTime::now_utc() > self.expires_at
+    }
+
+    /// Forever shares are stored with a zero (server) or far-future (gist) expiry
+    pub fn never_expires(&self) -> bool {
+        self.expires_at.unix_timestamp() == 0
+            || self.expires_at > OffsetDateTime::now_utc() + time::Duration::days(365 * 50)
+    }
+
+    /// The last server check found it expired or deleted
+    pub fn is_gone(&self) -> bool {
+        matches!(
+            self.remote_status,
+            Some(RemoteStatus::Expired | RemoteStatus::Deleted)
+        )
+    }
+
+    /// When the record last changed, for picking between two copies of it
+    pub fn last_modified(&self) -> OffsetDateTime {
+        self.updated_at.unwrap_or(self.created_at)
+    }
+
+    /// Wipe the key and delete token, keeping the rest of the record
+    pub fn scrub(&mut self) {
+        self.key.clear();
+        self.delete_token.clear();
+        self.strip_url_key();
+        self.scrubbed = true;
+    }
+
+    /// "active", "expired", or "forever", or "deleted" once a server check says so
+    pub fn status(&self) -> &'static str {
+        if let Some(RemoteStatus::Deleted) = self.remote_status {
+            "deleted"
+        } else if let Some(RemoteStatus::Expired) = self.remote_status {
+            "expired"
+        } else if self.never_expires() {
+            "forever"
+        } else if self.is_expired() {
+            "expired"
+        } else {
+            "active"
+        }
+    }
+
+    /// Relative expiry such as "in 6d" or "2h ago"
+    pub fn expires_in(&self, now: OffsetDateTime) -> String {
+        if self.never_expires() {
+            return "never".to_string();
+        }
+        let delta = self.expires_at - now;
+        if delta.is_negative() {
+            format!("{} ago", format_duration(-delta))
+        } else {
+            format!("in {}", format_duration(delta))
+        }
+    }
+}
+
+fn format_duration(delta: time::Duration) -> String {
+    if delta.whole_days() > 0 {
+        format!("{}d", delta.whole_days())
+    } else if delta.whole_hours() > 0 {
+        format!("{}h", delta.whole_hours())
+    } else {
+        format!("{}m", delta.whole_minutes().max(1))
+    }
+}
+
+/// Human-readable byte size ("842 B", "12.3 KB", "1.4 MB")
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b < KB {
+        format!("{bytes} B")
+    } else if b < KB * KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{:.1} MB", b / (KB * KB))
+    }
+}
+
+/// Column to sort the shares listing by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShareSort {
+    #[default]
+    Created,
+    Expires,
+    Tool,
+    Size,
+    Tags,
+}
+
+/// Sort shares in place by the given column (ascending)
+pub fn sort_shares(shares: &mut [Share], sort: ShareSort) {
+    match sort {
+        ShareSort::Created => shares.sort_by_key(|s| s.created_at),
+        // Forever shares sort last
+        ShareSort::Expires => shares.sort_by_key(|s| (s.never_expires(), s.expires_at)),
+        ShareSort::Tool => shares.sort_by(|a, b| a.tool.cmp(&b.tool)),
+        ShareSort::Size => shares.sort_by_key(|s| s.size_bytes.unwrap_or(0)),
+        ShareSort::Tags => shares.sort_by_key(|s| s.tags.join(",")),
+    }
+}
+
+/// A `key=value` filter for the shares listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareFilter {
+    Tool(String),
+    Storage(String),
+    Tag(String),
+    Status(String),
+    Session(String),
+    Lang(String),
+}
+
+impl ShareFilter {
+    pub fn parse(s: &str) -> Result<Self> {
+        let Some((key, value)) = s.split_once('=') else {
+            bail!("invalid filter {s:?}: expected key=value");
+        };
+        let value = value.trim().to_lowercase();
+        match key.trim() {
+            "tool" => Ok(Self::Tool(value)),
+            "storage" => Ok(Self::Storage(value)),
+            "tag" => Ok(Self::Tag(value)),
+            "status" => Ok(Self::Status(value)),
+            "session" => Ok(Self::Session(value)),
+            "lang" => Ok(Self::Lang(value)),
+            other => bail!(
+                "unknown filter key {other:?}: must be tool, storage, tag, status, session, or lang"
+            ),
+        }
+    }
+
+    pub fn matches(&self, share: &Share) -> bool {
+        match self {
+            Self::Tool(tool) => share.tool.eq_ignore_ascii_case(tool),
+            Self::Storage(storage) => share.storage_type.to_string() == *storage,
+            Self::Tag(tag) => share.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Self::Status(status) => share.status() == status,
+            Self::Session(status) => share.session_status.is_some_and(|s| s.as_str() == status),
+            Self::Lang(lang) => share
+                .lang
+                .as_deref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(lang)),
+        }
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct SharesFile {
     shares: Vec<Share>,
+    /// Ids of shares removed here, so a sync doesn't bring them back
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    removed: Vec<String>,
+}
+
+impl SharesFile {
+    /// Blank every key and delete token, and the key in each link, for a
+    /// synced copy that shouldn't be able to open or delete the shares
+    fn strip_secrets(&mut self) {
+        for share in &mut self.shares {
+            if !share.key.is_empty() {
+                share.key.clear();
+                share.key_withheld = true;
+            }
+            share.delete_token.clear();
+            share.strip_url_key();
+        }
+    }
 }
 
 /// Get the path to the shares file
@@ -58,43 +342,56 @@ fn shares_file_path() -> Result<PathBuf> {
 
 /// Load all shares from local storage
 pub fn load_shares() -> Result<Vec<Share>> {
+    Ok(load_file()?.shares)
+}
+
+fn load_file() -> Result<SharesFile> {
     let path = shares_file_path()?;
     if !path.exists() {
-        return Ok(Vec::new());
+        return Ok(SharesFile::default());
     }
 
     let content = fs::read_to_string(&path).context("Failed to read shares file")?;
-    let file: SharesFile = serde_json::from_str(&content).context("Failed to parse shares file")?;
-    Ok(file.shares)
+    serde_json::from_str(&content).context("Failed to parse shares file")
 }
 
-/// Save a new share to local storage
+/// Serializes read-modify-write updates of shares.json within this process
+static SHARES_LOCK: Mutex<()> = Mutex::new(());
+
+/// Save a new or changed share to local storage, stamping its `updated_at`
 pub fn save_share(share: &Share) -> Result<()> {
-    let mut shares = load_shares().unwrap_or_default();
+    let _guard = SHARES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load_file().unwrap_or_default();
+    let mut share = share.clone();
+    share.updated_at = Some(OffsetDateTime::now_utc());
 
     // Check if this share already exists (by id + upload_url)
-    let existing = shares
+    let existing = file
+        .shares
         .iter()
         .position(|s| s.id == share.id && s.upload_url == share.upload_url);
 
+    file.removed.retain(|id| *id != share.id);
     if let Some(idx) = existing {
-        shares[idx] = share.clone();
+        file.shares[idx] = share;
     } else {
-        shares.push(share.clone());
+        file.shares.push(share);
     }
 
-    write_shares(&shares)
+    write_file(&file)
 }
 
 /// Remove a share from local storage by id
 pub fn remove_share(id: &str) -> Result<Option<Share>> {
-    let mut shares = load_shares()?;
+    let _guard = SHARES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load_file()?;
 
-    let idx = shares.iter().position(|s| s.id == id);
-    let removed = idx.map(|i| shares.remove(i));
+    let idx = file.shares.iter().position(|s| s.id == id);
+    let removed = idx.map(|i| file.shares.remove(i));
 
     if removed.is_some() {
-        write_shares(&shares)?;
+        file.removed.push(id.to_string());
+        write_file(&file)?;
     }
 
     Ok(removed)
@@ -106,22 +403,371 @@ pub fn get_share(id: &str) -> Result<Option<Share>> {
     Ok(shares.into_iter().find(|s| s.id == id))
 }
 
+/// `title`, numbered "title (2)", "title (3)", ... if another share from the
+/// same working directory already uses it
+pub fn unique_title(shares: &[Share], title: &str, cwd: Option<&str>) -> String {
+    let taken: HashSet<&str> = shares
+        .iter()
+        .filter(|s| s.cwd.as_deref() == cwd)
+        .filter_map(|s| s.title.as_deref())
+        .collect();
+    if !taken.contains(title) {
+        return title.to_string();
+    }
+    (2..)
+        .map(|n| format!("{title} ({n})"))
+        .find(|candidate| !taken.contains(candidate.as_str()))
+        .expect("unbounded range")
+}
+
+/// Position of a share in its `relates_to` chain (1 for a standalone share)
+pub fn series_part(shares: &[Share], id: &str) -> u32 {
+    let mut part = 1;
+    let mut current = id;
+    // Bound the walk by the number of shares so a cycle can't loop forever
+    for _ in 0..shares.len() {
+        let Some(parent) = shares
+            .iter()
+            .find(|s| s.id == current)
+            .and_then(|s| s.relates_to.as_deref())
+        else {
+            break;
+        };
+        part += 1;
+        current = parent;
+    }
+    part
+}
+
+/// What `shares verify` found after fetching and decrypting a share
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+    /// Size of the blob as stored on the server
+    pub blob_bytes: usize,
+    /// Size of the decrypted JSON payload
+    pub payload_bytes: usize,
+    /// `transcript` or `report`
+    pub kind: String,
+    /// Messages in a transcript, or sessions in a report
+    pub items: usize,
+}
+
+/// Ask the server (or GitHub) whether a share is still there
+pub fn check_remote(share: &Share) -> Result<RemoteStatus> {
+    let status = if share.storage_type == StorageType::Gist {
+        upload::check_gist_status(&share.id)?
+    } else {
+        upload::check_blob_status(&share.upload_url, &share.id)?
+    };
+    Ok(match status {
+        BlobStatus::Active => RemoteStatus::Live,
+        BlobStatus::Expired => RemoteStatus::Expired,
+        // The server also answers 404 once an expired blob has been cleaned up
+        BlobStatus::NotFound if share.is_expired() => RemoteStatus::Expired,
+        BlobStatus::NotFound => RemoteStatus::Deleted,
+        BlobStatus::Unknown => bail!("Unexpected response checking share {}", share.id),
+    })
+}
+
+/// How long a check counts as fresh when `shares list` refreshes liveness
+pub const CHECK_MAX_AGE: time::Duration = time::Duration::hours(1);
+
+/// Most server checks in flight at once
+const CHECK_CONCURRENCY: usize = 8;
+
+/// Check every share on its server and record the answers in shares.json.
+/// Returns each share's id with its status, or the error checking it.
+pub fn check_all() -> Result<Vec<(String, Result<RemoteStatus>)>> {
+    check_where(|_| true)
+}
+
+/// Check the shares that could still be live and weren't checked within
+/// `max_age`, recording the answers like [`check_all`]. Returns only the
+/// shares checked.
+pub fn refresh_stale(max_age: time::Duration) -> Result<Vec<(String, Result<RemoteStatus>)>> {
+    let now = OffsetDateTime::now_utc();
+    check_where(|share| {
+        !share.is_gone()
+            && !share.is_expired()
+            && share.checked_at.is_none_or(|at| now - at > max_age)
+    })
+}
+
+/// Check the shares `wanted` picks, a few at a time, and record the answers
+fn check_where(wanted: impl Fn(&Share) -> bool) -> Result<Vec<(String, Result<RemoteStatus>)>> {
+    let mut shares = load_shares()?;
+    let picked: Vec<usize> = (0..shares.len()).filter(|&i| wanted(&shares[i])).collect();
+    let mut statuses: Vec<Option<Result<RemoteStatus>>> =
+        std::iter::repeat_with(|| None).take(shares.len()).collect();
+    std::thread::scope(|scope| {
+        let shares = &shares;
+        let workers: Vec<_> = (0..CHECK_CONCURRENCY.min(picked.len()))
+            .map(|worker| {
+                let mine: Vec<usize> = picked
+                    .iter()
+                    .copied()
+                    .skip(worker)
+                    .step_by(CHECK_CONCURRENCY)
+                    .collect();
+                scope.spawn(move || {
+                    mine.into_iter()
+                        .map(|i| {
+                            // A deleted share can't come back
+                            let status = match shares[i].remote_status {
+                                Some(RemoteStatus::Deleted) => Ok(RemoteStatus::Deleted),
+                                _ => check_remote(&shares[i]),
+                            };
+                            (i, status)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (i, status) in worker.join().expect("share check thread panicked") {
+                statuses[i] = Some(status);
+            }
+        }
+    });
+
+    let now = OffsetDateTime::now_utc();
+    let mut results = Vec::new();
+    let mut changed = false;
+    for (share, status) in shares.iter_mut().zip(statuses) {
+        let Some(status) = status else {
+            continue;
+        };
+        if let Ok(status) = &status {
+            share.remote_status = Some(*status);
+            share.checked_at = Some(now);
+            changed = true;
+        }
+        results.push((share.id.clone(), status));
+    }
+    if changed {
+        let _guard = SHARES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // Merge into the current file so shares published meanwhile are kept
+        let mut current = load_file()?;
+        for share in &mut current.shares {
+            if let Some(checked) = shares.iter().find(|s| s.id == share.id) {
+                share.remote_status = checked.remote_status;
+                share.checked_at = checked.checked_at;
+            }
+        }
+        write_file(&current)?;
+    }
+    Ok(results)
+}
+
+/// Wipe the key and delete token of every share that has expired or is gone
+/// from its server, so an old shares.json can't open or delete them. Returns
+/// the ids scrubbed.
+pub fn scrub_expired() -> Result<Vec<String>> {
+    let _guard = SHARES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load_file()?;
+    let mut scrubbed = Vec::new();
+    for share in &mut file.shares {
+        if !share.scrubbed && (share.is_expired() || share.is_gone()) {
+            share.scrub();
+            scrubbed.push(share.id.clone());
+        }
+    }
+    if !scrubbed.is_empty() {
+        write_file(&file)?;
+    }
+    Ok(scrubbed)
+}
+
+/// Download a server share and decrypt it with the stored key, returning the
+/// stored size and the payload JSON, with any appended chunks merged in
+pub fn fetch_payload(share: &Share) -> Result<(usize, String)> {
+    share.require_key()?;
+    let decode = |blob: Vec<u8>| -> Result<String> {
+        if share.is_plaintext() {
+            // Plaintext org-mode share
+            String::from_utf8(blob).context("Plaintext share is not UTF-8")
+        } else {
+            crate::crypto::decrypt_with_key_b64(&blob, &share.key)
+        }
+    };
+    let blob = upload::download_blob(&share.upload_url, &share.id)?;
+    let mut size = blob.len();
+    let json = decode(blob)?;
+    if share.chunks == 0 {
+        return Ok((size, json));
+    }
+    let mut payload: SharePayload =
+        serde_json::from_str(&json).context("Share payload is not valid JSON")?;
+    for index in 0..share.chunks {
+        let chunk = upload::download_chunk(&share.upload_url, &share.id, index)?;
+        size += chunk.len();
+        let chunk = decode(chunk)?;
+        payload
+            .append_chunk(serde_json::from_str(&chunk).context("Share chunk is not valid JSON")?);
+    }
+    Ok((size, serde_json::to_string(&payload)?))
+}
+
+/// Download a share, decrypt it with the stored key, and check the payload parses
+pub fn verify_share(share: &Share) -> Result<Verification> {
+    if share.storage_type == StorageType::Gist {
+        bail!(
+            "Gist shares are not encrypted; open {} to check it",
+            share.url()
+        );
+    }
+    if share.is_expired() {
+        bail!(
+            "Share {} expired locally ({})",
+            share.id,
+            share.expires_at.date()
+        );
+    }
+    let (blob_bytes, json) = fetch_payload(share)?;
+    let payload: serde_json::Value =
+        serde_json::from_str(&json).context("Decrypted payload is not valid JSON")?;
+    let (kind, items) = match payload.get("kind").and_then(|k| k.as_str()) {
+        Some("report") => ("report", payload["sessions"].as_array().map(Vec::len)),
+        _ => ("transcript", payload["messages"].as_array().map(Vec::len)),
+    };
+    let Some(items) = items else {
+        bail!("Decrypted payload is missing its {kind} contents");
+    };
+    Ok(Verification {
+        blob_bytes,
+        payload_bytes: json.len(),
+        kind: kind.to_string(),
+        items,
+    })
+}
+
 /// Write shares to disk
-fn write_shares(shares: &[Share]) -> Result<()> {
+fn write_file(file: &SharesFile) -> Result<()> {
     let path = shares_file_path()?;
-    let file = SharesFile {
-        shares: shares.to_vec(),
-    };
-    let content = serde_json::to_string_pretty(&file)?;
-    fs::write(&path, format!("{content}\n")).context("Failed to write shares file")?;
+    let content = serde_json::to_string_pretty(file)?;
+    atomic::write(&path, format!("{content}\n")).context("Failed to write shares file")?;
     Ok(())
 }
 
+/// What a sync changed on the side it wrote to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SyncReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+/// Merge `incoming` into `into`. A share on both sides keeps the copy changed
+/// last, keeping `into` on a tie, and a share removed on either side stays
+/// removed. A key scrubbed on either side stays scrubbed, and a key or delete
+/// token missing on one side (synced without secrets) is taken from the other.
+fn merge_files(into: &mut SharesFile, incoming: SharesFile) -> SyncReport {
+    let mut report = SyncReport::default();
+    for id in incoming.removed {
+        if !into.removed.contains(&id) {
+            into.removed.push(id);
+        }
+    }
+    let before = into.shares.len();
+    into.shares.retain(|s| !into.removed.contains(&s.id));
+    report.removed = before - into.shares.len();
+
+    for share in incoming.shares {
+        if into.removed.contains(&share.id) {
+            continue;
+        }
+        match into.shares.iter_mut().find(|s| s.id == share.id) {
+            Some(existing) => {
+                let mut share = share;
+                if existing.scrubbed {
+                    share.scrub();
+                }
+                let filled = !share.scrubbed && fill_secrets(existing, &share);
+                if !share.scrubbed {
+                    fill_secrets(&mut share, existing);
+                }
+                let changed =
+                    serde_json::to_value(&*existing).ok() != serde_json::to_value(&share).ok();
+                if changed && share.last_modified() > existing.last_modified() {
+                    *existing = share;
+                    report.updated += 1;
+                } else if share.scrubbed && !existing.scrubbed {
+                    existing.scrub();
+                    report.updated += 1;
+                } else if filled {
+                    report.updated += 1;
+                }
+            }
+            None => {
+                into.shares.push(share);
+                report.added += 1;
+            }
+        }
+    }
+    into.shares.sort_by_key(|s| s.created_at);
+    report
+}
+
+/// Copy `from`'s key, with the link that carries it, and delete token into
+/// `share` where it has none (it was synced without them). Returns whether
+/// anything was copied.
+fn fill_secrets(share: &mut Share, from: &Share) -> bool {
+    let mut filled = false;
+    if share.key.is_empty() && !from.key.is_empty() {
+        share.key.clone_from(&from.key);
+        share.share_url.clone_from(&from.share_url);
+        share.key_withheld = false;
+        filled = true;
+    }
+    if share.delete_token.is_empty() && !from.delete_token.is_empty() {
+        share.delete_token.clone_from(&from.delete_token);
+        filled = true;
+    }
+    filled
+}
+
+/// Merge a synced copy of shares.json (from another machine) into this one
+pub(crate) fn merge_synced(content: &str) -> Result<SyncReport> {
+    let incoming: SharesFile =
+        serde_json::from_str(content).context("Failed to parse synced shares file")?;
+    let _guard = SHARES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load_file()?;
+    let report = merge_files(&mut file, incoming);
+    write_file(&file)?;
+    Ok(report)
+}
+
+/// The synced copy with this machine's shares merged in, ready to upload.
+/// Keys and delete tokens are left out unless `include_secrets`.
+pub(crate) fn merge_for_sync(
+    synced: Option<&str>,
+    include_secrets: bool,
+) -> Result<(String, SyncReport)> {
+    let mut merged: SharesFile = match synced {
+        Some(content) => {
+            serde_json::from_str(content).context("Failed to parse synced shares file")?
+        }
+        None => SharesFile::default(),
+    };
+    let mut local = load_file()?;
+    if !include_secrets {
+        merged.strip_secrets();
+        local.strip_secrets();
+    }
+    let report = merge_files(&mut merged, local);
+    Ok((
+        format!("{}\n", serde_json::to_string_pretty(&merged)?),
+        report,
+    ))
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::test_mode::TEST_ENDPOINTS_ENV;
+    use crate::test_utils::{EnvGuard, env_lock, serve_once, serve_routes};
 
-    fn make_test_share(id: &str) -> Share {
+    pub(crate) fn make_test_share(id: &str) -> Share {
         Share {
             id: id.to_string(),
             key: "key123".to_string(),
@@ -130,9 +776,25 @@ mod tests {
             share_url: None,
             storage_type: StorageType::Agentexport,
             created_at: OffsetDateTime::now_utc(),
+            updated_at: None,
             expires_at: OffsetDateTime::now_utc(),
             tool: "claude".to_string(),
             transcript_path: "/tmp/test.jsonl".to_string(),
+            relates_to: None,
+            size_bytes: None,
+            tags: Vec::new(),
+            gzip_path: None,
+            fingerprint: None,
+            session_status: None,
+            errors: Vec::new(),
+            title: None,
+            cwd: None,
+            lang: None,
+            remote_status: None,
+            checked_at: None,
+            chunks: 0,
+            scrubbed: false,
+            key_withheld: false,
         }
     }
 
@@ -140,6 +802,14 @@ mod tests {
     fn test_share_url() {
         let share = make_test_share("abc123");
         assert_eq!(share.url(), "https://example.com/v/abc123#key123");
+        assert_eq!(share.url_without_key(), "https://example.com/v/abc123");
+    }
+
+    #[test]
+    fn test_plaintext_share_url_has_no_fragment() {
+        let mut share = make_test_share("abc123");
+        share.key = String::new();
+        assert_eq!(share.url(), "https://example.com/v/abc123");
     }
 
     #[test]
@@ -167,6 +837,7 @@ mod tests {
         let share = make_test_share("test123");
         let file = SharesFile {
             shares: vec![share.clone()],
+            removed: Vec::new(),
         };
 
         let json = serde_json::to_string(&file).unwrap();
@@ -175,4 +846,340 @@ mod tests {
         assert_eq!(parsed.shares.len(), 1);
         assert_eq!(parsed.shares[0].id, "test123");
     }
+
+    #[test]
+    fn test_series_part_follows_chain() {
+        let first = make_test_share("a");
+        let mut second = make_test_share("b");
+        second.relates_to = Some("a".to_string());
+        let mut third = make_test_share("c");
+        third.relates_to = Some("b".to_string());
+        let shares = vec![first, second, third];
+
+        assert_eq!(series_part(&shares, "a"), 1);
+        assert_eq!(series_part(&shares, "c"), 3);
+        assert_eq!(series_part(&shares, "missing"), 1);
+    }
+
+    #[test]
+    fn test_unique_title_numbers_repeats_per_cwd() {
+        let share = |id: &str, title: &str, cwd: &str| Share {
+            title: Some(title.to_string()),
+            cwd: Some(cwd.to_string()),
+            ..make_test_share(id)
+        };
+        let shares = vec![
+            share("a", "Fix tests", "/repo"),
+            share("b", "Fix tests (2)", "/repo"),
+            share("c", "Add docs", "/other"),
+        ];
+        assert_eq!(
+            unique_title(&shares, "Fix tests", Some("/repo")),
+            "Fix tests (3)"
+        );
+        assert_eq!(
+            unique_title(&shares, "Fix tests", Some("/other")),
+            "Fix tests"
+        );
+        assert_eq!(unique_title(&shares, "Add docs", Some("/repo")), "Add docs");
+    }
+
+    #[test]
+    fn test_series_part_stops_on_cycle() {
+        let mut a = make_test_share("a");
+        a.relates_to = Some("b".to_string());
+        let mut b = make_test_share("b");
+        b.relates_to = Some("a".to_string());
+
+        assert_eq!(series_part(&[a, b], "a"), 3);
+    }
+
+    #[test]
+    fn test_share_forever_never_expires() {
+        let mut share = make_test_share("abc123");
+        share.expires_at = OffsetDateTime::UNIX_EPOCH;
+        assert!(!share.is_expired());
+        assert_eq!(share.status(), "forever");
+        assert_eq!(share.expires_in(OffsetDateTime::now_utc()), "never");
+    }
+
+    #[test]
+    fn test_share_expires_in() {
+        let now = OffsetDateTime::now_utc();
+        let mut share = make_test_share("abc123");
+        share.expires_at = now + time::Duration::hours(6 * 24 + 3);
+        assert_eq!(share.expires_in(now), "in 6d");
+        share.expires_at = now - time::Duration::hours(2);
+        assert_eq!(share.expires_in(now), "2h ago");
+    }
+
+    #[test]
+    fn test_share_filter_parse_and_match() {
+        let mut share = make_test_share("abc123");
+        share.tool = "codex".to_string();
+        share.tags = vec!["infra".to_string()];
+        share.lang = Some("es".to_string());
+
+        assert!(ShareFilter::parse("tool=Codex").unwrap().matches(&share));
+        assert!(ShareFilter::parse("tag=infra").unwrap().matches(&share));
+        assert!(ShareFilter::parse("lang=ES").unwrap().matches(&share));
+        assert!(!ShareFilter::parse("lang=en").unwrap().matches(&share));
+        assert!(!ShareFilter::parse("tool=claude").unwrap().matches(&share));
+        assert!(ShareFilter::parse("tool").is_err());
+        assert!(ShareFilter::parse("color=red").is_err());
+    }
+
+    #[test]
+    fn test_sort_shares_by_size() {
+        let mut small = make_test_share("small");
+        small.size_bytes = Some(10);
+        let mut big = make_test_share("big");
+        big.size_bytes = Some(1000);
+        let mut shares = vec![big, small];
+        sort_shares(&mut shares, ShareSort::Size);
+        assert_eq!(shares[0].id, "small");
+    }
+
+    #[test]
+    fn test_merge_files_keeps_newer_copies_and_removals() {
+        let now = OffsetDateTime::now_utc();
+        let mut old = make_test_share("both");
+        old.created_at = now - time::Duration::days(1);
+        let mut new = make_test_share("both");
+        new.created_at = now;
+        new.title = Some("Republished".to_string());
+        let mut gone = make_test_share("gone");
+        gone.created_at = now;
+
+        let mut laptop = SharesFile {
+            shares: vec![new.clone(), make_test_share("laptop")],
+            removed: vec!["gone".to_string()],
+        };
+        let desktop = SharesFile {
+            shares: vec![old, gone, make_test_share("desktop")],
+            removed: Vec::new(),
+        };
+        let report = merge_files(&mut laptop, desktop);
+        assert_eq!(
+            report,
+            SyncReport {
+                added: 1,
+                updated: 0,
+                removed: 0
+            }
+        );
+        let mut ids: Vec<&str> = laptop.shares.iter().map(|s| s.id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["both", "desktop", "laptop"]);
+        let both = laptop.shares.iter().find(|s| s.id == "both").unwrap();
+        assert_eq!(both.title.as_deref(), Some("Republished"));
+
+        // The other direction takes the newer copy and the removal
+        let mut desktop = SharesFile {
+            shares: vec![make_test_share("gone")],
+            removed: Vec::new(),
+        };
+        desktop.shares[0].created_at = now - time::Duration::days(2);
+        let mut stale = new.clone();
+        stale.created_at = now - time::Duration::days(1);
+        stale.title = None;
+        desktop.shares.push(stale);
+        let report = merge_files(&mut desktop, laptop);
+        assert_eq!(
+            report,
+            SyncReport {
+                added: 2,
+                updated: 1,
+                removed: 1
+            }
+        );
+        assert!(desktop.removed.contains(&"gone".to_string()));
+        assert_eq!(desktop.shares.len(), 3);
+
+        // A retitle keeps `created_at`, so the later `updated_at` decides,
+        // and a copy synced without secrets keeps the local ones
+        let mut retitled = make_test_share("edited");
+        retitled.created_at = now - time::Duration::days(1);
+        retitled.updated_at = Some(now);
+        retitled.title = Some("New title".to_string());
+        let mut stale = retitled.clone();
+        stale.updated_at = Some(now - time::Duration::hours(1));
+        stale.title = Some("Old title".to_string());
+        let mut local = SharesFile {
+            shares: vec![retitled],
+            removed: Vec::new(),
+        };
+        let mut synced = SharesFile {
+            shares: vec![stale],
+            removed: Vec::new(),
+        };
+        synced.strip_secrets();
+        assert_eq!(merge_files(&mut local, synced).updated, 0);
+        assert_eq!(local.shares[0].title.as_deref(), Some("New title"));
+        let mut newer = local.shares[0].clone();
+        newer.updated_at = Some(now + time::Duration::minutes(1));
+        newer.tags = vec!["ops".to_string()];
+        let mut synced = SharesFile {
+            shares: vec![newer],
+            removed: Vec::new(),
+        };
+        synced.strip_secrets();
+        assert_eq!(merge_files(&mut local, synced).updated, 1);
+        assert_eq!(local.shares[0].tags, ["ops"]);
+        assert_eq!(local.shares[0].key, "key123");
+        assert_eq!(local.shares[0].delete_token, "token123");
+    }
+
+    #[test]
+    fn test_scrub_expired_keeps_metadata_only() {
+        let _lock = crate::test_utils::env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = crate::test_utils::EnvGuard::set("HOME", home.path().to_str().unwrap());
+        let now = OffsetDateTime::now_utc();
+        let mut expired = make_test_share("expired");
+        expired.expires_at = now - time::Duration::days(1);
+        expired.title = Some("Old work".to_string());
+        expired.share_url = Some("https://example.com/v/expired#key123".to_string());
+        let mut live = make_test_share("live");
+        live.expires_at = now + time::Duration::days(1);
+        let mut deleted = make_test_share("deleted");
+        deleted.expires_at = now + time::Duration::days(1);
+        deleted.remote_status = Some(RemoteStatus::Deleted);
+        for share in [&expired, &live, &deleted] {
+            save_share(share).unwrap();
+        }
+
+        let mut ids = scrub_expired().unwrap();
+        ids.sort();
+        assert_eq!(ids, ["deleted", "expired"]);
+        assert!(scrub_expired().unwrap().is_empty());
+        let scrubbed = get_share("expired").unwrap().unwrap();
+        assert!(scrubbed.scrubbed && scrubbed.key.is_empty() && scrubbed.delete_token.is_empty());
+        assert_eq!(scrubbed.url(), "https://example.com/v/expired");
+        assert!(!serde_json::to_string(&scrubbed).unwrap().contains("key123"));
+        assert_eq!(scrubbed.title.as_deref(), Some("Old work"));
+        assert!(fetch_payload(&scrubbed).is_err());
+        assert_eq!(get_share("live").unwrap().unwrap().key, "key123");
+
+        // Syncing in an unscrubbed copy from another machine doesn't bring the key back
+        let mut file = load_file().unwrap();
+        let other = SharesFile {
+            shares: vec![expired],
+            removed: Vec::new(),
+        };
+        merge_files(&mut file, other);
+        let merged = file.shares.iter().find(|s| s.id == "expired").unwrap();
+        assert!(merged.scrubbed && merged.key.is_empty());
+        assert!(!serde_json::to_string(merged).unwrap().contains("key123"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(842), "842 B");
+        assert_eq!(format_size(12_600), "12.3 KB");
+    }
+
+    #[test]
+    fn verify_share_decrypts_downloaded_blob() {
+        let _lock = env_lock();
+        let payload =
+            r#"{"tool":"Claude Code","shared_at":"","messages":[{"role":"user","content":"hi"}]}"#;
+        let encrypted = crate::crypto::encrypt_html(payload, None).unwrap();
+        let (base, server) = serve_once(encrypted.blob.clone());
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let mut share = make_test_share("gabc123def4567890");
+        share.key = encrypted.key_b64;
+        share.expires_at = time::OffsetDateTime::now_utc() + time::Duration::days(1);
+        let check = verify_share(&share).unwrap();
+        assert_eq!(check.kind, "transcript");
+        assert_eq!(check.items, 1);
+        assert_eq!(check.blob_bytes, encrypted.blob.len());
+        assert!(
+            server
+                .join()
+                .unwrap()
+                .starts_with("GET /blob/gabc123def4567890 ")
+        );
+    }
+
+    #[test]
+    fn verify_share_reports_wrong_key() {
+        let _lock = env_lock();
+        let encrypted = crate::crypto::encrypt_html("{}", None).unwrap();
+        let (base, server) = serve_once(encrypted.blob);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let mut share = make_test_share("gabc123def4567890");
+        share.key = crate::crypto::encrypt_html("{}", None).unwrap().key_b64;
+        share.expires_at = time::OffsetDateTime::now_utc() + time::Duration::days(1);
+        let err = verify_share(&share).unwrap_err();
+        assert!(err.to_string().contains("Decryption failed"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn check_all_records_server_status() {
+        let _lock = env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", home.path().to_str().unwrap());
+        // Shares are checked a few at a time, so answer by path
+        let (base, server) = serve_routes(vec![
+            ("/blob/glive000000000001 ", 200),
+            ("/blob/gold0000000000003 ", 404),
+            ("/blob/ggone000000000001 ", 404),
+        ]);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let now = time::OffsetDateTime::now_utc();
+        for (id, expires_at) in [
+            ("glive000000000001", now + time::Duration::days(5)),
+            ("gold0000000000003", now - time::Duration::days(5)),
+            ("ggone000000000001", now + time::Duration::days(5)),
+        ] {
+            save_share(&Share {
+                expires_at,
+                ..make_test_share(id)
+            })
+            .unwrap();
+        }
+
+        let results = check_all().unwrap();
+        let statuses: Vec<_> = results.into_iter().map(|(_, s)| s.unwrap()).collect();
+        use RemoteStatus;
+        assert_eq!(
+            statuses,
+            [
+                RemoteStatus::Live,
+                RemoteStatus::Expired,
+                RemoteStatus::Deleted
+            ]
+        );
+        let requests = server.join().unwrap();
+        assert!(
+            requests
+                .iter()
+                .all(|(head, _)| head.starts_with("HEAD /blob/"))
+        );
+
+        let gone = get_share("ggone000000000001").unwrap().unwrap();
+        assert_eq!(gone.status(), "deleted");
+        assert!(gone.is_gone());
+        // Deleted shares aren't probed again
+        let results = check_all();
+        assert!(results.unwrap()[2].1.is_ok());
+
+        // A refresh skips fresh checks and shares that are gone
+        let refreshed = refresh_stale(CHECK_MAX_AGE).unwrap();
+        assert!(refreshed.is_empty());
+        let (base, server) = serve_routes(vec![("/blob/glive000000000001 ", 410)]);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+        let refreshed = refresh_stale(time::Duration::ZERO).unwrap();
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!(refreshed[0].0, "glive000000000001");
+        assert_eq!(refreshed[0].1.as_ref().unwrap(), &RemoteStatus::Expired);
+        assert_eq!(server.join().unwrap().len(), 1);
+        let live = get_share("glive000000000001").unwrap().unwrap();
+        assert!(live.checked_at.is_some() && live.is_gone());
+    }
 }
@@ -2,26 +2,161 @@
 
 use anyhow::{Result, bail};
 use dialoguer::{Select, theme::ColorfulTheme};
-use time::format_description;
+use time::OffsetDateTime;
+use time::format_description::{self, well_known::Rfc3339};
 
 use agentexport::{
-    StorageType,
-    shares::{self, Share},
+    Config, StorageType, backup,
+    output::{Output, Table},
+    prune::{self, ARTIFACT_MIN_AGE},
+    shares::{self, RemoteStatus, Share, ShareFilter, ShareSort},
+    sync::{self, SyncBackend},
 };
+use std::path::PathBuf;
 
-use crate::SharesAction;
+use crate::{SharesAction, SyncDirection};
 
-pub fn run(action: Option<SharesAction>) -> Result<()> {
+/// `json` is the global `--json` flag; tables are written for `output`
+pub fn run(action: Option<SharesAction>, json: bool, output: Output) -> Result<()> {
+    if !matches!(action, Some(SharesAction::Scrub))
+        && Config::load().unwrap_or_default().scrub_expired_keys
+    {
+        shares::scrub_expired()?;
+    }
     match action {
-        Some(SharesAction::List) => list_shares(),
+        Some(SharesAction::List {
+            sort,
+            reverse,
+            filter,
+            expiring,
+            check,
+            offline,
+        }) => list_shares(ListOptions {
+            sort,
+            reverse,
+            filters: filter,
+            expiring,
+            json,
+            check,
+            offline,
+            output,
+        }),
+        Some(SharesAction::Restore {
+            id,
+            out,
+            republish,
+            ttl,
+        }) => restore(&id, out, republish, ttl),
         Some(SharesAction::Unshare { id }) => unshare(&id),
+        Some(SharesAction::Verify { id }) => verify(&id),
+        Some(SharesAction::Retitle { id, title }) => retitle(&id, &title),
+        Some(SharesAction::Extend { id, ttl }) => extend(&id, ttl),
+        Some(SharesAction::Rekey { id, all }) => rekey(id, all),
+        Some(SharesAction::Migrate {
+            id,
+            to,
+            upload_url,
+            delete_old,
+        }) => migrate(&id, &to, upload_url, delete_old),
+        Some(SharesAction::Sync {
+            direction,
+            target,
+            include_secrets,
+        }) => sync(direction, target, include_secrets, json),
+        Some(SharesAction::Scrub) => scrub(json),
+        Some(SharesAction::Prune {
+            older_than,
+            dry_run,
+            yes,
+        }) => prune(older_than, dry_run, yes, json),
+        None if json => list_shares(ListOptions {
+            sort: ShareSort::Created,
+            reverse: false,
+            filters: Vec::new(),
+            expiring: None,
+            json,
+            check: false,
+            offline: false,
+            output,
+        }),
         None => interactive(),
     }
 }
 
-/// List all shares in plain text
-fn list_shares() -> Result<()> {
-    let shares = shares::load_shares()?;
+/// Options for `shares list`
+pub struct ListOptions {
+    pub sort: ShareSort,
+    pub reverse: bool,
+    pub filters: Vec<String>,
+    pub expiring: Option<i64>,
+    pub json: bool,
+    /// Probe the server for each share first
+    pub check: bool,
+    /// Skip refreshing the status of shares not checked lately
+    pub offline: bool,
+    pub output: Output,
+}
+
+/// List shares as a table (or JSON)
+fn list_shares(options: ListOptions) -> Result<()> {
+    let filters = options
+        .filters
+        .iter()
+        .map(|f| ShareFilter::parse(f))
+        .collect::<Result<Vec<_>>>()?;
+    let now = OffsetDateTime::now_utc();
+
+    if options.check {
+        check_shares()?;
+    } else if !options.offline {
+        // A failed check just leaves the recorded status in place
+        shares::refresh_stale(shares::CHECK_MAX_AGE)?;
+    }
+    let mut shares: Vec<Share> = shares::load_shares()?
+        .into_iter()
+        .filter(|s| filters.iter().all(|f| f.matches(s)))
+        .filter(|s| match options.expiring {
+            Some(days) => {
+                !s.is_expired()
+                    && !s.never_expires()
+                    && s.expires_at - now <= time::Duration::days(days)
+            }
+            None => true,
+        })
+        .collect();
+    shares::sort_shares(&mut shares, options.sort);
+    if options.reverse {
+        shares.reverse();
+    }
+
+    if options.json {
+        let rows: Vec<serde_json::Value> = shares
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "id": s.id,
+                    "url": s.url(),
+                    "status": s.status(),
+                    "tool": s.tool,
+                    "title": s.title,
+                    "storage_type": s.storage_type.to_string(),
+                    "created_at": s.created_at.format(&Rfc3339).unwrap_or_default(),
+                    "expires_at": s.expires_at.format(&Rfc3339).unwrap_or_default(),
+                    "expires_in": s.expires_in(now),
+                    "size_bytes": s.size_bytes,
+                    "tags": s.tags,
+                    "relates_to": s.relates_to,
+                    "gzip_path": s.gzip_path,
+                    "session_status": s.session_status,
+                    "errors": s.errors,
+                    "scrubbed": s.scrubbed,
+                    "checked_at": s.checked_at.and_then(|t| t.format(&Rfc3339).ok()),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
 
     if shares.is_empty() {
         println!("No shares found.");
@@ -29,24 +164,64 @@ fn list_shares() -> Result<()> {
     }
 
     let format = format_description::parse("[year]-[month]-[day] [hour]:[minute]")?;
+    let mut table = Table::new(&[
+        "ID", "STATUS", "SESSION", "TOOL", "CREATED", "EXPIRES", "SIZE", "TAGS", "URL",
+    ])
+    .right_align(&[6])
+    .shrink(&[7]);
+    for s in &shares {
+        table.push(vec![
+            s.id.clone(),
+            s.status().to_string(),
+            s.session_status
+                .map(|status| status.as_str().to_string())
+                .unwrap_or_default(),
+            s.tool.clone(),
+            s.created_at.format(&format).unwrap_or_default(),
+            s.expires_in(now),
+            s.size_bytes.map(shares::format_size).unwrap_or_default(),
+            s.tags.join(","),
+            s.url(),
+        ]);
+    }
+    print!("{}", table.render(&options.output));
 
-    for share in shares {
-        let status = if share.is_expired() {
-            "expired"
-        } else {
-            "active"
-        };
-        let created = share.created_at.format(&format).unwrap_or_default();
-        println!(
-            "{} [{}] {} - {} ({})",
-            share.id,
-            status,
-            share.tool,
-            created,
-            share.url()
-        );
+    Ok(())
+}
+
+/// Probe every share on its server and record whether it is live, expired, or deleted
+fn check_shares() -> Result<()> {
+    let (mut live, mut expired, mut deleted) = (0, 0, 0);
+    for (id, status) in shares::check_all()? {
+        match status {
+            Ok(RemoteStatus::Live) => live += 1,
+            Ok(RemoteStatus::Expired) => expired += 1,
+            Ok(RemoteStatus::Deleted) => deleted += 1,
+            Err(err) => eprintln!("warning: could not check {id}: {err:#}"),
+        }
     }
+    eprintln!("Checked shares: {live} live, {expired} expired, {deleted} deleted");
+    Ok(())
+}
 
+/// Recover a share's payload from the local backup, optionally re-uploading it
+fn restore(id: &str, out: Option<PathBuf>, republish: bool, ttl: Option<u64>) -> Result<()> {
+    if republish {
+        let config = Config::load()?;
+        let share =
+            agentexport::republish(id, ttl.unwrap_or(config.default_ttl), config.gist_format)?;
+        println!("{}", share.url());
+        return Ok(());
+    }
+
+    let json = backup::load_backup(id)?;
+    match out {
+        Some(path) => {
+            std::fs::write(&path, format!("{json}\n"))?;
+            eprintln!("Wrote {}", path.display());
+        }
+        None => println!("{json}"),
+    }
     Ok(())
 }
 
@@ -65,6 +240,7 @@ fn unshare(id: &str) -> Result<()> {
 
             // Remove from local storage
             shares::remove_share(id)?;
+            backup::remove_backup(id)?;
             println!("Removed from local storage.");
             Ok(())
         }
@@ -74,6 +250,235 @@ fn unshare(id: &str) -> Result<()> {
     }
 }
 
+/// Check that a share's link works: the blob downloads, decrypts, and parses
+fn verify(id: &str) -> Result<()> {
+    let Some(share) = shares::get_share(id)? else {
+        bail!("Share not found: {id}");
+    };
+    let check = shares::verify_share(&share)?;
+    let unit = if check.kind == "report" {
+        "sessions"
+    } else {
+        "messages"
+    };
+    println!(
+        "OK {id}: {} blob decrypts to a {} {} with {} {unit}",
+        shares::format_size(check.blob_bytes as u64),
+        shares::format_size(check.payload_bytes as u64),
+        check.kind,
+        check.items,
+    );
+    println!("{}", share.url());
+    Ok(())
+}
+
+/// Rename a share and print its (possibly new) link
+fn retitle(id: &str, title: &str) -> Result<()> {
+    let share = agentexport::retitle(id, title)?;
+    if share.id != id {
+        eprintln!("Re-uploaded as {}; the old link no longer works.", share.id);
+    }
+    println!("{}", share.url());
+    Ok(())
+}
+
+/// Change a share's retention and print its new expiry
+fn extend(id: &str, ttl: u64) -> Result<()> {
+    let share = agentexport::extend(id, ttl)?;
+    if share.never_expires() {
+        println!("{id} now never expires.");
+    } else {
+        println!(
+            "{id} now expires {} ({}).",
+            share.expires_at.date(),
+            share.expires_in(OffsetDateTime::now_utc())
+        );
+    }
+    Ok(())
+}
+
+/// Rotate the key of one share, or of every live encrypted share with `all`,
+/// printing each old id with its new link
+fn rekey(id: Option<String>, all: bool) -> Result<()> {
+    let ids: Vec<String> = if all {
+        shares::load_shares()?
+            .into_iter()
+            .filter(|s| s.storage_type == StorageType::Agentexport && !s.key.is_empty())
+            .filter(|s| !s.is_expired() && !s.is_gone())
+            .map(|s| s.id)
+            .collect()
+    } else {
+        id.into_iter().collect()
+    };
+    let mut failed = 0;
+    for id in &ids {
+        match agentexport::rekey(id) {
+            Ok(share) => println!("{id}  {}", share.url()),
+            Err(err) if all => {
+                failed += 1;
+                eprintln!("warning: could not rekey {id}: {err:#}");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    if all {
+        eprintln!(
+            "Rekeyed {} of {} share(s); the old links no longer work.",
+            ids.len() - failed,
+            ids.len()
+        );
+    }
+    Ok(())
+}
+
+/// Move a share to another backend and print its new link
+fn migrate(id: &str, to: &str, upload_url: Option<String>, delete_old: bool) -> Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let storage_type = StorageType::parse(to)?;
+    let upload_url = upload_url.unwrap_or(config.upload_url);
+    let share = agentexport::migrate(
+        id,
+        storage_type,
+        &upload_url,
+        config.gist_format,
+        delete_old,
+    )?;
+    if delete_old {
+        eprintln!("Moved to {}; the old link no longer works.", share.id);
+    } else {
+        eprintln!("Copied to {}; the original share is still live.", share.id);
+    }
+    println!("{}", share.url());
+    Ok(())
+}
+
+/// Push or pull the shares list and print what changed
+fn sync(
+    direction: SyncDirection,
+    target: Option<String>,
+    include_secrets: bool,
+    json: bool,
+) -> Result<()> {
+    let target = match target {
+        Some(target) => target,
+        None => Config::load()?.shares_sync.unwrap_or_default(),
+    };
+    let backend = SyncBackend::parse(&target)?;
+    let (report, verb) = match direction {
+        SyncDirection::Push => (sync::push(&backend, include_secrets)?, "Pushed to"),
+        SyncDirection::Pull => (sync::pull(&backend)?, "Pulled from"),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!(
+            "{verb} {backend}: {} added, {} updated, {} removed",
+            report.added, report.updated, report.removed
+        );
+    }
+    Ok(())
+}
+
+/// Wipe the keys and delete tokens of expired shares and say how many
+fn scrub(json: bool) -> Result<()> {
+    let ids = shares::scrub_expired()?;
+    if json {
+        println!("{}", serde_json::json!({ "scrubbed": ids }));
+    } else if ids.is_empty() {
+        println!("No expired shares with keys left.");
+    } else {
+        println!(
+            "Scrubbed the keys and delete tokens of {} expired share(s).",
+            ids.len()
+        );
+    }
+    Ok(())
+}
+
+/// Drop expired shares and orphaned artifacts, deleting shares older than
+/// `older_than` days (or config `prune_after_days`) from their server first
+fn prune(older_than: Option<u64>, dry_run: bool, yes: bool, json: bool) -> Result<()> {
+    let days = older_than
+        .or(Config::load()?.prune_after_days)
+        .filter(|&days| days > 0);
+    let delete_after = days.map(|days| time::Duration::days(days as i64));
+    let plan = prune::plan(delete_after, ARTIFACT_MIN_AGE)?;
+    let ids = |shares: &[Share]| shares.iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+
+    if dry_run || plan.is_empty() {
+        if json {
+            let value = serde_json::json!({
+                "expired": ids(&plan.expired),
+                "delete": ids(&plan.aged),
+                "artifacts": plan.objects.len() + plan.files.len(),
+            });
+            println!("{value}");
+        } else if plan.is_empty() {
+            println!("Nothing to prune.");
+        } else {
+            for share in &plan.expired {
+                println!("Would forget expired share {}", share.id);
+            }
+            for share in &plan.aged {
+                println!("Would delete {} from the server", share.url());
+            }
+            println!(
+                "Would remove {} artifact(s).",
+                plan.objects.len() + plan.files.len()
+            );
+        }
+        return Ok(());
+    }
+
+    if !plan.aged.is_empty() && !yes {
+        let days = days.unwrap_or_default();
+        if !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+            bail!(
+                "{} share(s) are older than {days} days; pass --yes to delete them from the server",
+                plan.aged.len()
+            );
+        }
+        let confirm = dialoguer::Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "Delete {} share(s) older than {days} days from the server?",
+                plan.aged.len()
+            ))
+            .default(false)
+            .interact()?;
+        if !confirm {
+            println!("Prune cancelled.");
+            return Ok(());
+        }
+    }
+
+    let report = prune::execute(&plan, ARTIFACT_MIN_AGE)?;
+    if json {
+        let failed: Vec<&String> = report.failed_shares.iter().map(|(id, _)| id).collect();
+        let value = serde_json::json!({
+            "removed": report.shares_removed,
+            "deleted": report.shares_deleted,
+            "artifacts": report.artifacts_removed,
+            "failed": failed,
+        });
+        println!("{value}");
+    } else {
+        println!(
+            "Removed {} share(s) ({} deleted from the server) and {} artifact(s).",
+            report.shares_removed, report.shares_deleted, report.artifacts_removed
+        );
+    }
+    for (id, err) in &report.failed_shares {
+        eprintln!("warning: share {id} was not deleted from the server: {err}");
+    }
+    if !report.failed_shares.is_empty() {
+        bail!(
+            "{} share(s) could not be deleted",
+            report.failed_shares.len()
+        );
+    }
+    Ok(())
+}
+
 /// Interactive TUI for managing shares
 fn interactive() -> Result<()> {
     let theme = ColorfulTheme::default();
@@ -91,7 +496,7 @@ fn interactive() -> Result<()> {
         let items: Vec<String> = shares
             .iter()
             .map(|s| {
-                let status = if s.is_expired() { "EXPIRED" } else { "active" };
+                let status = s.status();
                 let created = s.created_at.format(&format).unwrap_or_default();
                 format!("[{}] {} {} - {}", status, s.id, s.tool, created)
             })
@@ -119,23 +524,29 @@ fn interactive() -> Result<()> {
         println!("ID:         {}", share.id);
         println!("URL:        {}", share.url());
         println!("Tool:       {}", share.tool);
+        if let Some(title) = &share.title {
+            println!("Title:      {title}");
+        }
         println!(
             "Created:    {}",
             share.created_at.format(&format).unwrap_or_default()
         );
         println!(
-            "Expires:    {}",
-            share.expires_at.format(&format).unwrap_or_default()
-        );
-        println!(
-            "Status:     {}",
-            if share.is_expired() {
-                "EXPIRED"
-            } else {
-                "active"
-            }
+            "Expires:    {} ({})",
+            share.expires_at.format(&format).unwrap_or_default(),
+            share.expires_in(OffsetDateTime::now_utc())
         );
+        println!("Status:     {}", share.status());
+        if let Some(session_status) = share.session_status {
+            println!("Session:    {}", session_status.as_str());
+        }
+        for error in &share.errors {
+            println!("Error:      {error}");
+        }
         println!("Transcript: {}", share.transcript_path);
+        if let Some(gzip_path) = &share.gzip_path {
+            println!("Gzip:       {gzip_path}");
+        }
         println!();
 
         let actions = vec!["Copy URL", "Open in browser", "Unshare (delete)", "Back"];
@@ -145,6 +556,12 @@ fn interactive() -> Result<()> {
             .default(0)
             .interact()?;
 
+        if action < 2
+            && let Err(err) = share.require_key()
+        {
+            eprintln!("\n{err:#}\n");
+            continue;
+        }
         match action {
             0 => {
                 // Copy URL - just print it (user can pipe to pbcopy)
@@ -194,30 +611,16 @@ fn delete_share(share: &Share) -> Result<()> {
 }
 
 fn delete_from_gist(share: &Share) -> Result<()> {
-    let output = std::process::Command::new("gh")
-        .args(["api", "-X", "DELETE", &format!("gists/{}", share.id)])
-        .output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("GitHub delete failed: {}", stderr.trim());
-    }
-
-    Ok(())
+    agentexport::delete_gist(&share.id)
 }
 
 /// Delete blob from server using the delete token
 fn delete_from_server(share: &Share) -> Result<()> {
-    let endpoint = format!("{}/blob/{}", share.upload_url, share.id);
-
-    let response = ureq::delete(&endpoint)
-        .set("X-Delete-Token", &share.delete_token)
-        .call()?;
-
-    if response.status() >= 400 {
-        let status = response.status();
-        bail!("Delete failed with status {status}");
+    if share.scrubbed {
+        bail!("its delete token was scrubbed after it expired");
     }
-
-    Ok(())
+    if share.delete_token.is_empty() {
+        bail!("its delete token is not on this machine; it was synced without --include-secrets");
+    }
+    agentexport::delete_blob(&share.upload_url, &share.id, &share.delete_token)
 }
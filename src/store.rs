@@ -0,0 +1,473 @@
+//! Content-addressed artifact store for transcript gzips and rendered payloads.
+//!
+//! Objects live under `<cache>/agentexport/store/objects/<aa>/<sha256>` and are
+//! described by `manifest.json`. Writing the same bytes twice is a no-op, so
+//! artifacts are shared between publishes and can be verified or collected.
+//! `sessions.json` next to the manifest remembers each session's publish
+//! preferences.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+use crate::atomic;
+use crate::transcript::cache_dir;
+
+/// Kind of artifact held in the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectKind {
+    /// Gzipped raw transcript
+    Gzip,
+    /// Rendered share payload JSON
+    Render,
+}
+
+/// Manifest entry for a stored object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectEntry {
+    pub kind: ObjectKind,
+    pub size: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    /// Human-readable origin, e.g. "codex-term-1700000000"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Transcript the object was made from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    objects: BTreeMap<String, ObjectEntry>,
+}
+
+/// A problem found by `Store::verify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyIssue {
+    /// Listed in the manifest but missing on disk
+    Missing(String),
+    /// Content no longer hashes to its name
+    Corrupt(String),
+    /// On disk but not listed in the manifest
+    Untracked(String),
+}
+
+impl std::fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyIssue::Missing(hash) => write!(f, "missing   {hash}"),
+            VerifyIssue::Corrupt(hash) => write!(f, "corrupt   {hash}"),
+            VerifyIssue::Untracked(hash) => write!(f, "untracked {hash}"),
+        }
+    }
+}
+
+/// Serializes manifest updates within this process (e.g. `publish --tool all`)
+static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    /// Open the default store under the agentexport cache dir
+    pub fn open() -> Result<Self> {
+        Ok(Self::at(cache_dir()?.join("agentexport").join("store")))
+    }
+
+    pub fn at(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.root.join("manifest.json")
+    }
+
+    /// Path an object with the given hash is stored at
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        let prefix = hash.get(..2).unwrap_or("00");
+        self.root.join("objects").join(prefix).join(hash)
+    }
+
+    fn sessions_path(&self) -> PathBuf {
+        self.root.join("sessions.json")
+    }
+
+    fn load_manifest(&self) -> Result<Manifest> {
+        let path = self.manifest_path();
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+        let content = fs::read_to_string(&path).context("Failed to read store manifest")?;
+        serde_json::from_str(&content).context("Failed to parse store manifest")
+    }
+
+    fn save_manifest(&self, manifest: &Manifest) -> Result<()> {
+        fs::create_dir_all(&self.root)?;
+        atomic::write(
+            self.manifest_path(),
+            serde_json::to_string_pretty(manifest)? + "\n",
+        )
+        .context("Failed to write store manifest")
+    }
+
+    /// Store everything read from `reader`, returning its sha256 hash
+    pub fn put_reader(
+        &self,
+        reader: &mut dyn Read,
+        kind: ObjectKind,
+        label: Option<&str>,
+        source: Option<&Path>,
+    ) -> Result<String> {
+        fs::create_dir_all(&self.root)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.root)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            tmp.write_all(&buf[..n])?;
+            size += n as u64;
+        }
+        let hash = hex::encode(hasher.finalize());
+
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            fs::create_dir_all(path.parent().unwrap())?;
+            tmp.persist(&path)
+                .with_context(|| format!("Failed to store object {hash}"))?;
+        }
+
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut manifest = self.load_manifest()?;
+        manifest
+            .objects
+            .entry(hash.clone())
+            .or_insert_with(|| ObjectEntry {
+                kind,
+                size,
+                created_at: OffsetDateTime::now_utc(),
+                label: label.map(|s| s.to_string()),
+                source: source.map(|path| path.display().to_string()),
+            });
+        self.save_manifest(&manifest)?;
+        Ok(hash)
+    }
+
+    /// Store a byte slice, returning its sha256 hash
+    pub fn put_bytes(
+        &self,
+        bytes: &[u8],
+        kind: ObjectKind,
+        label: Option<&str>,
+        source: Option<&Path>,
+    ) -> Result<String> {
+        self.put_reader(&mut &bytes[..], kind, label, source)
+    }
+
+    /// Look up an object's manifest entry
+    pub fn entry(&self, hash: &str) -> Result<Option<ObjectEntry>> {
+        Ok(self.load_manifest()?.objects.remove(hash))
+    }
+
+    /// Most recently stored object of `kind` whose label starts with `label_prefix`
+    pub fn latest(&self, kind: ObjectKind, label_prefix: &str) -> Result<Option<String>> {
+        Ok(self
+            .load_manifest()?
+            .objects
+            .into_iter()
+            .filter(|(_, entry)| {
+                entry.kind == kind
+                    && entry
+                        .label
+                        .as_deref()
+                        .is_some_and(|label| label.starts_with(label_prefix))
+            })
+            .max_by_key(|(_, entry)| entry.created_at)
+            .map(|(hash, _)| hash))
+    }
+
+    /// Objects made from one of the transcripts in `sources`, or carrying one of `labels`
+    pub fn derived_from(
+        &self,
+        sources: &HashSet<String>,
+        labels: &HashSet<String>,
+    ) -> Result<Vec<String>> {
+        Ok(self
+            .load_manifest()?
+            .objects
+            .into_iter()
+            .filter(|(_, entry)| {
+                entry.source.as_ref().is_some_and(|s| sources.contains(s))
+                    || entry
+                        .label
+                        .as_ref()
+                        .is_some_and(|label| labels.contains(label))
+            })
+            .map(|(hash, _)| hash)
+            .collect())
+    }
+
+    /// Delete an object and its manifest entry
+    pub fn remove(&self, hash: &str) -> Result<()> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.object_path(hash);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove object {hash}"))?;
+        }
+        let mut manifest = self.load_manifest()?;
+        if manifest.objects.remove(hash).is_some() {
+            self.save_manifest(&manifest)?;
+        }
+        Ok(())
+    }
+
+    fn load_sessions(&self) -> Result<BTreeMap<String, serde_json::Value>> {
+        let path = self.sessions_path();
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path).context("Failed to read session preferences")?;
+        serde_json::from_str(&content).context("Failed to parse session preferences")
+    }
+
+    fn save_sessions(&self, sessions: &BTreeMap<String, serde_json::Value>) -> Result<()> {
+        fs::create_dir_all(&self.root)?;
+        atomic::write(
+            self.sessions_path(),
+            serde_json::to_string_pretty(sessions)? + "\n",
+        )
+        .context("Failed to write session preferences")
+    }
+
+    /// Preferences remembered for `session`, if any
+    pub fn session_prefs<T: DeserializeOwned>(&self, session: &str) -> Result<Option<T>> {
+        self.load_sessions()?
+            .remove(session)
+            .map(serde_json::from_value)
+            .transpose()
+            .with_context(|| format!("Failed to parse preferences for session {session}"))
+    }
+
+    /// Remember preferences for `session`, replacing earlier ones
+    pub fn save_session_prefs<T: Serialize>(&self, session: &str, prefs: &T) -> Result<()> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut sessions = self.load_sessions()?;
+        sessions.insert(session.to_string(), serde_json::to_value(prefs)?);
+        self.save_sessions(&sessions)
+    }
+
+    /// Forget `session`'s preferences, returning whether there were any
+    pub fn forget_session_prefs(&self, session: &str) -> Result<bool> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut sessions = self.load_sessions()?;
+        if sessions.remove(session).is_none() {
+            return Ok(false);
+        }
+        self.save_sessions(&sessions)?;
+        Ok(true)
+    }
+
+    /// Re-hash every object and report anything inconsistent with the manifest
+    pub fn verify(&self) -> Result<Vec<VerifyIssue>> {
+        let manifest = self.load_manifest()?;
+        let mut issues = Vec::new();
+
+        for hash in manifest.objects.keys() {
+            let path = self.object_path(hash);
+            if !path.exists() {
+                issues.push(VerifyIssue::Missing(hash.clone()));
+            } else if hash_file(&path)? != *hash {
+                issues.push(VerifyIssue::Corrupt(hash.clone()));
+            }
+        }
+
+        for hash in self.object_hashes()? {
+            if !manifest.objects.contains_key(&hash) {
+                issues.push(VerifyIssue::Untracked(hash));
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Hashes of all objects present on disk
+    fn object_hashes(&self) -> Result<Vec<String>> {
+        let objects = self.root.join("objects");
+        if !objects.exists() {
+            return Ok(Vec::new());
+        }
+        let mut hashes = Vec::new();
+        for prefix in fs::read_dir(&objects)? {
+            let prefix = prefix?;
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for object in fs::read_dir(prefix.path())? {
+                hashes.push(object?.file_name().to_string_lossy().into_owned());
+            }
+        }
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    /// Remove objects not in `keep` that are older than `min_age`.
+    /// Untracked files are always removed. Returns the removed hashes.
+    pub fn gc(
+        &self,
+        keep: &HashSet<String>,
+        min_age: time::Duration,
+        dry_run: bool,
+    ) -> Result<Vec<String>> {
+        let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut manifest = self.load_manifest()?;
+        let cutoff = OffsetDateTime::now_utc() - min_age;
+        let mut removed = Vec::new();
+
+        for hash in self.object_hashes()? {
+            let collect = match manifest.objects.get(&hash) {
+                Some(entry) => !keep.contains(&hash) && entry.created_at <= cutoff,
+                None => true,
+            };
+            if collect {
+                removed.push(hash);
+            }
+        }
+        // Drop manifest entries whose objects are already gone
+        let on_disk: HashSet<String> = self.object_hashes()?.into_iter().collect();
+        let stale: Vec<String> = manifest
+            .objects
+            .keys()
+            .filter(|hash| !on_disk.contains(*hash))
+            .cloned()
+            .collect();
+
+        if dry_run {
+            return Ok(removed);
+        }
+        for hash in &removed {
+            fs::remove_file(self.object_path(hash))
+                .with_context(|| format!("Failed to remove object {hash}"))?;
+            manifest.objects.remove(hash);
+        }
+        for hash in stale {
+            manifest.objects.remove(&hash);
+        }
+        self.save_manifest(&manifest)?;
+        Ok(removed)
+    }
+}
+
+/// sha256 of a file's contents, hex-encoded
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Extract the object hash from a path inside a store
+pub fn hash_from_path(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    (name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit())).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn put_is_content_addressed_and_idempotent() {
+        let tmp = TempDir::new().unwrap();
+        let store = Store::at(tmp.path().to_path_buf());
+
+        let a = store
+            .put_bytes(b"hello", ObjectKind::Render, None, None)
+            .unwrap();
+        let b = store
+            .put_bytes(b"hello", ObjectKind::Render, None, None)
+            .unwrap();
+        assert_eq!(a, b);
+        assert_eq!(
+            a,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(fs::read(store.object_path(&a)).unwrap(), b"hello");
+        assert_eq!(store.entry(&a).unwrap().unwrap().size, 5);
+    }
+
+    #[test]
+    fn session_prefs_roundtrip_and_forget() {
+        let tmp = TempDir::new().unwrap();
+        let store = Store::at(tmp.path().to_path_buf());
+        assert_eq!(store.session_prefs::<Vec<u32>>("a").unwrap(), None);
+
+        store.save_session_prefs("a", &vec![1, 2]).unwrap();
+        store.save_session_prefs("b", &vec![3]).unwrap();
+        store.save_session_prefs("a", &vec![4]).unwrap();
+        assert_eq!(store.session_prefs::<Vec<u32>>("a").unwrap(), Some(vec![4]));
+
+        assert!(store.forget_session_prefs("a").unwrap());
+        assert!(!store.forget_session_prefs("a").unwrap());
+        assert_eq!(store.session_prefs::<Vec<u32>>("a").unwrap(), None);
+        assert_eq!(store.session_prefs::<Vec<u32>>("b").unwrap(), Some(vec![3]));
+    }
+
+    #[test]
+    fn verify_reports_corrupt_missing_and_untracked() {
+        let tmp = TempDir::new().unwrap();
+        let store = Store::at(tmp.path().to_path_buf());
+        let corrupt = store
+            .put_bytes(b"one", ObjectKind::Gzip, None, None)
+            .unwrap();
+        let missing = store
+            .put_bytes(b"two", ObjectKind::Gzip, None, None)
+            .unwrap();
+        assert!(store.verify().unwrap().is_empty());
+
+        fs::write(store.object_path(&corrupt), b"tampered").unwrap();
+        fs::remove_file(store.object_path(&missing)).unwrap();
+        let untracked = "ab".repeat(32);
+        fs::create_dir_all(store.object_path(&untracked).parent().unwrap()).unwrap();
+        fs::write(store.object_path(&untracked), b"x").unwrap();
+
+        let issues = store.verify().unwrap();
+        assert!(issues.contains(&VerifyIssue::Corrupt(corrupt)));
+        assert!(issues.contains(&VerifyIssue::Missing(missing)));
+        assert!(issues.contains(&VerifyIssue::Untracked(untracked)));
+    }
+
+    #[test]
+    fn gc_keeps_referenced_objects() {
+        let tmp = TempDir::new().unwrap();
+        let store = Store::at(tmp.path().to_path_buf());
+        let kept = store
+            .put_bytes(b"keep", ObjectKind::Gzip, None, None)
+            .unwrap();
+        let dropped = store
+            .put_bytes(b"drop", ObjectKind::Gzip, None, None)
+            .unwrap();
+
+        let keep: HashSet<String> = [kept.clone()].into_iter().collect();
+        let preview = store.gc(&keep, time::Duration::ZERO, true).unwrap();
+        assert_eq!(preview, vec![dropped.clone()]);
+        assert!(store.object_path(&dropped).exists());
+
+        store.gc(&keep, time::Duration::ZERO, false).unwrap();
+        assert!(store.object_path(&kept).exists());
+        assert!(!store.object_path(&dropped).exists());
+        assert!(store.entry(&dropped).unwrap().is_none());
+    }
+}
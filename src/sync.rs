@@ -0,0 +1,287 @@
+//! Shares metadata sync across machines (`shares sync push|pull`).
+//!
+//! The synced copy of shares.json lives in a file inside a git checkout
+//! (pulled and pushed with the checkout's upstream, when it has one) or in an
+//! S3 object (through the `aws` CLI). Pushing merges this machine's shares
+//! into the copy; pulling merges the copy into this machine's shares.json.
+//! Keys and delete tokens are only pushed when asked for, since anyone who
+//! can read the copy could otherwise open and delete every share.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::atomic;
+use crate::shares::{self, SyncReport};
+
+/// Where the synced copy of shares.json is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncBackend {
+    /// A file in a git checkout
+    Git(PathBuf),
+    /// An `s3://bucket/key` object
+    S3(String),
+}
+
+impl SyncBackend {
+    /// Parse a sync target: `s3://bucket/key`, or the path of a file in a git checkout
+    pub fn parse(target: &str) -> Result<Self> {
+        let target = target.trim();
+        if target.is_empty() {
+            bail!(
+                "no shares sync target; set one with `agentexport config set shares_sync <target>`"
+            );
+        }
+        if let Some(rest) = target.strip_prefix("s3://") {
+            if !rest.contains('/') || rest.ends_with('/') {
+                bail!("invalid S3 target {target}: expected s3://bucket/key");
+            }
+            return Ok(Self::S3(target.to_string()));
+        }
+        let path = match target.strip_prefix("~/") {
+            Some(rest) => PathBuf::from(std::env::var("HOME").context("HOME not set")?).join(rest),
+            None => PathBuf::from(target),
+        };
+        if path.is_dir() {
+            bail!("{} is a folder; name the file to sync into", path.display());
+        }
+        let dir = parent_dir(&path);
+        if !git(dir, &["rev-parse", "--is-inside-work-tree"])?
+            .status
+            .success()
+        {
+            bail!(
+                "{} is not in a git checkout; use a file in a git repo or s3://bucket/key",
+                path.display()
+            );
+        }
+        Ok(Self::Git(path))
+    }
+
+    /// The synced copy, or None if nothing was pushed yet
+    fn fetch(&self) -> Result<Option<String>> {
+        match self {
+            Self::Git(path) => {
+                let dir = parent_dir(path);
+                if has_upstream(dir)? {
+                    check(git(dir, &["pull", "--ff-only", "--quiet"])?, "git pull")?;
+                }
+                match fs::read_to_string(path) {
+                    Ok(content) => Ok(Some(content)),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                    Err(err) => Err(err).with_context(|| format!("read {}", path.display())),
+                }
+            }
+            Self::S3(uri) => {
+                let output = aws(&["s3", "cp", uri, "-"], None)?;
+                if output.status.success() {
+                    return Ok(Some(String::from_utf8(output.stdout)?));
+                }
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if stderr.contains("(404)") || stderr.contains("NoSuchKey") {
+                    return Ok(None);
+                }
+                bail!("aws s3 cp {uri} failed: {}", stderr.trim())
+            }
+        }
+    }
+
+    /// Replace the synced copy with `content`
+    fn store(&self, content: &str) -> Result<()> {
+        match self {
+            Self::Git(path) => {
+                let dir = parent_dir(path);
+                let name = path.file_name().context("sync target has no file name")?;
+                let name = name.to_string_lossy();
+                atomic::write(path, content)
+                    .with_context(|| format!("write {}", path.display()))?;
+                check(git(dir, &["add", "--", &name])?, "git add")?;
+                let unchanged = git(dir, &["diff", "--cached", "--quiet", "--", &name])?
+                    .status
+                    .success();
+                if !unchanged {
+                    check(
+                        git(
+                            dir,
+                            &[
+                                "commit",
+                                "--quiet",
+                                "-m",
+                                "Update agentexport shares",
+                                "--",
+                                &name,
+                            ],
+                        )?,
+                        "git commit",
+                    )?;
+                }
+                if has_upstream(dir)? {
+                    check(git(dir, &["push", "--quiet"])?, "git push")?;
+                }
+                Ok(())
+            }
+            Self::S3(uri) => check(
+                aws(&["s3", "cp", "-", uri], Some(content.as_bytes()))?,
+                "aws s3 cp",
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for SyncBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Git(path) => write!(f, "{}", path.display()),
+            Self::S3(uri) => f.write_str(uri),
+        }
+    }
+}
+
+/// Merge this machine's shares into the synced copy. Keys and delete tokens
+/// stay on this machine unless `include_secrets`.
+pub fn push(backend: &SyncBackend, include_secrets: bool) -> Result<SyncReport> {
+    let synced = backend.fetch()?;
+    let (merged, report) = shares::merge_for_sync(synced.as_deref(), include_secrets)?;
+    if synced.as_deref() != Some(merged.as_str()) {
+        backend.store(&merged)?;
+    }
+    Ok(report)
+}
+
+/// Merge the synced copy into this machine's shares
+pub fn pull(backend: &SyncBackend) -> Result<SyncReport> {
+    match backend.fetch()? {
+        Some(content) => shares::merge_synced(&content),
+        None => bail!("nothing synced to {backend} yet; run `agentexport shares sync push` first"),
+    }
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")
+}
+
+fn has_upstream(dir: &Path) -> Result<bool> {
+    Ok(git(dir, &["rev-parse", "--abbrev-ref", "@{upstream}"])?
+        .status
+        .success())
+}
+
+fn aws(args: &[&str], stdin: Option<&[u8]>) -> Result<Output> {
+    let mut child = match Command::new("aws")
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            bail!("aws not found; install the AWS CLI to sync shares through S3")
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(bytes) = stdin
+        && let Some(mut pipe) = child.stdin.take()
+    {
+        pipe.write_all(bytes)?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+fn check(output: Output, what: &str) -> Result<()> {
+    if !output.status.success() {
+        bail!(
+            "{what} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shares::{get_share, load_shares, remove_share, save_share, tests::make_test_share};
+    use crate::test_utils::{EnvGuard, env_lock};
+    use tempfile::TempDir;
+
+    #[test]
+    fn git_backend_round_trips_shares() {
+        let _lock = env_lock();
+        let repo = TempDir::new().unwrap();
+        let laptop = TempDir::new().unwrap();
+        let desktop = TempDir::new().unwrap();
+        let _name = EnvGuard::set("GIT_AUTHOR_NAME", "test");
+        let _email = EnvGuard::set("GIT_AUTHOR_EMAIL", "test@example.com");
+        let _committer = EnvGuard::set("GIT_COMMITTER_NAME", "test");
+        let _committer_email = EnvGuard::set("GIT_COMMITTER_EMAIL", "test@example.com");
+        check(git(repo.path(), &["init", "--quiet"]).unwrap(), "git init").unwrap();
+
+        let target = repo.path().join("shares.json");
+        let backend = SyncBackend::parse(target.to_str().unwrap()).unwrap();
+        assert_eq!(backend, SyncBackend::Git(target.clone()));
+        assert!(SyncBackend::parse(repo.path().to_str().unwrap()).is_err());
+        assert_eq!(
+            SyncBackend::parse("s3://bucket/agentexport/shares.json").unwrap(),
+            SyncBackend::S3("s3://bucket/agentexport/shares.json".to_string())
+        );
+        assert!(SyncBackend::parse("s3://bucket").is_err());
+
+        {
+            let _home = EnvGuard::set("HOME", laptop.path().to_str().unwrap());
+            assert!(pull(&backend).is_err());
+            let mut from_laptop = make_test_share("from-laptop");
+            from_laptop.share_url = Some("https://example.com/v/from-laptop#key123".to_string());
+            save_share(&from_laptop).unwrap();
+            save_share(&make_test_share("dropped")).unwrap();
+            assert_eq!(push(&backend, false).unwrap().added, 2);
+        }
+        // Keys, in links too, and delete tokens stay on the machine that has them
+        let synced = fs::read_to_string(&target).unwrap();
+        assert!(!synced.contains("key123") && !synced.contains("token123"));
+        {
+            let _home = EnvGuard::set("HOME", desktop.path().to_str().unwrap());
+            save_share(&make_test_share("from-desktop")).unwrap();
+            assert_eq!(pull(&backend).unwrap().added, 2);
+            let pulled = get_share("from-laptop").unwrap().unwrap();
+            assert!(pulled.key.is_empty() && !pulled.is_plaintext());
+            assert_eq!(pulled.url(), "https://example.com/v/from-laptop");
+            let err = crate::shares::fetch_payload(&pulled).unwrap_err();
+            assert!(err.to_string().contains("not on this machine"), "{err}");
+            remove_share("dropped").unwrap();
+            push(&backend, false).unwrap();
+        }
+        let _home = EnvGuard::set("HOME", laptop.path().to_str().unwrap());
+        let report = pull(&backend).unwrap();
+        assert_eq!((report.added, report.removed), (1, 1));
+        let mut ids: Vec<String> = load_shares().unwrap().into_iter().map(|s| s.id).collect();
+        ids.sort();
+        assert_eq!(ids, ["from-desktop", "from-laptop"]);
+        let kept = get_share("from-laptop").unwrap().unwrap();
+        assert_eq!(kept.url(), "https://example.com/v/from-laptop#key123");
+        assert!(!kept.key_withheld);
+
+        push(&backend, true).unwrap();
+        assert!(fs::read_to_string(&target).unwrap().contains("key123"));
+
+        let log = git(repo.path(), &["log", "--oneline"]).unwrap();
+        assert_eq!(String::from_utf8_lossy(&log.stdout).lines().count(), 3);
+    }
+}
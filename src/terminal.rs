@@ -28,6 +28,9 @@ pub fn compute_term_key(
 
 /// Get the current tty path
 fn current_tty() -> Result<String> {
+    if let Some(tty) = crate::test_mode::tty() {
+        return Ok(tty);
+    }
     unsafe {
         let ptr = libc::ttyname(libc::STDIN_FILENO);
         if !ptr.is_null() {
@@ -0,0 +1,116 @@
+//! Hermetic test mode for running the CLI without network or a terminal.
+//!
+//! - `AGENTEXPORT_TEST_ENDPOINTS=http://127.0.0.1:PORT` sends uploads, blob
+//!   deletes/status checks, gist calls (`POST /gists`, `GET`/`PATCH`/`DELETE
+//!   /gists/{id}` instead of `gh`), Confluence page creation
+//!   (`/rest/api/content`), and update checks
+//!   (`GET /repos/{repo}/releases/latest`) to that base URL.
+//! - `AGENTEXPORT_TEST_TTY=/dev/ttys001` stands in for the controlling tty
+//!   when computing the terminal key.
+
+/// Base URL that replaces every network endpoint
+pub const TEST_ENDPOINTS_ENV: &str = "AGENTEXPORT_TEST_ENDPOINTS";
+
+/// Fixture tty path used for terminal identity
+pub const TEST_TTY_ENV: &str = "AGENTEXPORT_TEST_TTY";
+
+fn non_empty_env(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Mock server base URL, when test mode is on
+pub fn endpoints() -> Option<String> {
+    non_empty_env(TEST_ENDPOINTS_ENV).map(|base| base.trim_end_matches('/').to_string())
+}
+
+/// Server to upload to: the mock server in test mode, otherwise `configured`
+pub fn upload_url(configured: &str) -> String {
+    endpoints().unwrap_or_else(|| configured.trim_end_matches('/').to_string())
+}
+
+/// GitHub API base for release lookups
+pub fn github_api() -> String {
+    endpoints().unwrap_or_else(|| "https://api.github.com".to_string())
+}
+
+/// GitHub base for release downloads
+pub fn github_downloads() -> String {
+    endpoints().unwrap_or_else(|| "https://github.com".to_string())
+}
+
+/// Fixture tty, if one was injected
+pub fn tty() -> Option<String> {
+    non_empty_env(TEST_TTY_ENV)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock, serve_once};
+    use crate::upload;
+
+    #[test]
+    fn endpoints_override_urls() {
+        let _lock = env_lock();
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, "http://127.0.0.1:9999/");
+        assert_eq!(
+            upload_url("https://agentexports.com"),
+            "http://127.0.0.1:9999"
+        );
+        assert_eq!(github_api(), "http://127.0.0.1:9999");
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, "");
+        assert_eq!(upload_url("https://example.com/"), "https://example.com");
+        assert_eq!(github_downloads(), "https://github.com");
+    }
+
+    #[test]
+    fn upload_goes_to_mock_server() {
+        let _lock = env_lock();
+        let (base, server) = serve_once(r#"{"id":"mock123","expires_at":0}"#);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let result =
+            upload::upload_blob("https://agentexports.com", b"blob", "key", 30, None).unwrap();
+        assert_eq!(result.share_url, format!("{base}/v/mock123#key"));
+        let head = server.join().unwrap();
+        assert!(head.starts_with("POST /upload "));
+    }
+
+    #[test]
+    fn plaintext_upload_sends_token_and_omits_key() {
+        let _lock = env_lock();
+        let (base, server) = serve_once(r#"{"id":"plain1","expires_at":0}"#);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let result =
+            upload::upload_plaintext("https://agentexports.com", "{}", "org-token", 30, None)
+                .unwrap();
+        assert_eq!(result.share_url, format!("{base}/v/plain1"));
+        assert!(result.key.is_empty());
+        let head = server.join().unwrap().to_ascii_lowercase();
+        assert!(head.contains("x-encryption: none"));
+        assert!(head.contains("authorization: bearer org-token"));
+    }
+
+    #[test]
+    fn gist_create_goes_to_mock_server() {
+        let _lock = env_lock();
+        let (base, server) = serve_once(r#"{"id":"gist42"}"#);
+        let _guard = EnvGuard::set(TEST_ENDPOINTS_ENV, &base);
+
+        let payload = r#"{"tool":"Claude Code","shared_at":"","messages":[]}"#;
+        let result = upload::upload_gist(
+            "gist",
+            payload,
+            "test",
+            crate::config::GistFormat::Json,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(result.id, "gist42");
+        assert!(server.join().unwrap().starts_with("POST /gists "));
+    }
+}
@@ -2,7 +2,12 @@
 //!
 //! All tests that manipulate environment variables or the current directory
 //! must use the shared `env_lock()` to prevent race conditions.
+//!
+//! The `serve*` helpers answer HTTP requests on a local port, for tests that
+//! point `AGENTEXPORT_TEST_ENDPOINTS` at them.
 
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 
@@ -66,3 +71,83 @@ impl Drop for DirGuard {
         let _ = std::env::set_current_dir(&self.original);
     }
 }
+
+/// Serve one HTTP request with `body`, returning the request line and headers
+pub fn serve_once(
+    body: impl AsRef<[u8]> + Send + 'static,
+) -> (String, std::thread::JoinHandle<String>) {
+    let (base, handle) = serve(vec![body.as_ref().to_vec()]);
+    let handle = std::thread::spawn(move || handle.join().unwrap().remove(0).0);
+    (base, handle)
+}
+
+/// Serve one HTTP request per response body, returning each request's
+/// request line and headers, and its body
+pub fn serve(bodies: Vec<Vec<u8>>) -> (String, std::thread::JoinHandle<Vec<(String, String)>>) {
+    serve_status(bodies.into_iter().map(|body| (200, body)).collect())
+}
+
+/// Like `serve`, with a status code per response
+pub fn serve_status(
+    responses: Vec<(u16, Vec<u8>)>,
+) -> (String, std::thread::JoinHandle<Vec<(String, String)>>) {
+    let mut responses = responses.into_iter();
+    serve_with(responses.len(), move |_| responses.next().unwrap())
+}
+
+/// Answer each request with an empty body and the status of the route
+/// whose path is in its request line, whatever order they arrive in
+pub fn serve_routes(
+    routes: Vec<(&'static str, u16)>,
+) -> (String, std::thread::JoinHandle<Vec<(String, String)>>) {
+    serve_with(routes.len(), move |request_line| {
+        let (_, status) = routes
+            .iter()
+            .find(|(path, _)| request_line.contains(path))
+            .unwrap_or_else(|| panic!("unexpected request {request_line}"));
+        (*status, Vec::new())
+    })
+}
+
+/// Serve `count` requests, answering each with `respond(request line)`
+pub fn serve_with(
+    count: usize,
+    mut respond: impl FnMut(&str) -> (u16, Vec<u8>) + Send + 'static,
+) -> (String, std::thread::JoinHandle<Vec<(String, String)>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for _ in 0..count {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                head.push_str(&line);
+            }
+            let mut request_body = vec![0; length];
+            reader.read_exact(&mut request_body).unwrap();
+            let (status, body) = respond(head.lines().next().unwrap_or_default());
+            let reason = if status == 200 { "OK" } else { "Error" };
+            let response = format!(
+                "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let stream = reader.get_mut();
+            stream.write_all(response.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+            requests.push((head, String::from_utf8_lossy(&request_body).into_owned()));
+        }
+        requests
+    });
+    (base, handle)
+}
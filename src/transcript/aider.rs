@@ -0,0 +1,485 @@
+//! Aider discovery. Aider appends every chat to `.aider.chat.history.md` in
+//! the repo root, as markdown: `#### ` lines are the user's, `> ` lines are
+//! aider's own output (edits applied, commits, command output), and the rest
+//! is the model's reply, with edits as SEARCH/REPLACE blocks.
+//!
+//! The newest chat in the history is converted to rendered messages and
+//! written as a JSONL snapshot under the cache dir, like Cursor chats.
+
+use anyhow::{Context, Result, bail};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::discovery::{cache_dir, is_fresh};
+use super::parser::truncate;
+use super::types::RenderedMessage;
+
+/// Aider's default chat history file name
+pub const HISTORY_FILE: &str = ".aider.chat.history.md";
+
+const SESSION_HEADER: &str = "# aider chat started at";
+
+/// Snapshot path for chat `id`, whether or not it was exported yet
+pub fn aider_snapshot_path(id: &str) -> Result<PathBuf> {
+    Ok(cache_dir()?
+        .join("agentexport")
+        .join("aider")
+        .join(format!("{id}.jsonl")))
+}
+
+/// The chat history for `cwd`: `$AIDER_CHAT_HISTORY_FILE`, or the history
+/// file in `cwd` or the nearest parent, up to the repo root
+pub fn find_history(cwd: &Path) -> Option<PathBuf> {
+    if let Some(file) = std::env::var_os("AIDER_CHAT_HISTORY_FILE").filter(|f| !f.is_empty()) {
+        let file = cwd.join(file);
+        return file.is_file().then_some(file);
+    }
+    for dir in cwd.ancestors() {
+        let file = dir.join(HISTORY_FILE);
+        if file.is_file() {
+            return Some(file);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// One chat of the history file
+#[derive(Debug, Default)]
+pub struct AiderChat {
+    /// Local time from the `# aider chat started at` header
+    pub started: String,
+    pub model: Option<String>,
+    pub messages: Vec<RenderedMessage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    User,
+    Output,
+    Reply,
+}
+
+fn message(role: &str, content: String, model: Option<&str>) -> RenderedMessage {
+    RenderedMessage {
+        role: role.to_string(),
+        content,
+        raw: None,
+        raw_label: None,
+        tool_use_id: None,
+        model: model.map(str::to_string),
+        abandoned: false,
+        part: None,
+        id: None,
+        highlighted: false,
+        images: Vec::new(),
+    }
+}
+
+/// Model from aider's startup lines (`Main model: gpt-4o with diff edit format`)
+fn startup_model(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let rest = line
+            .strip_prefix("Main model: ")
+            .or_else(|| line.strip_prefix("Model: "))?;
+        let model = rest.split(" with ").next().unwrap_or(rest).trim();
+        (!model.is_empty()).then(|| model.to_string())
+    })
+}
+
+impl AiderChat {
+    fn push(&mut self, block: Block, lines: &[String]) {
+        let text = lines.join("\n").trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        match block {
+            Block::User if text.starts_with('/') => {
+                self.messages.push(message("command", text, None));
+            }
+            Block::User => self.messages.push(message("user", text, None)),
+            // Output before the first prompt is aider's startup banner
+            Block::Output if self.messages.iter().all(|m| m.role == "system") => {
+                self.model = self.model.take().or_else(|| startup_model(&text));
+                self.messages.push(message("system", text, None));
+            }
+            Block::Output => self.messages.push(message("tool", text, None)),
+            Block::Reply => {
+                let model = self.model.clone();
+                self.messages
+                    .extend(reply_messages(&text, model.as_deref()));
+            }
+        }
+    }
+}
+
+/// Every chat in a history file, oldest first
+pub fn parse_history(text: &str) -> Vec<AiderChat> {
+    let mut chats: Vec<AiderChat> = Vec::new();
+    let mut chat = AiderChat::default();
+    let mut block = Block::Reply;
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if let Some(started) = line.strip_prefix(SESSION_HEADER) {
+            chat.push(block, &lines);
+            lines.clear();
+            chats.push(std::mem::take(&mut chat));
+            chat.started = started.trim().to_string();
+            block = Block::Reply;
+            in_fence = false;
+            continue;
+        }
+        // Replies can quote markdown in code; only fences end them
+        let (kind, text) = if block == Block::Reply && in_fence {
+            (Block::Reply, line)
+        } else if let Some(rest) = line.strip_prefix("####") {
+            (Block::User, rest.strip_prefix(' ').unwrap_or(rest))
+        } else if let Some(rest) = line.strip_prefix('>') {
+            (Block::Output, rest.strip_prefix(' ').unwrap_or(rest))
+        } else if line.trim().is_empty() {
+            (block, "")
+        } else {
+            (Block::Reply, line)
+        };
+        if kind != block {
+            chat.push(block, &lines);
+            lines.clear();
+            block = kind;
+        }
+        if kind == Block::Reply && text.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        // Aider ends quoted lines with a markdown line break
+        lines.push(text.trim_end().to_string());
+    }
+    chat.push(block, &lines);
+    chats.push(chat);
+    chats.retain(|c| !c.messages.is_empty());
+    chats
+}
+
+/// A reply split into prose and one tool message per SEARCH/REPLACE edit
+fn reply_messages(text: &str, model: Option<&str>) -> Vec<RenderedMessage> {
+    let mut messages = Vec::new();
+    let mut prose: Vec<&str> = Vec::new();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let close = line
+            .trim_start()
+            .starts_with("```")
+            .then(|| {
+                lines[index + 1..]
+                    .iter()
+                    .position(|l| l.trim_start().starts_with("```"))
+            })
+            .flatten()
+            .map(|offset| index + 1 + offset);
+        let Some(close) = close.filter(|&c| {
+            lines[index + 1..c]
+                .iter()
+                .any(|l| l.starts_with("<<<<<<< SEARCH"))
+        }) else {
+            prose.push(line);
+            index += 1;
+            continue;
+        };
+
+        // The file name sits on the line before the fence, or opens the block
+        let body = &lines[index + 1..close];
+        let mut file = None;
+        let mut start = index;
+        if let Some(previous) = prose.last().map(|l| l.trim().trim_matches(['`', '*']))
+            && !previous.is_empty()
+            && !previous.contains(' ')
+        {
+            file = Some(previous.to_string());
+            prose.pop();
+            start -= 1;
+        }
+        let search = body
+            .iter()
+            .position(|l| l.starts_with("<<<<<<< SEARCH"))
+            .unwrap_or(0);
+        if let Some(first) = body[..search]
+            .iter()
+            .map(|l| l.trim())
+            .find(|l| !l.is_empty())
+        {
+            file = Some(first.to_string());
+        }
+
+        let text = prose.join("\n").trim().to_string();
+        if !text.is_empty() {
+            messages.push(message("assistant", text, model));
+        }
+        prose.clear();
+        let file = file.unwrap_or_else(|| "file".to_string());
+        let raw = lines[start..=close].join("\n");
+        messages.push(RenderedMessage {
+            raw: Some(truncate(&raw, 20000)),
+            raw_label: Some("Edit block".to_string()),
+            ..message(
+                "tool",
+                format!("Edit {file}\n```diff\n{}```", edit_diff(&body[search..])),
+                None,
+            )
+        });
+        index = close + 1;
+    }
+    let text = prose.join("\n").trim().to_string();
+    if !text.is_empty() {
+        messages.push(message("assistant", text, model));
+    }
+    messages
+}
+
+/// SEARCH lines as removals and REPLACE lines as additions
+fn edit_diff(body: &[&str]) -> String {
+    let mut diff = String::new();
+    let mut sign = ' ';
+    for line in body {
+        if line.starts_with("<<<<<<< SEARCH") {
+            sign = '-';
+        } else if line.starts_with("=======") && sign == '-' {
+            sign = '+';
+        } else if line.starts_with(">>>>>>> REPLACE") {
+            sign = ' ';
+        } else if sign != ' ' {
+            diff.push(sign);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    diff
+}
+
+/// Stable id for the chat that started at `started` in `history`
+fn chat_id(history: &Path, started: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(history.to_string_lossy().as_bytes());
+    hasher.update(b"|");
+    hasher.update(started.as_bytes());
+    hex::encode(&hasher.finalize()[..8])
+}
+
+/// Export the newest chat in `history` to its snapshot path, returning the
+/// path and chat id
+fn export_history(history: &Path) -> Result<(PathBuf, String)> {
+    let text = fs::read_to_string(history)
+        .with_context(|| format!("Failed to read {}", history.display()))?;
+    let Some(chat) = parse_history(&text).pop() else {
+        bail!("no Aider chat in {}", history.display());
+    };
+    let history = history.canonicalize()?;
+    let id = chat_id(&history, &chat.started);
+    let path = aider_snapshot_path(&id)?;
+    fs::create_dir_all(path.parent().context("snapshot path has no parent")?)?;
+    let mut file = File::create(&path)
+        .with_context(|| format!("Failed to write Aider snapshot {}", path.display()))?;
+    let header = json!({
+        "type": "aider_session",
+        "id": id,
+        "cwd": history.parent().map(|p| p.display().to_string()),
+        "started_at": chat.started,
+        "model": chat.model,
+    });
+    writeln!(file, "{header}")?;
+    for message in &chat.messages {
+        writeln!(
+            file,
+            "{}",
+            json!({ "type": "aider_message", "message": message })
+        )?;
+    }
+    // Keep the history's modification time so freshness checks see the chat's age
+    file.set_modified(fs::metadata(&history)?.modified()?)?;
+    Ok((path, id))
+}
+
+/// Resolve an Aider transcript: an explicit snapshot or history file, or the
+/// history found from the cwd. Returns the snapshot path and chat id.
+pub fn resolve_aider_transcript(
+    transcript_arg: Option<PathBuf>,
+    max_age_minutes: u64,
+) -> Result<(PathBuf, Option<String>)> {
+    if let Some(path) = transcript_arg {
+        if path.extension().is_some_and(|ext| ext == "md") {
+            let (path, id) = export_history(&path)?;
+            return Ok((path, Some(id)));
+        }
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(str::to_string);
+        return Ok((path, id));
+    }
+
+    let cwd = std::env::current_dir().context("unable to resolve cwd; pass --transcript")?;
+    let Some(history) = find_history(&cwd) else {
+        bail!("no {HISTORY_FILE} found for current directory; pass --transcript");
+    };
+    let modified = fs::metadata(&history)?.modified()?;
+    if !is_fresh(modified, max_age_minutes) {
+        bail!(
+            "{} hasn't changed in the last {max_age_minutes} minutes; pass --max-age-minutes 0 to share it anyway",
+            history.display()
+        );
+    }
+    let (path, id) = export_history(&history)?;
+    Ok((path, Some(id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{DirGuard, EnvGuard, env_lock};
+    use crate::transcript::{extract_transcript_meta, parse_transcript};
+    use tempfile::TempDir;
+
+    const HISTORY: &str = "
+# aider chat started at 2025-03-01 09:00:00
+
+> Aider v0.80.0
+> Main model: gpt-4o with diff edit format
+
+#### hi
+
+Hello!
+
+# aider chat started at 2025-03-02 10:15:42
+
+> Aider v0.82.1
+> Main model: anthropic/claude-sonnet-4-5 with diff edit format
+> Git repo: .git with 12 files
+
+#### /add hello.py
+
+> Added hello.py to the chat
+
+#### make hello greet by name
+#### and keep it short
+
+I'll add a `name` parameter.
+
+hello.py
+```python
+<<<<<<< SEARCH
+def hello():
+    print(\"hi\")
+=======
+def hello(name):
+    print(f\"hi {name}\")
+>>>>>>> REPLACE
+```
+
+> Tokens: 2.1k sent, 96 received. Cost: $0.01 message, $0.01 session.
+> Applied edit to hello.py
+> Commit 1a2b3c4 feat: Greet by name
+
+#### how do I quote in markdown?
+
+Like this:
+
+```md
+> a quote
+#### a heading
+```
+";
+
+    #[test]
+    fn history_becomes_rendered_messages() {
+        let chats = parse_history(HISTORY);
+        assert_eq!(chats.len(), 2);
+        let chat = &chats[1];
+        assert_eq!(chat.started, "2025-03-02 10:15:42");
+        assert_eq!(chat.model.as_deref(), Some("anthropic/claude-sonnet-4-5"));
+
+        let roles: Vec<&str> = chat.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(
+            roles,
+            [
+                "system",
+                "command",
+                "tool",
+                "user",
+                "assistant",
+                "tool",
+                "tool",
+                "user",
+                "assistant"
+            ]
+        );
+        let content = |i: usize| chat.messages[i].content.as_str();
+        assert!(content(0).ends_with("Git repo: .git with 12 files"));
+        assert_eq!(content(1), "/add hello.py");
+        assert_eq!(content(3), "make hello greet by name\nand keep it short");
+        assert_eq!(content(4), "I'll add a `name` parameter.");
+        assert_eq!(
+            chat.messages[4].model.as_deref(),
+            Some("anthropic/claude-sonnet-4-5")
+        );
+        assert_eq!(
+            content(5),
+            "Edit hello.py\n```diff\n-def hello():\n-    print(\"hi\")\n+def hello(name):\n+    print(f\"hi {name}\")\n```"
+        );
+        assert!(
+            chat.messages[5]
+                .raw
+                .as_deref()
+                .unwrap()
+                .starts_with("hello.py\n```python")
+        );
+        assert!(content(6).contains("Applied edit to hello.py"));
+        assert_eq!(
+            content(8),
+            "Like this:\n\n```md\n> a quote\n#### a heading\n```"
+        );
+    }
+
+    #[test]
+    fn newest_chat_is_found_from_a_subfolder() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let repo = tmp.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src")).unwrap();
+        fs::write(repo.join(HISTORY_FILE), HISTORY).unwrap();
+        let repo = repo.canonicalize().unwrap();
+        let _cache = EnvGuard::set("AGENTEXPORT_CACHE_DIR", tmp.path().to_str().unwrap());
+        let _history = EnvGuard::set("AIDER_CHAT_HISTORY_FILE", "");
+        let _cwd = DirGuard::set(&repo.join("src")).unwrap();
+
+        let (path, id) = resolve_aider_transcript(None, 10).unwrap();
+        let id = id.unwrap();
+        assert_eq!(path, aider_snapshot_path(&id).unwrap());
+        // Exporting again keeps the same id
+        assert_eq!(resolve_aider_transcript(None, 10).unwrap().1, Some(id));
+
+        let parsed = parse_transcript(&path).unwrap();
+        assert_eq!(parsed.messages.len(), 9);
+        assert_eq!(
+            parsed.messages[3].content,
+            "make hello greet by name\nand keep it short"
+        );
+        let meta = extract_transcript_meta(&path);
+        assert_eq!(
+            meta.title(None).as_deref(),
+            Some("make hello greet by name\nand keep it short")
+        );
+        assert_eq!(meta.cwd.as_deref(), repo.to_str());
+
+        // The search stops at the repo root
+        let outside = tmp.path().join("elsewhere");
+        fs::create_dir_all(outside.join(".git")).unwrap();
+        let _elsewhere = DirGuard::set(&outside).unwrap();
+        assert!(resolve_aider_transcript(None, 10).is_err());
+    }
+}
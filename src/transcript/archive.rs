@@ -0,0 +1,305 @@
+//! Chats exported from other tools, for `agentexport import`. A file is
+//! recognized by its shape: a ChatGPT data export or a saved ChatGPT share
+//! page, or a Claude.ai export or the JSON that Claude sharing tools save.
+//! A picked conversation is written as a JSONL snapshot under the cache dir
+//! and published like any other session.
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::discovery::is_fresh;
+use super::types::{RenderedMessage, Tool};
+use super::{chatgpt, claude_ai};
+
+/// One conversation of an archive, for picking
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    pub id: String,
+    pub title: String,
+    pub updated_at: Option<OffsetDateTime>,
+    /// Messages the snapshot will hold
+    pub messages: usize,
+}
+
+/// The conversations in an exported file, and the tool they came from
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub tool: Tool,
+    pub conversations: Vec<Value>,
+}
+
+impl Archive {
+    /// Read an exported file, telling the tool apart by its shape
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if text.trim_start().starts_with('<') {
+            let conversation = chatgpt::read_share_page(&text)
+                .with_context(|| format!("{} is not a saved ChatGPT share page", path.display()))?;
+            return Ok(Self {
+                tool: Tool::ChatGpt,
+                conversations: vec![conversation],
+            });
+        }
+        let value: Value = serde_json::from_str(&text)
+            .with_context(|| format!("{} is not valid JSON", path.display()))?;
+        let first = match &value {
+            Value::Array(items) => items.first(),
+            Value::Object(_) => Some(&value),
+            _ => None,
+        };
+        let tool = match first {
+            // An empty export, which has nothing to pick anyway
+            None if value.is_array() => Tool::ChatGpt,
+            Some(item) if item.get("mapping").is_some() => Tool::ChatGpt,
+            Some(item) if item.get("chat_messages").is_some() || item.get("messages").is_some() => {
+                Tool::ClaudeAi
+            }
+            _ => bail!(
+                "{} is not a ChatGPT or Claude.ai export (no `mapping`, `chat_messages`, or `messages`)",
+                path.display()
+            ),
+        };
+        let conversations = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+        let conversations = match tool {
+            Tool::ClaudeAi => conversations.into_iter().map(claude_ai::with_id).collect(),
+            _ => conversations,
+        };
+        Ok(Self {
+            tool,
+            conversations,
+        })
+    }
+
+    fn id<'a>(&self, conversation: &'a Value) -> Option<&'a str> {
+        match self.tool {
+            Tool::ClaudeAi => claude_ai::conversation_id(conversation),
+            _ => chatgpt::conversation_id(conversation),
+        }
+    }
+
+    fn title(&self, conversation: &Value) -> String {
+        let title = match self.tool {
+            Tool::ClaudeAi => claude_ai::conversation_title(conversation),
+            _ => chatgpt::conversation_title(conversation),
+        };
+        title
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .unwrap_or("Untitled")
+            .to_string()
+    }
+
+    /// Messages of `conversation`, oldest first, with the time each was sent
+    pub fn messages(&self, conversation: &Value) -> Vec<(RenderedMessage, Option<OffsetDateTime>)> {
+        match self.tool {
+            Tool::ClaudeAi => claude_ai::conversation_messages(conversation),
+            _ => chatgpt::conversation_messages(conversation),
+        }
+    }
+
+    /// The archive's conversations, most recently updated first
+    pub fn list(&self) -> Vec<ImportedConversation> {
+        let mut conversations: Vec<ImportedConversation> = self
+            .conversations
+            .iter()
+            .filter_map(|conversation| {
+                Some(ImportedConversation {
+                    id: self.id(conversation)?.to_string(),
+                    title: self.title(conversation),
+                    updated_at: match self.tool {
+                        Tool::ClaudeAi => claude_ai::updated_at(conversation),
+                        _ => chatgpt::updated_at(conversation),
+                    },
+                    messages: self.messages(conversation).len(),
+                })
+            })
+            .collect();
+        conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+        conversations
+    }
+
+    /// The conversation `query` names: its id, or a title containing it when
+    /// only one does
+    pub fn find(&self, query: &str) -> Result<&Value> {
+        if let Some(conversation) = self
+            .conversations
+            .iter()
+            .find(|c| self.id(c) == Some(query))
+        {
+            return Ok(conversation);
+        }
+        let needle = query.to_lowercase();
+        let matches: Vec<&Value> = self
+            .conversations
+            .iter()
+            .filter(|c| self.title(c).to_lowercase().contains(&needle))
+            .collect();
+        match matches.as_slice() {
+            [] => bail!("no conversation with id or title {query:?} in the export"),
+            [conversation] => Ok(conversation),
+            _ => {
+                let titles: Vec<String> = matches
+                    .iter()
+                    .map(|c| format!("  {}  {}", self.id(c).unwrap_or("?"), self.title(c)))
+                    .collect();
+                bail!(
+                    "{} conversations match {query:?}; pass an id:\n{}",
+                    matches.len(),
+                    titles.join("\n")
+                )
+            }
+        }
+    }
+
+    /// Write `conversation` to its snapshot path, returning the path and id
+    pub fn import(&self, conversation: &Value) -> Result<(PathBuf, String)> {
+        let Some(id) = self.id(conversation) else {
+            bail!("conversation has no id");
+        };
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            bail!("invalid conversation id: {id}");
+        }
+        let messages = self.messages(conversation);
+        if messages.is_empty() {
+            bail!("conversation {id} has no messages");
+        }
+        let (path, kind) = match self.tool {
+            Tool::ClaudeAi => (claude_ai::claude_ai_snapshot_path(id)?, "claude_ai"),
+            _ => (chatgpt::chatgpt_snapshot_path(id)?, "chatgpt"),
+        };
+        fs::create_dir_all(path.parent().context("snapshot path has no parent")?)?;
+        let mut file = File::create(&path).with_context(|| {
+            format!(
+                "Failed to write {} snapshot {}",
+                self.tool.display_name(),
+                path.display()
+            )
+        })?;
+        let header = json!({
+            "type": format!("{kind}_session"),
+            "id": id,
+            "title": self.title(conversation),
+        });
+        writeln!(file, "{header}")?;
+        for (message, at) in &messages {
+            let mut line = json!({ "type": format!("{kind}_message"), "message": message });
+            if let Some(at) = at.and_then(|at| at.format(&Rfc3339).ok()) {
+                line["timestamp"] = Value::String(at);
+            }
+            writeln!(file, "{line}")?;
+        }
+        Ok((path, id.to_string()))
+    }
+}
+
+/// Resolve an imported transcript: an explicit snapshot, or the one most
+/// recently written next to `snapshot` (any path in the tool's snapshot
+/// folder). Returns the snapshot path and conversation id.
+pub(super) fn resolve_snapshot(
+    tool: Tool,
+    snapshot: &Path,
+    transcript_arg: Option<PathBuf>,
+    max_age_minutes: u64,
+) -> Result<(PathBuf, Option<String>)> {
+    let name = tool.display_name();
+    let path = match transcript_arg {
+        Some(path)
+            if path
+                .extension()
+                .is_some_and(|ext| ext == "json" || ext == "html") =>
+        {
+            bail!(
+                "{} is a {name} export; pick a conversation with `agentexport import` first",
+                path.display()
+            )
+        }
+        Some(path) => path,
+        None => {
+            let dir = snapshot.parent().context("snapshot path has no parent")?;
+            let newest = fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+                    Some((entry.metadata().ok()?.modified().ok()?, entry.path()))
+                })
+                .max();
+            let Some((modified, path)) = newest else {
+                bail!("no imported {name} conversation; run `agentexport import` first");
+            };
+            if !is_fresh(modified, max_age_minutes) {
+                bail!(
+                    "no {name} conversation imported in the last {max_age_minutes} minutes; pass --session-id or --max-age-minutes 0"
+                );
+            }
+            path
+        }
+    };
+    let id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(str::to_string);
+    Ok((path, id))
+}
+
+/// A rendered message with no raw body, as imported chats carry
+pub(super) fn message(role: &str, content: String, model: Option<&str>) -> RenderedMessage {
+    RenderedMessage {
+        role: role.to_string(),
+        content,
+        raw: None,
+        raw_label: None,
+        tool_use_id: None,
+        model: model.map(str::to_string),
+        abandoned: false,
+        part: None,
+        id: None,
+        highlighted: false,
+        images: Vec::new(),
+    }
+}
+
+/// Stable id for a conversation that carries none: a hash of its contents
+pub(super) fn content_id(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(&Sha256::digest(text.as_bytes())[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn archive_of(text: &str) -> Result<Archive> {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+        Archive::read(file.path())
+    }
+
+    #[test]
+    fn tells_tools_apart_by_shape() {
+        let chatgpt = archive_of(r#"[{"id":"c1","title":"T","mapping":{}}]"#).unwrap();
+        assert!(matches!(chatgpt.tool, Tool::ChatGpt));
+        let export = archive_of(r#"[{"uuid":"u1","name":"N","chat_messages":[]}]"#).unwrap();
+        assert!(matches!(export.tool, Tool::ClaudeAi));
+        let shared =
+            archive_of(r#"{"title":"N","messages":[{"role":"user","content":"Hi"}]}"#).unwrap();
+        assert!(matches!(shared.tool, Tool::ClaudeAi));
+        // Shared chats without an id get one from their contents
+        let id = shared.list()[0].id.clone();
+        assert_eq!(id.len(), 16);
+        assert_eq!(shared.find(&id).unwrap()["title"], "N");
+        assert!(matches!(archive_of("[]").unwrap().tool, Tool::ChatGpt));
+        assert!(archive_of(r#"{"hello":"world"}"#).is_err());
+        assert!(archive_of("not json").is_err());
+    }
+}
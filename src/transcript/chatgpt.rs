@@ -0,0 +1,586 @@
+//! ChatGPT data export import. The export's `conversations.json` is an array
+//! of conversations whose messages form a tree: `mapping` holds every node by
+//! id with its `parent` and `children`, and `current_node` is the last message
+//! of the branch the conversation ended on. Walking up from it gives the
+//! conversation as it was last shown.
+//!
+//! A share page saved from the browser holds the same conversation: in the
+//! page's `__NEXT_DATA__` JSON when it has one, or else only in the rendered
+//! messages, which are read back into a single branch.
+//!
+//! A picked conversation is converted to rendered messages and written as a
+//! JSONL snapshot under the cache dir, like Cursor and Aider chats.
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+use super::archive::{content_id, message, resolve_snapshot};
+use super::discovery::cache_dir;
+use super::parser::truncate;
+use super::types::{RenderedMessage, Tool};
+
+/// Snapshot path for conversation `id`, whether or not it was imported yet
+pub fn chatgpt_snapshot_path(id: &str) -> Result<PathBuf> {
+    Ok(cache_dir()?
+        .join("agentexport")
+        .join("chatgpt")
+        .join(format!("{id}.jsonl")))
+}
+
+pub(super) fn conversation_id(conversation: &Value) -> Option<&str> {
+    conversation
+        .get("conversation_id")
+        .or_else(|| conversation.get("id"))
+        .and_then(|v| v.as_str())
+}
+
+pub(super) fn conversation_title(conversation: &Value) -> Option<&str> {
+    conversation.get("title").and_then(|v| v.as_str())
+}
+
+pub(super) fn updated_at(conversation: &Value) -> Option<OffsetDateTime> {
+    unix_time(
+        conversation
+            .get("update_time")
+            .or_else(|| conversation.get("create_time")),
+    )
+}
+
+fn unix_time(value: Option<&Value>) -> Option<OffsetDateTime> {
+    let secs = value?.as_f64()?;
+    OffsetDateTime::from_unix_timestamp_nanos((secs * 1e9) as i128).ok()
+}
+
+/// Text of a message's `content.parts`; images and other attachments become
+/// placeholders
+fn parts_text(content: &Value) -> String {
+    let Some(parts) = content.get("parts").and_then(|v| v.as_array()) else {
+        return String::new();
+    };
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            Value::String(text) => Some(text.clone()),
+            Value::Object(_) => match part.get("content_type").and_then(|v| v.as_str()) {
+                Some("image_asset_pointer") => Some("[Image]".to_string()),
+                Some("audio_transcription") => part
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                _ => part
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+            },
+            _ => None,
+        })
+        .filter(|text| !text.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Rendered message for one node's `message`, or None for hidden and empty ones
+fn node_message(value: &Value) -> Option<RenderedMessage> {
+    if value
+        .pointer("/metadata/is_visually_hidden_from_conversation")
+        .and_then(|v| v.as_bool())
+        == Some(true)
+    {
+        return None;
+    }
+    let role = value.pointer("/author/role").and_then(|v| v.as_str())?;
+    let content = value.get("content")?;
+    let content_type = content
+        .get("content_type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("text");
+    let model = value
+        .pointer("/metadata/model_slug")
+        .and_then(|v| v.as_str());
+    let recipient = value
+        .get("recipient")
+        .and_then(|v| v.as_str())
+        .unwrap_or("all");
+
+    let rendered = match (role, content_type) {
+        // Custom instructions and memory aren't part of the conversation
+        (_, "user_editable_context" | "model_editable_context") => return None,
+        (_, "thoughts") => {
+            let thoughts: Vec<String> = content
+                .get("thoughts")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|t| {
+                    let summary = t.get("summary").and_then(|v| v.as_str()).unwrap_or("");
+                    let body = t.get("content").and_then(|v| v.as_str()).unwrap_or("");
+                    let text = match (summary.is_empty(), body.is_empty()) {
+                        (false, false) => format!("**{summary}**\n{body}"),
+                        (false, true) => summary.to_string(),
+                        _ => body.to_string(),
+                    };
+                    (!text.trim().is_empty()).then_some(text)
+                })
+                .collect();
+            message("thinking", thoughts.join("\n\n"), model)
+        }
+        // "Thought for 12s"
+        (_, "reasoning_recap") => return None,
+        ("assistant", _) if recipient != "all" => {
+            // A call to a tool (python, browsing, image generation)
+            let body = content
+                .get("text")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| parts_text(content));
+            let mut call = message(
+                "tool",
+                format!("{recipient}\n{}", truncate(&body, 2000)),
+                None,
+            );
+            call.raw = Some(truncate(&body, 20000));
+            call.raw_label = Some("Call".to_string());
+            call
+        }
+        ("assistant", "code") => {
+            let code = content.get("text").and_then(|v| v.as_str()).unwrap_or("");
+            message("assistant", format!("```\n{code}\n```"), model)
+        }
+        ("assistant" | "user", _) => message(role, parts_text(content), model),
+        ("tool", _) => {
+            let name = value
+                .pointer("/author/name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("tool");
+            let body = match content_type {
+                "execution_output" | "tether_quote" | "system_error" => content
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_default(),
+                "tether_browsing_display" => content
+                    .get("result")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_default(),
+                _ => parts_text(content),
+            };
+            let mut result = message("tool", format!("{name}\n{}", truncate(&body, 2000)), None);
+            result.raw = Some(truncate(&body, 20000));
+            result.raw_label = Some("Result".to_string());
+            result
+        }
+        ("system", _) => message("system", parts_text(content), None),
+        _ => return None,
+    };
+    (!rendered.content.trim().is_empty()).then_some(rendered)
+}
+
+/// Messages on the branch ending at `current_node`, oldest first, with the
+/// time each was sent
+pub fn conversation_messages(
+    conversation: &Value,
+) -> Vec<(RenderedMessage, Option<OffsetDateTime>)> {
+    let Some(mapping) = conversation.get("mapping").and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    // Exports without a current node end on the newest leaf
+    let current = conversation
+        .get("current_node")
+        .and_then(|v| v.as_str())
+        .filter(|id| mapping.contains_key(*id))
+        .map(str::to_string)
+        .or_else(|| {
+            mapping
+                .iter()
+                .filter(|(_, node)| {
+                    node.get("children")
+                        .and_then(|v| v.as_array())
+                        .is_none_or(|c| c.is_empty())
+                })
+                .max_by(|(_, a), (_, b)| {
+                    let time = |n: &Value| {
+                        n.pointer("/message/create_time")
+                            .and_then(|v| v.as_f64())
+                            .unwrap_or(0.0)
+                    };
+                    time(a).total_cmp(&time(b))
+                })
+                .map(|(id, _)| id.clone())
+        });
+
+    let mut branch = Vec::new();
+    let mut next = current;
+    // Bound the walk by the node count so a malformed cycle can't loop forever
+    for _ in 0..mapping.len() {
+        let Some(node) = next.as_deref().and_then(|id| mapping.get(id)) else {
+            break;
+        };
+        branch.push(node);
+        next = node
+            .get("parent")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+    }
+    branch
+        .into_iter()
+        .rev()
+        .filter_map(|node| {
+            let value = node.get("message")?;
+            Some((node_message(value)?, unix_time(value.get("create_time"))))
+        })
+        .collect()
+}
+
+/// The conversation on a saved ChatGPT share page, in export form
+pub fn read_share_page(html: &str) -> Result<Value> {
+    let mut conversation = next_data(html)
+        .and_then(|data| find_mapping(&data).cloned())
+        .or_else(|| rendered_conversation(html))
+        .context("no conversation found on the page")?;
+    if conversation_id(&conversation).is_none() {
+        conversation["id"] = Value::String(share_id(html).unwrap_or_else(|| content_id(html)));
+    }
+    if conversation_title(&conversation).is_none_or(|t| t.trim().is_empty())
+        && let Some(title) = page_title(html)
+    {
+        conversation["title"] = Value::String(title);
+    }
+    Ok(conversation)
+}
+
+/// The page's `__NEXT_DATA__` JSON
+fn next_data(html: &str) -> Option<Value> {
+    let start = html.find("id=\"__NEXT_DATA__\"")?;
+    let body = &html[start..];
+    let body = &body[body.find('>')? + 1..];
+    serde_json::from_str(&body[..body.find("</script>")?]).ok()
+}
+
+/// The first object holding a conversation `mapping`, however deep
+fn find_mapping(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Object(map) if map.get("mapping").is_some_and(Value::is_object) => Some(value),
+        Value::Object(map) => map.values().find_map(find_mapping),
+        Value::Array(items) => items.iter().find_map(find_mapping),
+        _ => None,
+    }
+}
+
+/// A single-branch conversation read back from the rendered messages, which
+/// carry `data-message-author-role`
+fn rendered_conversation(html: &str) -> Option<Value> {
+    const MARKER: &str = "data-message-author-role=\"";
+    let end = html.rfind("</main>").unwrap_or(html.len());
+    let starts: Vec<usize> = html[..end].match_indices(MARKER).map(|(i, _)| i).collect();
+    let mut mapping = serde_json::Map::new();
+    let mut parent: Option<String> = None;
+    for (index, &start) in starts.iter().enumerate() {
+        let rest = &html[start + MARKER.len()..];
+        let role = &rest[..rest.find('"')?];
+        let body_start = start + html[start..].find('>')? + 1;
+        let body_end = starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(end)
+            .max(body_start);
+        let text = html_text(&html[body_start..body_end]);
+        if text.is_empty() {
+            continue;
+        }
+        let id = format!("m{index}");
+        if let Some(parent) = &parent {
+            mapping[parent]["children"] = json!([id]);
+        }
+        mapping.insert(
+            id.clone(),
+            json!({
+                "id": id,
+                "parent": parent,
+                "children": [],
+                "message": {
+                    "author": { "role": role },
+                    "content": { "content_type": "text", "parts": [text] },
+                },
+            }),
+        );
+        parent = Some(id);
+    }
+    let current = parent?;
+    Some(json!({ "mapping": mapping, "current_node": current }))
+}
+
+/// The share id from the page's `/share/<id>` link
+fn share_id(html: &str) -> Option<String> {
+    html.match_indices("/share/").find_map(|(i, m)| {
+        let id: String = html[i + m.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect();
+        (id.len() >= 8).then_some(id)
+    })
+}
+
+fn page_title(html: &str) -> Option<String> {
+    let start = html.find("<title>")? + "<title>".len();
+    let end = start + html[start..].find("</title>")?;
+    let title = html_text(&html[start..end]);
+    let title = title.strip_prefix("ChatGPT - ").unwrap_or(&title).trim();
+    (!title.is_empty() && title != "ChatGPT").then(|| title.to_string())
+}
+
+/// Text of an HTML fragment: tags dropped, block ends kept as line breaks,
+/// and entities decoded
+fn html_text(html: &str) -> String {
+    const BLOCKS: &[&str] = &[
+        "p", "div", "br", "li", "pre", "tr", "h1", "h2", "h3", "h4", "h5", "h6",
+    ];
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        // Indentation of the page source between tags isn't text
+        let between = &rest[..open];
+        if !(between.trim().is_empty() && between.contains('\n')) {
+            text.push_str(&decode_entities(between));
+        }
+        let Some(close) = rest[open..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[open + 1..open + close];
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        rest = &rest[open + close + 1..];
+        if matches!(name.as_str(), "script" | "style")
+            && !tag.starts_with('/')
+            && let Some(end) = rest.find(&format!("</{name}"))
+        {
+            rest = &rest[end..];
+        } else if BLOCKS.contains(&name.as_str()) && (tag.starts_with('/') || name == "br") {
+            text.push('\n');
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if !(line.is_empty() && lines.last().is_none_or(|l| l.is_empty())) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end]);
+        let decoded = match entity {
+            Some("amp") => Some('&'),
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("quot") => Some('"'),
+            Some("apos") => Some('\''),
+            Some("nbsp") => Some(' '),
+            Some(code) if code.starts_with("#x") || code.starts_with("#X") => {
+                u32::from_str_radix(&code[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            Some(code) if code.starts_with('#') => code[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match (decoded, entity) {
+            (Some(c), Some(entity)) => {
+                out.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Resolve a ChatGPT transcript: an explicit snapshot, or the most recently
+/// imported one. Returns the snapshot path and conversation id.
+pub fn resolve_chatgpt_transcript(
+    transcript_arg: Option<PathBuf>,
+    max_age_minutes: u64,
+) -> Result<(PathBuf, Option<String>)> {
+    resolve_snapshot(
+        Tool::ChatGpt,
+        &chatgpt_snapshot_path("_")?,
+        transcript_arg,
+        max_age_minutes,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use crate::transcript::archive::Archive;
+    use crate::transcript::{extract_transcript_meta, parse_transcript};
+    use tempfile::TempDir;
+
+    /// A conversation where the user edited their second prompt: the old
+    /// branch (`b1`, `b2`) is left behind
+    const EXPORT: &str = r#"[
+        {"title":"Sourdough schedule","create_time":1735725600.0,"update_time":1735729200.5,
+         "conversation_id":"67a1-conv","current_node":"c2",
+         "mapping":{
+            "root":{"id":"root","message":null,"parent":null,"children":["sys"]},
+            "sys":{"id":"sys","parent":"root","children":["ctx"],"message":{"author":{"role":"system"},
+                "content":{"content_type":"text","parts":[""]},"metadata":{"is_visually_hidden_from_conversation":true}}},
+            "ctx":{"id":"ctx","parent":"sys","children":["u1"],"message":{"author":{"role":"user"},
+                "content":{"content_type":"user_editable_context","user_profile":"I bake"}}},
+            "u1":{"id":"u1","parent":"ctx","children":["a1"],"message":{"author":{"role":"user"},"create_time":1735725600.0,
+                "content":{"content_type":"text","parts":["When should I feed the starter?"]}}},
+            "a1":{"id":"a1","parent":"u1","children":["b1","c1"],"message":{"author":{"role":"assistant"},"create_time":1735725610.0,
+                "recipient":"all","metadata":{"model_slug":"gpt-4o"},
+                "content":{"content_type":"text","parts":["Feed it 8 hours before mixing."]}}},
+            "b1":{"id":"b1","parent":"a1","children":["b2"],"message":{"author":{"role":"user"},
+                "content":{"content_type":"text","parts":["What about rye?"]}}},
+            "b2":{"id":"b2","parent":"b1","children":[],"message":{"author":{"role":"assistant"},
+                "content":{"content_type":"text","parts":["Rye ferments faster."]}}},
+            "c1":{"id":"c1","parent":"a1","children":["t1"],"message":{"author":{"role":"user"},"create_time":1735729000.0,
+                "content":{"content_type":"multimodal_text","parts":[{"content_type":"image_asset_pointer","asset_pointer":"file-service://x"},"Is this ready?"]}}},
+            "t1":{"id":"t1","parent":"c1","children":["t2"],"message":{"author":{"role":"assistant"},"recipient":"python",
+                "content":{"content_type":"code","text":"rise = 1.8"}}},
+            "t2":{"id":"t2","parent":"t1","children":["c2"],"message":{"author":{"role":"tool","name":"python"},
+                "content":{"content_type":"execution_output","text":"1.8"}}},
+            "c2":{"id":"c2","parent":"t2","children":[],"message":{"author":{"role":"assistant"},"create_time":1735729200.5,
+                "recipient":"all","metadata":{"model_slug":"gpt-4o"},
+                "content":{"content_type":"text","parts":["Yes, it nearly doubled."]}}}
+         }},
+        {"title":"Trip ideas","update_time":1735000000.0,"id":"older-conv","mapping":{}}
+    ]"#;
+
+    fn archive() -> Archive {
+        Archive {
+            tool: Tool::ChatGpt,
+            conversations: serde_json::from_str(EXPORT).unwrap(),
+        }
+    }
+
+    #[test]
+    fn reads_the_branch_the_conversation_ended_on() {
+        let archive = archive();
+        let export = &archive.conversations;
+        let conversations = archive.list();
+        assert_eq!(conversations[0].id, "67a1-conv");
+        assert_eq!(conversations[0].title, "Sourdough schedule");
+        assert_eq!(conversations[0].messages, 6);
+        assert_eq!(conversations[1].title, "Trip ideas");
+
+        let messages = conversation_messages(&export[0]);
+        let rendered: Vec<(&str, &str)> = messages
+            .iter()
+            .map(|(m, _)| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                ("user", "When should I feed the starter?"),
+                ("assistant", "Feed it 8 hours before mixing."),
+                ("user", "[Image]\n\nIs this ready?"),
+                ("tool", "python\nrise = 1.8"),
+                ("tool", "python\n1.8"),
+                ("assistant", "Yes, it nearly doubled."),
+            ]
+        );
+        assert_eq!(messages[1].0.model.as_deref(), Some("gpt-4o"));
+        assert!(messages[0].1.is_some() && messages[3].1.is_none());
+
+        assert_eq!(archive.find("older-conv").unwrap()["title"], "Trip ideas");
+        assert_eq!(
+            archive.find("sourdough").unwrap()["title"],
+            "Sourdough schedule"
+        );
+        assert!(archive.find("pasta").is_err());
+    }
+
+    #[test]
+    fn imported_snapshot_parses_like_other_tools() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _cache = EnvGuard::set("AGENTEXPORT_CACHE_DIR", tmp.path().to_str().unwrap());
+        let archive = archive();
+
+        let (path, id) = archive.import(&archive.conversations[0]).unwrap();
+        assert_eq!(id, "67a1-conv");
+        assert_eq!(path, chatgpt_snapshot_path("67a1-conv").unwrap());
+        assert_eq!(
+            resolve_chatgpt_transcript(None, 60).unwrap(),
+            (path.clone(), Some(id))
+        );
+
+        let parsed = parse_transcript(&path).unwrap();
+        assert_eq!(parsed.messages.len(), 6);
+        assert_eq!(parsed.dominant_model().as_deref(), Some("gpt-4o"));
+        assert!(parsed.timeline().is_some());
+        let meta = extract_transcript_meta(&path);
+        assert_eq!(meta.title(None).as_deref(), Some("Sourdough schedule"));
+        assert!(archive.import(&archive.conversations[1]).is_err());
+    }
+
+    #[test]
+    fn reads_saved_share_pages() {
+        let data = json!({
+            "props": { "pageProps": { "serverResponse": { "data": {
+                "title": "Sourdough schedule",
+                "mapping": serde_json::from_str::<Vec<Value>>(EXPORT).unwrap()[0]["mapping"],
+                "current_node": "c2",
+            }}}}
+        });
+        let page = format!(
+            r#"<html><head><title>ChatGPT - Sourdough schedule</title>
+            <link rel="canonical" href="https://chatgpt.com/share/6791ab-share-id"></head>
+            <body><script id="__NEXT_DATA__" type="application/json">{data}</script></body></html>"#
+        );
+        let conversation = read_share_page(&page).unwrap();
+        assert_eq!(conversation_id(&conversation), Some("6791ab-share-id"));
+        assert_eq!(conversation_messages(&conversation).len(), 6);
+
+        // Without the page data, only the rendered messages are left
+        let page = r#"<html><head><title>ChatGPT - Knots &amp; hitches</title></head><body><main>
+            <div data-message-author-role="user"><div>How do I tie a bowline?</div></div>
+            <div data-message-author-role="assistant"><p>Make a loop.</p><p>Pass the end &lt;up&gt; through it.</p>
+            <pre><code>loop
+  end</code></pre></div>
+            </main><footer>Report content</footer></body></html>"#;
+        let conversation = read_share_page(page).unwrap();
+        assert_eq!(conversation_title(&conversation), Some("Knots & hitches"));
+        assert_eq!(conversation_id(&conversation).unwrap().len(), 16);
+        let rendered: Vec<(String, String)> = conversation_messages(&conversation)
+            .into_iter()
+            .map(|(m, _)| (m.role, m.content))
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                ("user".to_string(), "How do I tie a bowline?".to_string()),
+                (
+                    "assistant".to_string(),
+                    "Make a loop.\nPass the end <up> through it.\nloop\n  end".to_string()
+                ),
+            ]
+        );
+        assert!(read_share_page("<html><body>Not found</body></html>").is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Claude.ai chat import. The account export's `conversations.json` is an
+//! array of chats, each with a `name` and its `chat_messages` in order; a
+//! message's `sender` is `human` or `assistant`, and its `content` blocks
+//! hold text, thinking, and tool calls and results. Sharing tools save a
+//! single chat in a looser shape, `{title, messages: [{role, content}]}`,
+//! which is read the same way.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::archive::{content_id, message, resolve_snapshot};
+use super::discovery::cache_dir;
+use super::parser::truncate;
+use super::types::{RenderedMessage, Tool};
+
+/// Snapshot path for chat `id`, whether or not it was imported yet
+pub fn claude_ai_snapshot_path(id: &str) -> Result<PathBuf> {
+    Ok(cache_dir()?
+        .join("agentexport")
+        .join("claude-ai")
+        .join(format!("{id}.jsonl")))
+}
+
+pub(super) fn conversation_id(conversation: &Value) -> Option<&str> {
+    ["uuid", "id", "conversation_id"]
+        .iter()
+        .find_map(|key| conversation.get(*key).and_then(|v| v.as_str()))
+}
+
+/// `conversation` with an id of its own, hashed from its contents when the
+/// tool that saved it left it out
+pub(super) fn with_id(mut conversation: Value) -> Value {
+    if conversation_id(&conversation).is_none() && conversation.is_object() {
+        conversation["uuid"] = Value::String(content_id(&conversation.to_string()));
+    }
+    conversation
+}
+
+pub(super) fn conversation_title(conversation: &Value) -> Option<&str> {
+    conversation
+        .get("name")
+        .or_else(|| conversation.get("title"))
+        .and_then(|v| v.as_str())
+}
+
+fn timestamp(value: Option<&Value>) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(value?.as_str()?, &Rfc3339).ok()
+}
+
+pub(super) fn updated_at(conversation: &Value) -> Option<OffsetDateTime> {
+    timestamp(
+        conversation
+            .get("updated_at")
+            .or_else(|| conversation.get("created_at")),
+    )
+}
+
+/// Text of a tool result's `content`: a string, or a list of text blocks
+fn result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Rendered messages for one chat message: its text, with thinking and each
+/// tool call or result split out in order
+fn chat_message(value: &Value, model: Option<&str>) -> Vec<RenderedMessage> {
+    let sender = value
+        .get("sender")
+        .or_else(|| value.get("role"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let role = match sender {
+        "human" | "user" => "user",
+        "assistant" => "assistant",
+        _ => return Vec::new(),
+    };
+    let model = value
+        .get("model")
+        .and_then(|v| v.as_str())
+        .or(model)
+        .filter(|_| role == "assistant");
+
+    let mut rendered = Vec::new();
+    let mut text: Vec<String> = Vec::new();
+    let flush = |text: &mut Vec<String>, rendered: &mut Vec<RenderedMessage>| {
+        let joined = text.join("\n\n");
+        if !joined.trim().is_empty() {
+            rendered.push(message(role, joined, model));
+        }
+        text.clear();
+    };
+    match value.get("content") {
+        Some(Value::Array(blocks)) if !blocks.is_empty() => {
+            for block in blocks {
+                let field = |key: &str| block.get(key).and_then(|v| v.as_str()).unwrap_or("");
+                match field("type") {
+                    "text" => text.push(field("text").to_string()),
+                    "image" => text.push("[Image]".to_string()),
+                    "thinking" if !field("thinking").trim().is_empty() => {
+                        flush(&mut text, &mut rendered);
+                        rendered.push(message("thinking", field("thinking").to_string(), model));
+                    }
+                    "tool_use" => {
+                        flush(&mut text, &mut rendered);
+                        let input = block
+                            .get("input")
+                            .map(|v| serde_json::to_string_pretty(v).unwrap_or_default())
+                            .unwrap_or_default();
+                        let mut call = message(
+                            "tool",
+                            format!("{}\n{}", field("name"), truncate(&input, 2000)),
+                            None,
+                        );
+                        call.raw = Some(truncate(&input, 20000));
+                        call.raw_label = Some("Call".to_string());
+                        rendered.push(call);
+                    }
+                    "tool_result" => {
+                        flush(&mut text, &mut rendered);
+                        let body = result_text(block.get("content"));
+                        let name = Some(field("name")).filter(|n| !n.is_empty());
+                        let mut result = message(
+                            "tool",
+                            format!("{}\n{}", name.unwrap_or("tool"), truncate(&body, 2000)),
+                            None,
+                        );
+                        result.raw = Some(truncate(&body, 20000));
+                        result.raw_label = Some("Result".to_string());
+                        rendered.push(result);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some(Value::String(content)) => text.push(content.clone()),
+        _ => {
+            if let Some(body) = value.get("text").and_then(|v| v.as_str()) {
+                text.push(body.to_string());
+            }
+        }
+    }
+    // Uploaded files aren't in the export, only their names
+    for key in ["attachments", "files"] {
+        for file in value
+            .get(key)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(name) = file.get("file_name").and_then(|v| v.as_str()) {
+                text.push(format!("[Attachment: {name}]"));
+            }
+        }
+    }
+    flush(&mut text, &mut rendered);
+    rendered
+}
+
+/// The chat's messages, oldest first, with the time each was sent
+pub(super) fn conversation_messages(
+    conversation: &Value,
+) -> Vec<(RenderedMessage, Option<OffsetDateTime>)> {
+    let model = conversation.get("model").and_then(|v| v.as_str());
+    conversation
+        .get("chat_messages")
+        .or_else(|| conversation.get("messages"))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .flat_map(|value| {
+            let at = timestamp(value.get("created_at"));
+            chat_message(value, model)
+                .into_iter()
+                .map(move |message| (message, at))
+        })
+        .collect()
+}
+
+/// Resolve a Claude.ai transcript: an explicit snapshot, or the most recently
+/// imported one. Returns the snapshot path and chat id.
+pub fn resolve_claude_ai_transcript(
+    transcript_arg: Option<PathBuf>,
+    max_age_minutes: u64,
+) -> Result<(PathBuf, Option<String>)> {
+    resolve_snapshot(
+        Tool::ClaudeAi,
+        &claude_ai_snapshot_path("_")?,
+        transcript_arg,
+        max_age_minutes,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use crate::transcript::archive::Archive;
+    use crate::transcript::{extract_transcript_meta, parse_transcript};
+    use tempfile::TempDir;
+
+    const EXPORT: &str = r#"[
+        {"uuid":"28e1-chat","name":"Regex for dates","created_at":"2025-03-01T09:00:00Z",
+         "updated_at":"2025-03-01T09:05:00Z","chat_messages":[
+            {"sender":"human","created_at":"2025-03-01T09:00:00Z","text":"Match ISO dates",
+             "content":[{"type":"text","text":"Match ISO dates"}],
+             "attachments":[{"file_name":"samples.txt","extracted_content":"2025-01-01"}],"files":[]},
+            {"sender":"assistant","created_at":"2025-03-01T09:00:10Z","content":[
+                {"type":"thinking","thinking":"Four digits, dash, two, dash, two."},
+                {"type":"text","text":"Let me check the samples."},
+                {"type":"tool_use","name":"repl","input":{"code":"test(samples)"}},
+                {"type":"tool_result","name":"repl","content":[{"type":"text","text":"1 match"}]},
+                {"type":"text","text":"Use \\d{4}-\\d{2}-\\d{2}."}
+            ]}
+         ]},
+        {"uuid":"old-chat","name":"Empty","updated_at":"2024-01-01T00:00:00Z","chat_messages":[]}
+    ]"#;
+
+    #[test]
+    fn imports_export_chats_in_order() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _cache = EnvGuard::set("AGENTEXPORT_CACHE_DIR", tmp.path().to_str().unwrap());
+        let archive = Archive {
+            tool: Tool::ClaudeAi,
+            conversations: serde_json::from_str(EXPORT).unwrap(),
+        };
+        let listed = archive.list();
+        assert_eq!(
+            (listed[0].id.as_str(), listed[0].messages),
+            ("28e1-chat", 6)
+        );
+        assert_eq!(listed[1].title, "Empty");
+
+        let messages = conversation_messages(&archive.conversations[0]);
+        let rendered: Vec<(&str, &str)> = messages
+            .iter()
+            .map(|(m, _)| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                ("user", "Match ISO dates\n\n[Attachment: samples.txt]"),
+                ("thinking", "Four digits, dash, two, dash, two."),
+                ("assistant", "Let me check the samples."),
+                ("tool", "repl\n{\n  \"code\": \"test(samples)\"\n}"),
+                ("tool", "repl\n1 match"),
+                ("assistant", "Use \\d{4}-\\d{2}-\\d{2}."),
+            ]
+        );
+
+        let (path, id) = archive.import(archive.find("regex").unwrap()).unwrap();
+        assert_eq!(path, claude_ai_snapshot_path("28e1-chat").unwrap());
+        assert_eq!(
+            resolve_claude_ai_transcript(None, 60).unwrap(),
+            (path.clone(), Some(id))
+        );
+        let parsed = parse_transcript(&path).unwrap();
+        assert_eq!(parsed.messages.len(), 6);
+        assert!(parsed.timeline().is_some());
+        let meta = extract_transcript_meta(&path);
+        assert_eq!(meta.title(None).as_deref(), Some("Regex for dates"));
+        assert!(archive.import(&archive.conversations[1]).is_err());
+    }
+
+    #[test]
+    fn reads_chats_saved_by_sharing_tools() {
+        let shared = with_id(serde_json::json!({
+            "title": "Naming a cat",
+            "model": "claude-3-5-sonnet",
+            "messages": [
+                {"role": "user", "content": "Ideas for a grey cat?"},
+                {"role": "assistant", "content": "Ash, Smoke, or Pebble."},
+                {"role": "system", "content": "ignored"}
+            ]
+        }));
+        assert_eq!(
+            conversation_id(&shared),
+            conversation_id(&with_id(shared.clone()))
+        );
+        let messages = conversation_messages(&shared);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0.model, None);
+        assert_eq!(messages[1].0.model.as_deref(), Some("claude-3-5-sonnet"));
+        assert_eq!(messages[1].0.content, "Ash, Smoke, or Pebble.");
+    }
+}
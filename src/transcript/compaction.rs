@@ -0,0 +1,211 @@
+//! Claude sessions split by compaction. When Claude compacts or resumes a
+//! session into a new file, the new file continues the old one: its first
+//! entries point back with a `parentUuid` (or a compaction boundary's
+//! `logicalParentUuid`) that isn't in the file, or open with a `summary`
+//! whose `leafUuid` is the last message of the earlier file. Following those
+//! links through the project folder recovers the whole history.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::discovery::cache_dir;
+
+/// The uuid in an earlier session file that `path` continues from, if any
+fn continued_from(path: &Path) -> Result<Option<String>> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut own = HashSet::new();
+    let mut links = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let Ok(value) = serde_json::from_str::<Value>(&line?) else {
+            continue;
+        };
+        if let Some(uuid) = value.get("uuid").and_then(|v| v.as_str()) {
+            own.insert(uuid.to_string());
+        }
+        let link = if value.get("type").and_then(|v| v.as_str()) == Some("summary") {
+            value.get("leafUuid")
+        } else {
+            ["parentUuid", "logicalParentUuid"]
+                .iter()
+                .find_map(|key| value.get(*key).filter(|v| v.is_string()))
+        };
+        if let Some(link) = link.and_then(|v| v.as_str()) {
+            links.push(link.to_string());
+        }
+    }
+    // Summaries can also name a leaf of their own file
+    Ok(links.into_iter().find(|link| !own.contains(link)))
+}
+
+/// The session file in `dir` holding event `uuid`, newest first, skipping `seen`
+fn file_with_event(dir: &Path, uuid: &str, seen: &HashSet<PathBuf>) -> Result<Option<PathBuf>> {
+    let mut candidates: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "jsonl" || seen.contains(&path) {
+                return None;
+            }
+            Some((fs::metadata(&path).ok()?.modified().ok()?, path))
+        })
+        .collect();
+    candidates.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    let needle = format!("\"uuid\":\"{uuid}\"");
+    for (_, path) in candidates {
+        let file = File::open(&path)?;
+        for line in BufReader::new(file).lines() {
+            if line?.contains(&needle) {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The session files that `path` was compacted or resumed from, oldest first
+/// and ending with `path` itself. A session that was never split is just
+/// `[path]`.
+pub fn compaction_chain(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = path.parent().context("transcript has no parent folder")?;
+    let mut chain = vec![path.to_path_buf()];
+    let mut seen: HashSet<PathBuf> = chain.iter().cloned().collect();
+    while let Some(uuid) = continued_from(&chain[0])? {
+        let Some(earlier) = file_with_event(dir, &uuid, &seen)? else {
+            break;
+        };
+        seen.insert(earlier.clone());
+        chain.insert(0, earlier);
+    }
+    Ok(chain)
+}
+
+/// Stitch a compaction chain into one transcript under the cache dir, named
+/// after the last file so its session id still matches. Events repeated in a
+/// later file are kept once, and a later file's root events are re-parented
+/// onto the event it continues from, so the history reads as one branch.
+pub fn stitch_compaction_chain(chain: &[PathBuf]) -> Result<PathBuf> {
+    let last = chain.last().context("empty compaction chain")?;
+    let out = cache_dir()?
+        .join("agentexport")
+        .join("claude-stitched")
+        .join(last.file_name().context("transcript has no file name")?);
+    fs::create_dir_all(out.parent().context("stitched path has no parent")?)?;
+    let mut writer = std::io::BufWriter::new(File::create(&out)?);
+    let mut seen = HashSet::new();
+    for (index, path) in chain.iter().enumerate() {
+        let link = if index == 0 {
+            None
+        } else {
+            continued_from(path)?
+        };
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let Ok(mut value) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if let Some(uuid) = value.get("uuid").and_then(|v| v.as_str())
+                && !seen.insert(uuid.to_string())
+            {
+                continue;
+            }
+            let is_root = value.get("parentUuid").is_some_and(Value::is_null)
+                && value.get("logicalParentUuid").is_none_or(Value::is_null);
+            if is_root && let Some(link) = &link {
+                value["parentUuid"] = Value::String(link.clone());
+                writeln!(writer, "{value}")?;
+            } else {
+                writeln!(writer, "{line}")?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use crate::transcript::parse_transcript;
+    use tempfile::TempDir;
+
+    fn write_session(dir: &Path, name: &str, lines: &[&str]) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, lines.join("\n")).unwrap();
+        path
+    }
+
+    #[test]
+    fn stitches_sessions_split_by_compaction() {
+        let _lock = env_lock();
+        let project = TempDir::new().unwrap();
+        let cache = TempDir::new().unwrap();
+        let _guard = EnvGuard::set("AGENTEXPORT_CACHE_DIR", cache.path().to_str().unwrap());
+
+        write_session(
+            project.path(),
+            "first.jsonl",
+            &[
+                r#"{"type":"user","uuid":"u1","parentUuid":null,"message":{"content":"Write a parser"}}"#,
+                r#"{"type":"assistant","uuid":"a1","parentUuid":"u1","message":{"id":"m1","content":[{"type":"text","text":"Here it is"}]}}"#,
+            ],
+        );
+        write_session(
+            project.path(),
+            "second.jsonl",
+            &[
+                r#"{"type":"summary","summary":"Parser work","leafUuid":"a1"}"#,
+                r#"{"type":"user","uuid":"u2","parentUuid":null,"message":{"content":"Make it faster"}}"#,
+                r#"{"type":"assistant","uuid":"a2","parentUuid":"u2","message":{"id":"m2","content":[{"type":"text","text":"Faster now"}]}}"#,
+            ],
+        );
+        let third = write_session(
+            project.path(),
+            "third.jsonl",
+            &[
+                // A resumed session repeats the event it continues from
+                r#"{"type":"assistant","uuid":"a2","parentUuid":"u2","message":{"id":"m2","content":[{"type":"text","text":"Faster now"}]}}"#,
+                r#"{"type":"user","uuid":"u3","parentUuid":"a2","message":{"content":"Add tests"}}"#,
+            ],
+        );
+        write_session(
+            project.path(),
+            "unrelated.jsonl",
+            &[r#"{"type":"user","uuid":"x1","parentUuid":null,"message":{"content":"Hi"}}"#],
+        );
+
+        let chain = compaction_chain(&third).unwrap();
+        let names: Vec<_> = chain
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["first.jsonl", "second.jsonl", "third.jsonl"]);
+        assert_eq!(compaction_chain(&chain[0]).unwrap(), [chain[0].clone()]);
+
+        let stitched = stitch_compaction_chain(&chain).unwrap();
+        assert_eq!(stitched.file_name(), third.file_name());
+        assert!(stitched.starts_with(cache.path()));
+        let parsed = parse_transcript(&stitched).unwrap();
+        let texts: Vec<_> = parsed
+            .messages
+            .iter()
+            .filter(|m| !m.abandoned)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "Write a parser",
+                "Here it is",
+                "**Session Summary:** Parser work",
+                "Make it faster",
+                "Faster now",
+                "Add tests"
+            ]
+        );
+    }
+}
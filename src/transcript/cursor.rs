@@ -0,0 +1,547 @@
+//! Cursor discovery. Cursor keeps agent chats ("composers") in SQLite rather
+//! than JSONL: each workspace's `state.vscdb` lists the chats of that folder,
+//! and the global `state.vscdb` holds their messages ("bubbles").
+//!
+//! The newest chat for the cwd is converted to rendered messages and written
+//! as a JSONL snapshot under the cache dir, which is then gzipped, parsed, and
+//! published like any other transcript. The databases are read with the
+//! `sqlite3` command-line tool.
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::discovery::{cache_dir, is_fresh};
+use super::parser::truncate;
+use super::types::RenderedMessage;
+
+/// Bubble `type` of a user message (assistant bubbles are 2)
+const USER_BUBBLE: u64 = 1;
+
+/// Cursor's `User` folder, holding `workspaceStorage` and `globalStorage`
+pub fn cursor_user_dir() -> Result<PathBuf> {
+    if let Ok(dir) = std::env::var("AGENTEXPORT_CURSOR_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = PathBuf::from(std::env::var("HOME").context("HOME not set")?);
+    if cfg!(target_os = "macos") {
+        return Ok(home.join("Library/Application Support/Cursor/User"));
+    }
+    let config = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| home.join(".config"));
+    Ok(config.join("Cursor").join("User"))
+}
+
+/// Snapshot path for chat `id`, whether or not it was exported yet
+pub fn cursor_snapshot_path(id: &str) -> Result<PathBuf> {
+    Ok(cache_dir()?
+        .join("agentexport")
+        .join("cursor")
+        .join(format!("{id}.jsonl")))
+}
+
+/// Run a query against a database opened read-only, one JSON object per row
+fn query(db: &Path, sql: &str) -> Result<Vec<Value>> {
+    let output = Command::new("sqlite3")
+        .arg("-readonly")
+        .arg("-json")
+        .arg(db)
+        .arg(sql)
+        .output()
+        .context("Failed to run sqlite3, which is needed to read Cursor chats")?;
+    if !output.status.success() {
+        bail!(
+            "sqlite3 failed on {}: {}",
+            db.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&stdout).context("Unexpected sqlite3 output")
+}
+
+/// Quote a string as an SQL literal
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// The JSON value stored under `key` in a key-value table
+fn lookup(db: &Path, table: &str, key: &str) -> Result<Option<Value>> {
+    let sql = format!(
+        "SELECT CAST(value AS TEXT) AS value FROM {table} WHERE key = {}",
+        sql_string(key)
+    );
+    query(db, &sql)?
+        .first()
+        .and_then(|row| row.get("value"))
+        .and_then(|v| v.as_str())
+        .map(serde_json::from_str)
+        .transpose()
+        .with_context(|| format!("Failed to parse {key} in {}", db.display()))
+}
+
+/// Decode `%XX` escapes in a URI path
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Folder a workspace was opened on, from the `file://` URI in `workspace.json`
+fn workspace_folder(dir: &Path) -> Option<String> {
+    let text = fs::read_to_string(dir.join("workspace.json")).ok()?;
+    let value: Value = serde_json::from_str(&text).ok()?;
+    let uri = value.get("folder")?.as_str()?;
+    let path = percent_decode(uri.strip_prefix("file://")?);
+    Some(path.trim_end_matches('/').to_string())
+}
+
+/// A chat listed in a workspace database
+#[derive(Debug)]
+struct Composer {
+    id: String,
+    name: Option<String>,
+    /// Last update, in milliseconds since the epoch
+    updated_ms: u64,
+}
+
+fn workspace_composers(db: &Path) -> Result<Vec<Composer>> {
+    let Some(data) = lookup(db, "ItemTable", "composer.composerData")? else {
+        return Ok(Vec::new());
+    };
+    let composers = data
+        .get("allComposers")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|c| {
+            Some(Composer {
+                id: c.get("composerId")?.as_str()?.to_string(),
+                name: c
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .filter(|n| !n.trim().is_empty())
+                    .map(str::to_string),
+                updated_ms: c
+                    .get("lastUpdatedAt")
+                    .or_else(|| c.get("createdAt"))
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0),
+            })
+        })
+        .collect();
+    Ok(composers)
+}
+
+/// The most recently updated chat of the workspaces opened on `cwd`
+fn newest_composer_for_cwd(
+    user_dir: &Path,
+    cwd: &str,
+    max_age_minutes: u64,
+) -> Result<Option<Composer>> {
+    let storage = user_dir.join("workspaceStorage");
+    if !storage.exists() {
+        return Ok(None);
+    }
+    let mut newest: Option<Composer> = None;
+    for entry in fs::read_dir(&storage)? {
+        let dir = entry?.path();
+        let db = dir.join("state.vscdb");
+        if workspace_folder(&dir).as_deref() != Some(cwd) || !db.is_file() {
+            continue;
+        }
+        for composer in workspace_composers(&db)? {
+            let updated = UNIX_EPOCH + Duration::from_millis(composer.updated_ms);
+            if is_fresh(updated, max_age_minutes)
+                && newest
+                    .as_ref()
+                    .is_none_or(|n| composer.updated_ms > n.updated_ms)
+            {
+                newest = Some(composer);
+            }
+        }
+    }
+    Ok(newest)
+}
+
+/// Chat `id`'s data and bubbles, oldest first. Older Cursor versions keep the
+/// bubbles inline; newer ones store each under `bubbleId:<chat>:<bubble>`.
+fn composer_bubbles(global_db: &Path, id: &str) -> Result<(Value, Vec<Value>)> {
+    let Some(data) = lookup(global_db, "cursorDiskKV", &format!("composerData:{id}"))? else {
+        bail!("Cursor chat {id} not found in {}", global_db.display());
+    };
+    if let Some(inline) = data
+        .get("conversation")
+        .and_then(|v| v.as_array())
+        .filter(|c| !c.is_empty())
+    {
+        let bubbles = inline.clone();
+        return Ok((data, bubbles));
+    }
+
+    // A key range rather than LIKE, so `_` and `%` in ids match literally
+    let sql = format!(
+        "SELECT key, CAST(value AS TEXT) AS value FROM cursorDiskKV WHERE key >= {} AND key < {}",
+        sql_string(&format!("bubbleId:{id}:")),
+        sql_string(&format!("bubbleId:{id};")),
+    );
+    let mut stored: HashMap<String, Value> = HashMap::new();
+    for row in query(global_db, &sql)? {
+        let (Some(key), Some(value)) = (
+            row.get("key").and_then(|v| v.as_str()),
+            row.get("value").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        if let (Some((_, bubble_id)), Ok(bubble)) =
+            (key.rsplit_once(':'), serde_json::from_str(value))
+        {
+            stored.insert(bubble_id.to_string(), bubble);
+        }
+    }
+    let bubbles = data
+        .get("fullConversationHeadersOnly")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|header| stored.remove(header.get("bubbleId")?.as_str()?))
+        .collect();
+    Ok((data, bubbles))
+}
+
+fn message(role: &str, content: String, model: Option<&str>) -> RenderedMessage {
+    RenderedMessage {
+        role: role.to_string(),
+        content,
+        raw: None,
+        raw_label: None,
+        tool_use_id: None,
+        model: model.map(str::to_string),
+        abandoned: false,
+        part: None,
+        id: None,
+        highlighted: false,
+        images: Vec::new(),
+    }
+}
+
+/// Rendered messages for one bubble: a user prompt, or the assistant's
+/// thinking, reply, and tool call
+fn bubble_messages(bubble: &Value, model: Option<&str>) -> Vec<RenderedMessage> {
+    let text = |pointer: &str| {
+        bubble
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+    };
+    let mut messages = Vec::new();
+    if bubble.get("type").and_then(|v| v.as_u64()) == Some(USER_BUBBLE) {
+        messages.extend(text("/text").map(|t| message("user", t, None)));
+        return messages;
+    }
+
+    messages.extend(text("/thinking/text").map(|t| message("thinking", t, model)));
+    messages.extend(text("/text").map(|t| message("assistant", t, model)));
+    if let Some(tool) = bubble.get("toolFormerData").filter(|t| t.is_object()) {
+        let name = tool.get("name").and_then(|v| v.as_str()).unwrap_or("tool");
+        let args = tool
+            .get("rawArgs")
+            .or_else(|| tool.get("params"))
+            .and_then(|v| v.as_str())
+            .map(|raw| {
+                serde_json::from_str::<Value>(raw)
+                    .and_then(|v| serde_json::to_string_pretty(&v))
+                    .unwrap_or_else(|_| raw.to_string())
+            });
+        let content = match args {
+            Some(args) => format!("{name}\n{}", truncate(&args, 2000)),
+            None => name.to_string(),
+        };
+        let raw = tool
+            .get("result")
+            .and_then(|v| v.as_str())
+            .map(|r| truncate(r, 20000));
+        messages.push(RenderedMessage {
+            role: "tool".to_string(),
+            content,
+            raw_label: raw.as_ref().map(|_| "Results".to_string()),
+            raw,
+            tool_use_id: tool
+                .get("toolCallId")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            model: None,
+            abandoned: false,
+            part: None,
+            id: None,
+            highlighted: false,
+            images: Vec::new(),
+        });
+    }
+    messages
+}
+
+/// Write a chat snapshot: a `cursor_session` header, then one
+/// `cursor_message` line per rendered message. The chat name goes in `slug`
+/// so it becomes the default title, as Claude's session slug does.
+fn write_snapshot(
+    path: &Path,
+    composer: &Composer,
+    cwd: &str,
+    messages: &[RenderedMessage],
+) -> Result<()> {
+    fs::create_dir_all(path.parent().context("snapshot path has no parent")?)?;
+    let mut file = File::create(path)
+        .with_context(|| format!("Failed to write Cursor snapshot {}", path.display()))?;
+    let header = json!({
+        "type": "cursor_session",
+        "id": composer.id,
+        "slug": composer.name,
+        "cwd": cwd,
+    });
+    writeln!(file, "{header}")?;
+    for message in messages {
+        writeln!(
+            file,
+            "{}",
+            json!({ "type": "cursor_message", "message": message })
+        )?;
+    }
+    // Stamp the chat's own update time so freshness checks see the chat's age
+    file.set_modified(UNIX_EPOCH + Duration::from_millis(composer.updated_ms))?;
+    Ok(())
+}
+
+/// Export chat `composer` from Cursor's databases to its snapshot path
+fn export_composer(user_dir: &Path, composer: &Composer, cwd: &str) -> Result<PathBuf> {
+    let global_db = user_dir.join("globalStorage").join("state.vscdb");
+    let (data, bubbles) = composer_bubbles(&global_db, &composer.id)?;
+    let model = data
+        .pointer("/modelConfig/modelName")
+        .and_then(|v| v.as_str())
+        .filter(|m| !m.is_empty() && *m != "default");
+    let messages: Vec<RenderedMessage> = bubbles
+        .iter()
+        .flat_map(|bubble| bubble_messages(bubble, model))
+        .collect();
+    if messages.is_empty() {
+        bail!("Cursor chat {} has no messages", composer.id);
+    }
+    let path = cursor_snapshot_path(&composer.id)?;
+    write_snapshot(&path, composer, cwd, &messages)?;
+    Ok(path)
+}
+
+/// Resolve a Cursor transcript: an explicit snapshot path, or the newest chat
+/// for the cwd exported to a fresh snapshot. Returns the path and chat id.
+pub fn resolve_cursor_transcript(
+    transcript_arg: Option<PathBuf>,
+    max_age_minutes: u64,
+) -> Result<(PathBuf, Option<String>)> {
+    if let Some(path) = transcript_arg {
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(str::to_string);
+        return Ok((path, id));
+    }
+
+    let cwd = std::env::current_dir()
+        .ok()
+        .and_then(|path| path.to_str().map(|s| s.to_string()))
+        .context("unable to resolve cwd; pass --transcript")?;
+    let user_dir = cursor_user_dir()?;
+    let Some(composer) = newest_composer_for_cwd(&user_dir, &cwd, max_age_minutes)? else {
+        bail!(
+            "no recent Cursor chat found for current directory; open this folder in Cursor, or pass --transcript"
+        );
+    };
+    let path = export_composer(&user_dir, &composer, &cwd)?;
+    Ok((path, Some(composer.id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{DirGuard, EnvGuard, env_lock};
+    use crate::transcript::{extract_transcript_meta, parse_transcript};
+    use tempfile::TempDir;
+
+    #[test]
+    fn bubbles_become_rendered_messages() {
+        let user = json!({"type": 1, "text": "  Rename the parser  "});
+        let messages = bubble_messages(&user, Some("gpt-5"));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            (messages[0].role.as_str(), messages[0].content.as_str()),
+            ("user", "Rename the parser")
+        );
+        assert_eq!(messages[0].model, None);
+
+        let assistant = json!({
+            "type": 2,
+            "text": "Done.",
+            "thinking": {"text": "Look at src/parser.rs first"},
+            "toolFormerData": {
+                "name": "read_file",
+                "toolCallId": "call_1",
+                "rawArgs": "{\"target_file\":\"src/parser.rs\"}",
+                "result": "fn parse() {}"
+            }
+        });
+        let messages = bubble_messages(&assistant, Some("gpt-5"));
+        let roles: Vec<_> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["thinking", "assistant", "tool"]);
+        assert_eq!(messages[1].model.as_deref(), Some("gpt-5"));
+        assert!(messages[2].content.starts_with("read_file\n{"));
+        assert!(
+            messages[2]
+                .content
+                .contains("\"target_file\": \"src/parser.rs\"")
+        );
+        assert_eq!(messages[2].raw.as_deref(), Some("fn parse() {}"));
+        assert_eq!(messages[2].tool_use_id.as_deref(), Some("call_1"));
+
+        assert!(bubble_messages(&json!({"type": 2, "text": " "}), None).is_empty());
+        assert_eq!(
+            percent_decode("/Users/me/My%20Project"),
+            "/Users/me/My Project"
+        );
+    }
+
+    fn sqlite(db: &Path, sql: &str) {
+        let status = Command::new("sqlite3").arg(db).arg(sql).status().unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn exports_newest_chat_for_cwd() {
+        if Command::new("sqlite3").arg("-version").output().is_err() {
+            eprintln!("skipping: sqlite3 not installed");
+            return;
+        }
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let user_dir = tmp.path().join("User");
+        let project = tmp.path().join("my project");
+        fs::create_dir_all(&project).unwrap();
+        let project = project.canonicalize().unwrap();
+        let _cursor = EnvGuard::set("AGENTEXPORT_CURSOR_DIR", user_dir.to_str().unwrap());
+        let _cache = EnvGuard::set("AGENTEXPORT_CACHE_DIR", tmp.path().to_str().unwrap());
+        let _cwd = DirGuard::set(&project).unwrap();
+
+        let workspace = user_dir.join("workspaceStorage").join("abc123");
+        fs::create_dir_all(&workspace).unwrap();
+        let folder = project.display().to_string().replace(' ', "%20");
+        fs::write(
+            workspace.join("workspace.json"),
+            json!({ "folder": format!("file://{folder}") }).to_string(),
+        )
+        .unwrap();
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let composers = json!({"allComposers": [
+            {"composerId": "old-chat", "name": "Old", "lastUpdatedAt": now_ms - 60_000},
+            {"composerId": "new-chat", "name": "Parser rename", "lastUpdatedAt": now_ms},
+        ]});
+        sqlite(
+            &workspace.join("state.vscdb"),
+            &format!(
+                "CREATE TABLE ItemTable (key TEXT UNIQUE, value BLOB); INSERT INTO ItemTable VALUES ('composer.composerData', {});",
+                sql_string(&composers.to_string())
+            ),
+        );
+
+        let global = user_dir.join("globalStorage");
+        fs::create_dir_all(&global).unwrap();
+        let data = json!({
+            "composerId": "new-chat",
+            "modelConfig": {"modelName": "claude-4-sonnet"},
+            "fullConversationHeadersOnly": [
+                {"bubbleId": "b1", "type": 1},
+                {"bubbleId": "b2", "type": 2}
+            ]
+        });
+        let rows = [
+            ("composerData:new-chat".to_string(), data),
+            (
+                "bubbleId:new-chat:b2".to_string(),
+                json!({"type": 2, "text": "Renamed it."}),
+            ),
+            (
+                "bubbleId:new-chat:b1".to_string(),
+                json!({"type": 1, "text": "Rename the parser's 'main'"}),
+            ),
+            (
+                "bubbleId:other-chat:b9".to_string(),
+                json!({"type": 1, "text": "Unrelated"}),
+            ),
+        ];
+        let inserts: String = rows
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "INSERT INTO cursorDiskKV VALUES ({}, {});",
+                    sql_string(key),
+                    sql_string(&value.to_string())
+                )
+            })
+            .collect();
+        sqlite(
+            &global.join("state.vscdb"),
+            &format!("CREATE TABLE cursorDiskKV (key TEXT UNIQUE, value BLOB); {inserts}"),
+        );
+
+        let (path, id) = resolve_cursor_transcript(None, 10).unwrap();
+        assert_eq!(id.as_deref(), Some("new-chat"));
+        assert_eq!(path, cursor_snapshot_path("new-chat").unwrap());
+
+        let parsed = parse_transcript(&path).unwrap();
+        let messages: Vec<_> = parsed
+            .messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("user", "Rename the parser's 'main'"),
+                ("assistant", "Renamed it.")
+            ]
+        );
+        assert_eq!(parsed.messages[1].model.as_deref(), Some("claude-4-sonnet"));
+        let meta = extract_transcript_meta(&path);
+        assert_eq!(meta.title(None).as_deref(), Some("Parser rename"));
+        assert_eq!(meta.cwd.as_deref(), project.to_str());
+
+        let _elsewhere = DirGuard::set(tmp.path()).unwrap();
+        assert!(resolve_cursor_transcript(None, 10).is_err());
+    }
+}
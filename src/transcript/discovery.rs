@@ -1,15 +1,23 @@
-//! Transcript discovery: finding transcripts by cwd for Claude and Codex.
+//! Transcript discovery: finding transcripts by cwd for Claude and Codex
+//! (Cursor and Aider chats are exported by the `cursor` and `aider` modules,
+//! ChatGPT and Claude.ai conversations imported through `archive`).
 
 use anyhow::{Context, Result, bail};
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+use super::aider::{aider_snapshot_path, resolve_aider_transcript};
+use super::chatgpt::{chatgpt_snapshot_path, resolve_chatgpt_transcript};
+use super::claude_ai::{claude_ai_snapshot_path, resolve_claude_ai_transcript};
+use super::cursor::{cursor_snapshot_path, resolve_cursor_transcript};
+use super::source::Discovered;
 use super::types::Tool;
 
 /// Metadata from Codex session_meta event
@@ -55,16 +63,17 @@ pub fn codex_sessions_dir() -> Result<PathBuf> {
 
 /// Get the Codex home directory
 pub fn codex_home_dir() -> Result<PathBuf> {
-    if let Ok(dir) = std::env::var("CODEX_HOME") {
-        if !dir.trim().is_empty() {
-            return Ok(PathBuf::from(dir));
-        }
+    if let Ok(dir) = std::env::var("CODEX_HOME")
+        && !dir.trim().is_empty()
+    {
+        return Ok(PathBuf::from(dir));
     }
     let home = std::env::var("HOME").context("HOME not set")?;
     Ok(PathBuf::from(home).join(".codex"))
 }
 
-fn claude_projects_dir() -> Result<PathBuf> {
+/// Get the Claude Code projects directory
+pub fn claude_projects_dir() -> Result<PathBuf> {
     let home = std::env::var("HOME").context("HOME not set")?;
     Ok(PathBuf::from(home).join(".claude").join("projects"))
 }
@@ -82,7 +91,7 @@ fn now_unix() -> u64 {
         .as_secs()
 }
 
-fn is_fresh(modified: SystemTime, max_age_minutes: u64) -> bool {
+pub(super) fn is_fresh(modified: SystemTime, max_age_minutes: u64) -> bool {
     if max_age_minutes == 0 {
         return true;
     }
@@ -114,23 +123,119 @@ fn read_session_id_from_transcript(path: &Path) -> Result<Option<String>> {
     Ok(None)
 }
 
-/// Find the most recent Claude transcript for a given cwd.
-/// Returns (transcript_path, session_id) if found.
-fn find_claude_transcript_for_cwd(
-    cwd: &str,
-    max_age_minutes: u64,
-) -> Result<Option<(PathBuf, String)>> {
-    let projects_dir = claude_projects_dir()?;
-    let folder_name = cwd_to_project_folder(cwd);
-    let project_dir = projects_dir.join(&folder_name);
+/// Trimmed stdout of a git command run in `cwd`, if it succeeded
+fn git_output(cwd: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(cwd)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    if !project_dir.exists() {
-        return Ok(None);
+/// Current branch of the git checkout at `cwd` (None when detached or not a repo)
+fn git_branch(cwd: &str) -> Option<String> {
+    git_output(cwd, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .filter(|branch| !branch.is_empty() && branch != "HEAD")
+}
+
+/// Paths of the other worktrees of the repository containing `cwd`
+fn git_other_worktrees(cwd: &str) -> Vec<String> {
+    let Some(list) = git_output(cwd, &["worktree", "list", "--porcelain"]) else {
+        return Vec::new();
+    };
+    let current = fs::canonicalize(cwd).ok();
+    list.lines()
+        .filter_map(|line| line.strip_prefix("worktree "))
+        .filter(|path| fs::canonicalize(path).ok() != current)
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// Directories a Claude session for `cwd` may have been started in, closest first:
+/// the cwd, its ancestors up to the repo root (monorepo sub-projects), then the same
+/// paths in the main checkout when `cwd` is inside a linked worktree
+fn claude_search_paths(cwd: &str) -> Vec<String> {
+    let mut paths = vec![cwd.to_string()];
+    let Some(info) = git_output(cwd, &["rev-parse", "--show-toplevel", "--git-common-dir"]) else {
+        return paths;
+    };
+    let mut lines = info.lines();
+    let (Some(toplevel), Some(common_dir)) = (lines.next(), lines.next()) else {
+        return paths;
+    };
+    let toplevel = PathBuf::from(toplevel);
+    let Some(relative) = fs::canonicalize(cwd)
+        .ok()
+        .and_then(|cwd| Some(cwd.strip_prefix(&toplevel).ok()?.to_path_buf()))
+    else {
+        return paths;
+    };
+
+    let mut roots = vec![toplevel.clone()];
+    // The common dir is the main checkout's `.git`; it is relative when cwd is the main checkout
+    let common_dir = Path::new(cwd).join(common_dir);
+    if common_dir.file_name().is_some_and(|name| name == ".git")
+        && let Some(main) = common_dir.parent().and_then(|p| fs::canonicalize(p).ok())
+        && main != toplevel
+    {
+        roots.push(main);
     }
+    for root in roots {
+        // Joining an empty path would add a trailing slash
+        let start = if relative.as_os_str().is_empty() {
+            root.clone()
+        } else {
+            root.join(&relative)
+        };
+        for ancestor in start.ancestors() {
+            let dir = ancestor.display().to_string();
+            if !paths.contains(&dir) {
+                paths.push(dir);
+            }
+            if ancestor == root {
+                break;
+            }
+        }
+    }
+    paths
+}
 
-    // Find the most recently modified .jsonl file
-    let mut best: Option<(PathBuf, SystemTime)> = None;
-    for entry in fs::read_dir(&project_dir)? {
+/// Whether the end of a Claude transcript records `branch` as the session's git branch
+fn transcript_on_branch(path: &Path, branch: &str) -> bool {
+    const TAIL_BYTES: u64 = 256 * 1024;
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if file
+        .seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))
+        .is_err()
+    {
+        return false;
+    }
+    let mut tail = Vec::new();
+    if file.read_to_end(&mut tail).is_err() {
+        return false;
+    }
+    let needle = format!("\"gitBranch\":{}", Value::from(branch));
+    String::from_utf8_lossy(&tail).contains(&needle)
+}
+
+/// Fresh, non-empty Claude transcripts in a project folder with their mtimes
+fn claude_transcripts_in(
+    project_dir: &Path,
+    max_age_minutes: u64,
+) -> Result<Vec<(PathBuf, SystemTime)>> {
+    if !project_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut transcripts = Vec::new();
+    for entry in fs::read_dir(project_dir)? {
         let entry = entry?;
         let path = entry.path();
         if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
@@ -144,16 +249,54 @@ fn find_claude_transcript_for_cwd(
         if max_age_minutes > 0 && !is_fresh(modified, max_age_minutes) {
             continue;
         }
-        let dominated = match best.as_ref() {
-            Some((_, best_time)) => modified <= *best_time,
-            None => false,
-        };
-        if !dominated {
-            best = Some((path, modified));
+        transcripts.push((path, modified));
+    }
+    Ok(transcripts)
+}
+
+/// Find the Claude transcript for a given cwd.
+/// Prefers sessions on `branch` (including ones started from another worktree
+/// of the same repo), then sessions closest to the cwd, then the most recent.
+/// Returns (transcript_path, session_id) if found.
+fn find_claude_transcript_for_cwd(
+    cwd: &str,
+    max_age_minutes: u64,
+    branch: Option<&str>,
+) -> Result<Option<(PathBuf, String)>> {
+    let projects_dir = claude_projects_dir()?;
+    let project_dir = |dir: &str| projects_dir.join(cwd_to_project_folder(dir));
+
+    // (on branch, closeness to cwd, modified) for each candidate
+    let mut best: Option<((bool, usize, SystemTime), PathBuf)> = None;
+    let mut consider = |path: PathBuf, rank: (bool, usize, SystemTime)| {
+        if best.as_ref().is_none_or(|(best_rank, _)| rank > *best_rank) {
+            best = Some((rank, path));
+        }
+    };
+    let search_paths = claude_search_paths(cwd);
+    for (index, dir) in search_paths.iter().enumerate() {
+        let closeness = search_paths.len() - index;
+        for (path, modified) in claude_transcripts_in(&project_dir(dir), max_age_minutes)? {
+            let on_branch = branch.is_some_and(|b| transcript_on_branch(&path, b));
+            consider(path, (on_branch, closeness, modified));
+        }
+    }
+    // Other worktrees only count for sessions on the branch
+    if let Some(branch) = branch {
+        for worktree in git_other_worktrees(cwd) {
+            if search_paths.contains(&worktree) {
+                continue;
+            }
+            for (path, modified) in claude_transcripts_in(&project_dir(&worktree), max_age_minutes)?
+            {
+                if transcript_on_branch(&path, branch) {
+                    consider(path, (true, 0, modified));
+                }
+            }
         }
     }
 
-    let Some((path, _)) = best else {
+    let Some((_, path)) = best else {
         return Ok(None);
     };
 
@@ -173,6 +316,40 @@ fn find_claude_transcript_for_cwd(
     }
 }
 
+/// Which tool wrote the transcript at `path`: Cursor, Aider, and imported
+/// chat snapshots live in the cache, Aider histories are markdown, Codex rollouts
+/// carry Codex event types, and anything else is Claude
+pub fn transcript_tool(path: &Path) -> Result<Tool> {
+    if path.parent() == cursor_snapshot_path("_")?.parent() {
+        return Ok(Tool::Cursor);
+    }
+    if path.parent() == chatgpt_snapshot_path("_")?.parent() {
+        return Ok(Tool::ChatGpt);
+    }
+    if path.parent() == claude_ai_snapshot_path("_")?.parent() {
+        return Ok(Tool::ClaudeAi);
+    }
+    if path.parent() == aider_snapshot_path("_")?.parent()
+        || path.extension().is_some_and(|ext| ext == "md")
+    {
+        return Ok(Tool::Aider);
+    }
+    let file =
+        File::open(path).with_context(|| format!("missing transcript: {}", path.display()))?;
+    for line in BufReader::new(file).lines().take(50) {
+        let Ok(value) = serde_json::from_str::<Value>(line?.trim()) else {
+            continue;
+        };
+        if matches!(
+            value.get("type").and_then(|v| v.as_str()),
+            Some("session_meta" | "response_item" | "event_msg" | "turn_context")
+        ) {
+            return Ok(Tool::Codex);
+        }
+    }
+    Ok(Tool::Claude)
+}
+
 fn read_session_meta(path: &Path) -> Result<Option<SessionMeta>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -224,17 +401,59 @@ fn is_interactive_originator(originator: Option<&str>) -> bool {
     }
 }
 
-/// Find Codex transcript for a given cwd using history.jsonl
-pub fn find_codex_transcript_for_cwd_from_history(
+/// How to pick between multiple Codex sessions recorded for the same cwd
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SessionPreference {
+    /// Session with the most recently modified rollout file
+    NewestFile,
+    /// Session with the most recent history.jsonl entry
+    #[default]
+    NewestHistory,
+    /// Ask interactively when more than one session matches
+    Prompt,
+}
+
+impl SessionPreference {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionPreference::NewestFile => "newest-file",
+            SessionPreference::NewestHistory => "newest-history",
+            SessionPreference::Prompt => "prompt",
+        }
+    }
+}
+
+/// Options controlling Codex session discovery
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CodexDiscovery {
+    /// Also consider headless (`codex exec`) sessions
+    pub include_headless: bool,
+    /// Tie-break strategy when several sessions share the cwd
+    pub prefer: SessionPreference,
+}
+
+/// A Codex session matching the current cwd
+#[derive(Debug, Clone)]
+struct CodexCandidate {
+    id: String,
+    path: PathBuf,
+    modified: SystemTime,
+    history_ts: Option<u64>,
+    headless: bool,
+}
+
+/// Collect Codex sessions for a cwd, newest rollout file per session id
+fn collect_codex_candidates(
     cwd: &str,
     max_age_minutes: u64,
-) -> Result<Option<(PathBuf, String)>> {
+    include_headless: bool,
+) -> Result<Vec<CodexCandidate>> {
     let root = codex_sessions_dir()?;
     if !root.exists() {
-        return Ok(None);
+        return Ok(Vec::new());
     }
 
-    let mut session_map: HashMap<String, (PathBuf, SystemTime)> = HashMap::new();
+    let mut session_map: HashMap<String, CodexCandidate> = HashMap::new();
     for entry in WalkDir::new(&root).follow_links(true) {
         let entry = entry?;
         if !entry.file_type().is_file() {
@@ -256,64 +475,156 @@ pub fn find_codex_transcript_for_cwd_from_history(
         if session_meta.cwd.as_deref() != Some(cwd) {
             continue;
         }
-        if !is_interactive_originator(session_meta.originator.as_deref()) {
+        let headless = !is_interactive_originator(session_meta.originator.as_deref());
+        if headless && !include_headless {
             continue;
         }
         let replace = match session_map.get(&session_meta.id) {
-            Some((_, existing_modified)) => modified >= *existing_modified,
+            Some(existing) => modified >= existing.modified,
             None => true,
         };
         if replace {
-            session_map.insert(session_meta.id, (path.to_path_buf(), modified));
+            session_map.insert(
+                session_meta.id.clone(),
+                CodexCandidate {
+                    id: session_meta.id,
+                    path: path.to_path_buf(),
+                    modified,
+                    history_ts: None,
+                    headless,
+                },
+            );
         }
     }
 
     if session_map.is_empty() {
-        return Ok(None);
+        return Ok(Vec::new());
     }
 
     let history_path = codex_home_dir()?.join("history.jsonl");
-    if !history_path.exists() {
-        return Ok(None);
-    }
-
-    let now = now_unix();
-    let max_age_seconds = max_age_minutes.saturating_mul(60);
-    let file = File::open(&history_path)?;
-    let reader = BufReader::new(file);
-    let mut best: Option<(u64, String)> = None;
-
-    for line in reader.lines() {
-        let line = line?;
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        let entry: HistoryEntry = match serde_json::from_str(trimmed) {
-            Ok(entry) => entry,
-            Err(_) => continue,
-        };
-        if max_age_minutes > 0 && now.saturating_sub(entry.ts) > max_age_seconds {
-            continue;
-        }
-        if session_map.contains_key(&entry.session_id) {
-            let replace = match best.as_ref() {
-                Some((best_ts, _)) => entry.ts >= *best_ts,
-                None => true,
+    if history_path.exists() {
+        let now = now_unix();
+        let max_age_seconds = max_age_minutes.saturating_mul(60);
+        let file = File::open(&history_path)?;
+        let reader = BufReader::new(file);
+        for line in reader.lines() {
+            let line = line?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let entry: HistoryEntry = match serde_json::from_str(trimmed) {
+                Ok(entry) => entry,
+                Err(_) => continue,
             };
-            if replace {
-                best = Some((entry.ts, entry.session_id));
+            if max_age_minutes > 0 && now.saturating_sub(entry.ts) > max_age_seconds {
+                continue;
+            }
+            if let Some(candidate) = session_map.get_mut(&entry.session_id)
+                && candidate.history_ts.is_none_or(|ts| entry.ts >= ts)
+            {
+                candidate.history_ts = Some(entry.ts);
             }
         }
     }
 
-    let Some((_, session_id)) = best else {
-        return Ok(None);
+    Ok(session_map.into_values().collect())
+}
+
+/// Pick the session whose rollout file was written last. Like the history
+/// cutoff, only files written in the last `max_age_minutes` count.
+fn newest_by_file<'a>(
+    candidates: impl IntoIterator<Item = &'a CodexCandidate>,
+    max_age_minutes: u64,
+) -> Option<&'a CodexCandidate> {
+    candidates
+        .into_iter()
+        .filter(|c| is_fresh(c.modified, max_age_minutes))
+        .max_by_key(|c| c.modified)
+}
+
+/// Pick the session with the latest history entry. Headless (`codex exec`)
+/// sessions never appear in history, so fall back to the newest headless file.
+fn newest_by_history(
+    candidates: &[CodexCandidate],
+    max_age_minutes: u64,
+) -> Option<&CodexCandidate> {
+    candidates
+        .iter()
+        .filter(|c| c.history_ts.is_some())
+        .max_by_key(|c| c.history_ts)
+        .or_else(|| newest_by_file(candidates.iter().filter(|c| c.headless), max_age_minutes))
+}
+
+fn describe_candidate(candidate: &CodexCandidate) -> String {
+    let modified = time::OffsetDateTime::from(candidate.modified);
+    let modified = time::format_description::parse("[year]-[month]-[day] [hour]:[minute]")
+        .ok()
+        .and_then(|format| modified.format(&format).ok())
+        .unwrap_or_default();
+    let kind = if candidate.headless {
+        " (headless)"
+    } else {
+        ""
     };
-    let Some((path, _)) = session_map.get(&session_id) else {
+    format!(
+        "{}{} modified {} ({})",
+        candidate.id,
+        kind,
+        modified,
+        candidate.path.display()
+    )
+}
+
+fn prompt_for_candidate(candidates: &[CodexCandidate]) -> Result<Option<&CodexCandidate>> {
+    use dialoguer::{Select, theme::ColorfulTheme};
+
+    let mut sorted: Vec<&CodexCandidate> = candidates.iter().collect();
+    sorted.sort_by_key(|c| std::cmp::Reverse(c.modified));
+    let items: Vec<String> = sorted.iter().map(|c| describe_candidate(c)).collect();
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Multiple Codex sessions match this directory")
+        .items(&items)
+        .default(0)
+        .interact_opt()?;
+    Ok(selection.map(|idx| sorted[idx]))
+}
+
+/// Find Codex transcript for a given cwd, using history.jsonl and rollout
+/// file timestamps according to `discovery.prefer`. When several sessions
+/// match, the result carries a warning naming the one chosen.
+pub fn find_codex_transcript_for_cwd_from_history(
+    cwd: &str,
+    max_age_minutes: u64,
+    discovery: &CodexDiscovery,
+) -> Result<Option<Discovered>> {
+    let candidates = collect_codex_candidates(cwd, max_age_minutes, discovery.include_headless)?;
+
+    let chosen = match discovery.prefer {
+        SessionPreference::NewestHistory => newest_by_history(&candidates, max_age_minutes),
+        SessionPreference::NewestFile => newest_by_file(&candidates, max_age_minutes),
+        SessionPreference::Prompt if candidates.len() > 1 => prompt_for_candidate(&candidates)?,
+        SessionPreference::Prompt => candidates.first(),
+    };
+    let Some(chosen) = chosen else {
         return Ok(None);
     };
-    Ok(Some((path.clone(), session_id)))
+
+    let mut warnings = Vec::new();
+    if candidates.len() > 1 {
+        warnings.push(format!(
+            "{} Codex sessions match this directory; using {} [{}]",
+            candidates.len(),
+            describe_candidate(chosen),
+            discovery.prefer.as_str()
+        ));
+    }
+    Ok(Some(Discovered {
+        path: chosen.path.clone(),
+        session_id: None,
+        thread_id: Some(chosen.id.clone()),
+        warnings,
+    }))
 }
 
 /// Validate that a transcript file exists, is not empty, and is fresh enough
@@ -347,10 +658,110 @@ pub fn file_contains(path: &Path, needle: &str, max_bytes: usize) -> Result<bool
     Ok(content.contains(needle))
 }
 
-/// Resolve Claude transcript path, either from explicit path or by cwd discovery
+/// Every transcript file of session `id`: Claude's `<id>.jsonl` in any project
+/// folder, the Codex rollouts whose session_meta carries that id, or the
+/// exported snapshot of Cursor or Aider chat or ChatGPT conversation `id`
+pub fn find_session_transcripts(tool: Tool, id: &str) -> Result<Vec<PathBuf>> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        bail!("invalid session id: {id}");
+    }
+    let mut found = Vec::new();
+    match tool {
+        Tool::Claude => {
+            let root = claude_projects_dir()?;
+            if !root.exists() {
+                return Ok(found);
+            }
+            for entry in fs::read_dir(&root)? {
+                let path = entry?.path().join(format!("{id}.jsonl"));
+                if path.is_file() {
+                    found.push(path);
+                }
+            }
+        }
+        Tool::Codex => {
+            let root = codex_sessions_dir()?;
+            if !root.exists() {
+                return Ok(found);
+            }
+            for entry in WalkDir::new(&root).follow_links(true) {
+                let entry = entry?;
+                let path = entry.path();
+                if !entry.file_type().is_file()
+                    || path.extension().and_then(|s| s.to_str()) != Some("jsonl")
+                {
+                    continue;
+                }
+                if read_session_meta(path)?.is_some_and(|meta| meta.id == id) {
+                    found.push(path.to_path_buf());
+                }
+            }
+        }
+        Tool::Cursor => {
+            let path = cursor_snapshot_path(id)?;
+            if path.is_file() {
+                found.push(path);
+            }
+        }
+        Tool::Aider => {
+            let path = aider_snapshot_path(id)?;
+            if path.is_file() {
+                found.push(path);
+            }
+        }
+        Tool::ChatGpt => {
+            let path = chatgpt_snapshot_path(id)?;
+            if path.is_file() {
+                found.push(path);
+            }
+        }
+        Tool::ClaudeAi => {
+            let path = claude_ai_snapshot_path(id)?;
+            if path.is_file() {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// The one transcript of session `id` among `tools`, for publishing a past
+/// session from anywhere
+pub fn find_session(tools: &[Tool], id: &str) -> Result<(Tool, PathBuf)> {
+    let mut found = Vec::new();
+    for &tool in tools {
+        found.extend(
+            find_session_transcripts(tool, id)?
+                .into_iter()
+                .map(|path| (tool, path)),
+        );
+    }
+    match found.len() {
+        0 => {
+            let names: Vec<&str> = tools.iter().map(|t| t.as_str()).collect();
+            bail!("no {} session with id {id}", names.join(" or "))
+        }
+        1 => Ok(found.remove(0)),
+        _ => {
+            let paths: Vec<String> = found
+                .iter()
+                .map(|(_, path)| format!("  {}", path.display()))
+                .collect();
+            bail!(
+                "session id {id} matches several transcripts; pass one with --transcript:\n{}",
+                paths.join("\n")
+            )
+        }
+    }
+}
+
+/// Resolve Claude transcript path, either from explicit path or by cwd discovery.
+/// Discovery prefers sessions on `branch`, defaulting to the cwd's current git branch.
 pub fn resolve_claude_transcript(
     transcript_arg: Option<PathBuf>,
     max_age_minutes: u64,
+    branch: Option<&str>,
 ) -> Result<(PathBuf, Option<String>)> {
     // If explicit transcript path provided, use it
     if let Some(path) = transcript_arg {
@@ -369,7 +780,10 @@ pub fn resolve_claude_transcript(
         .and_then(|path| path.to_str().map(|s| s.to_string()))
         .context("unable to resolve cwd; pass --transcript")?;
 
-    if let Some((path, session_id)) = find_claude_transcript_for_cwd(&cwd, max_age_minutes)? {
+    let branch = branch.map(str::to_string).or_else(|| git_branch(&cwd));
+    if let Some((path, session_id)) =
+        find_claude_transcript_for_cwd(&cwd, max_age_minutes, branch.as_deref())?
+    {
         return Ok((path, Some(session_id)));
     }
 
@@ -382,9 +796,13 @@ pub fn resolve_claude_transcript(
 pub fn resolve_codex_transcript(
     transcript_arg: Option<PathBuf>,
     max_age_minutes: u64,
-) -> Result<(PathBuf, Option<String>)> {
+    discovery: &CodexDiscovery,
+) -> Result<Discovered> {
     if let Some(path) = transcript_arg {
-        return Ok((path, None));
+        return Ok(Discovered {
+            path,
+            ..Discovered::default()
+        });
     }
 
     let cwd = std::env::current_dir()
@@ -392,10 +810,10 @@ pub fn resolve_codex_transcript(
         .and_then(|path| path.to_str().map(|s| s.to_string()))
         .context("unable to resolve cwd; pass --transcript")?;
 
-    if let Some((path, thread_id)) =
-        find_codex_transcript_for_cwd_from_history(&cwd, max_age_minutes)?
+    if let Some(found) =
+        find_codex_transcript_for_cwd_from_history(&cwd, max_age_minutes, discovery)?
     {
-        return Ok((path, Some(thread_id)));
+        return Ok(found);
     }
 
     bail!(
@@ -403,28 +821,34 @@ pub fn resolve_codex_transcript(
     );
 }
 
-/// Resolve transcript based on tool type
+/// Resolve transcript based on tool type.
+/// `branch` overrides the git branch Claude discovery prefers.
 pub fn resolve_transcript(
     tool: Tool,
     transcript_arg: Option<PathBuf>,
     max_age_minutes: u64,
-) -> Result<(PathBuf, Option<String>, Option<String>)> {
-    match tool {
-        Tool::Claude => {
-            let (path, session_id) = resolve_claude_transcript(transcript_arg, max_age_minutes)?;
-            Ok((path, session_id, None))
-        }
-        Tool::Codex => {
-            let (path, thread_id) = resolve_codex_transcript(transcript_arg, max_age_minutes)?;
-            Ok((path, None, thread_id))
-        }
-    }
+    codex: &CodexDiscovery,
+    branch: Option<&str>,
+) -> Result<Discovered> {
+    let (path, session_id) = match tool {
+        Tool::Claude => resolve_claude_transcript(transcript_arg, max_age_minutes, branch)?,
+        Tool::Codex => return resolve_codex_transcript(transcript_arg, max_age_minutes, codex),
+        Tool::Cursor => resolve_cursor_transcript(transcript_arg, max_age_minutes)?,
+        Tool::Aider => resolve_aider_transcript(transcript_arg, max_age_minutes)?,
+        Tool::ChatGpt => resolve_chatgpt_transcript(transcript_arg, max_age_minutes)?,
+        Tool::ClaudeAi => resolve_claude_ai_transcript(transcript_arg, max_age_minutes)?,
+    };
+    Ok(Discovered {
+        path,
+        session_id,
+        ..Discovered::default()
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::{env_lock, DirGuard, EnvGuard};
+    use crate::test_utils::{DirGuard, EnvGuard, env_lock};
     use tempfile::TempDir;
 
     #[test]
@@ -482,11 +906,133 @@ mod tests {
         )
         .unwrap();
 
-        let found = find_codex_transcript_for_cwd_from_history("/work", 0)
+        let found =
+            find_codex_transcript_for_cwd_from_history("/work", 0, &CodexDiscovery::default())
+                .unwrap()
+                .unwrap();
+        assert_eq!(found.path, second);
+        assert_eq!(found.thread_id.as_deref(), Some("sess-b"));
+    }
+
+    #[test]
+    fn find_codex_transcript_skips_headless_unless_included() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard_sessions = EnvGuard::set(
+            "AGENTEXPORT_CODEX_SESSIONS_DIR",
+            tmp.path().to_str().unwrap(),
+        );
+        let _guard_home = EnvGuard::set("CODEX_HOME", tmp.path().to_str().unwrap());
+
+        let exec = tmp.path().join("exec.jsonl");
+        fs::write(
+            &exec,
+            "{\"type\":\"session_meta\",\"payload\":{\"id\":\"sess-exec\",\"cwd\":\"/ci\",\"originator\":\"codex_exec\"}}\n",
+        )
+        .unwrap();
+
+        assert!(
+            find_codex_transcript_for_cwd_from_history("/ci", 0, &CodexDiscovery::default())
+                .unwrap()
+                .is_none()
+        );
+        let found = find_codex_transcript_for_cwd_from_history(
+            "/ci",
+            0,
+            &CodexDiscovery {
+                include_headless: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(found.path, exec);
+        assert_eq!(found.thread_id.as_deref(), Some("sess-exec"));
+    }
+
+    #[test]
+    fn find_codex_transcript_prefer_newest_file_overrides_history() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard_sessions = EnvGuard::set(
+            "AGENTEXPORT_CODEX_SESSIONS_DIR",
+            tmp.path().to_str().unwrap(),
+        );
+        let _guard_home = EnvGuard::set("CODEX_HOME", tmp.path().to_str().unwrap());
+
+        let old = tmp.path().join("old.jsonl");
+        fs::write(
+            &old,
+            "{\"type\":\"session_meta\",\"payload\":{\"id\":\"sess-old\",\"cwd\":\"/work\",\"originator\":\"codex_cli_rs\"}}\n",
+        )
+        .unwrap();
+        let new = tmp.path().join("new.jsonl");
+        fs::write(
+            &new,
+            "{\"type\":\"session_meta\",\"payload\":{\"id\":\"sess-new\",\"cwd\":\"/work\",\"originator\":\"codex_cli_rs\"}}\n",
+        )
+        .unwrap();
+        let past = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&old)
             .unwrap()
+            .set_modified(past)
             .unwrap();
-        assert_eq!(found.0, second);
-        assert_eq!(found.1, "sess-b");
+
+        // History only knows about the old session
+        fs::write(
+            tmp.path().join("history.jsonl"),
+            "{\"session_id\":\"sess-old\",\"ts\":1,\"text\":\"old\"}\n",
+        )
+        .unwrap();
+
+        let by_history =
+            find_codex_transcript_for_cwd_from_history("/work", 0, &CodexDiscovery::default())
+                .unwrap()
+                .unwrap();
+        assert_eq!(by_history.thread_id.as_deref(), Some("sess-old"));
+
+        let by_file = find_codex_transcript_for_cwd_from_history(
+            "/work",
+            0,
+            &CodexDiscovery {
+                prefer: SessionPreference::NewestFile,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(by_file.path, new);
+        assert_eq!(by_file.thread_id.as_deref(), Some("sess-new"));
+        assert_eq!(by_file.warnings.len(), 1);
+        assert!(
+            by_file.warnings[0]
+                .starts_with("2 Codex sessions match this directory; using sess-new")
+        );
+
+        // Neither strategy reaches past the age cutoff
+        File::options()
+            .write(true)
+            .open(&new)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        for prefer in [
+            SessionPreference::NewestFile,
+            SessionPreference::NewestHistory,
+        ] {
+            let found = find_codex_transcript_for_cwd_from_history(
+                "/work",
+                30,
+                &CodexDiscovery {
+                    prefer,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert!(found.is_none(), "{prefer:?}");
+        }
     }
 
     #[test]
@@ -522,11 +1068,130 @@ mod tests {
 
         let _dir_guard = DirGuard::set(&cwd).unwrap();
 
-        let (path, session_id) = resolve_claude_transcript(None, 0).unwrap();
+        let (path, session_id) = resolve_claude_transcript(None, 0, None).unwrap();
         assert_eq!(session_id.as_deref(), Some("sess-abc"));
         assert_eq!(path, transcript);
     }
 
+    /// Write a Claude transcript recorded on `branch` into the project folder for `cwd`
+    fn write_claude_session(home: &Path, cwd: &Path, id: &str, branch: &str, age_secs: u64) {
+        let project_dir = home
+            .join(".claude")
+            .join("projects")
+            .join(cwd_to_project_folder(cwd.to_str().unwrap()));
+        fs::create_dir_all(&project_dir).unwrap();
+        let path = project_dir.join(format!("{id}.jsonl"));
+        fs::write(
+            &path,
+            format!("{{\"sessionId\":\"{id}\",\"gitBranch\":\"{branch}\",\"type\":\"user\",\"message\":{{\"content\":\"Hi\"}}}}\n"),
+        )
+        .unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_secs);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com", "-C"])
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn resolve_claude_prefers_session_on_branch() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard_home = EnvGuard::set("HOME", tmp.path().to_str().unwrap());
+        let cwd = fs::canonicalize(tmp.path()).unwrap().join("work");
+        fs::create_dir_all(&cwd).unwrap();
+        let _dir_guard = DirGuard::set(&cwd).unwrap();
+
+        write_claude_session(tmp.path(), &cwd, "sess-feature", "feature", 120);
+        write_claude_session(tmp.path(), &cwd, "sess-main", "main", 0);
+
+        let (_, newest) = resolve_claude_transcript(None, 0, None).unwrap();
+        assert_eq!(newest.as_deref(), Some("sess-main"));
+        let (_, on_branch) = resolve_claude_transcript(None, 0, Some("feature")).unwrap();
+        assert_eq!(on_branch.as_deref(), Some("sess-feature"));
+        let (_, fallback) = resolve_claude_transcript(None, 0, Some("other")).unwrap();
+        assert_eq!(fallback.as_deref(), Some("sess-main"));
+    }
+
+    #[test]
+    fn resolve_claude_finds_branch_session_from_other_worktree() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard_home = EnvGuard::set("HOME", tmp.path().to_str().unwrap());
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let repo = root.join("repo");
+        let worktree = root.join("repo-task");
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q", "-b", "main"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "init"]);
+        git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                "task",
+                worktree.to_str().unwrap(),
+            ],
+        );
+
+        // A session started in the main checkout that moved onto the task branch
+        write_claude_session(tmp.path(), &repo, "sess-task", "task", 60);
+        write_claude_session(tmp.path(), &repo, "sess-main", "main", 0);
+        let _dir_guard = DirGuard::set(&worktree).unwrap();
+
+        let (path, session_id) = resolve_claude_transcript(None, 0, None).unwrap();
+        assert_eq!(session_id.as_deref(), Some("sess-task"));
+        assert!(path.ends_with("sess-task.jsonl"));
+        // Off-branch, the main checkout's newest session is the fallback
+        let (_, fallback) = resolve_claude_transcript(None, 0, Some("elsewhere")).unwrap();
+        assert_eq!(fallback.as_deref(), Some("sess-main"));
+    }
+
+    #[test]
+    fn resolve_claude_searches_repo_ancestors() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _guard_home = EnvGuard::set("HOME", tmp.path().to_str().unwrap());
+        let repo = fs::canonicalize(tmp.path()).unwrap().join("mono");
+        let package = repo.join("packages").join("foo");
+        fs::create_dir_all(&package).unwrap();
+        git(&repo, &["init", "-q", "-b", "main"]);
+        let _dir_guard = DirGuard::set(&package).unwrap();
+        assert_eq!(
+            claude_search_paths(package.to_str().unwrap()),
+            [
+                package.display().to_string(),
+                repo.join("packages").display().to_string(),
+                repo.display().to_string(),
+            ]
+        );
+
+        // A session started at the repo root is found from the sub-project
+        write_claude_session(tmp.path(), &repo, "sess-root", "main", 0);
+        let (_, session_id) = resolve_claude_transcript(None, 0, None).unwrap();
+        assert_eq!(session_id.as_deref(), Some("sess-root"));
+
+        // One started in the sub-project itself wins even when older
+        write_claude_session(tmp.path(), &package, "sess-foo", "main", 120);
+        let (_, session_id) = resolve_claude_transcript(None, 0, None).unwrap();
+        assert_eq!(session_id.as_deref(), Some("sess-foo"));
+    }
+
     #[test]
     fn resolve_codex_uses_history_for_current_cwd() {
         let _lock = env_lock();
@@ -562,9 +1227,9 @@ mod tests {
         )
         .unwrap();
 
-        let (path, thread_id) = resolve_codex_transcript(None, 0).unwrap();
-        assert_eq!(thread_id.as_deref(), Some(session_id));
-        assert_eq!(path, session_path);
+        let found = resolve_codex_transcript(None, 0, &CodexDiscovery::default()).unwrap();
+        assert_eq!(found.thread_id.as_deref(), Some(session_id));
+        assert_eq!(found.path, session_path);
     }
 
     #[test]
@@ -594,9 +1259,51 @@ mod tests {
         )
         .unwrap();
 
-        let err = resolve_codex_transcript(None, 0).unwrap_err();
-        assert!(err
-            .to_string()
-            .contains("unable to resolve codex transcript from history"));
+        let err = resolve_codex_transcript(None, 0, &CodexDiscovery::default()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unable to resolve codex transcript from history")
+        );
+    }
+
+    #[test]
+    fn find_session_searches_claude_and_codex() {
+        let _lock = env_lock();
+        let tmp = TempDir::new().unwrap();
+        let _home = EnvGuard::set("HOME", tmp.path().to_str().unwrap());
+        let sessions_dir = tmp.path().join("codex");
+        let _sessions = EnvGuard::set(
+            "AGENTEXPORT_CODEX_SESSIONS_DIR",
+            sessions_dir.to_str().unwrap(),
+        );
+        let project = tmp.path().join(".claude/projects/-work-app");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("claude-1.jsonl"), "{}\n").unwrap();
+        let day = sessions_dir.join("2025/01/02");
+        fs::create_dir_all(&day).unwrap();
+        let rollout = day.join("rollout-2025-01-02T10-11-12-sess-1.jsonl");
+        fs::write(
+            &rollout,
+            "{\"type\":\"session_meta\",\"payload\":{\"id\":\"sess-1\",\"cwd\":\"/work\"}}\n",
+        )
+        .unwrap();
+
+        let both = [Tool::Claude, Tool::Codex];
+        let (tool, path) = find_session(&both, "claude-1").unwrap();
+        assert!(matches!(tool, Tool::Claude));
+        assert_eq!(path, project.join("claude-1.jsonl"));
+        let (tool, path) = find_session(&both, "sess-1").unwrap();
+        assert!(matches!(tool, Tool::Codex));
+        assert_eq!(path, rollout);
+
+        let err = find_session(&[Tool::Codex], "claude-1").unwrap_err();
+        assert_eq!(err.to_string(), "no codex session with id claude-1");
+
+        // The same id in two projects can't be told apart
+        let other = tmp.path().join(".claude/projects/-work-other");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("claude-1.jsonl"), "{}\n").unwrap();
+        let err = find_session(&both, "claude-1").unwrap_err();
+        assert!(err.to_string().contains("matches several transcripts"));
     }
 }
@@ -0,0 +1,200 @@
+//! Excerpt selection: narrow a transcript down to messages around a keyword,
+//! or to a sampled overview of a very long session.
+
+use std::collections::HashSet;
+
+use super::types::RenderedMessage;
+
+/// Select messages containing `keyword` (case-insensitive) plus `context`
+/// messages on either side. Tool calls and their results are kept together:
+/// if any message with a given tool_use_id is selected, all of them are.
+pub fn select_around(
+    messages: Vec<RenderedMessage>,
+    keyword: &str,
+    context: usize,
+) -> Vec<RenderedMessage> {
+    let needle = keyword.trim().to_lowercase();
+    if needle.is_empty() {
+        return messages;
+    }
+
+    let mut keep = vec![false; messages.len()];
+    for (idx, msg) in messages.iter().enumerate() {
+        if msg.content.to_lowercase().contains(&needle) {
+            let start = idx.saturating_sub(context);
+            let end = (idx + context).min(messages.len() - 1);
+            for flag in &mut keep[start..=end] {
+                *flag = true;
+            }
+        }
+    }
+
+    // Pull in the other half of any tool call/result pair
+    let tool_ids: HashSet<&str> = messages
+        .iter()
+        .zip(&keep)
+        .filter(|(_, kept)| **kept)
+        .filter_map(|(msg, _)| msg.tool_use_id.as_deref())
+        .collect();
+    for (idx, msg) in messages.iter().enumerate() {
+        if let Some(id) = msg.tool_use_id.as_deref()
+            && tool_ids.contains(id)
+        {
+            keep[idx] = true;
+        }
+    }
+
+    messages
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(msg, kept)| kept.then_some(msg))
+        .collect()
+}
+
+/// Overview of a long session: the first and last `turns` turns, plus `turns`
+/// more sampled evenly from the middle. A turn starts at each user message.
+/// Each skipped stretch becomes one system message saying how much it held.
+pub fn sample_turns(messages: Vec<RenderedMessage>, turns: usize) -> Vec<RenderedMessage> {
+    let mut starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(idx, msg)| *idx == 0 || msg.role == "user")
+        .map(|(idx, _)| idx)
+        .collect();
+    let count = starts.len();
+    if count <= turns * 3 {
+        return messages;
+    }
+
+    // The middle has more than `turns` turns; take the centre of each of
+    // `turns` equal stretches of it
+    let middle = count - 2 * turns;
+    let mut keep = vec![false; count];
+    for t in 0..turns {
+        keep[t] = true;
+        keep[count - 1 - t] = true;
+        keep[turns + (2 * t + 1) * middle / (2 * turns)] = true;
+    }
+
+    starts.push(messages.len());
+    let mut messages = messages.into_iter();
+    let mut sampled = Vec::new();
+    let mut skipped: Vec<RenderedMessage> = Vec::new();
+    for (turn, bounds) in starts.windows(2).enumerate() {
+        let chunk = messages.by_ref().take(bounds[1] - bounds[0]);
+        if !keep[turn] {
+            skipped.extend(chunk);
+            continue;
+        }
+        if let Some(first) = skipped.first() {
+            sampled.push(skip_marker(skipped.len(), first.part));
+            skipped.clear();
+        }
+        sampled.extend(chunk);
+    }
+    sampled
+}
+
+fn skip_marker(count: usize, part: Option<usize>) -> RenderedMessage {
+    let plural = if count == 1 { "" } else { "s" };
+    RenderedMessage {
+        role: "system".to_string(),
+        content: format!("… skipped {count} message{plural} …"),
+        raw: None,
+        raw_label: None,
+        tool_use_id: None,
+        model: None,
+        abandoned: false,
+        part,
+        id: None,
+        highlighted: false,
+        images: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str, tool_use_id: Option<&str>) -> RenderedMessage {
+        RenderedMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            raw: None,
+            raw_label: None,
+            tool_use_id: tool_use_id.map(|s| s.to_string()),
+            model: None,
+            abandoned: false,
+            part: None,
+            id: None,
+            highlighted: false,
+            images: Vec::new(),
+        }
+    }
+
+    fn contents(messages: &[RenderedMessage]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn selects_matches_with_context() {
+        let messages = vec![
+            msg("user", "one", None),
+            msg("assistant", "two", None),
+            msg("user", "found a Deadlock here", None),
+            msg("assistant", "four", None),
+            msg("user", "five", None),
+        ];
+        let selected = select_around(messages, "deadlock", 1);
+        assert_eq!(
+            contents(&selected),
+            ["two", "found a Deadlock here", "four"]
+        );
+    }
+
+    #[test]
+    fn keeps_tool_pairs_intact() {
+        let messages = vec![
+            msg("tool", "Bash\n{\"command\": \"cargo test\"}", Some("t1")),
+            msg("assistant", "filler", None),
+            msg("tool", "deadlock detected", Some("t1")),
+            msg("user", "unrelated", None),
+        ];
+        let selected = select_around(messages, "deadlock", 0);
+        assert_eq!(
+            contents(&selected),
+            ["Bash\n{\"command\": \"cargo test\"}", "deadlock detected"]
+        );
+    }
+
+    #[test]
+    fn samples_first_last_and_middle_turns() {
+        let mut messages = Vec::new();
+        for turn in 0..10 {
+            messages.push(msg("user", &format!("q{turn}"), None));
+            messages.push(msg("assistant", &format!("a{turn}"), None));
+        }
+        let sampled = sample_turns(messages.clone(), 1);
+        assert_eq!(
+            contents(&sampled),
+            [
+                "q0",
+                "a0",
+                "… skipped 8 messages …",
+                "q5",
+                "a5",
+                "… skipped 6 messages …",
+                "q9",
+                "a9"
+            ]
+        );
+        // Short sessions are left whole
+        assert_eq!(sample_turns(messages, 4).len(), 20);
+    }
+
+    #[test]
+    fn no_matches_yields_empty() {
+        let messages = vec![msg("user", "hello", None)];
+        assert!(select_around(messages, "deadlock", 5).is_empty());
+    }
+}
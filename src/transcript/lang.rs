@@ -0,0 +1,172 @@
+//! Language detection: the dominant natural language of a conversation.
+
+use whatlang::Lang;
+
+use super::types::RenderedMessage;
+
+/// Most characters of prose sampled for detection
+const MAX_SAMPLE_CHARS: usize = 20_000;
+
+/// Fewest characters of prose worth guessing a language from
+const MIN_SAMPLE_CHARS: usize = 40;
+
+/// Message text with fenced code blocks removed, since code reads as English
+fn prose(content: &str) -> String {
+    let mut in_fence = false;
+    let mut out = String::new();
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if !in_fence {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// BCP 47 tag for a detected language (its ISO 639-1 code)
+fn language_tag(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Afr => "af",
+        Lang::Aka => "ak",
+        Lang::Amh => "am",
+        Lang::Ara => "ar",
+        Lang::Aze => "az",
+        Lang::Bel => "be",
+        Lang::Ben => "bn",
+        Lang::Bul => "bg",
+        Lang::Cat => "ca",
+        Lang::Ces => "cs",
+        Lang::Cmn => "zh",
+        Lang::Dan => "da",
+        Lang::Deu => "de",
+        Lang::Ell => "el",
+        Lang::Eng => "en",
+        Lang::Epo => "eo",
+        Lang::Est => "et",
+        Lang::Fin => "fi",
+        Lang::Fra => "fr",
+        Lang::Guj => "gu",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Hrv => "hr",
+        Lang::Hun => "hu",
+        Lang::Hye => "hy",
+        Lang::Ind => "id",
+        Lang::Ita => "it",
+        Lang::Jav => "jv",
+        Lang::Jpn => "ja",
+        Lang::Kan => "kn",
+        Lang::Kat => "ka",
+        Lang::Khm => "km",
+        Lang::Kor => "ko",
+        Lang::Lat => "la",
+        Lang::Lav => "lv",
+        Lang::Lit => "lt",
+        Lang::Mal => "ml",
+        Lang::Mar => "mr",
+        Lang::Mkd => "mk",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Nld => "nl",
+        Lang::Nob => "nb",
+        Lang::Ori => "or",
+        Lang::Pan => "pa",
+        Lang::Pes => "fa",
+        Lang::Pol => "pl",
+        Lang::Por => "pt",
+        Lang::Ron => "ro",
+        Lang::Rus => "ru",
+        Lang::Sin => "si",
+        Lang::Slk => "sk",
+        Lang::Slv => "sl",
+        Lang::Sna => "sn",
+        Lang::Spa => "es",
+        Lang::Srp => "sr",
+        Lang::Swe => "sv",
+        Lang::Tam => "ta",
+        Lang::Tel => "te",
+        Lang::Tgl => "tl",
+        Lang::Tha => "th",
+        Lang::Tuk => "tk",
+        Lang::Tur => "tr",
+        Lang::Ukr => "uk",
+        Lang::Urd => "ur",
+        Lang::Uzb => "uz",
+        Lang::Vie => "vi",
+        Lang::Yid => "yi",
+        Lang::Zul => "zu",
+    }
+}
+
+/// Dominant language of the user and assistant messages, as a BCP 47 tag.
+/// `None` when there is too little prose or the guess isn't reliable.
+pub fn detect_language(messages: &[RenderedMessage]) -> Option<String> {
+    let mut sample = String::new();
+    for message in messages
+        .iter()
+        .filter(|m| matches!(m.role.as_str(), "user" | "assistant"))
+    {
+        if sample.len() >= MAX_SAMPLE_CHARS {
+            break;
+        }
+        sample.push_str(&prose(&message.content));
+    }
+    if sample.trim().chars().count() < MIN_SAMPLE_CHARS {
+        return None;
+    }
+    let info = whatlang::detect(&sample)?;
+    info.is_reliable()
+        .then(|| language_tag(info.lang()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> RenderedMessage {
+        RenderedMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            raw: None,
+            raw_label: None,
+            tool_use_id: None,
+            model: None,
+            abandoned: false,
+            part: None,
+            id: None,
+            highlighted: false,
+            images: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn detects_dominant_prose_language() {
+        let spanish = [
+            message(
+                "user",
+                "¿Puedes revisar por qué falla la prueba del carrito de compras?",
+            ),
+            message("tool", "cargo test cart -- --nocapture"),
+            message(
+                "assistant",
+                "La prueba falla porque el total no incluye los impuestos. Lo he corregido y ahora todas las pruebas pasan.\n```rust\nlet total = subtotal + tax;\n```",
+            ),
+        ];
+        assert_eq!(detect_language(&spanish).as_deref(), Some("es"));
+
+        let english = [
+            message("user", "Can you fix the failing test in src/cart.rs?"),
+            message(
+                "assistant",
+                "The test fails because `total()` skips tax. I updated it and all tests pass now.",
+            ),
+        ];
+        assert_eq!(detect_language(&english).as_deref(), Some("en"));
+
+        assert_eq!(detect_language(&[message("user", "fix it")]), None);
+    }
+}
@@ -1,16 +1,58 @@
-//! Transcript handling: discovery, parsing, and types.
+//! Transcript handling: discovery, parsing, sources, and types.
 
+#[cfg(feature = "cli")]
+mod aider;
+#[cfg(feature = "cli")]
+pub mod archive;
+#[cfg(feature = "cli")]
+mod chatgpt;
+#[cfg(feature = "cli")]
+mod claude_ai;
+#[cfg(feature = "cli")]
+mod compaction;
+#[cfg(feature = "cli")]
+mod cursor;
+#[cfg(feature = "cli")]
 mod discovery;
+mod excerpt;
+mod lang;
 mod parser;
+mod payload;
+mod recovery;
+mod source;
 mod types;
+mod usage;
 
+#[cfg(feature = "cli")]
+pub use compaction::{compaction_chain, stitch_compaction_chain};
+#[cfg(feature = "cli")]
 pub use discovery::{
-    cache_dir, codex_home_dir, codex_sessions_dir, file_contains, resolve_transcript,
-    validate_transcript_fresh,
+    CodexDiscovery, SessionPreference, cache_dir, claude_projects_dir, codex_home_dir,
+    codex_sessions_dir, file_contains, find_session, find_session_transcripts, resolve_transcript,
+    transcript_tool, validate_transcript_fresh,
 };
-pub use parser::{extract_transcript_meta, parse_transcript};
-pub use types::{SharePayload, Tool};
+pub use excerpt::{sample_turns, select_around};
+#[cfg(feature = "cli")]
+pub use parser::truncate;
+pub use parser::{
+    extract_transcript_meta, extract_transcript_meta_from_reader, parse_transcript,
+    parse_transcript_from_reader,
+};
+pub use payload::{build_share_payload, bundle_payloads};
+pub use recovery::LineRecovery;
+pub use source::{
+    DiscoverOptions, Discovered, TranscriptSource, named_source, register_source, source,
+    source_for_path, sources,
+};
+#[cfg(feature = "cli")]
+pub use types::FileDiff;
+pub use types::{BundlePart, ModelCost};
+pub use types::{
+    ParseResult, Publisher, RenderedMessage, SeriesLink, SessionStatus, SharePayload, TaskItem,
+    TaskProgress, Timeline, TitleSource, Tool, TranscriptMeta, ViewerDefaults,
+};
+pub use usage::{ModelPrice, ModelUsage, PricingTable, price_payload};
 
 // Re-export for tests
-#[cfg(test)]
+#[cfg(all(test, feature = "cli"))]
 pub use discovery::cwd_to_project_folder;
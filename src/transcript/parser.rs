@@ -1,12 +1,21 @@
 //! Transcript parsing: JSONL format parsing for Claude and Codex transcripts.
 
 use anyhow::Result;
+use serde::Deserialize;
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 
-use super::types::{MessageUsage, ParseResult, RenderedMessage, TranscriptMeta};
+use super::recovery::{JsonlValues, Line, read_line};
+use super::source::registered_for;
+use super::types::{
+    FileDiff, MessageUsage, ParseResult, RenderedMessage, SessionStatus, TaskItem, TaskProgress,
+    ToolStat, TranscriptMeta,
+};
 
 /// Truncate a string to max_chars, adding "..." if truncated
 pub fn truncate(input: &str, max_chars: usize) -> String {
@@ -24,6 +33,289 @@ pub fn truncate(input: &str, max_chars: usize) -> String {
     out
 }
 
+/// Largest binary output kept (escaped) in `raw`; bigger blobs only get the placeholder
+const BINARY_RAW_MAX_BYTES: usize = 4 * 1024;
+
+/// Whether tool output is binary data or an encoded blob rather than readable text:
+/// NUL bytes, mostly control/replacement characters, or one long high-entropy token
+fn looks_binary(text: &str) -> bool {
+    if text.contains('\0') {
+        return true;
+    }
+    let sample: Vec<char> = text.chars().take(8192).collect();
+    let garbage = sample
+        .iter()
+        .filter(|&&c| c == '\u{FFFD}' || (c.is_control() && !matches!(c, '\n' | '\r' | '\t')))
+        .count();
+    if garbage * 10 > sample.len() {
+        return true;
+    }
+    let trimmed = text.trim();
+    trimmed.len() > 4096 && !trimmed.contains(char::is_whitespace) && shannon_entropy(trimmed) > 5.0
+}
+
+/// Bits of entropy per byte
+fn shannon_entropy(text: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in text.bytes() {
+        counts[byte as usize] += 1;
+    }
+    let len = text.len() as f64;
+    counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Content and raw text for a tool result. Binary output becomes a size
+/// placeholder, keeping an escaped copy in raw only when it is small.
+fn tool_output_parts(output: &str) -> (String, Option<String>) {
+    if !looks_binary(output) {
+        return (truncate(output, 500), None);
+    }
+    let placeholder = format!("[binary data, {:.1} KB]", output.len() as f64 / 1024.0);
+    let raw = (output.len() <= BINARY_RAW_MAX_BYTES).then(|| output.escape_debug().to_string());
+    (placeholder, raw)
+}
+
+/// Counts calls per tool and times each one from its call to its result
+#[derive(Default)]
+struct ToolTimer {
+    /// Tool name and start time of calls awaiting their result, by call id
+    pending: HashMap<String, (String, Option<OffsetDateTime>)>,
+}
+
+impl ToolTimer {
+    fn call(
+        &mut self,
+        stats: &mut Vec<ToolStat>,
+        name: &str,
+        id: Option<&str>,
+        at: Option<OffsetDateTime>,
+    ) {
+        if let Some(id) = id {
+            self.pending.insert(id.to_string(), (name.to_string(), at));
+        }
+        match stats.iter_mut().find(|s| s.name == name) {
+            Some(stat) => stat.calls += 1,
+            None => stats.push(ToolStat {
+                name: name.to_string(),
+                calls: 1,
+                duration_ms: 0,
+            }),
+        }
+    }
+
+    fn result(&mut self, stats: &mut [ToolStat], id: Option<&str>, at: Option<OffsetDateTime>) {
+        let Some((name, started)) = id.and_then(|id| self.pending.remove(id)) else {
+            return;
+        };
+        if let (Some(start), Some(end)) = (started, at)
+            && end >= start
+            && let Some(stat) = stats.iter_mut().find(|s| s.name == name)
+        {
+            stat.duration_ms += (end - start).whole_milliseconds() as u64;
+        }
+    }
+}
+
+/// Collapses repeated Claude assistant text and thinking. Claude streams growing
+/// snapshots of one message id, and retries after API errors re-send the same answer
+/// under a new id; both keep only the final version, and retries are counted.
+#[derive(Default)]
+struct RetryTracker {
+    /// Claude message id behind each collapsible message, by index
+    ids: HashMap<usize, Option<String>>,
+    /// Retries folded into each surviving message, by index
+    retries: HashMap<usize, usize>,
+}
+
+impl RetryTracker {
+    /// Push `message`, unless it repeats the latest message of its role in the current
+    /// assistant turn; then it replaces that message's content and returns its index
+    fn push(
+        &mut self,
+        messages: &mut Vec<RenderedMessage>,
+        message: RenderedMessage,
+        id: Option<&str>,
+    ) -> Option<usize> {
+        let turn_start = messages
+            .iter()
+            .rposition(|m| !matches!(m.role.as_str(), "assistant" | "thinking"))
+            .map_or(0, |i| i + 1);
+        let previous = (turn_start..messages.len())
+            .rev()
+            .find(|&i| messages[i].role == message.role);
+        if let Some(index) = previous
+            && let Some(previous_id) = self.ids.get(&index)
+        {
+            let same_id = id.is_some() && previous_id.as_deref() == id;
+            let earlier = &messages[index].content;
+            let repeated = earlier
+                .split_whitespace()
+                .eq(message.content.split_whitespace());
+            let continued = same_id && message.content.starts_with(earlier.as_str());
+            if repeated || continued {
+                if !same_id {
+                    *self.retries.entry(index).or_default() += 1;
+                }
+                self.ids.insert(index, id.map(str::to_string));
+                messages[index].content = message.content;
+                return Some(index);
+            }
+        }
+        self.ids.insert(messages.len(), id.map(str::to_string));
+        messages.push(message);
+        None
+    }
+
+    /// Note the retry count on surviving assistant answers
+    fn annotate(&self, messages: &mut [RenderedMessage]) {
+        for (&index, &count) in &self.retries {
+            let message = &mut messages[index];
+            if message.role == "assistant" {
+                message.content = format!("{}\n\n*(retried {count}×)*", message.content.trim_end());
+            }
+        }
+    }
+}
+
+/// How an event can start a new conversation branch
+enum ForkKind {
+    /// A typed user prompt (editing one starts a sibling branch)
+    Prompt,
+    /// An assistant reply, with its Claude message id (regenerating starts a sibling)
+    Reply(Option<String>),
+    /// Tool results and bookkeeping, which never fork
+    Other,
+}
+
+/// Claude transcripts are trees: each event names its parent, and editing a prompt
+/// or regenerating a reply starts a sibling branch. Messages off the branch the
+/// session ended on are marked abandoned.
+#[derive(Default)]
+struct BranchTracker {
+    /// Parent and fork kind of each main-chain event, by uuid
+    nodes: HashMap<String, (Option<String>, ForkKind)>,
+    /// Event behind each message, parallel to `messages`
+    message_nodes: Vec<Option<String>>,
+    /// Last main-chain user or assistant event
+    leaf: Option<String>,
+}
+
+impl BranchTracker {
+    /// Record an event's place in the tree, returning its uuid
+    fn record(&mut self, value: &Value, event_type: &str) -> Option<String> {
+        let uuid = value.get("uuid").and_then(|v| v.as_str())?;
+        if value.get("isSidechain").and_then(|v| v.as_bool()) == Some(true) {
+            return None;
+        }
+        // Compaction restarts the chain; its boundary points back via logicalParentUuid
+        let parent = ["parentUuid", "logicalParentUuid"]
+            .iter()
+            .find_map(|key| value.get(*key).and_then(|v| v.as_str()))
+            .map(str::to_string);
+        let content = value.pointer("/message/content");
+        let kind = match event_type {
+            "assistant" => ForkKind::Reply(
+                value
+                    .pointer("/message/id")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+            ),
+            "user" if value.get("isMeta").and_then(|v| v.as_bool()) != Some(true) => {
+                let typed = match content {
+                    Some(Value::String(text)) => !text.trim().is_empty(),
+                    Some(Value::Array(parts)) => parts
+                        .iter()
+                        .any(|p| p.get("type").and_then(|t| t.as_str()) == Some("text")),
+                    _ => false,
+                };
+                if typed {
+                    ForkKind::Prompt
+                } else {
+                    ForkKind::Other
+                }
+            }
+            _ => ForkKind::Other,
+        };
+        if matches!(event_type, "user" | "assistant") {
+            self.leaf = Some(uuid.to_string());
+        }
+        self.nodes.insert(uuid.to_string(), (parent, kind));
+        Some(uuid.to_string())
+    }
+
+    /// Attribute the messages added since the last call to event `uuid`
+    fn stamp(&mut self, message_count: usize, uuid: Option<String>) {
+        let added = message_count - self.message_nodes.len();
+        self.message_nodes.extend(std::iter::repeat_n(uuid, added));
+    }
+
+    /// Move message `index` to event `uuid` after a later event replaced its content
+    fn adopt(&mut self, index: usize, uuid: Option<String>) {
+        if let Some(node) = self.message_nodes.get_mut(index) {
+            *node = uuid;
+        }
+    }
+
+    /// Whether `sibling` started a branch abandoned in favour of `active`
+    fn forks(&self, sibling: &str, active: &str) -> bool {
+        match (&self.nodes[sibling].1, &self.nodes[active].1) {
+            (ForkKind::Prompt, ForkKind::Prompt) => true,
+            (ForkKind::Reply(Some(a)), ForkKind::Reply(b)) => Some(a) != b.as_ref(),
+            _ => false,
+        }
+    }
+
+    /// Flag messages on branches that split off the path to the last event
+    fn mark(&self, messages: &mut [RenderedMessage]) {
+        let Some(leaf) = self.leaf.as_deref() else {
+            return;
+        };
+        // Active child of each event on the path; "" stands for the root
+        let mut active: HashMap<&str, &str> = HashMap::new();
+        let mut current = leaf;
+        while let Some((parent, _)) = self.nodes.get(current) {
+            let parent = parent.as_deref().unwrap_or("");
+            if active.insert(parent, current).is_some() || parent.is_empty() {
+                break;
+            }
+            current = parent;
+        }
+
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (uuid, (parent, _)) in &self.nodes {
+            children
+                .entry(parent.as_deref().unwrap_or(""))
+                .or_default()
+                .push(uuid);
+        }
+        let mut abandoned = HashSet::new();
+        let mut stack: Vec<&str> = Vec::new();
+        for (parent, on_path) in &active {
+            for &child in children.get(parent).into_iter().flatten() {
+                if child != *on_path && self.forks(child, on_path) {
+                    stack.push(child);
+                }
+            }
+        }
+        while let Some(uuid) = stack.pop() {
+            if abandoned.insert(uuid) {
+                stack.extend(children.get(uuid).into_iter().flatten());
+            }
+        }
+
+        for (message, node) in messages.iter_mut().zip(&self.message_nodes) {
+            message.abandoned = node.as_deref().is_some_and(|n| abandoned.contains(n));
+        }
+    }
+}
+
 /// Check if text looks like an internal/system block that should be filtered
 pub fn looks_like_internal_block(text: &str) -> bool {
     let trimmed = text.trim_start();
@@ -71,10 +363,10 @@ fn extract_text(value: &Value, depth: usize) -> Option<String> {
         Value::Array(items) => {
             let mut parts = Vec::new();
             for item in items {
-                if let Some(part) = extract_text(item, depth + 1) {
-                    if !part.trim().is_empty() {
-                        parts.push(part);
-                    }
+                if let Some(part) = extract_text(item, depth + 1)
+                    && !part.trim().is_empty()
+                {
+                    parts.push(part);
                 }
             }
             if parts.is_empty() {
@@ -87,25 +379,25 @@ fn extract_text(value: &Value, depth: usize) -> Option<String> {
             if let Some(text) = map.get("text").and_then(|v| v.as_str()) {
                 return Some(text.to_string());
             }
-            if let Some(content) = map.get("content") {
-                if let Some(text) = extract_text(content, depth + 1) {
-                    return Some(text);
-                }
+            if let Some(content) = map.get("content")
+                && let Some(text) = extract_text(content, depth + 1)
+            {
+                return Some(text);
             }
-            if let Some(value) = map.get("value") {
-                if let Some(text) = extract_text(value, depth + 1) {
-                    return Some(text);
-                }
+            if let Some(value) = map.get("value")
+                && let Some(text) = extract_text(value, depth + 1)
+            {
+                return Some(text);
             }
-            if let Some(delta) = map.get("delta") {
-                if let Some(text) = extract_text(delta, depth + 1) {
-                    return Some(text);
-                }
+            if let Some(delta) = map.get("delta")
+                && let Some(text) = extract_text(delta, depth + 1)
+            {
+                return Some(text);
             }
-            if let Some(message) = map.get("message") {
-                if let Some(text) = extract_text(message, depth + 1) {
-                    return Some(text);
-                }
+            if let Some(message) = map.get("message")
+                && let Some(text) = extract_text(message, depth + 1)
+            {
+                return Some(text);
             }
             None
         }
@@ -113,6 +405,98 @@ fn extract_text(value: &Value, depth: usize) -> Option<String> {
     }
 }
 
+/// Text of a tool result. Plain strings pass through; content arrays (MCP and
+/// structured tool outputs) keep their text parts, preserve resource URIs, and
+/// replace media with placeholders.
+fn tool_result_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.to_string()),
+        Value::Array(parts) => {
+            let parts: Vec<String> = parts.iter().filter_map(content_part_text).collect();
+            (!parts.is_empty()).then(|| parts.join("\n"))
+        }
+        Value::Object(_) => content_part_text(value),
+        _ => None,
+    }
+}
+
+/// Image types kept by `--include-images`; the viewer shows them inline
+const IMAGE_MEDIA_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// An inline image block (`image` with a base64 source, or `input_image`
+/// with a data URL) as a `data:` URI. Linked images have no data to keep.
+fn image_data_uri(block: &Value) -> Option<String> {
+    let field = |pointer: &str| block.pointer(pointer).and_then(|v| v.as_str());
+    let (media, data) = match field("/type")? {
+        "image" if field("/source/type") == Some("base64") => {
+            (field("/source/media_type")?, field("/source/data")?)
+        }
+        "input_image" => field("/image_url")?
+            .strip_prefix("data:")?
+            .split_once(";base64,")?,
+        _ => return None,
+    };
+    IMAGE_MEDIA_TYPES
+        .contains(&media)
+        .then(|| format!("data:{media};base64,{data}"))
+}
+
+/// Inline images in a tool result's content array
+fn tool_result_images(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(parts) => parts.iter().filter_map(image_data_uri).collect(),
+        Value::Object(_) => image_data_uri(value).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// One part of a structured tool result
+fn content_part_text(part: &Value) -> Option<String> {
+    let field = |pointer: &str| part.pointer(pointer).and_then(|v| v.as_str());
+    match field("/type").unwrap_or("") {
+        "text" | "input_text" | "output_text" => field("/text").map(str::to_string),
+        "image" => {
+            let media = field("/source/media_type").or(field("/mimeType"));
+            Some(format!("[Image: {}]", media.unwrap_or("image")))
+        }
+        "input_image" => match field("/image_url") {
+            // Inline data URLs carry the media type before the base64 payload
+            Some(url) if url.starts_with("data:") => {
+                let media = url[5..].split([';', ',']).next().unwrap_or("image");
+                Some(format!("[Image: {media}]"))
+            }
+            Some(url) => Some(format!("[Image] {url}")),
+            None => Some("[Image]".to_string()),
+        },
+        "audio" => Some(format!(
+            "[Audio: {}]",
+            field("/mimeType").unwrap_or("audio")
+        )),
+        "document" => Some(format!(
+            "[Document: {}]",
+            field("/source/media_type").unwrap_or("document")
+        )),
+        "resource_link" => {
+            let uri = field("/uri")?;
+            Some(match field("/name") {
+                Some(name) if name != uri => format!("[Resource: {name}] {uri}"),
+                _ => format!("[Resource] {uri}"),
+            })
+        }
+        "resource" => {
+            let uri = field("/resource/uri").unwrap_or("resource");
+            Some(match field("/resource/text") {
+                Some(text) => format!("[Resource] {uri}\n{text}"),
+                None => format!(
+                    "[Resource: {}] {uri}",
+                    field("/resource/mimeType").unwrap_or("binary")
+                ),
+            })
+        }
+        _ => extract_text(part, 0),
+    }
+}
+
 fn format_tool_call(value: &Value) -> String {
     let name = value
         .get("name")
@@ -140,6 +524,216 @@ fn format_tool_call(value: &Value) -> String {
     out
 }
 
+/// Extract the text between `<tag>` and `</tag>`, if present
+fn extract_tag<'a>(text: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let start = text.find(&open)? + open.len();
+    let end = text[start..].find(&close)? + start;
+    Some(&text[start..end])
+}
+
+/// Parse a Claude slash command invocation (`<command-name>` tags) into "/name args"
+pub fn parse_slash_command(text: &str) -> Option<String> {
+    let name = extract_tag(text, "command-name")?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let name = if name.starts_with('/') {
+        name.to_string()
+    } else {
+        format!("/{name}")
+    };
+    match extract_tag(text, "command-args").map(str::trim) {
+        Some(args) if !args.is_empty() => Some(format!("{name} {args}")),
+        _ => Some(name),
+    }
+}
+
+/// Format a Skill tool call as a command invocation ("/skill args")
+fn format_skill_invocation(input: Option<&Value>) -> String {
+    let skill = input
+        .and_then(|i| i.get("skill").or_else(|| i.get("command")))
+        .and_then(|v| v.as_str())
+        .unwrap_or("skill");
+    let skill = skill.trim_start_matches('/');
+    match input.and_then(|i| i.get("args")).and_then(|v| v.as_str()) {
+        Some(args) if !args.trim().is_empty() => format!("/{skill} {}", args.trim()),
+        _ => format!("/{skill}"),
+    }
+}
+
+/// Turn the list items of a plan into a markdown checklist ("- [ ] step")
+pub fn plan_checklist(plan: &str) -> String {
+    plan.trim()
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let indent = &line[..line.len() - trimmed.len()];
+            let item = ["- ", "* ", "+ "]
+                .iter()
+                .find_map(|m| trimmed.strip_prefix(m))
+                .or_else(|| {
+                    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+                    (digits > 0)
+                        .then(|| &trimmed[digits..])
+                        .and_then(|rest| rest.strip_prefix(". ").or(rest.strip_prefix(") ")))
+                });
+            match item {
+                Some(text) if !text.starts_with("[ ]") && !text.starts_with("[x]") => {
+                    format!("{indent}- [ ] {text}")
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Longest diff kept per edit; reports show less than this
+pub const DIFF_MAX_CHARS: usize = 4000;
+
+/// Files named in an apply_patch body ("*** Update File: path"), each with
+/// the hunk lines that follow its header
+fn patch_files(text: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut current: Option<usize> = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(path) = ["*** Update File: ", "*** Add File: ", "*** Delete File: "]
+            .iter()
+            .find_map(|prefix| trimmed.strip_prefix(prefix))
+        {
+            files.push(FileDiff {
+                path: path.trim().to_string(),
+                diff: String::new(),
+            });
+            current = Some(files.len() - 1);
+        } else if trimmed.starts_with("*** ") {
+            // "*** End Patch", "*** Move to: ..."
+            current = None;
+        } else if let Some(index) = current {
+            let diff = &mut files[index].diff;
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    files
+}
+
+/// Record an edit of `path` that changed `old` to `new`
+fn record_edit(result: &mut ParseResult, path: &str, old: &str, new: &str) {
+    *result.edited_files.entry(path.to_string()).or_insert(0) += 1;
+    let mut diff = String::new();
+    for (prefix, text) in [('-', old), ('+', new)] {
+        for line in text.lines() {
+            diff.push(prefix);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    result.diffs.push(FileDiff {
+        path: path.to_string(),
+        diff: truncate(&diff, DIFF_MAX_CHARS),
+    });
+}
+
+/// Record the edits a Claude Edit, MultiEdit, Write, or NotebookEdit call made
+fn record_claude_edits(input: &Value, result: &mut ParseResult) {
+    let Some(path) = input
+        .get("file_path")
+        .or(input.get("notebook_path"))
+        .and_then(|v| v.as_str())
+    else {
+        return;
+    };
+    let field = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    match input.get("edits").and_then(|v| v.as_array()) {
+        Some(edits) => {
+            let old: Vec<String> = edits.iter().map(|e| field(e, "old_string")).collect();
+            let new: Vec<String> = edits.iter().map(|e| field(e, "new_string")).collect();
+            record_edit(result, path, &old.join("\n"), &new.join("\n"));
+        }
+        None => {
+            let new = ["new_string", "content", "new_source"]
+                .iter()
+                .map(|key| field(input, key))
+                .find(|text| !text.is_empty())
+                .unwrap_or_default();
+            record_edit(result, path, &field(input, "old_string"), &new);
+        }
+    }
+}
+
+/// Count files edited by a Codex tool call (apply_patch via a function or custom tool)
+fn record_patch_files(value: &Value, result: &mut ParseResult) {
+    match value {
+        Value::String(text) => {
+            // Function arguments arrive as a JSON-encoded string
+            if let Ok(inner) = serde_json::from_str::<Value>(text)
+                && !inner.is_string()
+            {
+                record_patch_files(&inner, result);
+                return;
+            }
+            for file in patch_files(text) {
+                *result.edited_files.entry(file.path.clone()).or_insert(0) += 1;
+                result.diffs.push(FileDiff {
+                    diff: truncate(&file.diff, DIFF_MAX_CHARS),
+                    ..file
+                });
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| record_patch_files(v, result)),
+        Value::Object(map) => map.values().for_each(|v| record_patch_files(v, result)),
+        _ => {}
+    }
+}
+
+/// Read the `todos` list from a TodoWrite call
+fn parse_todos(input: &Value) -> Option<Vec<TaskItem>> {
+    let todos = input.get("todos")?.as_array()?;
+    Some(
+        todos
+            .iter()
+            .filter_map(|todo| {
+                Some(TaskItem {
+                    content: todo.get("content")?.as_str()?.to_string(),
+                    status: todo
+                        .get("status")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("pending")
+                        .to_string(),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Compact TodoWrite summary: progress plus the items that changed
+fn todo_summary(progress: &TaskProgress, changes: &[TaskItem]) -> String {
+    let mut out = format!(
+        "TodoWrite · {}/{} done",
+        progress.completed(),
+        progress.items.len()
+    );
+    for item in changes {
+        let mark = match item.status.as_str() {
+            "completed" => "✓",
+            "in_progress" => "→",
+            _ => "○",
+        };
+        out.push_str(&format!("\n{mark} {}", item.content));
+    }
+    out
+}
+
 fn tool_summary(value: &Value) -> String {
     let name = value
         .get("name")
@@ -190,37 +784,37 @@ fn format_tool_calls(value: &Value) -> String {
 }
 
 fn extract_content(value: &Value) -> Option<String> {
-    if let Some(content) = value.get("content") {
-        if let Some(text) = extract_text(content, 0) {
-            return Some(text);
-        }
+    if let Some(content) = value.get("content")
+        && let Some(text) = extract_text(content, 0)
+    {
+        return Some(text);
     }
     if let Some(message) = value.get("message") {
-        if let Some(content) = message.get("content") {
-            if let Some(text) = extract_text(content, 0) {
-                return Some(text);
-            }
+        if let Some(content) = message.get("content")
+            && let Some(text) = extract_text(content, 0)
+        {
+            return Some(text);
         }
         if let Some(text) = extract_text(message, 0) {
             return Some(text);
         }
     }
     for key in ["text", "delta", "output_text", "input_text", "message_text"] {
-        if let Some(value) = value.get(key) {
-            if let Some(text) = extract_text(value, 0) {
-                return Some(text);
-            }
-        }
-    }
-    if let Some(output) = value.get("output") {
-        if let Some(text) = extract_text(output, 0) {
+        if let Some(value) = value.get(key)
+            && let Some(text) = extract_text(value, 0)
+        {
             return Some(text);
         }
     }
-    if let Some(input) = value.get("input") {
-        if let Some(text) = extract_text(input, 0) {
-            return Some(text);
-        }
+    if let Some(output) = value.get("output")
+        && let Some(text) = extract_text(output, 0)
+    {
+        return Some(text);
+    }
+    if let Some(input) = value.get("input")
+        && let Some(text) = extract_text(input, 0)
+    {
+        return Some(text);
     }
     if let Some(tool_calls) = value.get("tool_calls") {
         return Some(format_tool_calls(tool_calls));
@@ -234,49 +828,151 @@ fn extract_content(value: &Value) -> Option<String> {
     None
 }
 
-/// Extract transcript metadata (title, first user message)
+/// Extract transcript metadata (title, first user message), through the
+/// registered source that reads the file when there is one
 pub fn extract_transcript_meta(path: &Path) -> TranscriptMeta {
-    let mut meta = TranscriptMeta::default();
-    let file = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return meta,
+    match registered_for(path) {
+        Some(source) => source.metadata(path),
+        None => jsonl_transcript_meta(path),
+    }
+}
+
+/// Metadata of a JSONL transcript file
+pub(super) fn jsonl_transcript_meta(path: &Path) -> TranscriptMeta {
+    match File::open(path) {
+        Ok(file) => extract_transcript_meta_from_reader(BufReader::new(file)),
+        Err(_) => TranscriptMeta::default(),
+    }
+}
+
+/// Title set by a `/title` or `/rename` command, typed as plain text (Codex)
+/// or recorded as Claude command markup
+fn title_command(text: &str) -> Option<String> {
+    let command = parse_slash_command(text).unwrap_or_else(|| text.trim().to_string());
+    let title = ["/title ", "/rename "]
+        .iter()
+        .find_map(|prefix| command.strip_prefix(prefix))?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Title set anywhere in the session by a JSONL entry: Claude's `custom-title`
+/// records, the conversation title of a ChatGPT or Claude.ai import, or a user
+/// message running `/title`
+fn title_from_entry(value: &Value) -> Option<String> {
+    let field = match value.get("type").and_then(|v| v.as_str()) {
+        Some("custom-title") => Some("customTitle"),
+        Some("chatgpt_session" | "claude_ai_session") => Some("title"),
+        _ => None,
     };
-    let reader = BufReader::new(file);
+    if let Some(field) = field {
+        return value
+            .get(field)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+    }
+    let text = value
+        .pointer("/message/content")
+        .or_else(|| {
+            value.pointer("/payload/message").filter(|_| {
+                value.pointer("/payload/type").and_then(|v| v.as_str()) == Some("user_message")
+            })
+        })
+        .and_then(|v| v.as_str())?;
+    title_command(text)
+}
+
+/// Extract transcript metadata from JSONL read from `reader`
+pub fn extract_transcript_meta_from_reader(reader: impl BufRead) -> TranscriptMeta {
+    let mut meta = TranscriptMeta::default();
+    // Only the first lines are searched for the rest, but a title can be set
+    // at any point, so later lines are skimmed for one
+    let mut skimming = false;
 
-    for line in reader.lines().take(100) {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
+    for (index, line) in reader.split(b'\n').enumerate() {
+        let Ok(line) = line else {
+            continue;
         };
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
+        skimming |= index >= 100;
+        let text = String::from_utf8_lossy(&line);
+        if skimming
+            && !["custom-title", "/title", "/rename"]
+                .iter()
+                .any(|m| text.contains(m))
+        {
             continue;
         }
-        let value: Value = match serde_json::from_str(trimmed) {
-            Ok(v) => v,
-            Err(_) => continue,
+        let values = match read_line(&line) {
+            Line::Clean(value) => vec![value],
+            Line::Repaired(values) => values,
+            Line::Blank | Line::Unreadable => continue,
         };
+        for value in values {
+            if let Some(title) = title_from_entry(&value) {
+                meta.custom_title = Some(title);
+            }
+            if skimming {
+                continue;
+            }
 
-        // Claude: look for slug field on user messages
-        if meta.slug.is_none() {
-            if let Some(slug) = value.get("slug").and_then(|v| v.as_str()) {
+            // Claude: look for slug field on user messages
+            if meta.slug.is_none()
+                && let Some(slug) = value.get("slug").and_then(|v| v.as_str())
+            {
                 meta.slug = Some(slug.to_string());
             }
-        }
 
-        // Extract first user message content
-        if meta.first_user_message.is_none() {
-            let is_user = value.get("type").and_then(|v| v.as_str()) == Some("user")
-                || value.pointer("/message/role").and_then(|v| v.as_str()) == Some("user")
-                || value.get("role").and_then(|v| v.as_str()) == Some("user");
-            if is_user {
-                if let Some(content) = value
-                    .pointer("/message/content")
+            // Claude: compaction summaries sit at the top of resumed sessions
+            if meta.summary.is_none()
+                && value.get("type").and_then(|v| v.as_str()) == Some("summary")
+                && let Some(summary) = value.get("summary").and_then(|v| v.as_str())
+            {
+                meta.summary = Some(summary.trim().to_string());
+            }
+
+            // Claude stamps cwd/gitBranch on each entry; Codex puts them in session_meta
+            let session = value
+                .get("payload")
+                .filter(|_| value.get("type").and_then(|v| v.as_str()) == Some("session_meta"));
+            if meta.cwd.is_none()
+                && let Some(cwd) = session
+                    .unwrap_or(&value)
+                    .get("cwd")
                     .and_then(|v| v.as_str())
-                    .or_else(|| value.get("content").and_then(|v| v.as_str()))
+            {
+                meta.cwd = Some(cwd.to_string());
+            }
+            if meta.branch.is_none()
+                && let Some(branch) = session
+                    .and_then(|p| p.pointer("/git/branch"))
+                    .or_else(|| value.get("gitBranch"))
+                    .and_then(|v| v.as_str())
+                    .filter(|b| !b.is_empty())
+            {
+                meta.branch = Some(branch.to_string());
+            }
+
+            // Extract first user message content
+            if meta.first_user_message.is_none() {
+                let is_user = value.get("type").and_then(|v| v.as_str()) == Some("user")
+                    || value.pointer("/message/role").and_then(|v| v.as_str()) == Some("user")
+                    || value.get("role").and_then(|v| v.as_str()) == Some("user");
+                if is_user
+                    && let Some(content) = value
+                        .pointer("/message/content")
+                        .and_then(|v| v.as_str())
+                        .or_else(|| value.get("content").and_then(|v| v.as_str()))
                 {
                     let trimmed = content.trim();
-                    if !trimmed.is_empty() && !looks_like_internal_block(trimmed) {
+                    if !trimmed.is_empty()
+                        && !looks_like_internal_block(trimmed)
+                        && parse_slash_command(trimmed).is_none()
+                        && title_command(trimmed).is_none()
+                    {
                         // Truncate to reasonable title length
                         let title = if trimmed.len() > 100 {
                             format!("{}...", &trimmed[..100])
@@ -287,50 +983,201 @@ pub fn extract_transcript_meta(path: &Path) -> TranscriptMeta {
                     }
                 }
             }
-        }
 
-        // Stop early if we have what we need
-        if meta.slug.is_some() && meta.first_user_message.is_some() {
-            break;
+            // Skim the rest once we have what we need
+            skimming =
+                meta.slug.is_some() && meta.first_user_message.is_some() && meta.cwd.is_some();
         }
     }
 
     meta
 }
 
-/// Parse a transcript file into messages and metadata
+/// Parse a transcript file into messages and metadata, through the
+/// registered source that reads the file when there is one
 pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
+    match registered_for(path) {
+        Some(source) => source.parse(path),
+        None => parse_jsonl_transcript(path),
+    }
+}
+
+/// Parse a JSONL transcript file
+pub(super) fn parse_jsonl_transcript(path: &Path) -> Result<ParseResult> {
     let file = File::open(path)?;
-    let reader = BufReader::new(file);
+    parse_transcript_from_reader(BufReader::new(file))
+}
+
+/// Collapse an error message to one line and keep it short
+fn error_summary(message: &str) -> String {
+    truncate(
+        &message.split_whitespace().collect::<Vec<_>>().join(" "),
+        200,
+    )
+}
+
+fn record_error(result: &mut ParseResult, message: &str) {
+    let summary = error_summary(message);
+    if !summary.is_empty() && result.errors.last() != Some(&summary) && result.errors.len() < 10 {
+        result.errors.push(summary);
+    }
+    result.status = SessionStatus::Errored;
+}
+
+/// Update the session outcome from an event. The status reflects the last turn:
+/// a successful reply after an error or interruption counts as completed.
+fn track_outcome(value: &Value, event_type: &str, result: &mut ParseResult) {
+    match event_type {
+        // Codex
+        "event_msg" => {
+            let payload_type = value
+                .pointer("/payload/type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            match payload_type {
+                "turn_aborted" => result.status = SessionStatus::Interrupted,
+                "error" | "stream_error" => {
+                    let message = value
+                        .pointer("/payload/message")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(payload_type);
+                    record_error(result, message);
+                }
+                "agent_message" | "task_complete" => result.status = SessionStatus::Completed,
+                _ => {}
+            }
+        }
+        "response_item"
+            if value.pointer("/payload/type").and_then(|v| v.as_str()) == Some("message")
+                && value.pointer("/payload/role").and_then(|v| v.as_str()) == Some("assistant") =>
+        {
+            result.status = SessionStatus::Completed;
+        }
+        // Cursor, Aider, and imported chat snapshots
+        "cursor_message" | "aider_message" | "chatgpt_message" | "claude_ai_message"
+            if value.pointer("/message/role").and_then(|v| v.as_str()) == Some("assistant") =>
+        {
+            result.status = SessionStatus::Completed;
+        }
+        // Claude
+        "user"
+            if value
+                .pointer("/message/content")
+                .and_then(|c| extract_text(c, 0))
+                .is_some_and(|t| t.trim_start().starts_with("[Request interrupted by user")) =>
+        {
+            result.status = SessionStatus::Interrupted;
+        }
+        "assistant" => {
+            if value.get("isApiErrorMessage").and_then(|v| v.as_bool()) == Some(true) {
+                let message = value
+                    .pointer("/message/content")
+                    .and_then(|c| extract_text(c, 0))
+                    .unwrap_or_else(|| "API error".to_string());
+                record_error(result, &message);
+            } else if value
+                .pointer("/message/content")
+                .and_then(|v| v.as_array())
+                .is_some_and(|blocks| {
+                    blocks
+                        .iter()
+                        .any(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+                })
+            {
+                result.status = SessionStatus::Completed;
+            }
+        }
+        "system" => {
+            let is_error = value.get("level").and_then(|v| v.as_str()) == Some("error")
+                || value
+                    .get("subtype")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|s| s.contains("error"));
+            if is_error {
+                let message = value
+                    .get("content")
+                    .and_then(|v| v.as_str())
+                    .or_else(|| value.pointer("/error/message").and_then(|v| v.as_str()))
+                    .or_else(|| {
+                        value
+                            .pointer("/error/error/message")
+                            .and_then(|v| v.as_str())
+                    })
+                    .unwrap_or("system error");
+                record_error(result, message);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parse JSONL transcript content read from `reader`
+pub fn parse_transcript_from_reader(reader: impl BufRead) -> Result<ParseResult> {
     let mut result = ParseResult::default();
     let mut codex_mode = false;
     let mut current_model: Option<String> = None;
-
-    for line in reader.lines() {
-        let line = line?;
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        let value: Value = match serde_json::from_str(trimmed) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+    // Most recent command/skill invocation awaiting its expanded prompt or output
+    let mut pending_command: Option<(usize, &str)> = None;
+    let mut event_time: Option<OffsetDateTime> = None;
+    let mut retries = RetryTracker::default();
+    let mut branches = BranchTracker::default();
+    let mut event_node: Option<String> = None;
+    let mut tools = ToolTimer::default();
+
+    let mut values = JsonlValues::new(reader);
+    for value in values.by_ref() {
+        stamp_new_messages(&mut result, event_time.take());
+        branches.stamp(result.messages.len(), event_node.take());
+        let value = value?;
 
         let event_type = value.get("type").and_then(|v| v.as_str()).unwrap_or("");
 
+        if let Some(ts) = value.get("timestamp").and_then(|v| v.as_str())
+            && let Ok(ts) = OffsetDateTime::parse(ts, &Rfc3339)
+        {
+            result.started_at = Some(result.started_at.map_or(ts, |s| s.min(ts)));
+            result.ended_at = Some(result.ended_at.map_or(ts, |e| e.max(ts)));
+            event_time = Some(ts);
+        }
+        track_outcome(&value, event_type, &mut result);
+        event_node = branches.record(&value, event_type);
+
         // Detect Codex mode
         if event_type == "session_meta" {
             if value
                 .pointer("/payload/originator")
                 .and_then(|v| v.as_str())
-                == Some("codex_cli_rs")
+                .is_some_and(|o| matches!(o, "codex_cli_rs" | "codex_exec"))
             {
                 codex_mode = true;
             }
             continue;
         }
 
+        // Cursor, Aider, and imported chat snapshots hold messages already
+        // rendered from the tool's chat database, history file, or export
+        if matches!(
+            event_type,
+            "cursor_session" | "aider_session" | "chatgpt_session" | "claude_ai_session"
+        ) {
+            continue;
+        }
+        if matches!(
+            event_type,
+            "cursor_message" | "aider_message" | "chatgpt_message" | "claude_ai_message"
+        ) {
+            if let Some(message) = value
+                .get("message")
+                .and_then(|m| RenderedMessage::deserialize(m).ok())
+            {
+                if let Some(model) = &message.model {
+                    *result.model_counts.entry(model.clone()).or_insert(0) += 1;
+                }
+                result.messages.push(message);
+            }
+            continue;
+        }
+
         // Skip internal events (but process event_msg in Codex mode for token usage)
         if matches!(event_type, "file-history-snapshot" | "queue-operation") {
             continue;
@@ -346,6 +1193,11 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                     raw_label: None,
                     tool_use_id: None,
                     model: None,
+                    abandoned: false,
+                    part: None,
+                    id: None,
+                    highlighted: false,
+                    images: Vec::new(),
                 });
             }
             continue;
@@ -367,24 +1219,18 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
             // Extract token usage from event_msg (Codex reports cumulative totals)
             if event_type == "event_msg" {
                 if let Some(payload_type) = value.pointer("/payload/type").and_then(|v| v.as_str())
+                    && payload_type == "token_count"
+                    && let Some(usage) = value.pointer("/payload/info/total_token_usage")
                 {
-                    if payload_type == "token_count" {
-                        if let Some(usage) = value.pointer("/payload/info/total_token_usage") {
-                            if let Some(input) = usage.get("input_tokens").and_then(|v| v.as_u64())
-                            {
-                                result.codex_total_input_tokens = input; // cumulative total
-                            }
-                            if let Some(output) =
-                                usage.get("output_tokens").and_then(|v| v.as_u64())
-                            {
-                                result.codex_total_output_tokens = output;
-                            }
-                            if let Some(cached) =
-                                usage.get("cached_input_tokens").and_then(|v| v.as_u64())
-                            {
-                                result.codex_total_cache_read_tokens = cached;
-                            }
-                        }
+                    if let Some(input) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
+                        result.codex_total_input_tokens = input; // cumulative total
+                    }
+                    if let Some(output) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
+                        result.codex_total_output_tokens = output;
+                    }
+                    if let Some(cached) = usage.get("cached_input_tokens").and_then(|v| v.as_u64())
+                    {
+                        result.codex_total_cache_read_tokens = cached;
                     }
                 }
                 continue;
@@ -413,6 +1259,11 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                                     raw_label: None,
                                     tool_use_id: None,
                                     model: current_model.clone(),
+                                    abandoned: false,
+                                    part: None,
+                                    id: None,
+                                    highlighted: false,
+                                    images: image_data_uri(block).into_iter().collect(),
                                 });
                             }
                         }
@@ -431,9 +1282,18 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                             raw_label: None,
                             tool_use_id: None,
                             model,
+                            abandoned: false,
+                            part: None,
+                            id: None,
+                            highlighted: false,
+                            images: Vec::new(),
                         });
                     }
                 } else if payload_type == "function_call" {
+                    result.tool_call_count += 1;
+                    if let Some(args) = payload.get("arguments") {
+                        record_patch_files(args, &mut result);
+                    }
                     let name = payload
                         .get("name")
                         .and_then(|v| v.as_str())
@@ -442,6 +1302,7 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                         .get("call_id")
                         .and_then(|v| v.as_str())
                         .map(|s| s.to_string());
+                    tools.call(&mut result.tool_stats, name, call_id.as_deref(), event_time);
                     let args = payload.get("arguments");
                     let content = if let Some(a) = args {
                         let pretty = serde_json::to_string_pretty(a).unwrap_or_default();
@@ -459,23 +1320,35 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                         raw_label: Some("Results".to_string()),
                         tool_use_id: call_id,
                         model: None,
+                        abandoned: false,
+                        part: None,
+                        id: None,
+                        highlighted: false,
+                        images: Vec::new(),
                     });
                 } else if payload_type == "function_call_output" {
                     let call_id = payload
                         .get("call_id")
                         .and_then(|v| v.as_str())
                         .map(|s| s.to_string());
-                    let output = payload
-                        .get("output")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("[output]");
+                    tools.result(&mut result.tool_stats, call_id.as_deref(), event_time);
+                    let output = payload.get("output");
+                    let text = output
+                        .and_then(tool_result_text)
+                        .unwrap_or_else(|| "[output]".to_string());
+                    let (content, raw) = tool_output_parts(&text);
                     result.messages.push(RenderedMessage {
                         role: "tool".to_string(),
-                        content: truncate(output, 500),
-                        raw: None,
-                        raw_label: None,
+                        raw_label: raw.as_ref().map(|_| "Binary".to_string()),
+                        content,
+                        raw,
                         tool_use_id: call_id,
                         model: None,
+                        abandoned: false,
+                        part: None,
+                        id: None,
+                        highlighted: false,
+                        images: output.map(tool_result_images).unwrap_or_default(),
                     });
                 } else if payload_type == "reasoning" {
                     // Codex reasoning/thinking - extract summary text (full content is encrypted)
@@ -501,10 +1374,32 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                                 raw_label: None,
                                 tool_use_id: None,
                                 model: current_model.clone(),
+                                abandoned: false,
+                                part: None,
+                                id: None,
+                                highlighted: false,
+                                images: Vec::new(),
                             });
                         }
                     }
                 } else if is_tool_payload(payload) {
+                    let call_id = payload
+                        .get("call_id")
+                        .or_else(|| payload.get("id"))
+                        .and_then(|v| v.as_str());
+                    if !payload_type.contains("output") && !payload_type.contains("result") {
+                        result.tool_call_count += 1;
+                        if let Some(input) = payload.get("input") {
+                            record_patch_files(input, &mut result);
+                        }
+                        let name = payload
+                            .get("name")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or(payload_type);
+                        tools.call(&mut result.tool_stats, name, call_id, event_time);
+                    } else {
+                        tools.result(&mut result.tool_stats, call_id, event_time);
+                    }
                     let content = tool_summary(payload);
                     let raw = serde_json::to_string_pretty(payload)
                         .ok()
@@ -520,6 +1415,11 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                         raw_label: Some("Tool payload".to_string()),
                         tool_use_id: tool_id,
                         model: None,
+                        abandoned: false,
+                        part: None,
+                        id: None,
+                        highlighted: false,
+                        images: Vec::new(),
                     });
                 }
             }
@@ -529,8 +1429,62 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
         // ===== CLAUDE FORMAT =====
         match event_type {
             "user" => {
+                // Expanded slash command prompts and loaded skills are meta messages that
+                // follow their invocation; group them under it instead of showing user text
+                let is_meta = value.get("isMeta").and_then(|v| v.as_bool()) == Some(true);
+                if is_meta && let Some((idx, label)) = pending_command {
+                    if let Some(text) = value
+                        .pointer("/message/content")
+                        .and_then(|c| extract_text(c, 0))
+                        .filter(|t| !t.trim().is_empty())
+                    {
+                        let command = &mut result.messages[idx];
+                        command.raw = Some(truncate(&text, 20000));
+                        command.raw_label = Some(label.to_string());
+                    }
+                    continue;
+                }
+
+                // Tool results come back as user messages; they only finish the timing
+                if let Some(blocks) = value.pointer("/message/content").and_then(|v| v.as_array()) {
+                    for block in blocks {
+                        if block.get("type").and_then(|v| v.as_str()) == Some("tool_result") {
+                            let id = block.get("tool_use_id").and_then(|v| v.as_str());
+                            tools.result(&mut result.tool_stats, id, event_time);
+                        }
+                    }
+                }
+
                 // User message: message.content is a string
                 if let Some(content) = value.pointer("/message/content").and_then(|v| v.as_str()) {
+                    // Output of built-in commands (e.g. /cost) belongs to the command
+                    if let Some(output) = extract_tag(content, "local-command-stdout") {
+                        if let Some((idx, _)) = pending_command
+                            && !output.trim().is_empty()
+                        {
+                            let command = &mut result.messages[idx];
+                            command.raw = Some(truncate(output.trim(), 20000));
+                            command.raw_label = Some("Output".to_string());
+                        }
+                        continue;
+                    }
+                    if let Some(command) = parse_slash_command(content) {
+                        pending_command = Some((result.messages.len(), "Command prompt"));
+                        result.messages.push(RenderedMessage {
+                            role: "command".to_string(),
+                            content: command,
+                            raw: None,
+                            raw_label: None,
+                            tool_use_id: None,
+                            model: None,
+                            abandoned: false,
+                            part: None,
+                            id: None,
+                            highlighted: false,
+                            images: Vec::new(),
+                        });
+                        continue;
+                    }
                     // Skip internal/system messages
                     if content.starts_with("Caveat:")
                         || content.starts_with("Unknown slash command:")
@@ -548,6 +1502,7 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                     } else {
                         "user"
                     };
+                    pending_command = None;
                     result.messages.push(RenderedMessage {
                         role: role.to_string(),
                         content: content.to_string(),
@@ -555,10 +1510,17 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                         raw_label: None,
                         tool_use_id: None,
                         model: None,
+                        abandoned: false,
+                        part: None,
+                        id: None,
+                        highlighted: false,
+                        images: Vec::new(),
                     });
                 }
             }
             "assistant" => {
+                pending_command = None;
+
                 // Extract model from message.model
                 let model = value
                     .pointer("/message/model")
@@ -602,10 +1564,13 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                             output_tokens: output,
                             cache_read_tokens: cache_read,
                             cache_creation_tokens: cache_create,
+                            model: model.clone(),
                         },
                     );
                 }
 
+                let msg_id = value.pointer("/message/id").and_then(|v| v.as_str());
+
                 // Assistant message: message.content is array of blocks
                 if let Some(content_arr) =
                     value.pointer("/message/content").and_then(|v| v.as_array())
@@ -614,27 +1579,109 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                         let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
                         match block_type {
                             "text" => {
-                                if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                                    if !text.trim().is_empty() {
-                                        result.messages.push(RenderedMessage {
+                                if let Some(text) = block.get("text").and_then(|v| v.as_str())
+                                    && !text.trim().is_empty()
+                                {
+                                    let folded = retries.push(
+                                        &mut result.messages,
+                                        RenderedMessage {
                                             role: "assistant".to_string(),
                                             content: text.to_string(),
                                             raw: None,
                                             raw_label: None,
                                             tool_use_id: None,
                                             model: model.clone(),
-                                        });
+                                            abandoned: false,
+                                            part: None,
+                                            id: None,
+                                            highlighted: false,
+                                            images: Vec::new(),
+                                        },
+                                        msg_id,
+                                    );
+                                    if let Some(index) = folded {
+                                        branches.adopt(index, event_node.clone());
                                     }
                                 }
                             }
                             "tool_use" => {
+                                result.tool_call_count += 1;
                                 let name =
                                     block.get("name").and_then(|v| v.as_str()).unwrap_or("tool");
                                 let tool_id = block
                                     .get("id")
                                     .and_then(|v| v.as_str())
                                     .map(|s| s.to_string());
+                                tools.call(
+                                    &mut result.tool_stats,
+                                    name,
+                                    tool_id.as_deref(),
+                                    event_time,
+                                );
                                 let input = block.get("input");
+                                if matches!(name, "Edit" | "MultiEdit" | "Write" | "NotebookEdit")
+                                    && let Some(input) = input
+                                {
+                                    record_claude_edits(input, &mut result);
+                                }
+                                if name == "TodoWrite"
+                                    && let Some(items) = input.and_then(parse_todos)
+                                {
+                                    let progress = result.tasks.get_or_insert_default();
+                                    let changes = progress.update(items);
+                                    let content = todo_summary(progress, &changes);
+                                    result.messages.push(RenderedMessage {
+                                        role: "tool".to_string(),
+                                        content,
+                                        raw: serde_json::to_string_pretty(block).ok(),
+                                        raw_label: Some("Todos".to_string()),
+                                        tool_use_id: tool_id,
+                                        model: None,
+                                        abandoned: false,
+                                        part: None,
+                                        id: None,
+                                        highlighted: false,
+                                        images: Vec::new(),
+                                    });
+                                    continue;
+                                }
+                                // Plan mode: show the proposed plan as a checklist
+                                if name == "ExitPlanMode"
+                                    && let Some(plan) =
+                                        input.and_then(|i| i.get("plan")).and_then(|v| v.as_str())
+                                {
+                                    result.messages.push(RenderedMessage {
+                                        role: "plan".to_string(),
+                                        content: plan_checklist(plan),
+                                        raw: None,
+                                        raw_label: None,
+                                        tool_use_id: tool_id,
+                                        model: model.clone(),
+                                        abandoned: false,
+                                        part: None,
+                                        id: None,
+                                        highlighted: false,
+                                        images: Vec::new(),
+                                    });
+                                    continue;
+                                }
+                                if name == "Skill" {
+                                    pending_command = Some((result.messages.len(), "Skill output"));
+                                    result.messages.push(RenderedMessage {
+                                        role: "command".to_string(),
+                                        content: format_skill_invocation(input),
+                                        raw: None,
+                                        raw_label: None,
+                                        tool_use_id: tool_id,
+                                        model: None,
+                                        abandoned: false,
+                                        part: None,
+                                        id: None,
+                                        highlighted: false,
+                                        images: Vec::new(),
+                                    });
+                                    continue;
+                                }
                                 let content = if let Some(inp) = input {
                                     let pretty =
                                         serde_json::to_string_pretty(inp).unwrap_or_default();
@@ -652,6 +1699,11 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                                     raw_label: Some("Results".to_string()),
                                     tool_use_id: tool_id,
                                     model: None,
+                                    abandoned: false,
+                                    part: None,
+                                    id: None,
+                                    highlighted: false,
+                                    images: Vec::new(),
                                 });
                             }
                             "tool_result" => {
@@ -659,38 +1711,59 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                                     .get("tool_use_id")
                                     .and_then(|v| v.as_str())
                                     .map(|s| s.to_string());
-                                let content = block
-                                    .get("content")
-                                    .and_then(|v| v.as_str())
-                                    .or_else(|| block.get("output").and_then(|v| v.as_str()))
-                                    .unwrap_or("[result]");
+                                tools.result(
+                                    &mut result.tool_stats,
+                                    tool_id.as_deref(),
+                                    event_time,
+                                );
+                                let output = block.get("content").or_else(|| block.get("output"));
+                                let text = output
+                                    .and_then(tool_result_text)
+                                    .unwrap_or_else(|| "[result]".to_string());
+                                let (content, raw) = tool_output_parts(&text);
                                 result.messages.push(RenderedMessage {
                                     role: "tool".to_string(),
-                                    content: truncate(content, 500),
-                                    raw: None,
-                                    raw_label: None,
+                                    raw_label: raw.as_ref().map(|_| "Binary".to_string()),
+                                    content,
+                                    raw,
                                     tool_use_id: tool_id,
                                     model: None,
+                                    abandoned: false,
+                                    part: None,
+                                    id: None,
+                                    highlighted: false,
+                                    images: output.map(tool_result_images).unwrap_or_default(),
                                 });
                             }
                             "thinking" => {
                                 if let Some(thinking_text) =
                                     block.get("thinking").and_then(|v| v.as_str())
+                                    && !thinking_text.trim().is_empty()
                                 {
-                                    if !thinking_text.trim().is_empty() {
-                                        result.messages.push(RenderedMessage {
+                                    let folded = retries.push(
+                                        &mut result.messages,
+                                        RenderedMessage {
                                             role: "thinking".to_string(),
                                             content: thinking_text.to_string(),
                                             raw: None,
                                             raw_label: None,
                                             tool_use_id: None,
                                             model: model.clone(),
-                                        });
+                                            abandoned: false,
+                                            part: None,
+                                            id: None,
+                                            highlighted: false,
+                                            images: Vec::new(),
+                                        },
+                                        msg_id,
+                                    );
+                                    if let Some(index) = folded {
+                                        branches.adopt(index, event_node.clone());
                                     }
                                 }
                             }
                             "image" => {
-                                // Placeholder for images - don't include base64 data
+                                // Placeholder text; the data is only shared with --include-images
                                 result.messages.push(RenderedMessage {
                                     role: "assistant".to_string(),
                                     content: "[Image]".to_string(),
@@ -698,6 +1771,11 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
                                     raw_label: None,
                                     tool_use_id: None,
                                     model: model.clone(),
+                                    abandoned: false,
+                                    part: None,
+                                    id: None,
+                                    highlighted: false,
+                                    images: image_data_uri(block).into_iter().collect(),
                                 });
                             }
                             _ => {}
@@ -713,13 +1791,28 @@ pub fn parse_transcript(path: &Path) -> Result<ParseResult> {
             }
         }
     }
+    stamp_new_messages(&mut result, event_time);
+    branches.stamp(result.messages.len(), event_node);
+    result.recovery = values.recovery;
+    for (index, message) in result.messages.iter_mut().enumerate() {
+        message.id = Some(index as u32 + 1);
+    }
+    branches.mark(&mut result.messages);
+    retries.annotate(&mut result.messages);
 
     Ok(result)
 }
 
+/// Record the time of the event that produced the messages added since the last call
+fn stamp_new_messages(result: &mut ParseResult, at: Option<OffsetDateTime>) {
+    let added = result.messages.len() - result.message_times.len();
+    result.message_times.extend(std::iter::repeat_n(at, added));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transcript::TitleSource;
     use std::fs;
     use tempfile::TempDir;
 
@@ -781,7 +1874,9 @@ mod tests {
 
     #[test]
     fn test_looks_like_internal_block_agents_md() {
-        assert!(looks_like_internal_block("# AGENTS.md\nThis is agents config"));
+        assert!(looks_like_internal_block(
+            "# AGENTS.md\nThis is agents config"
+        ));
     }
 
     #[test]
@@ -805,7 +1900,9 @@ mod tests {
 
     #[test]
     fn test_looks_like_internal_block_code() {
-        assert!(!looks_like_internal_block("fn main() { println!(\"hello\"); }"));
+        assert!(!looks_like_internal_block(
+            "fn main() { println!(\"hello\"); }"
+        ));
     }
 
     // ===== normalize_role tests =====
@@ -961,6 +2058,92 @@ mod tests {
         assert_eq!(result.total_output_tokens(), 100);
     }
 
+    #[test]
+    fn parse_claude_collapses_retried_answers() {
+        let data = [
+            r#"{"type":"user","message":{"content":"Fix it"}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"thinking","thinking":"Look at the parser"}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"The bug is in"}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"The bug is in the parser."}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_2","content":[{"type":"thinking","thinking":"Look at the parser"}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_2","content":[{"type":"text","text":"The bug is in  the parser.\n"}]}}"#,
+            r#"{"type":"user","message":{"content":"Thanks"}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_3","content":[{"type":"text","text":"The bug is in the parser."}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        let messages: Vec<(&str, &str)> = result
+            .messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("user", "Fix it"),
+                ("thinking", "Look at the parser"),
+                ("assistant", "The bug is in  the parser.\n\n*(retried 1×)*"),
+                ("user", "Thanks"),
+                ("assistant", "The bug is in the parser."),
+            ]
+        );
+        assert_eq!(result.message_times.len(), result.messages.len());
+    }
+
+    #[test]
+    fn parse_claude_marks_abandoned_branches() {
+        let data = [
+            r#"{"type":"user","uuid":"u1","parentUuid":null,"message":{"content":"Write a parser"}}"#,
+            r#"{"type":"assistant","uuid":"a1","parentUuid":"u1","message":{"id":"msg_1","content":[{"type":"text","text":"Here it is"}]}}"#,
+            r#"{"type":"user","uuid":"u2","parentUuid":"a1","message":{"content":"Make it faster"}}"#,
+            r#"{"type":"assistant","uuid":"a2","parentUuid":"u2","message":{"id":"msg_2","content":[{"type":"text","text":"Faster now"}]}}"#,
+            r#"{"type":"user","uuid":"u3","parentUuid":"a1","message":{"content":"Make it safer"}}"#,
+            r#"{"type":"assistant","uuid":"a3","parentUuid":"u3","message":{"id":"msg_3","content":[{"type":"text","text":"Safer now"}]}}"#,
+            r#"{"type":"assistant","uuid":"a4","parentUuid":"u3","message":{"id":"msg_4","content":[{"type":"text","text":"Safer, with tests"}]}}"#,
+            r#"{"type":"assistant","uuid":"a5","parentUuid":"a4","message":{"id":"msg_5","content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a.rs"}}]}}"#,
+            r#"{"type":"assistant","uuid":"a6","parentUuid":"a5","message":{"id":"msg_5","content":[{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"b.rs"}}]}}"#,
+            r#"{"type":"user","uuid":"r1","parentUuid":"a5","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"fn a() {}"}]}}"#,
+            r#"{"type":"user","uuid":"r2","parentUuid":"a6","message":{"content":[{"type":"tool_result","tool_use_id":"t2","content":"fn b() {}"}]}}"#,
+            r#"{"type":"assistant","uuid":"a7","parentUuid":"r2","message":{"id":"msg_6","content":[{"type":"text","text":"Done"}]}}"#,
+        ]
+        .join("\n");
+        let mut result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        let abandoned: Vec<&str> = result
+            .messages
+            .iter()
+            .filter(|m| m.abandoned)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(abandoned, ["Make it faster", "Faster now", "Safer now"]);
+        let ids: Vec<_> = result.messages.iter().filter_map(|m| m.id).collect();
+        assert_eq!(ids, (1..=result.messages.len() as u32).collect::<Vec<_>>());
+        let done_id = result.messages.last().unwrap().id;
+
+        result.drop_abandoned();
+        assert_eq!(result.message_times.len(), result.messages.len());
+        let tools: Vec<_> = result
+            .messages
+            .iter()
+            .filter(|m| m.role == "tool")
+            .collect();
+        assert!(tools.len() == 2 && tools.iter().all(|m| m.raw.is_some()));
+        assert_eq!(result.messages.last().unwrap().content, "Done");
+        // Ids stay put when other messages are dropped, so anchors keep working
+        assert_eq!(result.messages.last().unwrap().id, done_id);
+        assert_eq!(result.messages[1].id, Some(2));
+    }
+
+    #[test]
+    fn parse_claude_keeps_distinct_blocks_of_one_message() {
+        let data = [
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"Let me check."}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"Found it."}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        assert_eq!(result.messages.len(), 2);
+    }
+
     #[test]
     fn parse_codex_reasoning_summary() {
         let tmp = TempDir::new().unwrap();
@@ -1031,6 +2214,416 @@ mod tests {
         let result = parse_transcript(&path).unwrap();
         assert_eq!(result.messages.len(), 2);
         assert_eq!(result.messages[0].content, "[Image]");
+        assert_eq!(result.messages[0].images, ["data:image/png;base64,abc"]);
         assert_eq!(result.messages[1].content, "What is this?");
     }
+
+    #[test]
+    fn parse_tool_result_content_arrays() {
+        let data = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"Screenshot taken"},{"type":"image","source":{"type":"base64","media_type":"image/png","data":"iVBORw0"}},{"type":"resource_link","uri":"file:///tmp/page.html","name":"page.html"}]}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_result","tool_use_id":"t2","content":[{"type":"resource","resource":{"uri":"file:///src/a.diff","mimeType":"text/x-diff","text":"-old\n+new"}},{"type":"resource","resource":{"uri":"file:///out.pdf","mimeType":"application/pdf","blob":"JVBERi0"}}]}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        let codex = [
+            r#"{"type":"session_meta","payload":{"originator":"codex_cli_rs"}}"#,
+            r#"{"type":"response_item","payload":{"type":"function_call_output","call_id":"c1","output":[{"type":"input_text","text":"Rendered"},{"type":"input_image","image_url":"data:image/jpeg;base64,/9j/4AAQ"}]}}"#,
+        ]
+        .join("\n");
+        let codex = parse_transcript_from_reader(codex.as_bytes()).unwrap();
+        let contents: Vec<&str> = result
+            .messages
+            .iter()
+            .chain(&codex.messages)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                "Screenshot taken\n[Image: image/png]\n[Resource: page.html] file:///tmp/page.html",
+                "[Resource] file:///src/a.diff\n-old\n+new\n[Resource: application/pdf] file:///out.pdf",
+                "Rendered\n[Image: image/jpeg]",
+            ]
+        );
+        assert!(contents.iter().all(|c| !c.contains("base64")));
+        // The image data is kept aside for --include-images
+        assert_eq!(result.messages[0].images, ["data:image/png;base64,iVBORw0"]);
+        assert!(result.messages[1].images.is_empty());
+        assert_eq!(
+            codex.messages[0].images,
+            ["data:image/jpeg;base64,/9j/4AAQ"]
+        );
+    }
+
+    #[test]
+    fn binary_tool_output_becomes_placeholder() {
+        let (content, raw) = tool_output_parts("\u{89}PNG\r\n\u{1a}\n\0\0\0\rIHDR");
+        assert_eq!(content, "[binary data, 0.0 KB]");
+        assert!(raw.unwrap().contains("\\0"));
+
+        let garbage: String = "\u{FFFD}\u{1}ab".repeat(2000);
+        let (content, raw) = tool_output_parts(&garbage);
+        assert_eq!(content, "[binary data, 11.7 KB]");
+        assert!(raw.is_none());
+
+        let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let blob: String = (0..6000).map(|i| alphabet[i * 37 % 64] as char).collect();
+        assert!(looks_binary(&blob));
+
+        let (content, raw) = tool_output_parts("fn main() {\n\tprintln!(\"hi\");\n}");
+        assert!(content.starts_with("fn main()"));
+        assert!(raw.is_none());
+        assert!(!looks_binary(&"let x = 1;\n".repeat(1000)));
+    }
+
+    #[test]
+    fn parse_activity_timeline() {
+        let data = [
+            r#"{"type":"user","timestamp":"2025-01-01T10:00:00Z","message":{"content":"Start"}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:04:00Z","message":{"content":[{"type":"text","text":"One"},{"type":"text","text":"Two"}]}}"#,
+            r#"{"type":"user","message":{"content":"Untimed"}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:25:00Z","message":{"content":[{"type":"text","text":"Later"}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        assert_eq!(result.message_times.len(), result.messages.len());
+        assert!(result.message_times[3].is_none());
+
+        let timeline = result.timeline().unwrap();
+        assert_eq!(timeline.bucket_minutes, 10);
+        assert_eq!(timeline.buckets, [3, 0, 1]);
+        assert_eq!(timeline.ended_at - timeline.started_at, 25 * 60);
+    }
+
+    #[test]
+    fn parse_long_session_widens_timeline_buckets() {
+        let data = [
+            r#"{"type":"user","timestamp":"2025-01-01T00:00:00Z","message":{"content":"Start"}}"#,
+            r#"{"type":"user","timestamp":"2025-01-03T00:00:00Z","message":{"content":"Two days later"}}"#,
+        ]
+        .join("\n");
+        let timeline = parse_transcript_from_reader(data.as_bytes())
+            .unwrap()
+            .timeline()
+            .unwrap();
+        assert_eq!(timeline.bucket_minutes, 30);
+        assert_eq!(timeline.buckets.len(), 97);
+        assert_eq!(timeline.buckets.iter().sum::<u32>(), 2);
+        assert!(
+            parse_transcript_from_reader(&b"{}"[..])
+                .unwrap()
+                .timeline()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn parse_claude_slash_command_groups_expanded_prompt() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("claude.jsonl");
+        let data = concat!(
+            r#"{"type":"user","message":{"content":"<command-message>review is running…</command-message>\n<command-name>/review</command-name>\n<command-args>src/lib.rs</command-args>"}}"#,
+            "\n",
+            r#"{"type":"user","isMeta":true,"message":{"content":[{"type":"text","text":"Review the file for bugs."}]}}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Looks good"}]}}"#
+        );
+        fs::write(&path, data).unwrap();
+
+        let result = parse_transcript(&path).unwrap();
+        assert_eq!(result.messages.len(), 2);
+        assert_eq!(result.messages[0].role, "command");
+        assert_eq!(result.messages[0].content, "/review src/lib.rs");
+        assert_eq!(
+            result.messages[0].raw.as_deref(),
+            Some("Review the file for bugs.")
+        );
+        assert_eq!(
+            result.messages[0].raw_label.as_deref(),
+            Some("Command prompt")
+        );
+        assert_eq!(result.messages[1].content, "Looks good");
+    }
+
+    #[test]
+    fn parse_claude_local_command_output() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("claude.jsonl");
+        let data = concat!(
+            r#"{"type":"user","message":{"content":"<command-name>/cost</command-name>"}}"#,
+            "\n",
+            r#"{"type":"user","message":{"content":"<local-command-stdout>Total cost: $0.12</local-command-stdout>"}}"#
+        );
+        fs::write(&path, data).unwrap();
+
+        let result = parse_transcript(&path).unwrap();
+        assert_eq!(result.messages.len(), 1);
+        assert_eq!(result.messages[0].content, "/cost");
+        assert_eq!(result.messages[0].raw.as_deref(), Some("Total cost: $0.12"));
+        assert_eq!(result.messages[0].raw_label.as_deref(), Some("Output"));
+    }
+
+    #[test]
+    fn parse_claude_skill_tool_call() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("claude.jsonl");
+        let data = concat!(
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_1","name":"Skill","input":{"skill":"pdf","args":"report.pdf"}}]}}"#,
+            "\n",
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"Launching skill: pdf"}]}}"#,
+            "\n",
+            r#"{"type":"user","isMeta":true,"message":{"content":[{"type":"text","text":"Base directory for this skill: /skills/pdf"}]}}"#
+        );
+        fs::write(&path, data).unwrap();
+
+        let result = parse_transcript(&path).unwrap();
+        assert_eq!(result.messages.len(), 1);
+        assert_eq!(result.messages[0].role, "command");
+        assert_eq!(result.messages[0].content, "/pdf report.pdf");
+        assert_eq!(result.messages[0].tool_use_id.as_deref(), Some("toolu_1"));
+        assert_eq!(
+            result.messages[0].raw_label.as_deref(),
+            Some("Skill output")
+        );
+    }
+
+    #[test]
+    fn parse_claude_todo_write() {
+        let data = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"TodoWrite","input":{"todos":[{"content":"Parse","status":"in_progress"},{"content":"Render","status":"pending"}]}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t2","name":"TodoWrite","input":{"todos":[{"content":"Parse","status":"completed"},{"content":"Render","status":"pending"}]}}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        assert_eq!(result.messages.len(), 2);
+        assert_eq!(
+            result.messages[0].content,
+            "TodoWrite · 0/2 done\n→ Parse\n○ Render"
+        );
+        assert_eq!(result.messages[1].content, "TodoWrite · 1/2 done\n✓ Parse");
+
+        let tasks = result.tasks.unwrap();
+        assert_eq!(tasks.completed(), 1);
+        assert_eq!(tasks.updates.len(), 2);
+        assert_eq!(
+            tasks.updates[1],
+            vec![TaskItem {
+                content: "Parse".to_string(),
+                status: "completed".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn meta_reads_title_sources() {
+        let claude = [
+            r#"{"type":"summary","summary":"Refactor the parser","leafUuid":"x"}"#,
+            r#"{"type":"user","cwd":"/repo","gitBranch":"fix-parser","slug":"brave-otter","message":{"content":"Clean up parse.rs"}}"#,
+        ]
+        .join("\n");
+        let meta = extract_transcript_meta_from_reader(claude.as_bytes());
+        assert_eq!(meta.cwd.as_deref(), Some("/repo"));
+        assert_eq!(meta.title(None).as_deref(), Some("brave otter"));
+        assert_eq!(
+            meta.title(Some(TitleSource::FirstMessage)).as_deref(),
+            Some("Clean up parse.rs")
+        );
+        assert_eq!(
+            meta.title(Some(TitleSource::Summary)).as_deref(),
+            Some("Refactor the parser")
+        );
+        assert_eq!(
+            meta.title(Some(TitleSource::Branch)).as_deref(),
+            Some("fix-parser")
+        );
+
+        let codex = [
+            r#"{"type":"session_meta","payload":{"cwd":"/work","git":{"branch":"main"}}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":"hi"}}"#,
+        ]
+        .join("\n");
+        let meta = extract_transcript_meta_from_reader(codex.as_bytes());
+        assert_eq!(meta.cwd.as_deref(), Some("/work"));
+        assert_eq!(meta.branch.as_deref(), Some("main"));
+        // No summary: fall back to the default chain
+        assert_eq!(meta.title(Some(TitleSource::Summary)), meta.title(None));
+    }
+
+    #[test]
+    fn meta_prefers_a_title_set_in_the_session() {
+        let mut claude = vec![
+            r#"{"type":"user","cwd":"/repo","slug":"brave-otter","message":{"content":"Clean up parse.rs"}}"#.to_string(),
+            r#"{"type":"user","message":{"content":"<command-name>/title</command-name><command-args>Parser  cleanup</command-args>"}}"#.to_string(),
+        ];
+        let meta = extract_transcript_meta_from_reader(claude.join("\n").as_bytes());
+        assert_eq!(meta.title(None).as_deref(), Some("Parser cleanup"));
+        assert_eq!(
+            meta.title(Some(TitleSource::FirstMessage)).as_deref(),
+            Some("Parser cleanup")
+        );
+
+        // Renamed again much later in the session: the latest title wins
+        claude.extend(
+            (0..150).map(|_| r#"{"type":"assistant","message":{"content":"ok"}}"#.to_string()),
+        );
+        claude.push(
+            r#"{"type":"custom-title","customTitle":"Parser rewrite","sessionId":"s"}"#.to_string(),
+        );
+        let meta = extract_transcript_meta_from_reader(claude.join("\n").as_bytes());
+        assert_eq!(meta.title(None).as_deref(), Some("Parser rewrite"));
+        assert_eq!(
+            meta.first_user_message.as_deref(),
+            Some("Clean up parse.rs")
+        );
+
+        let codex = [
+            r#"{"type":"session_meta","payload":{"cwd":"/work"}}"#,
+            r#"{"type":"event_msg","payload":{"type":"user_message","message":"/title Release notes"}}"#,
+        ]
+        .join("\n");
+        let meta = extract_transcript_meta_from_reader(codex.as_bytes());
+        assert_eq!(meta.title(None).as_deref(), Some("Release notes"));
+        assert_eq!(title_command("/titles are hard"), None);
+        assert_eq!(title_command("/title   "), None);
+    }
+
+    #[test]
+    fn tool_stats_count_and_time_calls() {
+        let claude = [
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:00:00Z","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}}]}}"#,
+            r#"{"type":"user","timestamp":"2025-01-01T10:00:02.500Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"a.rs"}]}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:00:03Z","message":{"content":[{"type":"tool_use","id":"t2","name":"Edit","input":{"file_path":"a.rs"}}]}}"#,
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:00:04Z","message":{"content":[{"type":"tool_use","id":"t3","name":"Bash","input":{"command":"cargo test"}}]}}"#,
+            r#"{"type":"user","timestamp":"2025-01-01T10:00:10Z","message":{"content":[{"type":"tool_result","tool_use_id":"t3","content":"ok"}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(claude.as_bytes()).unwrap();
+        assert_eq!(
+            result.tool_stats_by_calls(),
+            [
+                ToolStat {
+                    name: "Bash".to_string(),
+                    calls: 2,
+                    duration_ms: 8500,
+                },
+                ToolStat {
+                    name: "Edit".to_string(),
+                    calls: 1,
+                    duration_ms: 0,
+                },
+            ]
+        );
+
+        let codex = [
+            r#"{"type":"session_meta","payload":{"originator":"codex_cli_rs"}}"#,
+            r#"{"timestamp":"2025-01-01T10:00:00Z","type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{}","call_id":"c1"}}"#,
+            r#"{"timestamp":"2025-01-01T10:00:01Z","type":"response_item","payload":{"type":"function_call_output","call_id":"c1","output":"done"}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(codex.as_bytes()).unwrap();
+        assert_eq!(result.tool_stats[0].name, "shell");
+        assert_eq!(result.tool_stats[0].duration_ms, 1000);
+    }
+
+    #[test]
+    fn edited_files_counted_for_both_formats() {
+        let claude = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"/src/a.rs","old_string":"x","new_string":"y"}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t2","name":"Write","input":{"file_path":"/src/a.rs","content":""}}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(claude.as_bytes()).unwrap();
+        assert_eq!(result.edited_files.get("/src/a.rs"), Some(&2));
+        assert_eq!(result.diffs[0].diff, "-x\n+y\n");
+        assert_eq!(result.diffs[1].line_counts(), (0, 0));
+
+        let codex = [
+            r#"{"type":"session_meta","payload":{"originator":"codex_cli_rs"}}"#,
+            r#"{"type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"c1","arguments":"{\"command\":[\"apply_patch\",\"*** Begin Patch\\n*** Update File: src/lib.rs\\n@@\\n-old\\n+new\\n*** Add File: src/new.rs\\n+fn main() {}\\n*** End Patch\"]}"}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(codex.as_bytes()).unwrap();
+        assert_eq!(result.edited_files.get("src/lib.rs"), Some(&1));
+        assert_eq!(result.edited_files.get("src/new.rs"), Some(&1));
+        let diffs: Vec<(&str, &str)> = result
+            .diffs
+            .iter()
+            .map(|d| (d.path.as_str(), d.diff.as_str()))
+            .collect();
+        assert_eq!(
+            diffs,
+            [
+                ("src/lib.rs", "@@\n-old\n+new\n"),
+                ("src/new.rs", "+fn main() {}\n")
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_claude_plan_mode() {
+        let data = r##"{"type":"assistant","message":{"model":"claude-opus-4-5","content":[{"type":"tool_use","id":"toolu_1","name":"ExitPlanMode","input":{"plan":"# Plan\n\n1. Add the parser\n2. Wire the viewer\n   - update CSS\n- [x] already done"}}]}}"##;
+        let result = parse_transcript_from_reader(data.as_bytes()).unwrap();
+        assert_eq!(result.messages.len(), 1);
+        assert_eq!(result.messages[0].role, "plan");
+        assert_eq!(
+            result.messages[0].content,
+            "# Plan\n\n- [ ] Add the parser\n- [ ] Wire the viewer\n   - [ ] update CSS\n- [x] already done"
+        );
+        assert_eq!(result.tool_call_count, 1);
+    }
+
+    #[test]
+    fn test_parse_slash_command() {
+        assert_eq!(
+            parse_slash_command("<command-name>init</command-name>").as_deref(),
+            Some("/init")
+        );
+        assert_eq!(
+            parse_slash_command(
+                "<command-name>/fix</command-name><command-args> issue 12 </command-args>"
+            )
+            .as_deref(),
+            Some("/fix issue 12")
+        );
+        assert_eq!(parse_slash_command("plain text"), None);
+    }
+
+    #[test]
+    fn session_status_tracks_last_turn() {
+        let completed = [
+            r#"{"type":"system","subtype":"api_error","level":"error","error":{"message":"Overloaded"}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Done"}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(completed.as_bytes()).unwrap();
+        assert_eq!(result.status, SessionStatus::Completed);
+        assert_eq!(result.errors, vec!["Overloaded"]);
+
+        let interrupted = [
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Working"}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"text","text":"[Request interrupted by user for tool use]"}]}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(interrupted.as_bytes()).unwrap();
+        assert_eq!(result.status, SessionStatus::Interrupted);
+
+        let errored = r#"{"type":"assistant","isApiErrorMessage":true,"message":{"content":[{"type":"text","text":"API Error: 500\n  internal"}]}}"#;
+        let result = parse_transcript_from_reader(errored.as_bytes()).unwrap();
+        assert_eq!(result.status, SessionStatus::Errored);
+        assert_eq!(result.errors, vec!["API Error: 500 internal"]);
+    }
+
+    #[test]
+    fn session_status_codex_events() {
+        let aborted = [
+            r#"{"type":"session_meta","payload":{"originator":"codex_cli_rs"}}"#,
+            r#"{"type":"event_msg","payload":{"type":"error","message":"stream disconnected"}}"#,
+            r#"{"type":"event_msg","payload":{"type":"turn_aborted","reason":"interrupted"}}"#,
+        ]
+        .join("\n");
+        let result = parse_transcript_from_reader(aborted.as_bytes()).unwrap();
+        assert_eq!(result.status, SessionStatus::Interrupted);
+        assert_eq!(result.errors, vec!["stream disconnected"]);
+    }
 }
@@ -0,0 +1,214 @@
+//! Share payload assembly from parsed transcript data.
+
+use super::lang::detect_language;
+use super::types::{BundlePart, ParseResult, SessionStatus, SharePayload, TranscriptMeta};
+use super::usage::{PricingTable, price_payload};
+
+/// Build the share payload for a parsed transcript.
+/// The title falls back from `title_override` to the session slug to the first user message.
+/// Costs use the built-in pricing; see [`price_payload`] for overrides.
+pub fn build_share_payload(
+    tool: &str,
+    parsed: ParseResult,
+    meta: TranscriptMeta,
+    session_id: Option<&str>,
+    title_override: Option<&str>,
+    shared_at: String,
+) -> SharePayload {
+    let title = title_override
+        .map(|s| s.to_string())
+        .or_else(|| meta.title(None));
+
+    let mut payload = SharePayload {
+        tool: tool.to_string(),
+        session_id: session_id.map(|s| s.to_string()),
+        title,
+        shared_at,
+        model: parsed.dominant_model(),
+        models: parsed.models_by_usage(),
+        status: parsed.status,
+        errors: parsed.errors.clone(),
+        tasks: parsed.tasks.clone(),
+        timeline: parsed.timeline(),
+        total_input_tokens: parsed.total_input_tokens(),
+        total_output_tokens: parsed.total_output_tokens(),
+        total_cache_read_tokens: parsed.total_cache_read_tokens(),
+        total_cache_creation_tokens: parsed.total_cache_creation_tokens(),
+        model_costs: parsed.model_costs(),
+        tool_stats: parsed.tool_stats_by_calls(),
+        estimated_cost_usd: None,
+        lang: detect_language(&parsed.messages),
+        messages: parsed.messages,
+        publisher: None,
+        series: None,
+        viewer: None,
+        metadata_only: false,
+        message_counts: Default::default(),
+        parts: Vec::new(),
+    };
+    price_payload(&mut payload, &PricingTable::new());
+    payload
+}
+
+/// Combine the payloads of several transcripts into one bundle share. Messages
+/// keep their order and name their part; token counts, costs, and tool calls
+/// add up. The title and session id come from the first part. A timeline or
+/// task list is kept only when a single part has one, since they can't be merged.
+pub fn bundle_payloads(parts: Vec<SharePayload>) -> SharePayload {
+    let mut bundle = SharePayload {
+        tool: String::new(),
+        session_id: None,
+        title: None,
+        shared_at: String::new(),
+        model: None,
+        models: Vec::new(),
+        messages: Vec::new(),
+        total_input_tokens: 0,
+        total_output_tokens: 0,
+        total_cache_read_tokens: 0,
+        total_cache_creation_tokens: 0,
+        model_costs: Vec::new(),
+        tool_stats: Vec::new(),
+        estimated_cost_usd: None,
+        publisher: None,
+        series: None,
+        status: SessionStatus::Completed,
+        errors: Vec::new(),
+        tasks: None,
+        timeline: None,
+        viewer: None,
+        lang: None,
+        metadata_only: false,
+        message_counts: Default::default(),
+        parts: Vec::new(),
+    };
+    let mut tools: Vec<String> = Vec::new();
+    let (mut tasks, mut timelines) = (Vec::new(), Vec::new());
+    for (index, part) in parts.into_iter().enumerate() {
+        if index == 0 {
+            bundle.session_id = part.session_id.clone();
+            bundle.title = part.title.clone();
+            bundle.shared_at = part.shared_at.clone();
+            bundle.model = part.model.clone();
+        }
+        if !tools.contains(&part.tool) {
+            tools.push(part.tool.clone());
+        }
+        for model in part.models {
+            if !bundle.models.contains(&model) {
+                bundle.models.push(model);
+            }
+        }
+        // Ids number on from the previous session, so anchors stay unique
+        let offset = bundle
+            .messages
+            .iter()
+            .filter_map(|m| m.id)
+            .max()
+            .unwrap_or(0);
+        bundle
+            .messages
+            .extend(part.messages.into_iter().map(|mut m| {
+                m.part = Some(index);
+                m.id = m.id.map(|id| id + offset);
+                m
+            }));
+        bundle.total_input_tokens += part.total_input_tokens;
+        bundle.total_output_tokens += part.total_output_tokens;
+        bundle.total_cache_read_tokens += part.total_cache_read_tokens;
+        bundle.total_cache_creation_tokens += part.total_cache_creation_tokens;
+        for cost in part.model_costs {
+            match bundle
+                .model_costs
+                .iter_mut()
+                .find(|c| c.model == cost.model)
+            {
+                Some(total) => {
+                    total.input_tokens += cost.input_tokens;
+                    total.output_tokens += cost.output_tokens;
+                    total.cache_read_tokens += cost.cache_read_tokens;
+                    total.cache_creation_tokens += cost.cache_creation_tokens;
+                }
+                None => bundle.model_costs.push(cost),
+            }
+        }
+        for stat in part.tool_stats {
+            match bundle.tool_stats.iter_mut().find(|s| s.name == stat.name) {
+                Some(total) => {
+                    total.calls += stat.calls;
+                    total.duration_ms += stat.duration_ms;
+                }
+                None => bundle.tool_stats.push(stat),
+            }
+        }
+        // The worst outcome stands for the bundle
+        bundle.status = match (bundle.status, part.status) {
+            (SessionStatus::Errored, _) | (_, SessionStatus::Errored) => SessionStatus::Errored,
+            (SessionStatus::Interrupted, _) | (_, SessionStatus::Interrupted) => {
+                SessionStatus::Interrupted
+            }
+            _ => SessionStatus::Completed,
+        };
+        bundle.errors.extend(part.errors);
+        tasks.extend(part.tasks);
+        timelines.extend(part.timeline);
+        bundle.lang = bundle.lang.or(part.lang);
+        bundle.parts.push(BundlePart {
+            tool: part.tool,
+            session_id: part.session_id,
+            title: part.title,
+            model: part.model,
+        });
+    }
+    bundle.tool = tools.join(" + ");
+    bundle
+        .tool_stats
+        .sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+    if tasks.len() == 1 {
+        bundle.tasks = tasks.pop();
+    }
+    if timelines.len() == 1 {
+        bundle.timeline = timelines.pop();
+    }
+    price_payload(&mut bundle, &PricingTable::new());
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_adds_up_usage_and_keeps_the_worst_status() {
+        let part = |tool: &str, status: &str, model: &str| -> SharePayload {
+            serde_json::from_str(&format!(
+                r#"{{"tool":"{tool}","shared_at":"Jan 1","status":"{status}","model":"{model}",
+                "models":["{model}"],"total_input_tokens":10,"model_costs":[
+                {{"model":"{model}","input_tokens":10}}],"tool_stats":[{{"name":"Bash","calls":2}}],"messages":[{{"role":"user","content":"hi","id":1}},{{"role":"assistant","content":"hello","id":2}}]}}"#
+            ))
+            .unwrap()
+        };
+        let bundle = bundle_payloads(vec![
+            part("Claude Code", "completed", "claude-sonnet-4-5"),
+            part("Codex", "interrupted", "gpt-5"),
+            part("Claude Code", "completed", "claude-sonnet-4-5"),
+        ]);
+        assert_eq!(bundle.tool, "Claude Code + Codex");
+        assert_eq!(bundle.status, SessionStatus::Interrupted);
+        assert_eq!(bundle.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(bundle.models, ["claude-sonnet-4-5", "gpt-5"]);
+        assert_eq!(bundle.total_input_tokens, 30);
+        assert_eq!(bundle.model_costs.len(), 2);
+        assert_eq!(bundle.model_costs[0].input_tokens, 20);
+        assert_eq!(bundle.parts.len(), 3);
+        assert_eq!(bundle.tool_stats.len(), 1);
+        assert_eq!(bundle.tool_stats[0].calls, 6);
+        let parts: Vec<_> = bundle.messages.iter().map(|m| m.part).collect();
+        assert_eq!(
+            parts,
+            [Some(0), Some(0), Some(1), Some(1), Some(2), Some(2)]
+        );
+        let ids: Vec<_> = bundle.messages.iter().filter_map(|m| m.id).collect();
+        assert_eq!(ids, [1, 2, 3, 4, 5, 6]);
+    }
+}
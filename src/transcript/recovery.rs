@@ -0,0 +1,179 @@
+//! Damaged JSONL recovery. Transcripts synced through Dropbox or rsync, or cut
+//! short by a crash, pick up artifacts: a byte-order mark, invalid UTF-8, two
+//! objects run together on one line, a trailing comma. Such lines are repaired
+//! where possible instead of skipped, and what was repaired or dropped is
+//! counted so it can be reported.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Split};
+
+/// Damaged lines met while reading a transcript
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LineRecovery {
+    /// Lines that only parsed after repair
+    pub recovered: usize,
+    /// Lines that couldn't be read even after repair
+    pub dropped: usize,
+}
+
+impl LineRecovery {
+    /// One-line note for a warning, or None when every line was clean
+    pub fn describe(&self) -> Option<String> {
+        let plural = |n: usize| if n == 1 { "line" } else { "lines" };
+        match (self.recovered, self.dropped) {
+            (0, 0) => None,
+            (recovered, 0) => Some(format!(
+                "repaired {recovered} damaged {}",
+                plural(recovered)
+            )),
+            (0, dropped) => Some(format!("skipped {dropped} unreadable {}", plural(dropped))),
+            (recovered, dropped) => Some(format!(
+                "repaired {recovered} damaged {}, skipped {dropped} unreadable {}",
+                plural(recovered),
+                plural(dropped)
+            )),
+        }
+    }
+}
+
+/// What one raw JSONL line held
+pub(crate) enum Line {
+    Blank,
+    Clean(Value),
+    /// Values read after repairing the line, in order
+    Repaired(Vec<Value>),
+    Unreadable,
+}
+
+/// Read one raw line, repairing it if it doesn't parse as is
+pub(crate) fn read_line(raw: &[u8]) -> Line {
+    if let Ok(text) = std::str::from_utf8(raw) {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            return Line::Blank;
+        }
+        if let Ok(value) = serde_json::from_str(trimmed) {
+            return Line::Clean(value);
+        }
+    }
+    let text = String::from_utf8_lossy(raw);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}' || c == '\0');
+    if text.is_empty() {
+        return Line::Blank;
+    }
+    let text = strip_trailing_commas(text);
+    // A streaming deserializer reads objects run together, stopping at the first bad one
+    let values: Vec<Value> = serde_json::Deserializer::from_str(&text)
+        .into_iter::<Value>()
+        .map_while(Result::ok)
+        .collect();
+    if values.is_empty() {
+        Line::Unreadable
+    } else {
+        Line::Repaired(values)
+    }
+}
+
+/// Drop commas that directly precede a closing brace or bracket, outside strings
+fn strip_trailing_commas(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && text[index + 1..].trim_start().starts_with(['}', ']']) {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// JSON values of a JSONL stream, repairing damaged lines as they're read
+pub(crate) struct JsonlValues<R> {
+    lines: Split<R>,
+    pending: VecDeque<Value>,
+    pub recovery: LineRecovery,
+}
+
+impl<R: BufRead> JsonlValues<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.split(b'\n'),
+            pending: VecDeque::new(),
+            recovery: LineRecovery::default(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlValues<R> {
+    type Item = io::Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.pending.pop_front() {
+                return Some(Ok(value));
+            }
+            let raw = match self.lines.next()? {
+                Ok(raw) => raw,
+                Err(err) => return Some(Err(err)),
+            };
+            match read_line(&raw) {
+                Line::Blank => {}
+                Line::Clean(value) => return Some(Ok(value)),
+                Line::Repaired(values) => {
+                    self.recovery.recovered += 1;
+                    self.pending.extend(values);
+                }
+                Line::Unreadable => self.recovery.dropped += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repairs_synced_file_artifacts() {
+        let mut data = Vec::new();
+        data.extend_from_slice("\u{feff}{\"n\":1}\n".as_bytes());
+        data.extend_from_slice(b"{\"n\":2}{\"n\":3}\n");
+        data.extend_from_slice(b"{\"n\":4,\"list\":[1,2,],}\r\n");
+        data.extend_from_slice(b"{\"n\":5,\"text\":\"caf\xe9\"}\n");
+        data.extend_from_slice(b"\n{\"n\":6,\"text\":\"a,}\"}\n");
+        data.extend_from_slice(b"{\"n\":7,\"text\":\"cut sho\n");
+        data.extend_from_slice(b"\0\0\0\n");
+
+        let mut values = JsonlValues::new(&data[..]);
+        let numbers: Vec<u64> = values
+            .by_ref()
+            .map(|v| v.unwrap()["n"].as_u64().unwrap())
+            .collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            values.recovery,
+            LineRecovery {
+                recovered: 4,
+                dropped: 1
+            }
+        );
+        assert_eq!(
+            values.recovery.describe().as_deref(),
+            Some("repaired 4 damaged lines, skipped 1 unreadable line")
+        );
+        assert_eq!(LineRecovery::default().describe(), None);
+    }
+}
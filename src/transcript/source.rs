@@ -0,0 +1,299 @@
+//! Transcript sources: one per agent format. A source finds the current
+//! session's transcript and reads its messages and metadata. The built-in
+//! tools are sources, and a program embedding agentexport can add its own
+//! with [`register_source`]. `publish --tool <name>` then discovers, parses,
+//! and labels shares through it, and `parse_transcript` and
+//! `extract_transcript_meta` hand it the files it detects, ahead of the
+//! built-in JSONL parser.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use super::parser::{jsonl_transcript_meta, parse_jsonl_transcript};
+use super::types::{ParseResult, TranscriptMeta};
+#[cfg(feature = "cli")]
+use super::{
+    discovery::{CodexDiscovery, resolve_transcript, transcript_tool},
+    types::Tool,
+};
+
+/// What [`TranscriptSource::discover`] looks for
+#[derive(Debug, Clone, Default)]
+pub struct DiscoverOptions {
+    /// Transcript the user named; discovery only checks it
+    pub transcript: Option<PathBuf>,
+    /// Only sessions written in the last this many minutes (0 for any age)
+    pub max_age_minutes: u64,
+    /// Only sessions that ran on this git branch
+    pub branch: Option<String>,
+    /// How to choose among Codex sessions
+    #[cfg(feature = "cli")]
+    pub codex: CodexDiscovery,
+}
+
+/// A transcript found by [`TranscriptSource::discover`]
+#[derive(Debug, Clone, Default)]
+pub struct Discovered {
+    pub path: PathBuf,
+    /// Session id, which the file name contains
+    pub session_id: Option<String>,
+    /// Codex thread id, which is recorded inside the file
+    pub thread_id: Option<String>,
+    /// How the transcript was picked when it wasn't the only candidate, for
+    /// the caller to show
+    pub warnings: Vec<String>,
+}
+
+/// An agent's transcript format
+pub trait TranscriptSource: Send + Sync {
+    /// Name the source is looked up by, like `claude` or `codex`
+    fn name(&self) -> &str;
+
+    /// Name shown in shares, like `Claude Code`
+    fn display_name(&self) -> &str {
+        self.name()
+    }
+
+    /// Whether the file at `path` is a transcript in this format
+    fn detect(&self, path: &Path) -> bool;
+
+    /// Resolve a transcript: the one `options` names, or else the current
+    /// session's
+    fn discover(&self, options: &DiscoverOptions) -> Result<Discovered>;
+
+    /// Parse the transcript at `path`. The default reads agentexport's JSONL,
+    /// so a source whose agent writes Claude or Codex style lines only needs
+    /// to find them.
+    fn parse(&self, path: &Path) -> Result<ParseResult> {
+        parse_jsonl_transcript(path)
+    }
+
+    /// Title, first prompt, and working directory of the transcript at `path`
+    fn metadata(&self, path: &Path) -> TranscriptMeta {
+        jsonl_transcript_meta(path)
+    }
+}
+
+static REGISTERED: RwLock<Vec<Arc<dyn TranscriptSource>>> = RwLock::new(Vec::new());
+
+/// Add a transcript format. Sources registered later are asked first, and
+/// all of them before the built-in tools.
+pub fn register_source(source: impl TranscriptSource + 'static) {
+    REGISTERED
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Arc::new(source));
+}
+
+/// Registered sources, most recent first
+fn registered() -> Vec<Arc<dyn TranscriptSource>> {
+    let sources = REGISTERED.read().unwrap_or_else(PoisonError::into_inner);
+    sources.iter().rev().cloned().collect()
+}
+
+/// The registered source that detects the file at `path`
+pub(super) fn registered_for(path: &Path) -> Option<Arc<dyn TranscriptSource>> {
+    registered().into_iter().find(|source| source.detect(path))
+}
+
+/// The built-in tools, as sources
+#[cfg(feature = "cli")]
+fn built_in() -> impl Iterator<Item = Arc<dyn TranscriptSource>> {
+    <Tool as clap::ValueEnum>::value_variants()
+        .iter()
+        .map(|&tool| Arc::new(tool) as Arc<dyn TranscriptSource>)
+}
+
+/// Without the CLI there is no discovery, so only registered sources exist
+#[cfg(not(feature = "cli"))]
+fn built_in() -> impl Iterator<Item = Arc<dyn TranscriptSource>> {
+    std::iter::empty()
+}
+
+/// Every source: the registered ones, then the built-in tools
+pub fn sources() -> Vec<Arc<dyn TranscriptSource>> {
+    registered().into_iter().chain(built_in()).collect()
+}
+
+/// The source named `name`
+pub fn source(name: &str) -> Option<Arc<dyn TranscriptSource>> {
+    sources().into_iter().find(|source| source.name() == name)
+}
+
+/// The source named `name`, or an error listing the ones there are
+pub fn named_source(name: &str) -> Result<Arc<dyn TranscriptSource>> {
+    source(name).with_context(|| {
+        let names: Vec<String> = sources().iter().map(|s| s.name().to_string()).collect();
+        format!(
+            "unknown tool {name:?} (expected one of {})",
+            names.join(", ")
+        )
+    })
+}
+
+/// The source that reads the file at `path`: a registered one that detects
+/// it, or else the built-in tool that wrote it
+pub fn source_for_path(path: &Path) -> Option<Arc<dyn TranscriptSource>> {
+    sources().into_iter().find(|source| source.detect(path))
+}
+
+#[cfg(feature = "cli")]
+impl TranscriptSource for Tool {
+    fn name(&self) -> &str {
+        self.as_str()
+    }
+
+    fn display_name(&self) -> &str {
+        Tool::display_name(*self)
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.is_file() && transcript_tool(path).is_ok_and(|tool| tool.as_str() == self.as_str())
+    }
+
+    fn discover(&self, options: &DiscoverOptions) -> Result<Discovered> {
+        resolve_transcript(
+            *self,
+            options.transcript.clone(),
+            options.max_age_minutes,
+            &options.codex,
+            options.branch.as_deref(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{RenderedMessage, extract_transcript_meta, parse_transcript};
+    use anyhow::{Context, bail};
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Plain-text chat logs: `> ` starts a prompt, other lines are replies
+    struct ChatLog;
+
+    impl TranscriptSource for ChatLog {
+        fn name(&self) -> &str {
+            "chatlog"
+        }
+
+        fn detect(&self, path: &Path) -> bool {
+            path.extension().is_some_and(|ext| ext == "chatlog")
+        }
+
+        fn discover(&self, options: &DiscoverOptions) -> Result<Discovered> {
+            let Some(path) = options.transcript.clone() else {
+                bail!("chatlog sessions can't be discovered; pass the file");
+            };
+            let session_id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_string);
+            Ok(Discovered {
+                path,
+                session_id,
+                ..Discovered::default()
+            })
+        }
+
+        fn parse(&self, path: &Path) -> Result<ParseResult> {
+            let text = fs::read_to_string(path).context("read chat log")?;
+            let mut parsed = ParseResult::default();
+            for line in text.lines() {
+                let (role, content) = match line.strip_prefix("> ") {
+                    Some(prompt) => ("user", prompt),
+                    None => ("assistant", line),
+                };
+                parsed
+                    .messages
+                    .push(serde_json::from_value::<RenderedMessage>(
+                        serde_json::json!({ "role": role, "content": content }),
+                    )?);
+            }
+            Ok(parsed)
+        }
+
+        fn metadata(&self, path: &Path) -> TranscriptMeta {
+            let first = self
+                .parse(path)
+                .ok()
+                .and_then(|parsed| parsed.messages.into_iter().next());
+            TranscriptMeta {
+                first_user_message: first.map(|message| message.content),
+                ..TranscriptMeta::default()
+            }
+        }
+    }
+
+    #[test]
+    fn registered_sources_read_their_files() {
+        register_source(ChatLog);
+        let tmp = TempDir::new().unwrap();
+        let log = tmp.path().join("standup.chatlog");
+        fs::write(&log, "> Summarize the standup\nShipped the parser.\n").unwrap();
+
+        assert_eq!(source_for_path(&log).unwrap().name(), "chatlog");
+        let found = source("chatlog")
+            .unwrap()
+            .discover(&DiscoverOptions {
+                transcript: Some(log.clone()),
+                ..DiscoverOptions::default()
+            })
+            .unwrap();
+        assert_eq!(
+            (found.path, found.session_id.as_deref()),
+            (log.clone(), Some("standup"))
+        );
+
+        let parsed = parse_transcript(&log).unwrap();
+        let messages: Vec<(&str, &str)> = parsed
+            .messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("user", "Summarize the standup"),
+                ("assistant", "Shipped the parser.")
+            ]
+        );
+        assert_eq!(
+            extract_transcript_meta(&log).first_user_message.as_deref(),
+            Some("Summarize the standup")
+        );
+
+        // Files the source doesn't detect still go to the built-in parser
+        let jsonl = tmp.path().join("session.jsonl");
+        fs::write(
+            &jsonl,
+            r#"{"type":"user","message":{"role":"user","content":"Hi"}}"#,
+        )
+        .unwrap();
+        assert_eq!(parse_transcript(&jsonl).unwrap().messages.len(), 1);
+        #[cfg(feature = "cli")]
+        assert_eq!(source_for_path(&jsonl).unwrap().name(), "claude");
+
+        // Publishing looks the source up by name and labels the share with it
+        #[cfg(feature = "cli")]
+        {
+            let source = named_source("chatlog").unwrap();
+            let payload = crate::publish::create_share_payload(
+                source.as_ref(),
+                &log,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+            assert_eq!(payload.tool, "chatlog");
+            assert_eq!(payload.messages.len(), 2);
+            let err = named_source("nope").err().unwrap().to_string();
+            assert!(err.contains("chatlog, claude, codex"), "{err}");
+        }
+    }
+}
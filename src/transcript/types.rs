@@ -2,12 +2,31 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use time::OffsetDateTime;
+
+use super::recovery::LineRecovery;
+
+pub use agentexport_protocol::{
+    BundlePart, ModelCost, Publisher, RenderedMessage, SeriesLink, SessionStatus, SharePayload,
+    TaskItem, TaskProgress, Timeline, ToolStat, ViewerDefaults,
+};
+
+/// Most buckets in a session timeline (one day at 10-minute resolution)
+const MAX_TIMELINE_BUCKETS: u64 = 144;
 
 /// Which tool produced the transcript
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum Tool {
     Claude,
     Codex,
+    Cursor,
+    Aider,
+    /// A conversation imported from a ChatGPT data export
+    #[cfg_attr(feature = "cli", value(name = "chatgpt"))]
+    ChatGpt,
+    /// A Claude.ai chat imported from an export or a sharing tool's JSON
+    ClaudeAi,
 }
 
 impl Tool {
@@ -15,6 +34,10 @@ impl Tool {
         match self {
             Tool::Claude => "claude",
             Tool::Codex => "codex",
+            Tool::Cursor => "cursor",
+            Tool::Aider => "aider",
+            Tool::ChatGpt => "chatgpt",
+            Tool::ClaudeAi => "claude-ai",
         }
     }
 
@@ -22,30 +45,104 @@ impl Tool {
         match self {
             Tool::Claude => "Claude Code",
             Tool::Codex => "Codex",
+            Tool::Cursor => "Cursor",
+            Tool::Aider => "Aider",
+            Tool::ChatGpt => "ChatGPT",
+            Tool::ClaudeAi => "Claude.ai",
         }
     }
 }
 
-/// A rendered message for the share payload
-#[derive(Debug, Clone, Serialize)]
-pub struct RenderedMessage {
-    pub role: String,
-    pub content: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub raw: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub raw_label: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_use_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub model: Option<String>,
+/// Where an automatically derived share title comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum TitleSource {
+    /// Claude's session slug
+    Slug,
+    /// First user message
+    FirstMessage,
+    /// Claude's compaction summary
+    Summary,
+    /// Git branch the session ran on
+    Branch,
 }
 
 /// Metadata extracted from the transcript (title, first message, etc.)
 #[derive(Debug, Clone, Default)]
 pub struct TranscriptMeta {
+    /// Title the user gave the session (`/title` or `/rename`); the latest wins
+    pub custom_title: Option<String>,
     pub slug: Option<String>,
     pub first_user_message: Option<String>,
+    pub summary: Option<String>,
+    pub branch: Option<String>,
+    /// Working directory the session ran in
+    pub cwd: Option<String>,
+}
+
+impl TranscriptMeta {
+    /// Fill a title template such as `"Demo: {first_message:60}"`. Fields are
+    /// `slug`, `first_message`, `summary`, `branch`, and `repo`; `:N` keeps at
+    /// most N characters. Missing fields render empty.
+    pub fn render_template(&self, template: &str) -> anyhow::Result<String> {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                anyhow::bail!("unclosed '{{' in title template: {template}");
+            };
+            let spec = &rest[start + 1..start + len];
+            let (name, limit) = match spec.split_once(':') {
+                Some((name, limit)) => {
+                    let limit: usize = limit.trim().parse().map_err(|_| {
+                        anyhow::anyhow!("invalid length in title template field {{{spec}}}")
+                    })?;
+                    (name.trim(), Some(limit))
+                }
+                None => (spec.trim(), None),
+            };
+            let value = match name {
+                "slug" => self.slug.as_ref().map(|s| s.replace('-', " ")),
+                "first_message" => self.first_user_message.clone(),
+                "summary" => self.summary.clone(),
+                "branch" => self.branch.clone(),
+                "repo" => self.cwd.as_ref().and_then(|cwd| {
+                    std::path::Path::new(cwd)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                }),
+                other => anyhow::bail!("unknown title template field {{{other}}}"),
+            }
+            .unwrap_or_default();
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            match limit {
+                Some(limit) => out.extend(value.chars().take(limit)),
+                None => out.push_str(&value),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        Ok(out.trim().to_string())
+    }
+
+    /// Title the user set in the session, else the one from `source`, falling
+    /// back to the slug and then the first user message
+    pub fn title(&self, source: Option<TitleSource>) -> Option<String> {
+        if let Some(title) = &self.custom_title {
+            return Some(title.clone());
+        }
+        let preferred = match source {
+            Some(TitleSource::Slug) | None => None,
+            Some(TitleSource::FirstMessage) => self.first_user_message.clone(),
+            Some(TitleSource::Summary) => self.summary.clone(),
+            Some(TitleSource::Branch) => self.branch.clone(),
+        };
+        preferred
+            .or_else(|| self.slug.as_ref().map(|s| s.replace('-', " ")))
+            .or_else(|| self.first_user_message.clone())
+    }
 }
 
 /// Token usage for a single message
@@ -55,6 +152,24 @@ pub struct MessageUsage {
     pub output_tokens: u64,
     pub cache_read_tokens: u64,
     pub cache_creation_tokens: u64,
+    pub model: Option<String>,
+}
+
+/// A file change made by an edit tool call, as `+`/`-` diff lines
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: String,
+    pub diff: String,
+}
+
+impl FileDiff {
+    /// Lines added and removed
+    pub fn line_counts(&self) -> (usize, usize) {
+        let lines = self.diff.lines();
+        let added = lines.clone().filter(|l| l.starts_with('+')).count();
+        let removed = lines.filter(|l| l.starts_with('-')).count();
+        (added, removed)
+    }
 }
 
 /// Result of parsing a transcript
@@ -69,6 +184,27 @@ pub struct ParseResult {
     pub codex_total_input_tokens: u64,
     pub codex_total_output_tokens: u64,
     pub codex_total_cache_read_tokens: u64,
+    /// Tool invocations made by the agent (not counting their results)
+    pub tool_call_count: usize,
+    /// Earliest and latest event timestamps, when the transcript records them
+    pub started_at: Option<OffsetDateTime>,
+    pub ended_at: Option<OffsetDateTime>,
+    /// Timestamp of the event behind each message, parallel to `messages`
+    pub message_times: Vec<Option<OffsetDateTime>>,
+    /// Outcome of the last turn
+    pub status: SessionStatus,
+    /// One-line summaries of errors seen during the session
+    pub errors: Vec<String>,
+    /// TodoWrite task list, if the agent kept one
+    pub tasks: Option<TaskProgress>,
+    /// Edit/write tool calls per file path
+    pub edited_files: HashMap<String, usize>,
+    /// What those calls changed, in order, each cut to `DIFF_MAX_CHARS`
+    pub diffs: Vec<FileDiff>,
+    /// Calls and timings per tool, in order of first use
+    pub tool_stats: Vec<ToolStat>,
+    /// Damaged lines repaired or skipped while reading
+    pub recovery: LineRecovery,
 }
 
 impl ParseResult {
@@ -120,6 +256,51 @@ impl ParseResult {
         }
     }
 
+    /// Wall-clock time between the first and last timestamped events
+    pub fn duration(&self) -> Option<time::Duration> {
+        Some(self.ended_at? - self.started_at?)
+    }
+
+    /// Tool stats, most called first (ties by name)
+    pub fn tool_stats_by_calls(&self) -> Vec<ToolStat> {
+        let mut stats = self.tool_stats.clone();
+        stats.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+        stats
+    }
+
+    /// Remove messages on abandoned conversation branches
+    pub fn drop_abandoned(&mut self) {
+        let mut times = std::mem::take(&mut self.message_times).into_iter();
+        let messages = std::mem::take(&mut self.messages);
+        for message in messages {
+            let at = times.next().flatten();
+            if !message.abandoned {
+                self.messages.push(message);
+                self.message_times.push(at);
+            }
+        }
+    }
+
+    /// Messages per 10-minute bucket across the session. Buckets widen in
+    /// 10-minute steps so long sessions stay within `MAX_TIMELINE_BUCKETS`.
+    pub fn timeline(&self) -> Option<Timeline> {
+        let (start, end) = (self.started_at?, self.ended_at?);
+        let span = (end - start).whole_minutes().max(0) as u64;
+        let bucket_minutes = 10 * (span / 10 + 1).div_ceil(MAX_TIMELINE_BUCKETS);
+        let mut buckets = vec![0; (span / bucket_minutes + 1) as usize];
+        for at in self.message_times.iter().flatten() {
+            let offset = (*at - start).whole_minutes().max(0) as u64;
+            let index = ((offset / bucket_minutes) as usize).min(buckets.len() - 1);
+            buckets[index] += 1;
+        }
+        Some(Timeline {
+            started_at: start.unix_timestamp(),
+            ended_at: end.unix_timestamp(),
+            bucket_minutes: bucket_minutes as u32,
+            buckets,
+        })
+    }
+
     /// Compute total cache creation tokens
     pub fn total_cache_creation_tokens(&self) -> u64 {
         self.usage_by_message_id
@@ -129,33 +310,28 @@ impl ParseResult {
     }
 }
 
-fn is_zero(val: &u64) -> bool {
-    *val == 0
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// Payload sent to the viewer (encrypted JSON)
-#[derive(Debug, Clone, Serialize)]
-pub struct SharePayload {
-    pub tool: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub session_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub title: Option<String>,
-    pub shared_at: String,
-    /// Primary model (most used), shown in header
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub model: Option<String>,
-    /// All models used, for "model1 + model2" display if multiple
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub models: Vec<String>,
-    pub messages: Vec<RenderedMessage>,
-    /// Token usage totals (if available)
-    #[serde(skip_serializing_if = "is_zero")]
-    pub total_input_tokens: u64,
-    #[serde(skip_serializing_if = "is_zero")]
-    pub total_output_tokens: u64,
-    #[serde(skip_serializing_if = "is_zero")]
-    pub total_cache_read_tokens: u64,
-    #[serde(skip_serializing_if = "is_zero")]
-    pub total_cache_creation_tokens: u64,
+    #[test]
+    fn title_templates_fill_and_truncate_fields() {
+        let meta = TranscriptMeta {
+            slug: Some("tidy-cart".to_string()),
+            first_user_message: Some("Please refactor\nthe cart module".to_string()),
+            cwd: Some("/work/shop".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            meta.render_template("Demo: {first_message:15}").unwrap(),
+            "Demo: Please refactor"
+        );
+        assert_eq!(
+            meta.render_template("{repo}: {slug} {branch}").unwrap(),
+            "shop: tidy cart"
+        );
+        assert!(meta.render_template("{model}").is_err());
+        assert!(meta.render_template("{slug:x}").is_err());
+        assert!(meta.render_template("Demo {slug").is_err());
+    }
 }
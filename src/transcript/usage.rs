@@ -0,0 +1,361 @@
+//! Per-model token usage and cost estimates.
+//!
+//! Pricing mirrors the viewer's `calculateCost` so the CLI and the share page
+//! agree on what a session cost. Prices from the `[pricing]` config table take
+//! precedence over the built-in ones.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::types::{ModelCost, ParseResult, SharePayload};
+
+/// Price of a model in USD per million tokens, as set under `[pricing.<model>]`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+/// Model name to price overrides
+pub type PricingTable = BTreeMap<String, ModelPrice>;
+
+/// USD per token; `above` rates apply past `threshold` tokens (long-context tier)
+struct Pricing {
+    input: f64,
+    output: f64,
+    cache_read: f64,
+    cache_create: f64,
+    threshold: Option<(u64, [f64; 4])>,
+}
+
+const SONNET_4_TIER: Option<(u64, [f64; 4])> = Some((200_000, [6e-6, 22.5e-6, 0.6e-6, 7.5e-6]));
+
+fn claude_pricing(model: &str) -> Option<Pricing> {
+    let (input, output, cache_read, cache_create, threshold) = match model {
+        "claude-opus-4-5-20251101" | "claude-opus-4-5" => (5e-6, 25e-6, 0.5e-6, 6.25e-6, None),
+        "claude-opus-4-20250514" | "claude-opus-4-1" => (15e-6, 75e-6, 1.5e-6, 18.75e-6, None),
+        "claude-sonnet-4-5-20250929" | "claude-sonnet-4-5" | "claude-sonnet-4-20250514" => {
+            (3e-6, 15e-6, 0.3e-6, 3.75e-6, SONNET_4_TIER)
+        }
+        "claude-haiku-4-5-20251001" | "claude-haiku-4-5" => (1e-6, 5e-6, 0.1e-6, 1.25e-6, None),
+        _ => return None,
+    };
+    Some(Pricing {
+        input,
+        output,
+        cache_read,
+        cache_create,
+        threshold,
+    })
+}
+
+fn codex_pricing(model: &str) -> Option<Pricing> {
+    let (input, output, cache_read) = match model {
+        "gpt-5" | "gpt-5-codex" | "gpt-5.1" => (1.25e-6, 10e-6, 0.125e-6),
+        "gpt-5.2" | "gpt-5.2-codex" => (1.75e-6, 14e-6, 0.175e-6),
+        _ => return None,
+    };
+    Some(Pricing {
+        input,
+        output,
+        cache_read,
+        cache_create: 0.0,
+        threshold: None,
+    })
+}
+
+fn normalize_claude_model(model: &str) -> String {
+    let mut m = model.trim().to_lowercase();
+    if let Some(rest) = m.strip_prefix("anthropic.") {
+        m = rest.to_string();
+    }
+    // e.g. "us.anthropic.claude-opus-4-5"
+    if let Some((_, tail)) = m.rsplit_once('.')
+        && tail.starts_with("claude-")
+    {
+        m = tail.to_string();
+    }
+    // Bedrock "-v1:0" suffix
+    if let Some(idx) = m.rfind("-v")
+        && m[idx + 2..].contains(':')
+    {
+        m.truncate(idx);
+    }
+    if claude_pricing(&m).is_some() {
+        return m;
+    }
+    // Drop a trailing -YYYYMMDD date
+    if let Some((base, date)) = m.rsplit_once('-')
+        && date.len() == 8
+        && date.chars().all(|c| c.is_ascii_digit())
+        && claude_pricing(base).is_some()
+    {
+        return base.to_string();
+    }
+    m
+}
+
+fn normalize_codex_model(model: &str) -> String {
+    let m = model.trim().to_lowercase();
+    let m = m.strip_prefix("openai/").unwrap_or(&m).to_string();
+    match m.strip_suffix("-codex") {
+        Some(base) if codex_pricing(base).is_some() => base.to_string(),
+        _ => m,
+    }
+}
+
+/// The override for `model`, matched as written or by its normalized name
+fn price_override(model: &str, pricing: &PricingTable) -> Option<ModelPrice> {
+    if pricing.is_empty() {
+        return None;
+    }
+    [
+        model.to_string(),
+        model.trim().to_lowercase(),
+        normalize_claude_model(model),
+        normalize_codex_model(model),
+    ]
+    .iter()
+    .find_map(|name| pricing.get(name).copied())
+}
+
+fn tiered(tokens: u64, base: f64, above: Option<(u64, f64)>) -> f64 {
+    match above {
+        Some((threshold, rate)) if tokens > threshold => {
+            threshold as f64 * base + (tokens - threshold) as f64 * rate
+        }
+        _ => tokens as f64 * base,
+    }
+}
+
+/// Token totals for one model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    /// Codex reports cached tokens as part of `input_tokens`
+    pub input_includes_cache: bool,
+}
+
+impl ModelUsage {
+    /// Share of prompt tokens served from cache (0.0 when there were none)
+    pub fn cache_hit_ratio(&self) -> f64 {
+        let prompt = if self.input_includes_cache {
+            self.input_tokens
+        } else {
+            self.input_tokens + self.cache_read_tokens + self.cache_creation_tokens
+        };
+        if prompt == 0 {
+            0.0
+        } else {
+            self.cache_read_tokens as f64 / prompt as f64
+        }
+    }
+
+    /// Estimated cost in USD, or None for models without known pricing
+    pub fn cost(&self, model: &str) -> Option<f64> {
+        self.cost_with(model, &PricingTable::new())
+    }
+
+    /// Like [`ModelUsage::cost`], preferring prices from `pricing`
+    pub fn cost_with(&self, model: &str, pricing: &PricingTable) -> Option<f64> {
+        if let Some(price) = price_override(model, pricing) {
+            let (input, cache_write) = if self.input_includes_cache {
+                (self.input_tokens.saturating_sub(self.cache_read_tokens), 0)
+            } else {
+                (self.input_tokens, self.cache_creation_tokens)
+            };
+            let cost = input as f64 * price.input
+                + self.output_tokens as f64 * price.output
+                + self.cache_read_tokens as f64 * price.cache_read
+                + cache_write as f64 * price.cache_write;
+            return Some(cost / 1e6);
+        }
+        if let Some(p) = claude_pricing(&normalize_claude_model(model)) {
+            let above = |i: usize| p.threshold.map(|(t, rates)| (t, rates[i]));
+            return Some(
+                tiered(self.input_tokens, p.input, above(0))
+                    + tiered(self.output_tokens, p.output, above(1))
+                    + tiered(self.cache_read_tokens, p.cache_read, above(2))
+                    + tiered(self.cache_creation_tokens, p.cache_create, above(3)),
+            );
+        }
+        let p = codex_pricing(&normalize_codex_model(model))?;
+        let cached = self.cache_read_tokens.min(self.input_tokens);
+        Some(
+            (self.input_tokens - cached) as f64 * p.input
+                + cached as f64 * p.cache_read
+                + self.output_tokens as f64 * p.output,
+        )
+    }
+}
+
+impl From<&ModelCost> for ModelUsage {
+    fn from(cost: &ModelCost) -> Self {
+        ModelUsage {
+            input_tokens: cost.input_tokens,
+            output_tokens: cost.output_tokens,
+            cache_read_tokens: cost.cache_read_tokens,
+            cache_creation_tokens: cost.cache_creation_tokens,
+            input_includes_cache: cost.input_includes_cache,
+        }
+    }
+}
+
+/// Estimate the cost of each model in `payload` and the session total,
+/// preferring prices from `pricing`
+pub fn price_payload(payload: &mut SharePayload, pricing: &PricingTable) {
+    let mut total = None;
+    for cost in &mut payload.model_costs {
+        cost.estimated_cost_usd = ModelUsage::from(&*cost).cost_with(&cost.model, pricing);
+        if let Some(usd) = cost.estimated_cost_usd {
+            *total.get_or_insert(0.0) += usd;
+        }
+    }
+    payload.estimated_cost_usd = total;
+}
+
+impl ParseResult {
+    /// Per-model usage for the share payload, not yet priced
+    pub fn model_costs(&self) -> Vec<ModelCost> {
+        self.usage_by_model()
+            .into_iter()
+            .map(|(model, usage)| ModelCost {
+                model,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                cache_read_tokens: usage.cache_read_tokens,
+                cache_creation_tokens: usage.cache_creation_tokens,
+                input_includes_cache: usage.input_includes_cache,
+                estimated_cost_usd: None,
+            })
+            .collect()
+    }
+
+    /// Token usage grouped by model. Codex only reports session totals, so they
+    /// are attributed to the dominant model.
+    pub fn usage_by_model(&self) -> BTreeMap<String, ModelUsage> {
+        let mut by_model: BTreeMap<String, ModelUsage> = BTreeMap::new();
+        if self.codex_total_input_tokens > 0 || self.codex_total_output_tokens > 0 {
+            let model = self
+                .dominant_model()
+                .unwrap_or_else(|| "unknown".to_string());
+            by_model.insert(
+                model,
+                ModelUsage {
+                    input_tokens: self.codex_total_input_tokens,
+                    output_tokens: self.codex_total_output_tokens,
+                    cache_read_tokens: self.codex_total_cache_read_tokens,
+                    cache_creation_tokens: 0,
+                    input_includes_cache: true,
+                },
+            );
+            return by_model;
+        }
+        for usage in self.usage_by_message_id.values() {
+            let model = usage.model.clone().unwrap_or_else(|| "unknown".to_string());
+            let entry = by_model.entry(model).or_default();
+            entry.input_tokens += usage.input_tokens;
+            entry.output_tokens += usage.output_tokens;
+            entry.cache_read_tokens += usage.cache_read_tokens;
+            entry.cache_creation_tokens += usage.cache_creation_tokens;
+        }
+        by_model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::parse_transcript_from_reader;
+
+    #[test]
+    fn claude_cost_matches_viewer() {
+        let usage = ModelUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            input_includes_cache: false,
+        };
+        let cost = usage
+            .cost("anthropic.claude-opus-4-5-20251101-v1:0")
+            .unwrap();
+        assert!((cost - 7.5).abs() < 1e-9);
+        // Sonnet long-context tier past 200k input tokens
+        let cost = usage.cost("claude-sonnet-4-5").unwrap();
+        assert!((cost - (0.6 + 4.8 + 1.5)).abs() < 1e-9);
+        assert!(usage.cost("mystery-model").is_none());
+    }
+
+    #[test]
+    fn codex_cost_subtracts_cached_input() {
+        let usage = ModelUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 0,
+            cache_read_tokens: 400_000,
+            cache_creation_tokens: 0,
+            input_includes_cache: true,
+        };
+        let cost = usage.cost("gpt-5-codex").unwrap();
+        assert!((cost - (0.75 + 0.05)).abs() < 1e-9);
+        assert!((usage.cache_hit_ratio() - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn payload_priced_per_model_with_overrides() {
+        let transcript = [
+            r#"{"type":"assistant","message":{"id":"a","model":"anthropic.claude-opus-4-5","usage":{"input_tokens":1000000,"output_tokens":0},"content":[]}}"#,
+            r#"{"type":"assistant","message":{"id":"b","model":"in-house-model","usage":{"input_tokens":2000000,"output_tokens":1000000},"content":[]}}"#,
+        ]
+        .join("\n");
+        let parsed = parse_transcript_from_reader(transcript.as_bytes()).unwrap();
+        let mut payload = crate::transcript::build_share_payload(
+            "Claude Code",
+            parsed,
+            Default::default(),
+            None,
+            None,
+            String::new(),
+        );
+        // Built-in pricing only knows Opus
+        assert_eq!(payload.model_costs.len(), 2);
+        assert!(payload.model_costs[1].estimated_cost_usd.is_none());
+        assert!((payload.estimated_cost_usd.unwrap() - 5.0).abs() < 1e-9);
+
+        let mut pricing = PricingTable::new();
+        let price = ModelPrice {
+            input: 1.0,
+            output: 2.0,
+            ..Default::default()
+        };
+        pricing.insert("in-house-model".to_string(), price);
+        // Matched through the normalized name
+        pricing.insert("claude-opus-4-5".to_string(), price);
+        price_payload(&mut payload, &pricing);
+        assert!((payload.model_costs[0].estimated_cost_usd.unwrap() - 1.0).abs() < 1e-9);
+        assert!((payload.model_costs[1].estimated_cost_usd.unwrap() - 4.0).abs() < 1e-9);
+        assert!((payload.estimated_cost_usd.unwrap() - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn usage_grouped_by_model() {
+        let transcript = [
+            r#"{"type":"assistant","message":{"id":"a","model":"claude-sonnet-4-5","usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":30},"content":[]}}"#,
+            r#"{"type":"assistant","message":{"id":"b","model":"claude-haiku-4-5","usage":{"input_tokens":7,"output_tokens":1},"content":[]}}"#,
+        ]
+        .join("\n");
+        let parsed = parse_transcript_from_reader(transcript.as_bytes()).unwrap();
+        let by_model = parsed.usage_by_model();
+        assert_eq!(by_model.len(), 2);
+        assert_eq!(by_model["claude-sonnet-4-5"].input_tokens, 10);
+        assert_eq!(by_model["claude-sonnet-4-5"].cache_read_tokens, 30);
+        assert_eq!(by_model["claude-haiku-4-5"].output_tokens, 1);
+    }
+}
@@ -1,23 +1,23 @@
 #![allow(dead_code)]
 
+use agentexport_protocol::{
+    BlobMetadata, CHUNK_INDEX_HEADER, DELETE_TOKEN_HEADER, ENCRYPTION_HEADER, ENCRYPTION_NONE,
+    ListingRequest, MAX_BLOB_SIZE, RELATES_TO_HEADER, TTL_DAYS_HEADER, TtlRequest, UPLOAD_PATH,
+    UploadResponse, blob_chunk_path, blob_chunks_path, blob_meta_path, blob_path, blob_ttl_path,
+    listing_path, viewer_path,
+};
 use anyhow::{Context, Result, bail};
 use rand::RngCore;
-use serde::Deserialize;
 use serde_json::Value;
 use std::fs;
-use std::io;
+use std::io::{self, Read};
 use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tempfile::tempdir;
 
 use crate::config::GistFormat;
-use crate::gist::render_gist_markdown;
-
-#[derive(Deserialize)]
-struct UploadResponse {
-    id: String,
-    expires_at: u64,
-}
+use crate::gist::{GIST_PART_BYTES, render_gist_markdown_parts};
+use crate::test_mode;
 
 /// Result of uploading a blob
 #[derive(Debug, Clone)]
@@ -45,50 +45,55 @@ fn far_future_expires_at() -> u64 {
     now.saturating_add(60 * 60 * 24 * 365 * 100)
 }
 
+/// File names and contents a payload is stored as in a gist: one markdown
+/// file, or several parts for a very long session, or the payload JSON
+fn gist_transcript_files(payload_json: &str, format: GistFormat) -> Result<Vec<(String, String)>> {
+    Ok(match format {
+        GistFormat::Markdown => render_gist_markdown_parts(payload_json, GIST_PART_BYTES)?,
+        GistFormat::Json => vec![("agentexport.json".to_string(), payload_json.to_string())],
+    })
+}
+
+/// Whether a gist file holds the transcript, in any format or part
+fn is_transcript_file(name: &str) -> bool {
+    name == "transcript.md"
+        || name == "agentexport.json"
+        || name
+            .strip_prefix("part-")
+            .and_then(|rest| rest.strip_suffix(".md"))
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The `files` of a gist: the transcript plus any attached (name, content) files
+fn gist_files(
+    payload_json: &str,
+    format: GistFormat,
+    attachments: &[(String, String)],
+) -> Result<Value> {
+    let mut files = serde_json::Map::new();
+    for (filename, content) in gist_transcript_files(payload_json, format)? {
+        files.insert(filename, serde_json::json!({ "content": content }));
+    }
+    for (name, content) in attachments {
+        files.insert(name.clone(), serde_json::json!({ "content": content }));
+    }
+    Ok(Value::Object(files))
+}
+
 pub fn upload_gist(
     upload_url: &str,
     payload_json: &str,
     description: &str,
     format: GistFormat,
+    attachments: &[(String, String)],
 ) -> Result<UploadResult> {
-    ensure_gh_ready()?;
-
-    let (filename, content) = match format {
-        GistFormat::Markdown => {
-            let md = render_gist_markdown(payload_json)?;
-            ("transcript.md".to_string(), md)
-        }
-        GistFormat::Json => ("agentexport.json".to_string(), payload_json.to_string()),
-    };
-
     let body = serde_json::json!({
         "public": false,
         "description": description,
-        "files": {
-            filename: {
-                "content": content
-            }
-        }
+        "files": gist_files(payload_json, format, attachments)?,
     });
 
-    let temp = tempdir().context("Failed to create temp dir for gist payload")?;
-    let body_path = temp.path().join("gist.json");
-    let body_bytes = serde_json::to_vec(&body).context("Failed to serialize gist payload")?;
-    fs::write(&body_path, body_bytes).context("Failed to write gist payload")?;
-
-    let output = Command::new("gh")
-        .args(["api", "gists", "--input"])
-        .arg(&body_path)
-        .output()
-        .context("Failed to run gh api for gist create")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("gh api gist create failed: {}", stderr.trim());
-    }
-
-    let response: Value =
-        serde_json::from_slice(&output.stdout).context("Failed to parse gist response")?;
+    let response = gist_api("POST", "gists", Some(&body))?;
     let id = response
         .get("id")
         .and_then(|v| v.as_str())
@@ -107,6 +112,110 @@ pub fn upload_gist(
     })
 }
 
+/// Rename a gist share: its description and the title inside its transcript file
+pub fn retitle_gist(id: &str, title: &str) -> Result<()> {
+    let gist = gist_api("GET", &format!("gists/{id}"), None)?;
+    let mut files = serde_json::Map::new();
+    for (name, file) in gist
+        .get("files")
+        .and_then(|f| f.as_object())
+        .into_iter()
+        .flatten()
+    {
+        let Some(content) = file.get("content").and_then(|c| c.as_str()) else {
+            continue;
+        };
+        let updated = if name.ends_with(".json") {
+            let mut payload: Value =
+                serde_json::from_str(content).context("Gist payload is not valid JSON")?;
+            payload["title"] = Value::String(title.to_string());
+            serde_json::to_string(&payload)?
+        } else if name.ends_with(".md") {
+            // The rendered markdown opens with the title as a heading
+            let rest = match content.split_once('\n') {
+                Some((first, rest)) if first.starts_with("# ") => rest,
+                _ => content,
+            };
+            format!("# {title}\n{rest}")
+        } else {
+            continue;
+        };
+        files.insert(name.clone(), serde_json::json!({ "content": updated }));
+    }
+    if files.is_empty() {
+        bail!("Gist {id} has no agentexport transcript file");
+    }
+    let body = serde_json::json!({
+        "description": format!("agentexport share: {title}"),
+        "files": files,
+    });
+    gist_api("PATCH", &format!("gists/{id}"), Some(&body))?;
+    Ok(())
+}
+
+/// Replace the transcript (and attachments) in a gist share, keeping its id and link
+pub fn update_gist(
+    id: &str,
+    payload_json: &str,
+    format: GistFormat,
+    attachments: &[(String, String)],
+) -> Result<()> {
+    let mut files = gist_files(payload_json, format, attachments)?;
+    // Transcript files the new version doesn't have (a part count that
+    // shrank, or another format) would be left behind; a null drops them
+    let gist = gist_api("GET", &format!("gists/{id}"), None)?;
+    for name in gist
+        .get("files")
+        .and_then(|f| f.as_object())
+        .into_iter()
+        .flat_map(|f| f.keys())
+    {
+        if is_transcript_file(name) && files.get(name).is_none() {
+            files[name] = Value::Null;
+        }
+    }
+    let body = serde_json::json!({ "files": files });
+    gist_api("PATCH", &format!("gists/{id}"), Some(&body))?;
+    Ok(())
+}
+
+/// Call the GitHub gist API through `gh`, or the mock server in test mode
+fn gist_api(method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+    if let Some(base) = test_mode::endpoints() {
+        let request = ureq::request(method, &format!("{base}/{path}"));
+        let response = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        }
+        .with_context(|| format!("gist {method} {path} failed"))?;
+        let text = response.into_string()?;
+        return Ok(serde_json::from_str(&text).unwrap_or(Value::Null));
+    }
+
+    ensure_gh_ready()?;
+    let temp = tempdir().context("Failed to create temp dir for gist payload")?;
+    let mut command = Command::new("gh");
+    command.args(["api", "-X", method, path]);
+    if let Some(body) = body {
+        let body_path = temp.path().join("gist.json");
+        let body_bytes = serde_json::to_vec(body).context("Failed to serialize gist payload")?;
+        fs::write(&body_path, body_bytes).context("Failed to write gist payload")?;
+        command.arg("--input").arg(&body_path);
+    }
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run gh api {method} {path}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("gh api {method} {path} failed: {}", stderr.trim());
+    }
+    if output.stdout.iter().all(|b| b.is_ascii_whitespace()) {
+        return Ok(Value::Null);
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse gist response")
+}
+
 fn ensure_gh_ready() -> Result<()> {
     let output = Command::new("gh")
         .args(["auth", "status", "-h", "github.com"])
@@ -140,47 +249,99 @@ pub fn upload_blob(
     blob: &[u8],
     key_b64: &str,
     ttl_days: u64,
+    relates_to: Option<&str>,
 ) -> Result<UploadResult> {
-    let endpoint = format!("{}/upload", upload_url.trim_end_matches('/'));
-    let delete_token = generate_delete_token();
-
-    let response = ureq::post(&endpoint)
-        .set("Content-Type", "application/octet-stream")
-        .set("X-Delete-Token", &delete_token)
-        .set("X-TTL-Days", &ttl_days.to_string())
-        .send_bytes(blob)
-        .context("Failed to upload blob")?;
-
-    if response.status() >= 400 {
-        let status = response.status();
-        let body = response.into_string().unwrap_or_default();
-        bail!("Upload failed: {status} - {body}");
-    }
-
-    let upload_response: UploadResponse = response
-        .into_json()
-        .context("Failed to parse upload response")?;
+    let (response, delete_token, upload_url) =
+        send_upload(upload_url, blob, None, ttl_days, relates_to)?;
 
     // Construct final URL with key in fragment
-    let base_url = upload_url.trim_end_matches('/');
-    let share_url = format!("{}/v/{}#{}", base_url, upload_response.id, key_b64);
+    let share_url = format!("{}{}#{}", upload_url, viewer_path(&response.id), key_b64);
 
     Ok(UploadResult {
-        id: upload_response.id,
+        id: response.id,
         key: key_b64.to_string(),
         delete_token,
         share_url,
-        upload_url: base_url.to_string(),
-        expires_at: upload_response.expires_at,
+        upload_url,
+        expires_at: response.expires_at,
     })
 }
 
+/// Upload an unencrypted payload to a server running in plaintext org mode.
+/// The server renders it itself, so the share URL carries no key.
+pub fn upload_plaintext(
+    upload_url: &str,
+    json: &str,
+    token: &str,
+    ttl_days: u64,
+    relates_to: Option<&str>,
+) -> Result<UploadResult> {
+    let (response, delete_token, upload_url) = send_upload(
+        upload_url,
+        json.as_bytes(),
+        Some(token),
+        ttl_days,
+        relates_to,
+    )?;
+    let share_url = format!("{}{}", upload_url, viewer_path(&response.id));
+
+    Ok(UploadResult {
+        id: response.id,
+        key: String::new(),
+        delete_token,
+        share_url,
+        upload_url,
+        expires_at: response.expires_at,
+    })
+}
+
+/// POST a body to `/upload`, returning the response, delete token, and resolved server URL.
+/// `plaintext_token` marks the body as unencrypted and authorizes it.
+fn send_upload(
+    upload_url: &str,
+    body: &[u8],
+    plaintext_token: Option<&str>,
+    ttl_days: u64,
+    relates_to: Option<&str>,
+) -> Result<(UploadResponse, String, String)> {
+    let upload_url = test_mode::upload_url(upload_url);
+    let endpoint = format!("{upload_url}{UPLOAD_PATH}");
+    let delete_token = generate_delete_token();
+
+    let mut request = ureq::post(&endpoint)
+        .set(DELETE_TOKEN_HEADER, &delete_token)
+        .set(TTL_DAYS_HEADER, &ttl_days.to_string());
+    request = match plaintext_token {
+        Some(token) => request
+            .set("Content-Type", "application/json")
+            .set(ENCRYPTION_HEADER, ENCRYPTION_NONE)
+            .set("Authorization", &format!("Bearer {token}")),
+        None => request.set("Content-Type", "application/octet-stream"),
+    };
+    if let Some(id) = relates_to {
+        request = request.set(RELATES_TO_HEADER, id);
+    }
+    let response = match request.send_bytes(body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("Upload failed: {status} - {body}");
+        }
+        Err(err) => return Err(err).context("Failed to upload blob"),
+    };
+
+    let upload_response: UploadResponse = response
+        .into_json()
+        .context("Failed to parse upload response")?;
+    Ok((upload_response, delete_token, upload_url))
+}
+
 /// Delete a blob from the server using the delete token
 pub fn delete_blob(upload_url: &str, id: &str, delete_token: &str) -> Result<()> {
-    let endpoint = format!("{}/blob/{}", upload_url.trim_end_matches('/'), id);
+    let endpoint = format!("{}{}", test_mode::upload_url(upload_url), blob_path(id));
 
     let response = ureq::delete(&endpoint)
-        .set("X-Delete-Token", delete_token)
+        .set(DELETE_TOKEN_HEADER, delete_token)
         .call()
         .context("Failed to delete blob")?;
 
@@ -193,11 +354,205 @@ pub fn delete_blob(upload_url: &str, id: &str, delete_token: &str) -> Result<()>
     Ok(())
 }
 
+/// List a plaintext share in the server's public gallery
+pub fn list_share(
+    upload_url: &str,
+    id: &str,
+    delete_token: &str,
+    title: &str,
+    tool: &str,
+) -> Result<()> {
+    let endpoint = format!("{}{}", test_mode::upload_url(upload_url), listing_path(id));
+    let listing = ListingRequest {
+        title: title.to_string(),
+        tool: tool.to_string(),
+    };
+    match ureq::put(&endpoint)
+        .set(DELETE_TOKEN_HEADER, delete_token)
+        .send_json(&listing)
+    {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("Listing failed: {status} - {body}")
+        }
+        Err(err) => Err(err).context("Failed to list share"),
+    }
+}
+
+/// What the server reports for a stored blob. `Ok(None)` when the blob is gone
+/// or the server predates the metadata endpoint.
+pub fn blob_metadata(upload_url: &str, id: &str) -> Result<Option<BlobMetadata>> {
+    let endpoint = format!(
+        "{}{}",
+        test_mode::upload_url(upload_url),
+        blob_meta_path(id)
+    );
+    match ureq::get(&endpoint).call() {
+        Ok(response) => Ok(Some(
+            response
+                .into_json()
+                .context("Failed to parse blob metadata")?,
+        )),
+        Err(ureq::Error::Status(404 | 405, _)) => Ok(None),
+        Err(err) => Err(err).context("Failed to fetch blob metadata"),
+    }
+}
+
+/// Replace a blob's content, keeping its id; returns the new expiry.
+/// `Ok(None)` when the server can't: the blob is gone, or the server predates
+/// replacing blobs. `plaintext_token` is needed for plaintext shares.
+pub fn replace_blob(
+    upload_url: &str,
+    id: &str,
+    delete_token: &str,
+    body: &[u8],
+    plaintext_token: Option<&str>,
+) -> Result<Option<u64>> {
+    let endpoint = format!("{}{}", test_mode::upload_url(upload_url), blob_path(id));
+    let request = ureq::put(&endpoint).set(DELETE_TOKEN_HEADER, delete_token);
+    let response = match with_encoding(request, plaintext_token).send_bytes(body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404 | 405 | 410, _)) => return Ok(None),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("Replace failed: {status} - {body}");
+        }
+        Err(err) => return Err(err).context("Failed to replace blob"),
+    };
+    let replaced: UploadResponse = response
+        .into_json()
+        .context("Failed to parse replace response")?;
+    Ok(Some(replaced.expires_at))
+}
+
+/// Append a chunk of new messages to a blob, as chunk number `index`; returns
+/// the blob's expiry. `Ok(None)` when the server won't: the blob is gone, the
+/// server predates chunks, or `index` isn't next (replace the blob instead).
+pub fn append_chunk(
+    upload_url: &str,
+    id: &str,
+    delete_token: &str,
+    index: u32,
+    body: &[u8],
+    plaintext_token: Option<&str>,
+) -> Result<Option<u64>> {
+    let endpoint = format!(
+        "{}{}",
+        test_mode::upload_url(upload_url),
+        blob_chunks_path(id)
+    );
+    let request = ureq::post(&endpoint)
+        .set(DELETE_TOKEN_HEADER, delete_token)
+        .set(CHUNK_INDEX_HEADER, &index.to_string());
+    let response = match with_encoding(request, plaintext_token).send_bytes(body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404 | 405 | 409 | 410, _)) => return Ok(None),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("Append failed: {status} - {body}");
+        }
+        Err(err) => return Err(err).context("Failed to append to blob"),
+    };
+    let appended: UploadResponse = response
+        .into_json()
+        .context("Failed to parse append response")?;
+    Ok(Some(appended.expires_at))
+}
+
+/// Change how long a blob is kept, counting from now; returns the new expiry.
+/// `Ok(None)` when the server can't: the blob is gone, or the server predates
+/// changing retention.
+pub fn set_blob_ttl(
+    upload_url: &str,
+    id: &str,
+    delete_token: &str,
+    ttl_days: u64,
+) -> Result<Option<u64>> {
+    let endpoint = format!("{}{}", test_mode::upload_url(upload_url), blob_ttl_path(id));
+    let response = match ureq::post(&endpoint)
+        .set(DELETE_TOKEN_HEADER, delete_token)
+        .send_json(&TtlRequest { ttl_days })
+    {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404 | 405, _)) => return Ok(None),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("Changing retention failed: {status} - {body}");
+        }
+        Err(err) => return Err(err).context("Failed to change retention"),
+    };
+    let updated: UploadResponse = response
+        .into_json()
+        .context("Failed to parse retention response")?;
+    Ok(Some(updated.expires_at))
+}
+
+/// Content headers for a blob body: JSON with the upload token for plaintext
+/// shares, opaque bytes otherwise
+fn with_encoding(request: ureq::Request, plaintext_token: Option<&str>) -> ureq::Request {
+    match plaintext_token {
+        Some(token) => request
+            .set("Content-Type", "application/json")
+            .set(ENCRYPTION_HEADER, ENCRYPTION_NONE)
+            .set("Authorization", &format!("Bearer {token}")),
+        None => request.set("Content-Type", "application/octet-stream"),
+    }
+}
+
+/// Delete a gist
+pub fn delete_gist(id: &str) -> Result<()> {
+    gist_api("DELETE", &format!("gists/{id}"), None)?;
+    Ok(())
+}
+
+/// Download a stored blob
+pub fn download_blob(upload_url: &str, id: &str) -> Result<Vec<u8>> {
+    download(&format!(
+        "{}{}",
+        test_mode::upload_url(upload_url),
+        blob_path(id)
+    ))
+}
+
+/// Download chunk number `index` appended to a blob
+pub fn download_chunk(upload_url: &str, id: &str, index: u32) -> Result<Vec<u8>> {
+    download(&format!(
+        "{}{}",
+        test_mode::upload_url(upload_url),
+        blob_chunk_path(id, index)
+    ))
+}
+
+fn download(endpoint: &str) -> Result<Vec<u8>> {
+    let response = match ureq::get(endpoint).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404 | 410, _)) => {
+            bail!("Blob not found on the server (expired or deleted)")
+        }
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            bail!("Download failed: {status} - {body}");
+        }
+        Err(err) => return Err(err).context("Failed to download blob"),
+    };
+    let mut blob = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_BLOB_SIZE as u64 + 1)
+        .read_to_end(&mut blob)
+        .context("Failed to read blob")?;
+    Ok(blob)
+}
+
 /// Check if a blob exists and is not expired
 pub fn check_blob_status(upload_url: &str, id: &str) -> Result<BlobStatus> {
-    let endpoint = format!("{}/blob/{}", upload_url.trim_end_matches('/'), id);
+    let endpoint = format!("{}{}", test_mode::upload_url(upload_url), blob_path(id));
 
-    match ureq::head(&endpoint).call() {
+    match ureq::head(&endpoint)
+        .timeout(std::time::Duration::from_secs(10))
+        .call()
+    {
         Ok(response) => {
             if response.status() == 200 {
                 Ok(BlobStatus::Active)
@@ -211,6 +566,15 @@ pub fn check_blob_status(upload_url: &str, id: &str) -> Result<BlobStatus> {
     }
 }
 
+/// Check if a gist still exists
+pub fn check_gist_status(id: &str) -> Result<BlobStatus> {
+    match gist_api("GET", &format!("gists/{id}"), None) {
+        Ok(_) => Ok(BlobStatus::Active),
+        Err(err) if format!("{err:#}").contains("404") => Ok(BlobStatus::NotFound),
+        Err(err) => Err(err),
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlobStatus {
     Active,
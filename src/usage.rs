@@ -0,0 +1,371 @@
+//! Local usage insights (`agentexport usage`): how often sessions are shared,
+//! how big shares are, and which repos they come from.
+//!
+//! Everything is read from files under `~/.agentexport`; nothing is sent
+//! anywhere. Each publish is logged to `history.jsonl` as it happens, so
+//! shares deleted since still count. Shares made before the log existed are
+//! read from `shares.json`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use crate::config::StorageType;
+use crate::export::repo_name;
+use crate::output::{Output, Table};
+use crate::shares::{self, Share, format_size};
+
+/// One publish, as logged to `history.jsonl`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishEvent {
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub id: String,
+    pub tool: String,
+    #[serde(default)]
+    pub storage_type: StorageType,
+    /// Uploaded size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Refreshed an existing share (`--update`, `watch`) instead of making one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub update: bool,
+}
+
+fn history_path() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME not set")?;
+    Ok(PathBuf::from(home)
+        .join(".agentexport")
+        .join("history.jsonl"))
+}
+
+/// Append a publish of `share` to the local history
+pub fn record_publish(share: &Share, update: bool) -> Result<()> {
+    let event = PublishEvent {
+        at: OffsetDateTime::now_utc(),
+        id: share.id.clone(),
+        tool: share.tool.clone(),
+        storage_type: share.storage_type,
+        size_bytes: share.size_bytes,
+        cwd: share.cwd.clone(),
+        update,
+    };
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&event)?)?;
+    Ok(())
+}
+
+/// Logged publishes, oldest first. Lines that don't parse (a write cut short)
+/// are skipped.
+pub fn load_history() -> Result<Vec<PublishEvent>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Shares published in one week
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeekCount {
+    /// Monday the week starts on (YYYY-MM-DD)
+    pub week: String,
+    pub shares: usize,
+}
+
+/// How often something was shared
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NamedCount {
+    pub name: String,
+    pub shares: usize,
+}
+
+/// What `agentexport usage` reports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageReport {
+    /// Earliest publish counted
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub since: Option<OffsetDateTime>,
+    /// New shares (not counting updates)
+    pub shares: usize,
+    /// Refreshes of existing shares
+    pub updates: usize,
+    pub shares_per_week: f64,
+    pub average_size_bytes: Option<u64>,
+    /// Shares that haven't expired, from `shares.json`
+    pub live: usize,
+    pub weeks: Vec<WeekCount>,
+    pub tools: Vec<NamedCount>,
+    pub storage: Vec<NamedCount>,
+    pub repos: Vec<NamedCount>,
+}
+
+/// Monday of the week `at` falls in
+fn week_start(at: OffsetDateTime) -> time::Date {
+    let date = at.date();
+    date - time::Duration::days(date.weekday().number_days_from_monday() as i64)
+}
+
+/// Counts by name, most first (ties by name)
+fn ranked(names: impl Iterator<Item = String>) -> Vec<NamedCount> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for name in names {
+        *counts.entry(name).or_default() += 1;
+    }
+    let mut ranked: Vec<_> = counts
+        .into_iter()
+        .map(|(name, shares)| NamedCount { name, shares })
+        .collect();
+    ranked.sort_by_key(|c| std::cmp::Reverse(c.shares));
+    ranked
+}
+
+/// Summarize `history`, plus the `shares` it doesn't cover, from `now - since`
+/// (everything when None). Only the `top` repos are kept.
+pub fn summarize(
+    history: &[PublishEvent],
+    shares: &[Share],
+    since: Option<Duration>,
+    top: usize,
+    now: OffsetDateTime,
+) -> UsageReport {
+    let logged: HashSet<&str> = history.iter().map(|e| e.id.as_str()).collect();
+    let earlier = shares
+        .iter()
+        .filter(|s| !logged.contains(s.id.as_str()))
+        .map(|s| PublishEvent {
+            at: s.created_at,
+            id: s.id.clone(),
+            tool: s.tool.clone(),
+            storage_type: s.storage_type,
+            size_bytes: s.size_bytes,
+            cwd: s.cwd.clone(),
+            update: false,
+        });
+    let cutoff = since.map(|since| now - since);
+    let mut events: Vec<PublishEvent> = history
+        .iter()
+        .cloned()
+        .chain(earlier)
+        .filter(|e| cutoff.is_none_or(|cutoff| e.at >= cutoff))
+        .collect();
+    events.sort_by_key(|e| e.at);
+    let (updates, created): (Vec<_>, Vec<_>) = events.iter().partition(|e| e.update);
+
+    // Every week from the first share to now, so quiet weeks show as zero
+    let mut weeks = Vec::new();
+    if let Some(first) = cutoff.or(created.first().map(|e| e.at)) {
+        let mut week = week_start(first);
+        while week <= now.date() {
+            let next = week + time::Duration::days(7);
+            let shares = created
+                .iter()
+                .filter(|e| e.at.date() >= week && e.at.date() < next)
+                .count();
+            weeks.push(WeekCount {
+                week: week.to_string(),
+                shares,
+            });
+            week = next;
+        }
+    }
+
+    let sizes: Vec<u64> = created.iter().filter_map(|e| e.size_bytes).collect();
+    let mut repos = ranked(
+        created
+            .iter()
+            .filter(|e| e.cwd.is_some())
+            .map(|e| repo_name(e.cwd.as_deref())),
+    );
+    repos.truncate(top);
+    UsageReport {
+        since: events.first().map(|e| e.at),
+        shares: created.len(),
+        updates: updates.len(),
+        shares_per_week: if weeks.is_empty() {
+            0.0
+        } else {
+            created.len() as f64 / weeks.len() as f64
+        },
+        average_size_bytes: (!sizes.is_empty())
+            .then(|| sizes.iter().sum::<u64>() / sizes.len() as u64),
+        live: shares.iter().filter(|s| !s.is_expired()).count(),
+        weeks,
+        tools: ranked(created.iter().map(|e| e.tool.clone())),
+        storage: ranked(created.iter().map(|e| match e.storage_type {
+            StorageType::Agentexport => "server".to_string(),
+            StorageType::Gist => "gist".to_string(),
+        })),
+        repos,
+    }
+}
+
+/// Options for `agentexport usage`
+#[derive(Debug, Clone)]
+pub struct UsageOptions {
+    /// How far back to look (None: all history)
+    pub since: Option<Duration>,
+    /// Repos to list
+    pub top: usize,
+}
+
+/// Read the local history and shares and summarize them
+pub fn usage(options: UsageOptions) -> Result<UsageReport> {
+    Ok(summarize(
+        &load_history()?,
+        &shares::load_shares()?,
+        options.since,
+        options.top,
+        OffsetDateTime::now_utc(),
+    ))
+}
+
+fn counts(counts: &[NamedCount]) -> String {
+    counts
+        .iter()
+        .map(|c| format!("{} {}", c.name, c.shares))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The report as text: totals, then shares per week and the top repos
+pub fn format_usage(report: &UsageReport, output: &Output) -> String {
+    let mut out = String::new();
+    let Some(since) = report.since else {
+        return "No shares published yet (only local history is read)\n".to_string();
+    };
+    out.push_str(&format!(
+        "Since {} (local history only; nothing is sent anywhere)\n\n",
+        since.date()
+    ));
+    out.push_str(&format!(
+        "Shares:        {} ({:.1} per week), {} update{}\n",
+        report.shares,
+        report.shares_per_week,
+        report.updates,
+        if report.updates == 1 { "" } else { "s" }
+    ));
+    if let Some(size) = report.average_size_bytes {
+        out.push_str(&format!("Average size:  {}\n", format_size(size)));
+    }
+    if !report.tools.is_empty() {
+        out.push_str(&format!("Tools:         {}\n", counts(&report.tools)));
+        out.push_str(&format!("Storage:       {}\n", counts(&report.storage)));
+    }
+    out.push_str(&format!("Live now:      {}\n", report.live));
+
+    let mut weeks = Table::new(&["WEEK", "SHARES"]).right_align(&[1]);
+    for week in &report.weeks {
+        weeks.push(vec![week.week.clone(), week.shares.to_string()]);
+    }
+    out.push('\n');
+    out.push_str(&weeks.render(output));
+    if !report.repos.is_empty() {
+        let mut repos = Table::new(&["REPO", "SHARES"])
+            .right_align(&[1])
+            .shrink(&[0]);
+        for repo in &report.repos {
+            repos.push(vec![repo.name.clone(), repo.shares.to_string()]);
+        }
+        out.push('\n');
+        out.push_str(&repos.render(output));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::format_description::well_known::Rfc3339;
+
+    fn at(rfc3339: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(rfc3339, &Rfc3339).unwrap()
+    }
+
+    fn event(at: OffsetDateTime, id: &str, cwd: &str, size: u64, update: bool) -> PublishEvent {
+        PublishEvent {
+            at,
+            id: id.to_string(),
+            tool: "claude".to_string(),
+            storage_type: StorageType::Agentexport,
+            size_bytes: Some(size),
+            cwd: Some(cwd.to_string()),
+            update,
+        }
+    }
+
+    #[test]
+    fn summarize_counts_weeks_sizes_and_repos() {
+        let now = at("2026-10-16T12:00:00Z");
+        let history = vec![
+            event(at("2026-10-01T09:00:00Z"), "a", "/src/api", 1000, false),
+            event(at("2026-10-02T09:00:00Z"), "a", "/src/api", 1200, true),
+            event(at("2026-10-14T09:00:00Z"), "b", "/src/web", 3000, false),
+            event(at("2026-10-15T09:00:00Z"), "c", "/src/api", 2000, false),
+        ];
+        // Shared before the history was kept
+        let older = Share {
+            created_at: at("2026-09-30T09:00:00Z"),
+            cwd: Some("/src/api".to_string()),
+            size_bytes: Some(2000),
+            storage_type: StorageType::Gist,
+            ..shares::tests::make_test_share("old")
+        };
+        let report = summarize(&history, &[older], None, 5, now);
+        assert_eq!(report.shares, 4);
+        assert_eq!(report.updates, 1);
+        assert_eq!(report.average_size_bytes, Some(2000));
+        assert_eq!(report.since, Some(at("2026-09-30T09:00:00Z")));
+        let weeks: Vec<_> = report
+            .weeks
+            .iter()
+            .map(|w| (w.week.as_str(), w.shares))
+            .collect();
+        assert_eq!(
+            weeks,
+            [("2026-09-28", 2), ("2026-10-05", 0), ("2026-10-12", 2)]
+        );
+        assert_eq!(report.repos[0].name, "api");
+        assert_eq!(report.repos[0].shares, 3);
+        assert_eq!(counts(&report.storage), "server 3, gist 1");
+
+        // A window of a week only keeps the last two shares
+        let recent = summarize(&history, &[], Some(Duration::from_secs(7 * 86_400)), 1, now);
+        assert_eq!(recent.shares, 2);
+        assert_eq!(recent.repos.len(), 1);
+        assert!(format_usage(&recent, &Output::PLAIN).contains("Shares:        2 (1.0 per week)"));
+    }
+
+    #[test]
+    fn publishes_are_appended_to_the_history() {
+        let _lock = crate::test_utils::env_lock();
+        let home = tempfile::TempDir::new().unwrap();
+        let _home = crate::test_utils::EnvGuard::set("HOME", home.path().to_str().unwrap());
+        let share = shares::tests::make_test_share("abc");
+        record_publish(&share, false).unwrap();
+        record_publish(&share, true).unwrap();
+        let history = load_history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(!history[0].update && history[1].update);
+    }
+}
@@ -0,0 +1,125 @@
+//! `agentexport watch`: keep one share in step with a live session.
+//!
+//! The transcript is polled the way `monitor` does. Once a change has settled,
+//! the session is published again over the same share, so the link can be
+//! handed out while the session is still running.
+
+use anyhow::{Result, bail};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::pretty::file_stamp;
+use crate::publish::{PublishOptions, PublishResult, publish};
+
+/// Options for `agentexport watch`
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub publish: PublishOptions,
+    /// Time between checks; a change is published once it has held for this long
+    pub interval: Duration,
+}
+
+/// Publish the session, then publish it again over the same share whenever the
+/// transcript changes. `report` is called with every publish; failed updates are
+/// reported as warnings and retried on the next change.
+pub fn watch(options: WatchOptions, mut report: impl FnMut(&PublishResult)) -> Result<()> {
+    let mut publish_options = options.publish;
+    let first = publish(publish_options.clone())?;
+    let Some(share_id) = first.share_id.clone() else {
+        bail!("Nothing to watch: {}", first.note);
+    };
+    report(&first);
+
+    // Stay on this transcript, however long the session goes quiet
+    let path = live_transcript(&first);
+    publish_options.transcript = Some(path.clone());
+    publish_options.max_age_minutes = 0;
+    publish_options.replaces = Some(share_id);
+
+    let mut stamp = file_stamp(&path);
+    loop {
+        std::thread::sleep(options.interval);
+        let mut current = file_stamp(&path);
+        if current.is_none() || current == stamp {
+            continue;
+        }
+        // Agents write in bursts; publish once the file stops changing
+        loop {
+            std::thread::sleep(options.interval);
+            let next = file_stamp(&path);
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        stamp = current;
+        match publish(publish_options.clone()) {
+            Ok(result) => {
+                // A share that couldn't be replaced in place was copied to a new id
+                publish_options.replaces = result.share_id.clone().or(publish_options.replaces);
+                report(&result);
+            }
+            Err(err) => eprintln!("warning: failed to update the share: {err:#}"),
+        }
+    }
+}
+
+/// The file the published session is still writing to: its own transcript,
+/// not the copy stitched from a compaction chain
+fn live_transcript(result: &PublishResult) -> PathBuf {
+    PathBuf::from(
+        result
+            .source_path
+            .as_ref()
+            .unwrap_or(&result.transcript_path),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{EnvGuard, env_lock};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn watches_the_live_file_of_a_stitched_session() {
+        let _lock = env_lock();
+        let project = TempDir::new().unwrap();
+        let cache = TempDir::new().unwrap();
+        let _guard = EnvGuard::set("AGENTEXPORT_CACHE_DIR", cache.path().to_str().unwrap());
+        let _guard_session = EnvGuard::set("AGENTEXPORT_CLAUDE_SESSION_ID", "");
+        fs::write(
+            project.path().join("first.jsonl"),
+            concat!(
+                r#"{"type":"user","uuid":"u1","parentUuid":null,"message":{"content":"Write a parser"}}"#,
+                "\n",
+                r#"{"type":"assistant","uuid":"a1","parentUuid":"u1","message":{"id":"m1","content":[{"type":"text","text":"Here it is"}]}}"#,
+            ),
+        )
+        .unwrap();
+        let live = project.path().join("second.jsonl");
+        fs::write(
+            &live,
+            concat!(
+                r#"{"type":"summary","summary":"Parser work","leafUuid":"a1"}"#,
+                "\n",
+                r#"{"type":"user","uuid":"u2","parentUuid":null,"message":{"content":"Make it faster"}}"#,
+            ),
+        )
+        .unwrap();
+
+        let stitched = publish(PublishOptions {
+            follow_compaction: true,
+            ..PublishOptions::for_test("claude", Some(live.clone()))
+        })
+        .unwrap();
+        assert!(Path::new(&stitched.transcript_path).starts_with(cache.path()));
+        assert_eq!(live_transcript(&stitched), live);
+
+        let single = publish(PublishOptions::for_test("claude", Some(live.clone()))).unwrap();
+        assert_eq!(single.source_path, None);
+        assert_eq!(live_transcript(&single), live);
+    }
+}
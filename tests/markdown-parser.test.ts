@@ -21,6 +21,10 @@ function parseMarkdownTranscript(text: string) {
     total_output_tokens?: number;
     total_cache_read_tokens?: number;
     total_cache_creation_tokens?: number;
+    publisher?: { name?: string; email?: string; host?: string };
+    series?: { part: number; previous_url: string };
+    status?: string;
+    errors?: string[];
   } = { messages: [], tool: "Claude Code", models: [] };
 
   // Extract title (first h1)
@@ -36,6 +40,24 @@ function parseMarkdownTranscript(text: string) {
     if (parts.length > 2) data.shared_at = parts[2];
   }
 
+  // Extract series navigation line
+  const seriesMatch = text.match(
+    /^\*Part (\d+) · \[previous part\]\(([^)]+)\)\*$/m
+  );
+  if (seriesMatch) {
+    data.series = {
+      part: parseInt(seriesMatch[1]),
+      previous_url: seriesMatch[2],
+    };
+  }
+
+  // Extract session outcome line ("*Session errored* — error; error")
+  const statusMatch = text.match(/^\*Session (interrupted|errored)\*(?: — (.+))?$/m);
+  if (statusMatch) {
+    data.status = statusMatch[1];
+    data.errors = statusMatch[2] ? statusMatch[2].split("; ") : [];
+  }
+
   // Split by message headers (### Role)
   // Note: We use \z for end-of-string since $ matches end-of-line in multiline mode
   // But JS doesn't support \z, so we use a two-pass approach or negative lookahead
@@ -52,6 +74,8 @@ function parseMarkdownTranscript(text: string) {
     else if (header.includes("Tool")) role = "tool";
     else if (header.includes("Thinking")) role = "thinking";
     else if (header.includes("System")) role = "system";
+    else if (header.includes("Command")) role = "command";
+    else if (header.includes("Plan")) role = "plan";
 
     // Extract model if present
     const modelMatch = header.match(/\(([^)]+)\)/);
@@ -68,6 +92,7 @@ function parseMarkdownTranscript(text: string) {
       raw = detailsMatch[2].trim();
       content = content.replace(detailsMatch[0], "").trim();
     }
+    if (role === "command") content = content.replace(/^`|`$/g, "");
 
     data.messages.push({ role, content, model, raw, raw_label: rawLabel });
   }
@@ -87,6 +112,18 @@ function parseMarkdownTranscript(text: string) {
       data.total_cache_creation_tokens = parseInt(cacheCreateMatch[1]);
   }
 
+  // Extract publisher attribution from footer
+  const publisherMatch = text.match(
+    /^\*Shared by (.+?)(?: \(([^)]+)\))?(?: from (.+))?\*$/m
+  );
+  if (publisherMatch) {
+    data.publisher = {
+      name: publisherMatch[1],
+      email: publisherMatch[2],
+      host: publisherMatch[3],
+    };
+  }
+
   return data;
 }
 
@@ -269,4 +306,138 @@ Fourth line.
     expect(content).toContain("Third line with **bold**.");
     expect(content).toContain("Fourth line.");
   });
+
+  test("parses command invocations with grouped prompt", () => {
+    const markdown = `# Commands
+
+*Claude Code · claude-opus-4-5-20251101 · Jan 1, 2025*
+
+---
+
+### Command
+
+\`/review src/lib.rs\`
+
+<details>
+<summary>Command prompt</summary>
+
+\`\`\`json
+Review the file for bugs.
+\`\`\`
+
+</details>
+
+### Assistant
+
+Looks good.
+
+---
+
+*Input: 10 tokens · Output: 5 tokens*
+`;
+
+    const result = parseMarkdownTranscript(markdown);
+
+    expect(result.messages.length).toBe(2);
+    expect(result.messages[0].role).toBe("command");
+    expect(result.messages[0].content).toBe("/review src/lib.rs");
+    expect(result.messages[0].raw_label).toBe("Command prompt");
+    expect(result.messages[0].raw).toBe("Review the file for bugs.");
+  });
+
+  test("parses publisher attribution footer", () => {
+    const markdown = `# Attributed
+
+*Claude Code · claude-sonnet-4-20250514 · Jan 1, 2025*
+
+---
+
+### User
+
+Hello
+
+---
+
+*Input: 5 tokens · Output: 10 tokens*
+
+*Shared by Nico (nico@example.com) from devbox*
+`;
+
+    const result = parseMarkdownTranscript(markdown);
+
+    expect(result.messages.length).toBe(1);
+    expect(result.publisher?.name).toBe("Nico");
+    expect(result.publisher?.email).toBe("nico@example.com");
+    expect(result.publisher?.host).toBe("devbox");
+  });
+
+  test("parses series navigation line", () => {
+    const markdown = `# Part Two
+
+*Claude Code · claude-sonnet-4-20250514 · Jan 2, 2025*
+
+*Part 2 · [previous part](https://agentexports.com/v/abc#key)*
+
+---
+
+### User
+
+Continuing from yesterday
+
+---
+
+*Input: 5 tokens · Output: 10 tokens*
+`;
+
+    const result = parseMarkdownTranscript(markdown);
+
+    expect(result.tool).toBe("Claude Code");
+    expect(result.series?.part).toBe(2);
+    expect(result.series?.previous_url).toBe("https://agentexports.com/v/abc#key");
+    expect(result.messages.length).toBe(1);
+  });
+
+  test("parses session status line", () => {
+    const markdown = `# Broken run
+
+*Codex · gpt-5 · Jan 2, 2025*
+
+*Session errored* — stream disconnected; API Error: 500
+
+---
+
+### User
+
+Do the thing
+
+---
+`;
+
+    const result = parseMarkdownTranscript(markdown);
+
+    expect(result.tool).toBe("Codex");
+    expect(result.status).toBe("errored");
+    expect(result.errors).toEqual(["stream disconnected", "API Error: 500"]);
+    expect(result.messages.length).toBe(1);
+  });
+
+  test("parses plan messages", () => {
+    const markdown = `# Planning
+
+---
+
+### Plan
+
+- [ ] Add the parser
+- [ ] Wire the viewer
+
+---
+`;
+
+    const result = parseMarkdownTranscript(markdown);
+
+    expect(result.messages.length).toBe(1);
+    expect(result.messages[0].role).toBe("plan");
+    expect(result.messages[0].content).toBe("- [ ] Add the parser\n- [ ] Wire the viewer");
+  });
 });
@@ -1,10 +1,14 @@
+use agentexport_protocol::{
+    BlobMetadata, ListingRequest, SharePayload, TtlRequest, UploadResponse, ViewerDefaults,
+    CHUNK_COUNT_HEADER, CHUNK_INDEX_HEADER, DELETE_TOKEN_HEADER, DELETE_TOKEN_LEN,
+    ENCRYPTION_HEADER, ENCRYPTION_NONE, IV_LEN, KEY_LEN, MAX_BLOB_SIZE, MAX_CHUNKS,
+    PASSPHRASE_ITERATIONS, PASSPHRASE_PREFIX, RELATES_TO_HEADER, TTL_DAYS_HEADER,
+};
 use maud::{html, PreEscaped, DOCTYPE};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use worker::*;
 
-const MAX_BLOB_SIZE: usize = 10 * 1024 * 1024; // 10MB
-
 // R2 metrics types
 #[derive(Serialize)]
 struct R2MetricsDataPoint {
@@ -107,11 +111,22 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
         .get("/og/viewer.png", |_, _| serve_png(OG_VIEWER))
         .post_async("/upload", handle_upload)
         .get_async("/v/:id", handle_viewer)
+        .get_async("/v/:id/static", handle_static_viewer)
         .get_async("/g/:gist_id", handle_gist_viewer)
         .get_async("/blob/:id", handle_blob)
+        .get_async("/blob/:id/meta", handle_blob_meta)
+        .put_async("/blob/:id", handle_replace)
         .delete_async("/blob/:id", handle_delete)
+        .post_async("/blob/:id/chunks", handle_append_chunk)
+        .post_async("/blob/:id/ttl", handle_set_ttl)
+        .get_async("/blob/:id/chunks/:index", handle_chunk)
+        .get_async("/explore", handle_explore)
+        .put_async("/explore/:id", handle_list_share)
         .options_async("/upload", handle_cors_preflight)
         .options_async("/blob/:id", handle_cors_preflight)
+        .options_async("/blob/:id/chunks", handle_cors_preflight)
+        .options_async("/blob/:id/ttl", handle_cors_preflight)
+        .options_async("/explore/:id", handle_cors_preflight)
         .run(req, env)
         .await
 }
@@ -119,10 +134,17 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
 fn cors_headers() -> Headers {
     let headers = Headers::new();
     let _ = headers.set("Access-Control-Allow-Origin", "*");
-    let _ = headers.set("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS");
+    let _ = headers.set(
+        "Access-Control-Allow-Methods",
+        "GET, POST, PUT, DELETE, OPTIONS",
+    );
     let _ = headers.set(
         "Access-Control-Allow-Headers",
-        "Content-Type, X-Delete-Token, X-TTL-Days",
+        "Content-Type, Authorization, X-Delete-Token, X-TTL-Days, X-Relates-To, X-Encryption, X-Chunk-Index",
+    );
+    let _ = headers.set(
+        "Access-Control-Expose-Headers",
+        "X-Relates-To, X-Chunk-Count",
     );
     headers
 }
@@ -171,6 +193,30 @@ fn parse_id(id: &str) -> Option<(String, String, u64)> {
     ))
 }
 
+/// R2 prefix of pointers to blobs whose retention was changed with
+/// `POST /blob/:id/ttl`. Their id still names the tier they were uploaded to;
+/// the pointer (kept outside the tiers, so lifecycle rules skip it) names the
+/// tier they live in now.
+const MOVED_PREFIX: &str = "moved/";
+
+/// Where the blob for `id` is stored, with its retention in days. None for
+/// malformed ids.
+async fn locate(bucket: &Bucket, id: &str) -> Result<Option<(String, u64)>> {
+    let Some((r2_path, hash, ttl_days)) = parse_id(id) else {
+        return Ok(None);
+    };
+    let moved_to = bucket
+        .head(format!("{MOVED_PREFIX}{hash}"))
+        .await?
+        .and_then(|pointer| pointer.custom_metadata().ok())
+        .and_then(|metadata| metadata.get("tier").and_then(|t| t.chars().next()))
+        .and_then(ttl_prefix_to_path);
+    Ok(Some(match moved_to {
+        Some((r2_prefix, days)) => (format!("{r2_prefix}/{hash}"), days),
+        None => (r2_path, ttl_days),
+    }))
+}
+
 fn current_timestamp() -> u64 {
     js_sys::Date::now() as u64 / 1000
 }
@@ -187,8 +233,8 @@ async fn handle_upload(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
 
     // Get delete token from header (required for delete auth)
     // This is separate from the encryption key - only uploader has it
-    let delete_token = req.headers().get("X-Delete-Token")?.unwrap_or_default();
-    if delete_token.is_empty() || delete_token.len() != 64 {
+    let delete_token = req.headers().get(DELETE_TOKEN_HEADER)?.unwrap_or_default();
+    if delete_token.len() != DELETE_TOKEN_LEN {
         return with_cors(Response::error(
             "Missing or invalid X-Delete-Token header",
             400,
@@ -198,7 +244,7 @@ async fn handle_upload(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
     // Get TTL from header (default 30 days)
     let ttl_days: u64 = req
         .headers()
-        .get("X-TTL-Days")?
+        .get(TTL_DAYS_HEADER)?
         .and_then(|s| s.parse().ok())
         .unwrap_or(30);
 
@@ -217,6 +263,21 @@ async fn handle_upload(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
         }
     }
 
+    // Plaintext (non-E2E) uploads are only accepted by deployments that set
+    // PLAINTEXT_UPLOAD_TOKEN, and only from callers presenting it
+    let plaintext = req.headers().get(ENCRYPTION_HEADER)?.as_deref() == Some(ENCRYPTION_NONE);
+    if plaintext {
+        if let Some(refusal) = plaintext_refusal(&req, &ctx)? {
+            return with_cors(refusal);
+        }
+    }
+
+    // Optional link to an earlier share (for "Part N" navigation)
+    let relates_to = req
+        .headers()
+        .get(RELATES_TO_HEADER)?
+        .filter(|id| parse_id(id).is_some());
+
     let body = req.bytes().await?;
     if body.len() > MAX_BLOB_SIZE {
         return with_cors(Response::error("Blob too large", 413)?);
@@ -246,29 +307,422 @@ async fn handle_upload(mut req: Request, ctx: RouteContext<()>) -> Result<Respon
     let mut metadata = std::collections::HashMap::new();
     metadata.insert("uploaded_at".to_string(), uploaded_at.to_string());
     metadata.insert("delete_token".to_string(), delete_token);
+    if let Some(relates_to) = relates_to {
+        metadata.insert("relates_to".to_string(), relates_to);
+    }
+    if plaintext {
+        metadata.insert("encryption".to_string(), ENCRYPTION_NONE.to_string());
+    }
     bucket
         .put(&r2_path, body)
         .custom_metadata(metadata)
         .execute()
         .await?;
 
-    let response_body = serde_json::json!({
-        "id": id,
-        "expires_at": expires_at
-    });
-    with_cors(Response::from_json(&response_body)?)
+    with_cors(Response::from_json(&UploadResponse { id, expires_at })?)
 }
 
-async fn handle_blob(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+/// Error response for a plaintext write, unless this deployment set
+/// PLAINTEXT_UPLOAD_TOKEN and the caller presents it
+fn plaintext_refusal(req: &Request, ctx: &RouteContext<()>) -> Result<Option<Response>> {
+    let expected = ctx
+        .env
+        .secret("PLAINTEXT_UPLOAD_TOKEN")
+        .map(|s| s.to_string())
+        .unwrap_or_default();
+    if expected.is_empty() {
+        return Ok(Some(Response::error(
+            "Plaintext uploads are not enabled on this server",
+            403,
+        )?));
+    }
+    let presented = req.headers().get("Authorization")?.unwrap_or_default();
+    let token = presented.strip_prefix("Bearer ").unwrap_or_default();
+    if !constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        return Ok(Some(Response::error("Invalid upload token", 401)?));
+    }
+    Ok(None)
+}
+
+/// Compare secrets in time that depends only on their length, not on where
+/// they first differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Replace a blob's content, keeping its id and link. Only the uploader can:
+/// it takes the delete token sent with the original upload.
+async fn handle_replace(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").unwrap().to_string();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, ttl_days) = match locate(&bucket, &id).await? {
+        Some(located) => located,
+        None => return with_cors(Response::error("Invalid ID", 400)?),
+    };
+
+    if let Some(len) = req.headers().get("content-length")? {
+        if let Ok(size) = len.parse::<usize>() {
+            if size > MAX_BLOB_SIZE {
+                return with_cors(Response::error("Blob too large", 413)?);
+            }
+        }
+    }
+
+    let delete_token = req.headers().get(DELETE_TOKEN_HEADER)?.unwrap_or_default();
+    if delete_token.is_empty() {
+        return with_cors(Response::error("Missing X-Delete-Token header", 401)?);
+    }
+
+    let Some(object) = bucket.head(&r2_path).await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let mut metadata = object.custom_metadata().unwrap_or_default();
+    let stored_token = metadata.get("delete_token").cloned().unwrap_or_default();
+    if stored_token.is_empty() {
+        return with_cors(Response::error("Blob predates delete support", 403)?);
+    }
+    if !constant_time_eq(stored_token.as_bytes(), delete_token.as_bytes()) {
+        return with_cors(Response::error("Invalid delete token", 401)?);
+    }
+
+    // A share keeps the encryption mode it was uploaded with
+    let plaintext = req.headers().get(ENCRYPTION_HEADER)?.as_deref() == Some(ENCRYPTION_NONE);
+    if plaintext != is_plaintext(&object) {
+        return with_cors(Response::error(
+            "Replacement must use the same encryption as the original",
+            400,
+        )?);
+    }
+    if plaintext {
+        if let Some(refusal) = plaintext_refusal(&req, &ctx)? {
+            return with_cors(refusal);
+        }
+    }
+
+    let body = req.bytes().await?;
+    if body.len() > MAX_BLOB_SIZE {
+        return with_cors(Response::error("Blob too large", 413)?);
+    }
+    if body.is_empty() {
+        return with_cors(Response::error("Empty body", 400)?);
+    }
+
+    let updated_at = current_timestamp();
+    metadata.insert("updated_at".to_string(), updated_at.to_string());
+    bucket
+        .put(&r2_path, body)
+        .custom_metadata(metadata)
+        .execute()
+        .await?;
+    // The new content is whole; chunks appended to the old one would repeat it
+    delete_chunks(&bucket, &r2_path).await?;
+
+    // Lifecycle rules count from the last write, so retention starts over
+    let expires_at = if ttl_days > 0 {
+        updated_at + (ttl_days * 24 * 60 * 60)
+    } else {
+        0
+    };
+    with_cors(Response::from_json(&UploadResponse { id, expires_at })?)
+}
+
+/// R2 key prefix of the chunks appended to the blob at `r2_path`
+fn chunk_prefix(r2_path: &str) -> String {
+    format!("{r2_path}/chunks/")
+}
+
+async fn chunk_count(bucket: &Bucket, r2_path: &str) -> Result<u32> {
+    let listed = bucket
+        .list()
+        .prefix(chunk_prefix(r2_path))
+        .execute()
+        .await?;
+    Ok(listed.objects().len() as u32)
+}
+
+/// Chunks appended to a blob, in order
+async fn read_chunks(bucket: &Bucket, r2_path: &str) -> Result<Vec<Vec<u8>>> {
+    let mut chunks = Vec::new();
+    for index in 0..chunk_count(bucket, r2_path).await? {
+        let key = format!("{}{index}", chunk_prefix(r2_path));
+        let Some(object) = bucket.get(&key).execute().await? else {
+            break;
+        };
+        let body = object.body().ok_or_else(|| Error::from("No body"))?;
+        chunks.push(body.bytes().await?);
+    }
+    Ok(chunks)
+}
+
+async fn delete_chunks(bucket: &Bucket, r2_path: &str) -> Result<()> {
+    let listed = bucket
+        .list()
+        .prefix(chunk_prefix(r2_path))
+        .execute()
+        .await?;
+    for object in listed.objects() {
+        bucket.delete(&object.key()).await?;
+    }
+    Ok(())
+}
+
+/// `payload` followed by the decoded chunks appended to it, stopping at the
+/// first chunk that doesn't parse
+fn with_chunks(payload: String, chunks: impl IntoIterator<Item = String>) -> String {
+    let Ok(mut merged) = serde_json::from_str::<SharePayload>(&payload) else {
+        return payload;
+    };
+    for chunk in chunks {
+        match serde_json::from_str::<SharePayload>(&chunk) {
+            Ok(chunk) => merged.append_chunk(chunk),
+            Err(_) => break,
+        }
+    }
+    serde_json::to_string(&merged).unwrap_or(payload)
+}
+
+/// Append messages to a blob without uploading it again (live shares). Takes
+/// the delete token, like replacing, and the index the chunk should get, so
+/// a chunk is never stored twice or out of order.
+async fn handle_append_chunk(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").unwrap().to_string();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, ttl_days) = match locate(&bucket, &id).await? {
+        Some(located) => located,
+        None => return with_cors(Response::error("Invalid ID", 400)?),
+    };
+
+    if let Some(len) = req.headers().get("content-length")? {
+        if let Ok(size) = len.parse::<usize>() {
+            if size > MAX_BLOB_SIZE {
+                return with_cors(Response::error("Blob too large", 413)?);
+            }
+        }
+    }
+
+    let delete_token = req.headers().get(DELETE_TOKEN_HEADER)?.unwrap_or_default();
+    if delete_token.is_empty() {
+        return with_cors(Response::error("Missing X-Delete-Token header", 401)?);
+    }
+    let Some(index) = req
+        .headers()
+        .get(CHUNK_INDEX_HEADER)?
+        .and_then(|v| v.parse::<u32>().ok())
+    else {
+        return with_cors(Response::error(
+            "Missing or invalid X-Chunk-Index header",
+            400,
+        )?);
+    };
+
+    let Some(object) = bucket.head(&r2_path).await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let metadata = object.custom_metadata().unwrap_or_default();
+    let stored_token = metadata.get("delete_token").cloned().unwrap_or_default();
+    if stored_token.is_empty() {
+        return with_cors(Response::error("Blob predates delete support", 403)?);
+    }
+    if !constant_time_eq(stored_token.as_bytes(), delete_token.as_bytes()) {
+        return with_cors(Response::error("Invalid delete token", 401)?);
+    }
+
+    let plaintext = req.headers().get(ENCRYPTION_HEADER)?.as_deref() == Some(ENCRYPTION_NONE);
+    if plaintext != is_plaintext(&object) {
+        return with_cors(Response::error(
+            "Chunks must use the same encryption as the blob",
+            400,
+        )?);
+    }
+    if plaintext {
+        if let Some(refusal) = plaintext_refusal(&req, &ctx)? {
+            return with_cors(refusal);
+        }
+    }
+
+    let stored = chunk_count(&bucket, &r2_path).await?;
+    if index != stored {
+        return with_cors(Response::error(
+            format!("Chunk index {index} doesn't follow the {stored} stored"),
+            409,
+        )?);
+    }
+    if stored >= MAX_CHUNKS {
+        return with_cors(Response::error(
+            "Too many chunks; replace the blob instead",
+            409,
+        )?);
+    }
+
+    let body = req.bytes().await?;
+    if body.len() > MAX_BLOB_SIZE {
+        return with_cors(Response::error("Blob too large", 413)?);
+    }
+    if body.is_empty() {
+        return with_cors(Response::error("Empty body", 400)?);
+    }
+    bucket
+        .put(&format!("{}{index}", chunk_prefix(&r2_path)), body)
+        .execute()
+        .await?;
+
+    // Appending doesn't rewrite the blob, so its expiry stands
+    let (_, expires_at) = written_and_expiry(&metadata, ttl_days);
+    with_cors(Response::from_json(&UploadResponse { id, expires_at })?)
+}
+
+async fn handle_chunk(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
     let id = ctx.param("id").unwrap();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, _) = match locate(&bucket, id).await? {
+        Some(located) => located,
+        None => return with_cors(Response::error("Invalid ID", 400)?),
+    };
+    let Some(index) = ctx
+        .param("index")
+        .and_then(|index| index.parse::<u32>().ok())
+    else {
+        return with_cors(Response::error("Invalid chunk index", 400)?);
+    };
 
-    // Parse ID to get R2 path
-    let (r2_path, _, _) = match parse_id(id) {
-        Some(parsed) => parsed,
+    let Some(blob) = bucket.head(&r2_path).await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let key = format!("{}{index}", chunk_prefix(&r2_path));
+    let Some(object) = bucket.get(&key).execute().await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let body = object.body().ok_or_else(|| Error::from("No body"))?;
+    let bytes = body.bytes().await?;
+
+    let headers = Headers::new();
+    headers.set(
+        "Content-Type",
+        if is_plaintext(&blob) {
+            "application/json"
+        } else {
+            "application/octet-stream"
+        },
+    )?;
+    // Replacing the blob drops its chunks and numbering starts over
+    headers.set("Cache-Control", "public, max-age=60")?;
+    let mut response = Response::from_bytes(bytes)?;
+    *response.headers_mut() = headers;
+    with_cors(response)
+}
+
+/// Change how long a blob is kept, without uploading it again. Lifecycle rules
+/// expire blobs by tier prefix, so the blob (and its chunks) is copied into the
+/// new tier and a pointer keeps its id working. Retention counts from now.
+async fn handle_set_ttl(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").unwrap().to_string();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, _) = match locate(&bucket, &id).await? {
+        Some(located) => located,
         None => return with_cors(Response::error("Invalid ID", 400)?),
     };
 
+    let delete_token = req.headers().get(DELETE_TOKEN_HEADER)?.unwrap_or_default();
+    if delete_token.is_empty() {
+        return with_cors(Response::error("Missing X-Delete-Token header", 401)?);
+    }
+    let Ok(request) = req.json::<TtlRequest>().await else {
+        return with_cors(Response::error("Invalid TTL request", 400)?);
+    };
+
+    if let Ok(max_ttl) = ctx.env.var("MAX_TTL_DAYS") {
+        if let Ok(max_days) = max_ttl.to_string().parse::<u64>() {
+            let effective_ttl = if request.ttl_days == 0 || request.ttl_days > 365 {
+                u64::MAX
+            } else {
+                request.ttl_days
+            };
+            if effective_ttl > max_days {
+                return with_cors(Response::error(
+                    format!("TTL exceeds maximum allowed ({} days)", max_days),
+                    400,
+                )?);
+            }
+        }
+    }
+
+    let Some(object) = bucket.get(&r2_path).execute().await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let mut metadata = object.custom_metadata().unwrap_or_default();
+    let stored_token = metadata.get("delete_token").cloned().unwrap_or_default();
+    if stored_token.is_empty() {
+        return with_cors(Response::error("Blob predates delete support", 403)?);
+    }
+    if !constant_time_eq(stored_token.as_bytes(), delete_token.as_bytes()) {
+        return with_cors(Response::error("Invalid delete token", 401)?);
+    }
+
+    // 0 asks for forever here, as the API documents
+    let tier = if request.ttl_days == 0 {
+        'n'
+    } else {
+        ttl_days_to_prefix(request.ttl_days)
+    };
+    let (r2_prefix, ttl_days) = ttl_prefix_to_path(tier).unwrap();
+    let hash = &r2_path[r2_path.rfind('/').map_or(0, |i| i + 1)..];
+    let new_path = format!("{r2_prefix}/{hash}");
+    // Writing the blob and its chunks, even in place, restarts their retention
+    let body = object.body().ok_or_else(|| Error::from("No body"))?;
+    let bytes = body.bytes().await?;
+    let chunks = read_chunks(&bucket, &r2_path).await?;
+    let updated_at = current_timestamp();
+    metadata.insert("updated_at".to_string(), updated_at.to_string());
+    bucket
+        .put(&new_path, bytes)
+        .custom_metadata(metadata)
+        .execute()
+        .await?;
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        bucket
+            .put(&format!("{}{index}", chunk_prefix(&new_path)), chunk)
+            .execute()
+            .await?;
+    }
+    if new_path != r2_path {
+        let mut pointer = std::collections::HashMap::new();
+        pointer.insert("tier".to_string(), tier.to_string());
+        bucket
+            .put(format!("{MOVED_PREFIX}{hash}"), Vec::<u8>::new())
+            .custom_metadata(pointer)
+            .execute()
+            .await?;
+        delete_chunks(&bucket, &r2_path).await?;
+        bucket.delete(&r2_path).await?;
+    }
+
+    let expires_at = if ttl_days > 0 {
+        updated_at + ttl_days * 24 * 60 * 60
+    } else {
+        0
+    };
+    // A gallery entry lapses with the share
+    if let Some(entry) = bucket.head(listing_key(&id)).await? {
+        let mut fields = entry.custom_metadata().unwrap_or_default();
+        fields.insert("expires_at".to_string(), expires_at.to_string());
+        bucket
+            .put(listing_key(&id), Vec::<u8>::new())
+            .custom_metadata(fields)
+            .execute()
+            .await?;
+    }
+    with_cors(Response::from_json(&UploadResponse { id, expires_at })?)
+}
+
+async fn handle_blob(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").unwrap();
+
+    // Find where the blob is stored
     let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, _) = match locate(&bucket, id).await? {
+        Some(located) => located,
+        None => return with_cors(Response::error("Invalid ID", 400)?),
+    };
 
     // R2 lifecycle rules handle expiration automatically
     match bucket.get(&r2_path).execute().await? {
@@ -276,45 +730,444 @@ async fn handle_blob(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
             let body = object.body().ok_or_else(|| Error::from("No body"))?;
             let bytes = body.bytes().await?;
 
+            let plaintext = is_plaintext(&object);
             let headers = Headers::new();
-            headers.set("Content-Type", "application/octet-stream")?;
-            headers.set("Cache-Control", "public, max-age=86400")?;
+            headers.set(
+                "Content-Type",
+                if plaintext {
+                    "application/json"
+                } else {
+                    "application/octet-stream"
+                },
+            )?;
+            // Blobs can be replaced in place, so caches only keep them briefly
+            headers.set("Cache-Control", "public, max-age=60")?;
+            if let Some(relates_to) = object
+                .custom_metadata()
+                .ok()
+                .and_then(|m| m.get("relates_to").cloned())
+            {
+                headers.set(RELATES_TO_HEADER, &relates_to)?;
+            }
+            let chunks = chunk_count(&bucket, &r2_path).await?;
+            if chunks > 0 {
+                headers.set(CHUNK_COUNT_HEADER, &chunks.to_string())?;
+            }
 
             let mut response = Response::from_bytes(bytes)?;
             *response.headers_mut() = headers;
             with_cors(response)
         }
-        None => with_cors(Response::error("Not found", 404)?),
-    }
+        None => with_cors(Response::error("Not found", 404)?),
+    }
+}
+
+/// Last write and expiry of a blob, as Unix seconds (expiry 0: never).
+/// Lifecycle rules count from the last write, so a replacement starts over.
+fn written_and_expiry(
+    metadata: &std::collections::HashMap<String, String>,
+    ttl_days: u64,
+) -> (u64, u64) {
+    let written_at = ["updated_at", "uploaded_at"]
+        .iter()
+        .find_map(|key| metadata.get(*key).and_then(|v| v.parse::<u64>().ok()))
+        .unwrap_or_else(current_timestamp);
+    let expires_at = if ttl_days > 0 {
+        written_at + ttl_days * 24 * 60 * 60
+    } else {
+        0
+    };
+    (written_at, expires_at)
+}
+
+async fn handle_blob_meta(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").unwrap();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, ttl_days) = match locate(&bucket, id).await? {
+        Some(located) => located,
+        None => return with_cors(Response::error("Invalid ID", 400)?),
+    };
+
+    let Some(object) = bucket.head(&r2_path).await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let metadata = object.custom_metadata().unwrap_or_default();
+    let (uploaded_at, expires_at) = written_and_expiry(&metadata, ttl_days);
+    with_cors(Response::from_json(&BlobMetadata {
+        id: id.to_string(),
+        size_bytes: u64::from(object.size()),
+        uploaded_at,
+        expires_at,
+    })?)
+}
+
+async fn handle_viewer(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").unwrap();
+
+    // Find where the blob is stored
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, _) = match locate(&bucket, id).await? {
+        Some(located) => located,
+        None => return Response::error("Invalid ID", 400),
+    };
+
+    // Check blob exists (lifecycle rules handle expiration)
+    let Some(object) = bucket.head(&r2_path).await? else {
+        return Response::error("Not found", 404);
+    };
+
+    // Plaintext shares have no key to fetch or decrypt with, so the worker
+    // renders them itself. Reports only render in the JS viewer; their JSON
+    // is inlined into the page.
+    let payload = if is_plaintext(&object) {
+        let Some(object) = bucket.get(&r2_path).execute().await? else {
+            return Response::error("Not found", 404);
+        };
+        let body = object.body().ok_or_else(|| Error::from("No body"))?;
+        let chunks = read_chunks(&bucket, &r2_path).await?;
+        let json = with_chunks(
+            body.text().await?,
+            chunks.into_iter().map_while(|c| String::from_utf8(c).ok()),
+        );
+        match parse_static_payload(&json) {
+            Ok(Some(payload)) => {
+                return static_html_response(static_viewer_html(Some(&payload), false));
+            }
+            Ok(None) => Some(json),
+            Err(message) => return Response::error(message, 400),
+        }
+    } else {
+        None
+    };
+
+    let html = viewer_html(id, payload.as_deref(), static_render_enabled(&ctx));
+    let mut response = Response::from_html(html)?;
+
+    response.headers_mut().set(
+        "Content-Security-Policy",
+        "default-src 'self'; script-src 'self' 'unsafe-inline' https://cdn.jsdelivr.net; style-src 'self' 'unsafe-inline'; img-src 'self' data:; frame-src 'self' blob:",
+    )?;
+    response
+        .headers_mut()
+        .set("X-Content-Type-Options", "nosniff")?;
+
+    Ok(response)
+}
+
+/// Whether this deployment opted into server-side rendering (`STATIC_RENDER = "true"`)
+fn static_render_enabled(ctx: &RouteContext<()>) -> bool {
+    ctx.env
+        .var("STATIC_RENDER")
+        .map(|v| v.to_string() == "true")
+        .unwrap_or(false)
+}
+
+/// No-JS fallback: decrypt with a key passed in the query string and render
+/// plain HTML. This hands the key to the server, so it is off by default.
+async fn handle_static_viewer(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    if !static_render_enabled(&ctx) {
+        return Response::error("Static rendering is not enabled on this server", 404);
+    }
+    let id = ctx.param("id").unwrap();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, _) = match locate(&bucket, id).await? {
+        Some(located) => located,
+        None => return Response::error("Invalid ID", 400),
+    };
+
+    let Some(object) = bucket.get(&r2_path).execute().await? else {
+        return Response::error("Not found", 404);
+    };
+    let plaintext = is_plaintext(&object);
+    let body = object.body().ok_or_else(|| Error::from("No body"))?;
+    let bytes = body.bytes().await?;
+
+    let chunks = read_chunks(&bucket, &r2_path).await?;
+
+    let json = if plaintext {
+        let json = String::from_utf8(bytes).map_err(|_| Error::from("Invalid payload"))?;
+        with_chunks(
+            json,
+            chunks.into_iter().map_while(|c| String::from_utf8(c).ok()),
+        )
+    } else {
+        let url = req.url()?;
+        let Some(key) = url
+            .query_pairs()
+            .find(|(name, _)| name == "key")
+            .map(|(_, value)| value.into_owned())
+        else {
+            return Response::error("Missing key", 400);
+        };
+        // Deriving the key would mean sending the passphrase to the server
+        if key.starts_with(PASSPHRASE_PREFIX) {
+            return Response::error(
+                "Passphrase-protected transcripts only open in the JavaScript viewer",
+                400,
+            );
+        }
+        match decrypt_blob(&bytes, &key) {
+            Some(json) => with_chunks(json, chunks.iter().map_while(|c| decrypt_blob(c, &key))),
+            None => return Response::error("Decryption failed (wrong key or corrupted data)", 400),
+        }
+    };
+    let payload = match parse_static_payload(&json) {
+        Ok(payload) => payload,
+        Err(message) => return Response::error(message, 400),
+    };
+
+    let mut response = static_html_response(static_viewer_html(payload.as_ref(), !plaintext))?;
+    let headers = response.headers_mut();
+    // The URL carries the key; keep it out of caches and outbound referrers
+    headers.set("Cache-Control", "no-store")?;
+    headers.set("Referrer-Policy", "no-referrer")?;
+    Ok(response)
+}
+
+/// Parse a payload for server-side rendering. Reports use a different
+/// payload and only render in the JS viewer, so they come back as `None`.
+fn parse_static_payload(json: &str) -> std::result::Result<Option<SharePayload>, &'static str> {
+    match serde_json::from_str::<serde_json::Value>(json) {
+        Ok(value) if value.get("kind").and_then(|k| k.as_str()) == Some("report") => Ok(None),
+        Ok(value) => serde_json::from_value::<SharePayload>(value)
+            .map(Some)
+            .map_err(|_| "Invalid payload"),
+        Err(_) => Err("Invalid payload"),
+    }
+}
+
+/// A server-rendered page, which runs no scripts
+fn static_html_response(html: String) -> Result<Response> {
+    let mut response = Response::from_html(html)?;
+    let headers = response.headers_mut();
+    headers.set(
+        "Content-Security-Policy",
+        "default-src 'none'; style-src 'unsafe-inline'; img-src data:",
+    )?;
+    headers.set("X-Content-Type-Options", "nosniff")?;
+    Ok(response)
+}
+
+/// Decrypt an `IV || AES-256-GCM(gzip(json))` blob with a base64url key
+fn decrypt_blob(blob: &[u8], key_b64: &str) -> Option<String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use base64::Engine;
+    use std::io::Read;
+
+    let key = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(key_b64.trim_end_matches('='))
+        .ok()?;
+    if key.len() != KEY_LEN || blob.len() < IV_LEN {
+        return None;
+    }
+    let (iv, ciphertext) = blob.split_at(IV_LEN);
+    let cipher = aes_gcm::Aes256Gcm::new_from_slice(&key).ok()?;
+    let compressed = cipher
+        .decrypt(aes_gcm::Nonce::from_slice(iv), ciphertext)
+        .ok()?;
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut json)
+        .ok()?;
+    Some(json)
+}
+
+/// Images shared with `--include-images`, as inline `data:` URIs of known
+/// image types; anything else is left out
+fn is_image_data_uri(src: &str) -> bool {
+    ["png", "jpeg", "gif", "webp"]
+        .iter()
+        .any(|kind| src.starts_with(&format!("data:image/{kind};base64,")))
+}
+
+fn static_images(images: &[String]) -> maud::Markup {
+    html! {
+        @for src in images.iter().filter(|src| is_image_data_uri(src)) {
+            img class="image" src=(src) alt="Image";
+        }
+    }
+}
+
+fn static_role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "tool" => "Tool",
+        "thinking" => "Thinking",
+        "system" => "System",
+        "command" => "Command",
+        "plan" => "Plan",
+        other => other,
+    }
+}
+
+/// Plain HTML for a share payload; `None` stands in for payloads (reports)
+/// that only the JS viewer can render
+fn static_viewer_html(payload: Option<&SharePayload>, server_decrypted: bool) -> String {
+    let heading = payload
+        .and_then(|p| p.title.as_deref())
+        .or(payload
+            .map(|p| p.tool.as_str())
+            .filter(|tool| !tool.is_empty()))
+        .unwrap_or("Transcript");
+    let markup = html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="UTF-8";
+                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                meta name="robots" content="noindex";
+                title { (heading) }
+                style { (PreEscaped(STATIC_VIEWER_CSS)) }
+            }
+            body {
+                @if server_decrypted {
+                    p class="notice" {
+                        "Rendered by the server without JavaScript. The decryption key in this link was sent to the server, so this copy is not end-to-end encrypted."
+                    }
+                }
+                @if let Some(payload) = payload {
+                    header {
+                        h1 { (heading) }
+                        p class="meta" {
+                            (payload.tool)
+                            @if !payload.shared_at.is_empty() { " · " (payload.shared_at) }
+                            @if let Some(timeline) = &payload.timeline {
+                                " · " (static_duration(timeline.ended_at - timeline.started_at))
+                            }
+                        }
+                    }
+                    main lang=[payload.lang.as_deref()] {
+                        @if !payload.tool_stats.is_empty() {
+                            @let total = payload.tool_stats.iter().map(|s| s.calls).sum::<u32>().max(1);
+                            section class="message tool-stats" {
+                                h2 { "Tool calls" }
+                                @for stat in &payload.tool_stats {
+                                    div class="tool-stat" {
+                                        span { (stat.name) }
+                                        span class="bar" { span style=(format!("width:{}%", stat.calls * 100 / total)) {} }
+                                        span { (stat.calls) @if stat.duration_ms > 0 { " · " (static_duration((stat.duration_ms / 1000) as i64)) } }
+                                    }
+                                }
+                            }
+                        }
+                        @if payload.metadata_only {
+                            section class="message stats" {
+                                h2 { "Session stats" }
+                                ul {
+                                    @for (role, count) in &payload.message_counts {
+                                        li { (count) " " (role) " messages" }
+                                    }
+                                    li { (payload.total_input_tokens) " input tokens · " (payload.total_output_tokens) " output tokens" }
+                                    @if let Some(cost) = payload.estimated_cost_usd {
+                                        li { "Estimated cost: $" (format!("{cost:.2}")) }
+                                    }
+                                }
+                                p { "The conversation itself was not shared." }
+                            }
+                        }
+                        @for message in &payload.messages {
+                            @let label = static_role_label(&message.role);
+                            @let anchor = message.id.map(|id| format!("msg-{id}"));
+                            @let highlighted = if message.highlighted { " highlighted" } else { "" };
+                            @if message.abandoned {
+                                details id=[anchor] class=(format!("message {} abandoned", message.role)) {
+                                    summary { (label) " (abandoned branch)" }
+                                    pre { (message.content) }
+                                }
+                            } @else if matches!(message.role.as_str(), "tool" | "thinking") {
+                                details id=[anchor] class=(format!("message {}{highlighted}", message.role)) open[static_expanded(payload.viewer, &message.role)] {
+                                    summary { (label) }
+                                    pre { (message.content) }
+                                    (static_images(&message.images))
+                                }
+                            } @else {
+                                section id=[anchor] class=(format!("message {}{highlighted}", message.role)) {
+                                    h2 { (label) }
+                                    pre { (message.content) }
+                                    (static_images(&message.images))
+                                }
+                            }
+                        }
+                    }
+                } @else {
+                    p { "Reports can only be viewed with JavaScript enabled." }
+                }
+                footer {
+                    "via "
+                    a href="https://agentexports.com" { "agentexports.com" }
+                }
+            }
+        }
+    };
+    markup.into_string()
 }
 
-async fn handle_viewer(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
-    let id = ctx.param("id").unwrap();
-
-    // Parse ID to get R2 path
-    let (r2_path, _, _) = match parse_id(id) {
-        Some(parsed) => parsed,
-        None => return Response::error("Invalid ID", 400),
-    };
+/// Session length like the JS viewer's `formatSeconds`
+fn static_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
 
-    // Check blob exists (lifecycle rules handle expiration)
-    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
-    if bucket.head(&r2_path).await?.is_none() {
-        return Response::error("Not found", 404);
+/// Whether a collapsible role starts open, per the publisher's viewer defaults
+fn static_expanded(viewer: Option<ViewerDefaults>, role: &str) -> bool {
+    let viewer = viewer.unwrap_or_default();
+    match role {
+        "tool" => viewer.tools,
+        "thinking" => viewer.thinking,
+        _ => None,
     }
+    .unwrap_or(false)
+}
 
-    let html = viewer_html(id);
-    let mut response = Response::from_html(html)?;
+const STATIC_VIEWER_CSS: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; max-width: 860px; margin: 0 auto; padding: 24px 16px; color: #222; background: #fff; }
+h1 { font-size: 1.4em; margin: 0 0 4px; }
+.meta { color: #666; margin: 0 0 24px; font-size: 0.9em; }
+.notice { background: #fff4e5; border: 1px solid #f0b46c; padding: 8px 12px; border-radius: 6px; font-size: 0.9em; }
+.message { border-top: 1px solid #eee; padding: 12px 0; }
+.message h2, .message summary { font-size: 0.8em; text-transform: uppercase; letter-spacing: 0.04em; color: #555; margin: 0 0 6px; cursor: default; }
+.message.user h2 { color: #1a7f37; }
+.message.assistant h2 { color: #0969da; }
+.message.abandoned { opacity: 0.6; }
+.message.highlighted { border-left: 3px solid #d4a72c; padding-left: 10px; }
+.message:target { background: #fff8c5; }
+pre { white-space: pre-wrap; word-wrap: break-word; font-family: inherit; margin: 0; line-height: 1.5; }
+details pre { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 0.85em; color: #444; }
+.tool-stat { display: grid; grid-template-columns: 10em 1fr 8em; align-items: center; gap: 8px; font-size: 0.85em; }
+.tool-stat .bar { height: 6px; background: #eee; border-radius: 3px; overflow: hidden; }
+.tool-stat .bar span { display: block; height: 100%; background: #0969da; }
+.message img.image { display: block; max-width: 100%; margin: 8px 0; border: 1px solid #eee; border-radius: 4px; }
+footer { border-top: 1px solid #eee; padding-top: 12px; margin-top: 24px; color: #888; font-size: 0.85em; }
+@media (prefers-color-scheme: dark) {
+    body { color: #ddd; background: #111; }
+    .meta, footer { color: #999; }
+    .notice { background: #3a2a12; border-color: #8a5a1c; }
+    .message, footer { border-color: #333; }
+    .tool-stat .bar { background: #333; }
+    .message:target { background: #2f2a12; }
+    details pre { color: #bbb; }
+}
+"#;
 
-    response.headers_mut().set(
-        "Content-Security-Policy",
-        "default-src 'self'; script-src 'self' 'unsafe-inline' https://cdn.jsdelivr.net; style-src 'self' 'unsafe-inline'; frame-src 'self' blob:",
-    )?;
-    response
-        .headers_mut()
-        .set("X-Content-Type-Options", "nosniff")?;
+/// Whether a stored blob was uploaded without E2E encryption
+fn is_plaintext(object: &Object) -> bool {
+    object
+        .custom_metadata()
+        .ok()
+        .and_then(|m| m.get("encryption").cloned())
+        .as_deref()
+        == Some(ENCRYPTION_NONE)
+}
 
-    Ok(response)
+/// Make JSON safe to embed in a <script> element. `<` only occurs inside
+/// JSON strings, where `\u003c` decodes back to the same character.
+fn escape_script_json(json: &str) -> String {
+    json.replace('<', "\\u003c")
 }
 
 async fn handle_gist_viewer(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
@@ -331,7 +1184,7 @@ async fn handle_gist_viewer(_req: Request, ctx: RouteContext<()>) -> Result<Resp
 
     response.headers_mut().set(
         "Content-Security-Policy",
-        "default-src 'self' https://api.github.com https://gist.githubusercontent.com; script-src 'self' 'unsafe-inline' https://cdn.jsdelivr.net; style-src 'self' 'unsafe-inline'; frame-src 'self' blob:; connect-src 'self' https://api.github.com https://gist.githubusercontent.com",
+        "default-src 'self' https://api.github.com https://gist.githubusercontent.com; script-src 'self' 'unsafe-inline' https://cdn.jsdelivr.net; style-src 'self' 'unsafe-inline'; img-src 'self' data:; frame-src 'self' blob:; connect-src 'self' https://api.github.com https://gist.githubusercontent.com",
     )?;
     response
         .headers_mut()
@@ -347,20 +1200,19 @@ async fn handle_gist_viewer(_req: Request, ctx: RouteContext<()>) -> Result<Resp
 async fn handle_delete(req: Request, ctx: RouteContext<()>) -> Result<Response> {
     let id = ctx.param("id").unwrap();
 
-    // Parse ID to get R2 path
-    let (r2_path, _, _) = match parse_id(id) {
-        Some(parsed) => parsed,
+    // Find where the blob is stored
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, _) = match locate(&bucket, id).await? {
+        Some(located) => located,
         None => return with_cors(Response::error("Invalid ID", 400)?),
     };
 
     // Get delete token from header
-    let delete_token = req.headers().get("X-Delete-Token")?.unwrap_or_default();
+    let delete_token = req.headers().get(DELETE_TOKEN_HEADER)?.unwrap_or_default();
     if delete_token.is_empty() {
         return with_cors(Response::error("Missing X-Delete-Token header", 401)?);
     }
 
-    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
-
     // Check blob exists and verify delete token
     match bucket.head(&r2_path).await? {
         Some(object) => {
@@ -375,18 +1227,211 @@ async fn handle_delete(req: Request, ctx: RouteContext<()>) -> Result<Response>
                 return with_cors(Response::error("Blob predates delete support", 403)?);
             }
 
-            if stored_token != delete_token {
+            if !constant_time_eq(stored_token.as_bytes(), delete_token.as_bytes()) {
                 return with_cors(Response::error("Invalid delete token", 401)?);
             }
 
-            // Delete the blob
+            // Delete the blob, and its gallery entry if it was listed
             bucket.delete(&r2_path).await?;
+            delete_chunks(&bucket, &r2_path).await?;
+            if let Some((_, hash, _)) = parse_id(id) {
+                bucket.delete(format!("{MOVED_PREFIX}{hash}")).await?;
+            }
+            bucket.delete(&listing_key(id)).await?;
             with_cors(Response::empty()?.with_status(204))
         }
         None => with_cors(Response::error("Not found", 404)?),
     }
 }
 
+/// R2 prefix of gallery entries (empty objects carrying their fields as metadata)
+const LISTING_PREFIX: &str = "explore/";
+
+/// Longest title kept for a gallery entry, in characters
+const LISTING_TITLE_MAX: usize = 200;
+
+/// Most entries shown in the gallery
+const LISTING_MAX: usize = 200;
+
+fn listing_key(id: &str) -> String {
+    format!("{LISTING_PREFIX}{id}")
+}
+
+/// Whether this deployment opted into the public gallery (`PUBLIC_GALLERY = "true"`)
+fn gallery_enabled(ctx: &RouteContext<()>) -> bool {
+    ctx.env
+        .var("PUBLIC_GALLERY")
+        .map(|v| v.to_string() == "true")
+        .unwrap_or(false)
+}
+
+/// List a share in the gallery. Only the uploader can (it takes the delete
+/// token), and only plaintext shares: the gallery never sees a key, so an
+/// encrypted share couldn't be opened from it.
+async fn handle_list_share(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    if !gallery_enabled(&ctx) {
+        return with_cors(Response::error(
+            "The public gallery is not enabled on this server",
+            404,
+        )?);
+    }
+    let id = ctx.param("id").unwrap().to_string();
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let (r2_path, ttl_days) = match locate(&bucket, &id).await? {
+        Some(located) => located,
+        None => return with_cors(Response::error("Invalid ID", 400)?),
+    };
+
+    let delete_token = req.headers().get(DELETE_TOKEN_HEADER)?.unwrap_or_default();
+    if delete_token.is_empty() {
+        return with_cors(Response::error("Missing X-Delete-Token header", 401)?);
+    }
+
+    let Some(object) = bucket.head(&r2_path).await? else {
+        return with_cors(Response::error("Not found", 404)?);
+    };
+    let metadata = object.custom_metadata().unwrap_or_default();
+    let stored_token = metadata.get("delete_token").cloned().unwrap_or_default();
+    if stored_token.is_empty()
+        || !constant_time_eq(stored_token.as_bytes(), delete_token.as_bytes())
+    {
+        return with_cors(Response::error("Invalid delete token", 401)?);
+    }
+    if !is_plaintext(&object) {
+        return with_cors(Response::error(
+            "Only plaintext shares can be listed; an encrypted share can't be opened without its key",
+            400,
+        )?);
+    }
+
+    let Ok(listing) = req.json::<ListingRequest>().await else {
+        return with_cors(Response::error("Invalid listing", 400)?);
+    };
+    let title: String = listing
+        .title
+        .trim()
+        .chars()
+        .take(LISTING_TITLE_MAX)
+        .collect();
+    let tool = listing.tool.trim();
+    if title.is_empty() || tool.is_empty() || tool.len() > 40 {
+        return with_cors(Response::error("Invalid listing", 400)?);
+    }
+
+    // The entry lapses with the share
+    let (_, expires_at) = written_and_expiry(&metadata, ttl_days);
+    let mut entry = std::collections::HashMap::new();
+    entry.insert("title".to_string(), title);
+    entry.insert("tool".to_string(), tool.to_string());
+    entry.insert("listed_at".to_string(), current_timestamp().to_string());
+    entry.insert("expires_at".to_string(), expires_at.to_string());
+    bucket
+        .put(&listing_key(&id), Vec::<u8>::new())
+        .custom_metadata(entry)
+        .execute()
+        .await?;
+    with_cors(Response::empty()?.with_status(204))
+}
+
+/// One share in the gallery
+struct GalleryEntry {
+    id: String,
+    title: String,
+    tool: String,
+    listed_at: u64,
+}
+
+/// The public gallery: listed shares, newest first. Entries of expired shares
+/// are dropped as they're found.
+async fn handle_explore(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    if !gallery_enabled(&ctx) {
+        return Response::error("The public gallery is not enabled on this server", 404);
+    }
+    let bucket = ctx.env.bucket("TRANSCRIPTS")?;
+    let listed = bucket
+        .list()
+        .prefix(LISTING_PREFIX)
+        .include(vec![Include::CustomMetadata])
+        .execute()
+        .await?;
+    let now = current_timestamp();
+    let mut entries = Vec::new();
+    for object in listed.objects() {
+        let key = object.key();
+        let metadata = object.custom_metadata().unwrap_or_default();
+        let field = |name: &str| metadata.get(name).cloned().unwrap_or_default();
+        let expires_at: u64 = field("expires_at").parse().unwrap_or(0);
+        if expires_at > 0 && expires_at <= now {
+            bucket.delete(&key).await?;
+            continue;
+        }
+        entries.push(GalleryEntry {
+            id: key.trim_start_matches(LISTING_PREFIX).to_string(),
+            title: field("title"),
+            tool: field("tool"),
+            listed_at: field("listed_at").parse().unwrap_or(0),
+        });
+    }
+    entries.sort_by(|a, b| b.listed_at.cmp(&a.listed_at));
+    entries.truncate(LISTING_MAX);
+
+    let mut response = Response::from_html(gallery_html(&entries))?;
+    response
+        .headers_mut()
+        .set("Cache-Control", "public, max-age=300")?;
+    Ok(response)
+}
+
+/// `YYYY-MM-DD` for unix seconds
+fn listing_date(secs: u64) -> String {
+    let date = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(secs as f64 * 1000.0));
+    String::from(date.to_iso_string())
+        .chars()
+        .take(10)
+        .collect()
+}
+
+fn gallery_html(entries: &[GalleryEntry]) -> String {
+    let markup = html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="UTF-8";
+                meta name="viewport" content="width=device-width, initial-scale=1.0";
+                title { "Explore shared agent sessions" }
+                style { (PreEscaped(STATIC_VIEWER_CSS)) (PreEscaped(GALLERY_CSS)) }
+            }
+            body {
+                header {
+                    h1 { "Explore" }
+                    p class="meta" { "Agent sessions their authors chose to list publicly." }
+                }
+                main {
+                    @if entries.is_empty() {
+                        p { "Nothing has been listed yet." }
+                    }
+                    @for entry in entries {
+                        section class="message listing" {
+                            a href=(format!("/v/{}", entry.id)) { (entry.title) }
+                            p class="meta" { (entry.tool) " · " (listing_date(entry.listed_at)) }
+                        }
+                    }
+                }
+                footer {
+                    "via "
+                    a href="https://agentexports.com" { "agentexports.com" }
+                }
+            }
+        }
+    };
+    markup.into_string()
+}
+
+const GALLERY_CSS: &str = r#"
+.listing a { font-size: 1.05em; font-weight: 600; color: inherit; }
+.listing .meta { margin: 4px 0 0; }
+"#;
+
 async fn handle_cors_preflight(_req: Request, _ctx: RouteContext<()>) -> Result<Response> {
     let mut response = Response::empty()?;
     *response.headers_mut() = cors_headers();
@@ -990,13 +2035,27 @@ fn gist_viewer_html(gist_id: &str) -> String {
                                     input #show-details type="checkbox";
                                     " Show tool calls"
                                 }
+                                label #show-tasks-label style="display:none" {
+                                    input #show-tasks type="checkbox" checked;
+                                    " Show tasks"
+                                }
+                                label #show-abandoned-label style="display:none" {
+                                    input #show-abandoned type="checkbox";
+                                    " Show abandoned branches"
+                                }
                             }
                         }
+                        div #timeline class="timeline" style="display:none" {}
+                        div #tool-stats class="tool-stats" style="display:none" {}
                     }
-                    section #messages class="messages hide-details" {}
+                    nav #series class="series" style="display:none" {}
+                    div #session-status class="session-status" style="display:none" {}
+                    section #tasks class="tasks" style="display:none" {}
+                    section #messages class="messages hide-details hide-system hide-abandoned" {}
                     footer {
                         "via "
                         a href="https://agentexports.com" { "agentexports.com" }
+                        span #publisher {}
                         " · "
                         a href=(format!("https://gist.github.com/{}", gist_id)) { "view raw gist" }
                     }
@@ -1016,17 +2075,25 @@ window.gistPromise = fetch("https://api.github.com/gists/{gist_id}")
     .then(r => {{ if (!r.ok) throw new Error("Gist not found"); return r.json(); }})
     .then(g => {{
         const files = g.files;
+        const text = url => fetch(url).then(r => {{ if (!r.ok) throw new Error("Failed to fetch content"); return r.text(); }});
+        // Very long markdown transcripts are split into part-01.md, part-02.md, ...
+        const parts = Object.keys(files)
+            .filter(name => /^part-\d+\.md$/.test(name))
+            .sort((a, b) => parseInt(a.slice(5)) - parseInt(b.slice(5)));
+        if (parts.length) {{
+            window.gistFilename = parts[0];
+            return Promise.all(parts.map(name => text(files[name].raw_url))).then(texts => texts.join("\n"));
+        }}
         const file = files["transcript.md"] || files["agentexport.json"] || Object.values(files)[0];
         if (!file) throw new Error("No files in gist");
         window.gistFilename = file.filename;
-        return fetch(file.raw_url);
-    }})
-    .then(r => {{ if (!r.ok) throw new Error("Failed to fetch content"); return r.text(); }});"#,
+        return text(file.raw_url);
+    }});"#,
         gist_id = gist_id
     )
 }
 
-fn viewer_html(blob_id: &str) -> String {
+fn viewer_html(blob_id: &str, payload: Option<&str>, static_render: bool) -> String {
     let og_url = format!("https://agentexports.com/v/{}", blob_id);
     let markup = html! {
         (DOCTYPE)
@@ -1051,10 +2118,36 @@ fn viewer_html(blob_id: &str) -> String {
             }
             body {
                 (PreEscaped(THEME_TOGGLE_BUTTON))
+                noscript {
+                    div class="error" {
+                        h2 { "JavaScript Required" }
+                        p { "Transcripts are decrypted in your browser, which needs JavaScript." }
+                        @if static_render && payload.is_none() {
+                            p {
+                                "To have this server render it instead, replace "
+                                code { "#" }
+                                " in the link with "
+                                code { "/static?key=" }
+                                ". That sends the decryption key to the server."
+                            }
+                        } @else if static_render {
+                            p { a href=(format!("/v/{blob_id}/static")) { "View without JavaScript" } }
+                        }
+                    }
+                }
                 div #loading class="loading" {
                     div class="spinner" {}
                     p { "Decrypting..." }
                 }
+                form #passphrase-form class="loading passphrase" style="display:none" {
+                    h2 { "Passphrase Required" }
+                    p { "This transcript is protected with a passphrase." }
+                    div class="passphrase-row" {
+                        input #passphrase-input type="password" autocomplete="off" aria-label="Passphrase";
+                        button type="submit" { "Unlock" }
+                    }
+                    p #passphrase-status class="passphrase-status" {}
+                }
                 div #error class="error" style="display:none" {
                     h2 { "Decryption Failed" }
                     p #error-message {}
@@ -1082,16 +2175,35 @@ fn viewer_html(blob_id: &str) -> String {
                                     input #show-details type="checkbox";
                                     " Show tool calls"
                                 }
+                                label #show-tasks-label style="display:none" {
+                                    input #show-tasks type="checkbox" checked;
+                                    " Show tasks"
+                                }
+                                label #show-abandoned-label style="display:none" {
+                                    input #show-abandoned type="checkbox";
+                                    " Show abandoned branches"
+                                }
                             }
                         }
+                        div #timeline class="timeline" style="display:none" {}
+                        div #tool-stats class="tool-stats" style="display:none" {}
                     }
-                    section #messages class="messages hide-details" {}
+                    nav #series class="series" style="display:none" {}
+                    div #session-status class="session-status" style="display:none" {}
+                    section #tasks class="tasks" style="display:none" {}
+                    section #messages class="messages hide-details hide-system hide-abandoned" {}
                     footer {
                         "via "
                         a href="https://agentexports.com" { "agentexports.com" }
+                        span #publisher {}
                     }
                 }
-                script { (PreEscaped(viewer_js(blob_id))) }
+                @if let Some(payload) = payload {
+                    script #payload type="application/json" { (PreEscaped(escape_script_json(payload))) }
+                    script { (PreEscaped(plaintext_viewer_js())) }
+                } @else {
+                    script { (PreEscaped(viewer_js(blob_id))) }
+                }
             }
         }
     };
@@ -1160,6 +2272,11 @@ body {
 }
 @keyframes spin { to { transform: rotate(360deg); } }
 .error { color: var(--error); }
+.passphrase h2 { margin-bottom: 0.5rem; }
+.passphrase-row { display: flex; gap: 8px; margin-top: 1rem; }
+.passphrase input { padding: 6px 10px; font-size: 14px; border: 1px solid var(--border); border-radius: 4px; background: transparent; color: inherit; }
+.passphrase button { padding: 6px 14px; font-size: 14px; border: 1px solid var(--border); border-radius: 4px; background: transparent; color: inherit; cursor: pointer; }
+.passphrase-status { min-height: 1.6em; margin-top: 0.5rem; font-size: 13px; color: var(--error); }
 .error h2 { margin-bottom: 0.5rem; }
 header { margin-bottom: 32px; }
 .title-row { display: flex; justify-content: space-between; align-items: baseline; margin-bottom: 8px; }
@@ -1168,6 +2285,14 @@ h1 { font-size: 18px; font-weight: 600; }
 .model { font-size: 13px; color: var(--text-secondary); font-family: ui-monospace, monospace; }
 .date { font-size: 13px; color: var(--text-secondary); }
 .meta-row { display: flex; justify-content: space-between; align-items: flex-start; margin-top: 8px; }
+.timeline { display: flex; align-items: flex-end; gap: 8px; margin-top: 8px; font-size: 12px; color: var(--text-secondary); }
+.timeline-bars { display: flex; align-items: flex-end; gap: 1px; height: 18px; flex: 1; }
+.timeline-bars span { flex: 1; max-width: 8px; min-height: 1px; background: var(--link); opacity: 0.6; border-radius: 1px; }
+.tool-stats { display: grid; grid-template-columns: max-content 1fr max-content; align-items: center; gap: 2px 8px; margin-top: 8px; font-size: 12px; color: var(--text-secondary); }
+.tool-stat { display: contents; }
+.tool-stat-name { font-family: ui-monospace, monospace; }
+.tool-stat-bar { height: 6px; background: var(--border); border-radius: 3px; overflow: hidden; }
+.tool-stat-bar span { display: block; height: 100%; background: var(--link); opacity: 0.6; }
 .token-col { display: flex; flex-direction: column; gap: 2px; }
 .toggles { font-size: 13px; color: var(--text-secondary); display: flex; flex-direction: column; gap: 4px; white-space: nowrap; flex-shrink: 0; }
 .toggles label { cursor: pointer; display: flex; align-items: center; gap: 4px; }
@@ -1176,6 +2301,12 @@ h1 { font-size: 18px; font-weight: 600; }
 .command { display: flex; align-items: center; gap: 8px; }
 .command-label { font-size: 11px; text-transform: uppercase; color: var(--text-muted); font-weight: 500; }
 .command-name { font-family: ui-monospace, monospace; font-size: 14px; color: var(--link); }
+.command-args { font-family: ui-monospace, monospace; font-size: 13px; color: var(--text-secondary); }
+.msg-role.command { color: var(--link); }
+.msg-role.plan { color: var(--link); }
+.msg.plan .msg-content { border: 1px solid var(--border); border-radius: 6px; padding: 12px 16px; }
+.msg.plan .msg-content ul:has(> li > input[type=checkbox]) { list-style: none; padding-left: 4px; }
+.msg.plan .msg-content input[type=checkbox] { margin-right: 8px; }
 .messages { margin-top: 24px; }
 .msg { padding: 16px 0; }
 .msg-header { display: flex; justify-content: space-between; align-items: baseline; margin-bottom: 6px; }
@@ -1201,9 +2332,50 @@ h1 { font-size: 18px; font-weight: 600; }
 .msg.thinking { opacity: 0.85; }
 .msg.thinking .msg-role { color: var(--thinking-role); }
 .msg.thinking .msg-content { font-size: 14px; color: var(--thinking-text); border-left: 3px solid var(--thinking-border); padding-left: 12px; background: var(--thinking-bg); margin-left: -12px; padding: 12px; border-radius: 0 6px 6px 0; }
-.hide-details .msg.tool, .hide-details .msg.system { display: none; }
+.series { font-size: 14px; color: var(--text-muted); margin-bottom: 16px; }
+.series a { color: var(--text-muted); }
+.session-status { font-size: 14px; color: var(--text-secondary); border-left: 3px solid #d97706; padding: 8px 12px; margin-bottom: 16px; }
+.session-status.errored { border-left-color: #dc2626; }
+.session-status ul { margin: 4px 0 0; padding-left: 20px; }
+.tasks { border: 1px solid var(--border); border-radius: 6px; padding: 12px 16px; margin-bottom: 16px; font-size: 14px; }
+.tasks-progress { height: 4px; background: var(--border); border-radius: 2px; margin: 6px 0 10px; overflow: hidden; }
+.tasks-progress div { height: 100%; background: var(--link); }
+.tasks ul { list-style: none; margin: 0; padding: 0; }
+.tasks li.completed { color: var(--text-muted); text-decoration: line-through; }
+.tasks li.in_progress { font-weight: 600; }
+.tasks details { margin-top: 8px; color: var(--text-secondary); }
+.tasks details ol { margin: 4px 0 0; padding-left: 20px; }
+.report-section { margin-bottom: 32px; }
+.report-section h2 { font-size: 16px; margin: 0 0 12px; }
+.report-section p { margin: 4px 0; color: var(--text-secondary); }
+.report-section table { border-collapse: collapse; width: 100%; font-size: 14px; }
+.report-section th, .report-section td { border: 1px solid var(--border); padding: 6px 10px; text-align: left; }
+.report-section th { background: var(--code-bg); font-weight: 600; }
+.report-session { padding: 10px 0; border-bottom: 1px solid var(--border); }
+.report-session.interrupted { border-left: 3px solid #d97706; padding-left: 10px; }
+.report-session.errored { border-left: 3px solid #dc2626; padding-left: 10px; }
+.report-session-title { font-weight: 600; color: var(--link); }
+span.report-session-title { color: inherit; }
+.report-session-meta { font-size: 13px; color: var(--text-secondary); margin-top: 2px; }
+.report-session-files { font-size: 12px; font-family: ui-monospace, monospace; color: var(--text-muted); margin-top: 2px; }
+.report-diff summary { font-size: 12px; font-family: ui-monospace, monospace; color: var(--text-secondary); cursor: pointer; margin-top: 4px; }
+.report-diff pre { font-size: 12px; background: var(--code-bg); padding: 8px; margin: 4px 0 0; overflow-x: auto; }
+.hide-details .msg.tool, .hide-system .msg.system { display: none; }
 .hide-thinking .msg.thinking { display: none; }
+.msg.abandoned { opacity: 0.5; border-left: 3px dashed var(--border); padding-left: 12px; }
+.msg-branch { font-size: 12px; color: var(--text-muted); margin-left: 8px; }
+.hide-abandoned .msg.abandoned { display: none; }
+.msg-anchor { margin-left: 8px; margin-right: auto; font-size: 12px; color: var(--text-muted); text-decoration: none; opacity: 0; }
+.msg:hover .msg-anchor, .msg.targeted .msg-anchor { opacity: 1; }
+.msg.highlighted { border-left: 3px solid #d4a72c; padding-left: 12px; }
+.msg.targeted { display: block !important; scroll-margin-top: 16px; }
+.part-tabs { display: flex; flex-wrap: wrap; gap: 6px; margin-top: 24px; }
+.part-tabs button { font-size: 13px; padding: 4px 10px; border: 1px solid var(--border); border-radius: 4px; background: transparent; color: var(--text-secondary); cursor: pointer; }
+.part-tabs button.active { color: var(--text); border-color: var(--link); }
+.part-heading { font-size: 14px; font-weight: 600; color: var(--text-secondary); border-bottom: 1px solid var(--border); padding: 24px 0 6px; }
+.messages .other-part { display: none; }
 .raw { margin-top: 8px; }
+.msg-image { display: block; max-width: 100%; max-height: 600px; margin-top: 8px; border: 1px solid var(--border); border-radius: 6px; }
 .raw summary { font-size: 12px; color: var(--text-secondary); cursor: pointer; }
 .raw pre { background: var(--code-bg); padding: 12px; border-radius: 6px; overflow-x: auto; font-size: 12px; margin-top: 8px; max-height: 300px; }
 footer { margin-top: 48px; font-size: 14px; color: var(--text-muted); text-align: center; }
@@ -1251,10 +2423,103 @@ function parseCommand(text) {
     return null;
 }
 
+// Split a rendered command invocation ("/name args") into its parts
+function splitCommand(text) {
+    const trimmed = text.trim();
+    const space = trimmed.indexOf(' ');
+    if (space === -1) return { name: trimmed, args: null };
+    return { name: trimmed.slice(0, space), args: trimmed.slice(space + 1) };
+}
+
+// Set the toggles from the publisher's defaults and keep the message classes in sync.
+// System messages follow the tool calls toggle unless the publisher pinned them.
+function applyViewerDefaults(prefs) {
+    const messages = document.getElementById('messages');
+    const details = document.getElementById('show-details');
+    const thinking = document.getElementById('show-thinking');
+    if (typeof prefs.tools === 'boolean') details.checked = prefs.tools;
+    if (typeof prefs.thinking === 'boolean') thinking.checked = prefs.thinking;
+    const sync = () => {
+        const showSystem = typeof prefs.system === 'boolean' ? prefs.system : details.checked;
+        messages.classList.toggle('hide-details', !details.checked);
+        messages.classList.toggle('hide-thinking', !thinking.checked);
+        messages.classList.toggle('hide-system', !showSystem);
+    };
+    details.addEventListener('change', sync);
+    thinking.addEventListener('change', sync);
+    sync();
+}
+
+// The fragment holds the key (or a passphrase salt) and, after '&', an optional
+// message anchor: #<key>&msg-12. Plaintext and gist shares use just #msg-12.
+function splitFragment() {
+    const parts = window.location.hash.slice(1).split('&');
+    const anchor = parts.find(p => /^msg-\d+$/.test(p)) || null;
+    return { secret: parts.filter(p => p !== anchor).join('&'), anchor };
+}
+
+// Link to message `id` that keeps the key in the fragment
+function messageHref(id) {
+    const secret = splitFragment().secret;
+    return '#' + (secret ? secret + '&' : '') + 'msg-' + id;
+}
+
+// Scroll to the message the fragment names, showing it even if its role is hidden
+function scrollToAnchor() {
+    for (const el of document.querySelectorAll('.msg.targeted')) el.classList.remove('targeted');
+    const anchor = splitFragment().anchor;
+    const target = anchor && document.getElementById(anchor);
+    if (!target) return;
+    target.classList.add('targeted');
+    target.scrollIntoView({ block: 'start' });
+}
+
 function render(data) {
     document.getElementById('tool-name').textContent = data.tool || 'Transcript';
     document.getElementById('shared-at').textContent = data.shared_at || '';
 
+    if (data.kind === 'report') {
+        renderReport(data);
+        return;
+    }
+
+    // Series navigation ("Part N")
+    if (data.series && data.series.previous_url) {
+        const seriesEl = document.getElementById('series');
+        seriesEl.textContent = 'Part ' + (data.series.part || 2) + ' · ';
+        const prev = document.createElement('a');
+        prev.href = data.series.previous_url;
+        prev.textContent = '← previous part';
+        seriesEl.appendChild(prev);
+        seriesEl.style.display = '';
+    }
+
+    // TodoWrite task list: latest state plus what each update changed
+    if (data.tasks && data.tasks.items && data.tasks.items.length) {
+        renderTasks(data.tasks);
+        document.getElementById('show-tasks-label').style.display = '';
+        document.getElementById('show-tasks').addEventListener('change', function() {
+            document.getElementById('tasks').style.display = this.checked ? '' : 'none';
+        });
+    }
+
+    // Flag sessions that were interrupted or ended in an error
+    if (data.status && data.status !== 'completed') {
+        const statusEl = document.getElementById('session-status');
+        statusEl.classList.add(data.status);
+        statusEl.textContent = 'Session ' + data.status;
+        if (data.errors && data.errors.length) {
+            const list = document.createElement('ul');
+            for (const error of data.errors) {
+                const item = document.createElement('li');
+                item.textContent = error;
+                list.appendChild(item);
+            }
+            statusEl.appendChild(list);
+        }
+        statusEl.style.display = '';
+    }
+
     // Model display
     const models = data.models || [];
     const modelEl = document.getElementById('model-info');
@@ -1267,10 +2532,29 @@ function render(data) {
     const showMultipleModels = models.length > 1;
     const container = document.getElementById('messages');
     container.innerHTML = '';
+    // Conversation language, for hyphenation and screen readers (the page chrome stays English)
+    if (data.lang) container.lang = data.lang;
+
+    // Bundle shares: a heading wherever the next session starts
+    const parts = data.parts || [];
+    let currentPart = null;
+    for (const [index, msg] of (data.messages || []).entries()) {
+        if (parts.length && msg.part != null && msg.part !== currentPart) {
+            currentPart = msg.part;
+            const heading = document.createElement('div');
+            heading.className = 'part-heading';
+            heading.dataset.part = msg.part;
+            heading.textContent = partLabel(parts[msg.part], msg.part);
+            container.appendChild(heading);
+        }
 
-    for (const msg of data.messages || []) {
         const div = document.createElement('div');
-        div.className = 'msg ' + (msg.role || 'event');
+        div.className = 'msg ' + (msg.role || 'event') + (msg.abandoned ? ' abandoned' : '')
+            + (msg.highlighted ? ' highlighted' : '');
+        if (msg.part != null) div.dataset.part = msg.part;
+        // Shares from before message ids were recorded are numbered by position
+        const anchorId = msg.id != null ? msg.id : index + 1;
+        div.id = 'msg-' + anchorId;
 
         const header = document.createElement('div');
         header.className = 'msg-header';
@@ -1280,6 +2564,12 @@ function render(data) {
         role.textContent = msg.role || 'event';
         header.appendChild(role);
 
+        const link = document.createElement('a');
+        link.className = 'msg-anchor';
+        link.href = messageHref(anchorId);
+        link.textContent = '#' + anchorId;
+        header.appendChild(link);
+
         if (showMultipleModels && msg.model) {
             const model = document.createElement('span');
             model.className = 'msg-model';
@@ -1287,14 +2577,22 @@ function render(data) {
             header.appendChild(model);
         }
 
+        if (msg.abandoned) {
+            const branch = document.createElement('span');
+            branch.className = 'msg-branch';
+            branch.textContent = 'abandoned branch';
+            header.appendChild(branch);
+        }
+
         div.appendChild(header);
 
         const content = document.createElement('div');
         content.className = 'msg-content';
         const msgContent = msg.content || '';
 
-        // Check if this is a command message
-        const cmd = msg.role === 'user' ? parseCommand(msgContent) : null;
+        // Check if this is a command message (legacy shares keep the raw tags on user messages)
+        const cmd = msg.role === 'command' ? splitCommand(msgContent)
+            : msg.role === 'user' ? parseCommand(msgContent) : null;
         if (cmd) {
             content.className = 'msg-content command';
             const label = document.createElement('span');
@@ -1305,6 +2603,12 @@ function render(data) {
             name.className = 'command-name';
             name.textContent = cmd.name;
             content.appendChild(name);
+            if (cmd.args) {
+                const args = document.createElement('span');
+                args.className = 'command-args';
+                args.textContent = cmd.args;
+                content.appendChild(args);
+            }
         } else if (msg.role === 'tool') {
             content.textContent = msgContent;
         } else {
@@ -1312,6 +2616,17 @@ function render(data) {
         }
         div.appendChild(content);
 
+        // Images shared with --include-images; only inline data of known image types
+        for (const src of msg.images || []) {
+            if (!/^data:image\/(png|jpeg|gif|webp);base64,[A-Za-z0-9+\/=]+$/.test(src)) continue;
+            const img = document.createElement('img');
+            img.className = 'msg-image';
+            img.src = src;
+            img.alt = 'Image';
+            img.loading = 'lazy';
+            div.appendChild(img);
+        }
+
         if (msg.raw) {
             const details = document.createElement('details');
             details.className = 'raw';
@@ -1326,14 +2641,17 @@ function render(data) {
 
         container.appendChild(div);
     }
+    if (parts.length > 1) renderPartTabs(parts);
 
-    document.getElementById('show-details').addEventListener('change', function() {
-        document.getElementById('messages').classList.toggle('hide-details', !this.checked);
-    });
+    applyViewerDefaults(data.viewer || {});
 
-    document.getElementById('show-thinking').addEventListener('change', function() {
-        document.getElementById('messages').classList.toggle('hide-thinking', !this.checked);
-    });
+    // Branches left behind by edited prompts or regenerated replies (published with --include-abandoned)
+    if ((data.messages || []).some(msg => msg.abandoned)) {
+        document.getElementById('show-abandoned-label').style.display = '';
+        document.getElementById('show-abandoned').addEventListener('change', function() {
+            container.classList.toggle('hide-abandoned', !this.checked);
+        });
+    }
 
     // Display token summary with cost
     const tokenEl = document.getElementById('token-summary');
@@ -1350,13 +2668,332 @@ function render(data) {
         tokenEl.textContent = row1.join(' · ');
 
         const row2 = [formatNum(output) + ' out'];
+        // Newer CLIs price each model themselves (with any configured overrides)
         const model = (data.models && data.models[0]) || '';
-        const cost = calculateCost(model, input, output, cacheRead, cacheCreate);
+        const cost = data.estimated_cost_usd != null
+            ? data.estimated_cost_usd
+            : calculateCost(model, input, output, cacheRead, cacheCreate);
         if (cost !== null) {
-            row2.push('$' + (cost < 0.01 ? cost.toFixed(4) : cost.toFixed(2)));
+            row2.push(formatCost(cost));
+        }
+        const summary2 = document.getElementById('token-summary-2');
+        summary2.textContent = row2.join(' · ');
+        const priced = (data.model_costs || []).filter(m => m.estimated_cost_usd != null);
+        if (priced.length > 1) {
+            summary2.title = priced.map(m => m.model + ': ' + formatCost(m.estimated_cost_usd)).join('\n');
+        }
+    }
+
+    renderTimeline(data.timeline);
+    renderToolStats(data.tool_stats);
+    if (data.metadata_only) renderStatsCard(data);
+
+    const publisher = formatPublisher(data.publisher);
+    if (publisher) {
+        document.getElementById('publisher').textContent = ' · shared by ' + publisher;
+    }
+
+    scrollToAnchor();
+    window.addEventListener('hashchange', scrollToAnchor);
+}
+
+function partLabel(part, index) {
+    return (index + 1) + '. ' + [part && part.tool, part && part.title].filter(Boolean).join(' · ');
+}
+
+// A tab per bundled session, plus one showing them all in order
+function renderPartTabs(parts) {
+    const container = document.getElementById('messages');
+    const tabs = document.createElement('div');
+    tabs.className = 'part-tabs';
+    const show = (index, button) => {
+        for (const tab of tabs.children) tab.classList.toggle('active', tab === button);
+        for (const el of container.querySelectorAll('[data-part]')) {
+            el.classList.toggle('other-part', index !== null && el.dataset.part !== String(index));
+        }
+    };
+    const addTab = (label, index) => {
+        const button = document.createElement('button');
+        button.type = 'button';
+        button.textContent = label;
+        button.addEventListener('click', () => show(index, button));
+        tabs.appendChild(button);
+        return button;
+    };
+    addTab('All', null).classList.add('active');
+    parts.forEach((part, i) => addTab(partLabel(part, i), i));
+    container.before(tabs);
+}
+
+function formatCost(cost) {
+    return '$' + (cost < 0.01 ? cost.toFixed(4) : cost.toFixed(2));
+}
+
+function formatCount(n) {
+    if (n >= 1000000) return (n / 1000000).toFixed(1) + 'M';
+    return n >= 1000 ? (n / 1000).toFixed(1) + 'K' : String(n);
+}
+
+// Session length plus a bar per time bucket, scaled to the busiest bucket
+function renderTimeline(timeline) {
+    if (!timeline || !timeline.buckets || !timeline.buckets.length) return;
+    const el = document.getElementById('timeline');
+    const label = document.createElement('span');
+    label.textContent = formatSeconds(Math.max(0, timeline.ended_at - timeline.started_at));
+    const bars = document.createElement('div');
+    bars.className = 'timeline-bars';
+    const peak = Math.max(1, ...timeline.buckets);
+    timeline.buckets.forEach((count, i) => {
+        const bar = document.createElement('span');
+        bar.style.height = Math.round(count / peak * 100) + '%';
+        const start = new Date((timeline.started_at + i * timeline.bucket_minutes * 60) * 1000);
+        bar.title = start.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' }) +
+            ' · ' + count + (count === 1 ? ' message' : ' messages');
+        bars.appendChild(bar);
+    });
+    el.append(label, bars);
+    el.style.display = '';
+}
+
+// Share of the tool calls each tool made, as bars; the long tail is one "other" row
+function renderToolStats(stats) {
+    if (!stats || !stats.length) return;
+    const el = document.getElementById('tool-stats');
+    const total = stats.reduce((sum, s) => sum + s.calls, 0) || 1;
+    const shown = stats.slice(0, 8);
+    const rest = stats.slice(8);
+    if (rest.length) {
+        shown.push({
+            name: rest.length + ' other',
+            calls: rest.reduce((sum, s) => sum + s.calls, 0),
+            duration_ms: rest.reduce((sum, s) => sum + (s.duration_ms || 0), 0),
+        });
+    }
+    for (const stat of shown) {
+        const row = document.createElement('div');
+        row.className = 'tool-stat';
+        const name = document.createElement('span');
+        name.className = 'tool-stat-name';
+        name.textContent = stat.name;
+        const bar = document.createElement('span');
+        bar.className = 'tool-stat-bar';
+        const fill = document.createElement('span');
+        fill.style.width = (stat.calls / total * 100) + '%';
+        bar.appendChild(fill);
+        const facts = [Math.round(stat.calls / total * 100) + '%',
+            stat.calls + (stat.calls === 1 ? ' call' : ' calls')];
+        if (stat.duration_ms) facts.push(formatSeconds(Math.round(stat.duration_ms / 1000)));
+        const count = document.createElement('span');
+        count.textContent = facts.join(' · ');
+        row.append(name, bar, count);
+        el.appendChild(row);
+    }
+    el.style.display = '';
+}
+
+function formatSeconds(secs) {
+    if (secs < 60) return secs + 's';
+    if (secs < 3600) return Math.floor(secs / 60) + 'm ' + (secs % 60) + 's';
+    return Math.floor(secs / 3600) + 'h ' + Math.floor((secs % 3600) / 60) + 'm';
+}
+
+// Metadata-only shares (publish --metadata-only) carry stats in place of the conversation
+function renderStatsCard(data) {
+    document.querySelector('.toggles').style.display = 'none';
+    document.getElementById('messages').classList.add('report');
+
+    const summary = reportSection('Session stats');
+    const counts = Object.entries(data.message_counts || {});
+    const facts = [counts.length
+        ? counts.map(([role, count]) => count + ' ' + role).join(' · ') + ' messages'
+        : 'No messages'];
+    if (data.timeline) {
+        facts.push('Ran for ' + formatSeconds(Math.max(0, data.timeline.ended_at - data.timeline.started_at)));
+    }
+    if (data.estimated_cost_usd != null) facts.push('Estimated cost ' + formatCost(data.estimated_cost_usd));
+    facts.push('The conversation itself was not shared.');
+    for (const fact of facts) {
+        const p = document.createElement('p');
+        p.textContent = fact;
+        summary.appendChild(p);
+    }
+
+    if (data.model_costs && data.model_costs.length) {
+        reportSection('Models').appendChild(reportTable(
+            ['Model', 'In', 'Out', 'Cache r', 'Cache w', 'Cost'],
+            data.model_costs.map(m => [m.model, formatCount(m.input_tokens || 0), formatCount(m.output_tokens || 0),
+                formatCount(m.cache_read_tokens || 0), formatCount(m.cache_creation_tokens || 0),
+                m.estimated_cost_usd == null ? '-' : formatCost(m.estimated_cost_usd)])
+        ));
+    }
+}
+
+function reportSection(title) {
+    const section = document.createElement('section');
+    section.className = 'report-section';
+    const heading = document.createElement('h2');
+    heading.textContent = title;
+    section.appendChild(heading);
+    document.getElementById('messages').appendChild(section);
+    return section;
+}
+
+function reportTable(headers, rows) {
+    const table = document.createElement('table');
+    const head = table.insertRow();
+    for (const h of headers) {
+        const th = document.createElement('th');
+        th.textContent = h;
+        head.appendChild(th);
+    }
+    for (const row of rows) {
+        const tr = table.insertRow();
+        for (const cell of row) {
+            const td = tr.insertCell();
+            if (cell instanceof Node) td.appendChild(cell);
+            else td.textContent = cell;
+        }
+    }
+    return table;
+}
+
+// Multi-session report: summary, per-model usage, most-edited files, sessions
+function renderReport(data) {
+    document.title = data.title || 'Agent report';
+    document.getElementById('model-info').textContent = data.title || '';
+    document.querySelector('.toggles').style.display = 'none';
+    const container = document.getElementById('messages');
+    container.innerHTML = '';
+    container.classList.add('report');
+
+    const totals = data.totals || {};
+    document.getElementById('token-summary').textContent =
+        formatCount(totals.input_tokens || 0) + ' in · ' + formatCount(totals.output_tokens || 0) + ' out';
+    document.getElementById('token-summary-2').textContent = formatCost(totals.cost || 0);
+
+    const summary = reportSection('Summary');
+    const facts = [
+        (totals.sessions || 0) + ' sessions in the last ' + data.since,
+        (totals.messages || 0) + ' messages · ' + (totals.tool_calls || 0) + ' tool calls',
+    ];
+    if (totals.interrupted || totals.errored) {
+        facts.push((totals.interrupted || 0) + ' interrupted · ' + (totals.errored || 0) + ' errored');
+    }
+    for (const fact of facts) {
+        const p = document.createElement('p');
+        p.textContent = fact;
+        summary.appendChild(p);
+    }
+
+    if (data.models && data.models.length) {
+        reportSection('Models').appendChild(reportTable(
+            ['Model', 'Sessions', 'In', 'Out', 'Cost'],
+            data.models.map(m => [m.model, String(m.sessions), formatCount(m.input_tokens),
+                formatCount(m.output_tokens), m.cost == null ? '-' : formatCost(m.cost)])
+        ));
+    }
+
+    if (data.files && data.files.length) {
+        reportSection('Most edited files').appendChild(reportTable(
+            ['File', 'Edits', 'Sessions'],
+            data.files.map(f => [f.path, String(f.edits), String(f.sessions)])
+        ));
+    }
+
+    const sessions = reportSection('Sessions');
+    for (const s of data.sessions || []) {
+        const div = document.createElement('div');
+        div.className = 'report-session ' + (s.status || 'completed');
+        const title = document.createElement(s.share_url ? 'a' : 'span');
+        title.className = 'report-session-title';
+        title.textContent = s.title || 'Untitled session';
+        if (s.share_url) title.href = s.share_url;
+        div.appendChild(title);
+
+        const meta = [s.tool];
+        if (s.model) meta.push(s.model);
+        if (s.started_at) meta.push(new Date(s.started_at).toLocaleString());
+        if (s.duration_secs != null) meta.push(formatSeconds(s.duration_secs));
+        meta.push(s.tool_calls + ' tool calls');
+        meta.push(formatCount(s.input_tokens + s.output_tokens) + ' tokens');
+        if (s.cost > 0) meta.push(formatCost(s.cost));
+        if (s.status && s.status !== 'completed') meta.push(s.status);
+        const metaEl = document.createElement('div');
+        metaEl.className = 'report-session-meta';
+        metaEl.textContent = meta.join(' · ');
+        div.appendChild(metaEl);
+
+        if (s.files && s.files.length) {
+            const files = document.createElement('div');
+            files.className = 'report-session-files';
+            files.textContent = s.files.slice(0, 5).join(', ') + (s.files.length > 5 ? ' +' + (s.files.length - 5) + ' more' : '');
+            div.appendChild(files);
+        }
+        for (const d of s.diffs || []) {
+            const details = document.createElement('details');
+            details.className = 'report-diff';
+            const summary = document.createElement('summary');
+            summary.textContent = d.path + ' +' + d.added + ' −' + d.removed;
+            const pre = document.createElement('pre');
+            pre.textContent = d.excerpt;
+            details.append(summary, pre);
+            div.appendChild(details);
+        }
+        sessions.appendChild(div);
+    }
+}
+
+const TASK_MARKS = { completed: '✓', in_progress: '→', pending: '○' };
+
+function renderTasks(tasks) {
+    const el = document.getElementById('tasks');
+    const done = tasks.items.filter(t => t.status === 'completed').length;
+    const heading = document.createElement('div');
+    heading.textContent = 'Tasks · ' + done + '/' + tasks.items.length + ' done';
+    el.appendChild(heading);
+
+    const bar = document.createElement('div');
+    bar.className = 'tasks-progress';
+    const fill = document.createElement('div');
+    fill.style.width = (100 * done / tasks.items.length) + '%';
+    bar.appendChild(fill);
+    el.appendChild(bar);
+
+    const list = document.createElement('ul');
+    for (const task of tasks.items) {
+        const item = document.createElement('li');
+        item.className = task.status;
+        item.textContent = (TASK_MARKS[task.status] || '○') + ' ' + task.content;
+        list.appendChild(item);
+    }
+    el.appendChild(list);
+
+    if (tasks.updates && tasks.updates.length > 1) {
+        const details = document.createElement('details');
+        const summary = document.createElement('summary');
+        summary.textContent = tasks.updates.length + ' updates';
+        details.appendChild(summary);
+        const history = document.createElement('ol');
+        for (const changes of tasks.updates) {
+            const entry = document.createElement('li');
+            entry.textContent = changes.length
+                ? changes.map(t => (TASK_MARKS[t.status] || '○') + ' ' + t.content).join(' · ')
+                : 'no changes';
+            history.appendChild(entry);
         }
-        document.getElementById('token-summary-2').textContent = row2.join(' · ');
+        details.appendChild(history);
+        el.appendChild(details);
     }
+    el.style.display = '';
+}
+
+// Format publisher info as "name (email) from host"
+function formatPublisher(p) {
+    if (!p) return null;
+    let who = p.name && p.email ? p.name + ' (' + p.email + ')' : (p.name || p.email || '');
+    if (!who && !p.host) return null;
+    if (!who) who = 'unknown';
+    return p.host ? who + ' from ' + p.host : who;
 }
 
 // Claude pricing (input/cache/output are SEPARATE categories)
@@ -1448,16 +3085,23 @@ fn viewer_js(blob_id: &str) -> String {
     format!(
         r#"
 const BLOB_ID = "{blob_id}";
+const PASSPHRASE_PREFIX = "{passphrase_prefix}";
+const PASSPHRASE_ITERATIONS = {passphrase_iterations};
+const CHUNK_COUNT_HEADER = "{chunk_count_header}";
 
 {common}
 
 async function main() {{
     try {{
-        const fragment = window.location.hash.slice(1);
+        const fragment = splitFragment().secret;
         if (!fragment) throw new Error("No decryption key in URL");
 
-        const keyBytes = base64UrlDecode(fragment);
-        if (keyBytes.length !== 32) throw new Error("Invalid key length");
+        // Passphrase shares carry a salt instead of the key
+        const salt = fragment.startsWith(PASSPHRASE_PREFIX)
+            ? base64UrlDecode(fragment.slice(PASSPHRASE_PREFIX.length))
+            : null;
+        const keyBytes = salt ? null : base64UrlDecode(fragment);
+        if (keyBytes && keyBytes.length !== 32) throw new Error("Invalid key length");
 
         const response = await fetch('/blob/' + BLOB_ID);
         if (response.status === 410) throw new Error("This transcript has expired");
@@ -1469,10 +3113,28 @@ async function main() {{
         const iv = encrypted.slice(0, 12);
         const ciphertext = encrypted.slice(12);
 
-        const key = await crypto.subtle.importKey("raw", keyBytes, {{ name: "AES-GCM" }}, false, ["decrypt"]);
-        const compressed = await crypto.subtle.decrypt({{ name: "AES-GCM", iv }}, key, ciphertext);
+        let compressed;
+        let key = null;
+        if (salt) {{
+            compressed = await decryptWithPassphrase(salt, iv, ciphertext);
+        }} else {{
+            key = await crypto.subtle.importKey("raw", keyBytes, {{ name: "AES-GCM" }}, false, ["decrypt"]);
+            compressed = await crypto.subtle.decrypt({{ name: "AES-GCM", iv }}, key, ciphertext);
+        }}
         const json = await decompress(new Uint8Array(compressed));
-        const data = JSON.parse(json);
+        let data = JSON.parse(json);
+
+        // Live shares get later messages as appended chunks, each encrypted
+        // like the blob. Passphrase shares are always replaced whole.
+        const chunkCount = key ? parseInt(response.headers.get(CHUNK_COUNT_HEADER) || '0', 10) : 0;
+        for (let i = 0; i < chunkCount; i++) {{
+            const chunkResponse = await fetch('/blob/' + BLOB_ID + '/chunks/' + i);
+            if (!chunkResponse.ok) break;
+            const bytes = await chunkResponse.arrayBuffer();
+            const plain = await crypto.subtle.decrypt({{ name: "AES-GCM", iv: bytes.slice(0, 12) }}, key, bytes.slice(12));
+            const chunk = JSON.parse(await decompress(new Uint8Array(plain)));
+            data = Object.assign({{}}, chunk, {{ messages: data.messages.concat(chunk.messages) }});
+        }}
 
         document.getElementById('loading').style.display = 'none';
         document.getElementById('app').style.display = 'block';
@@ -1484,6 +3146,36 @@ async function main() {{
     }}
 }}
 
+// Ask until a passphrase opens the blob; a wrong one fails the AES-GCM tag check
+async function decryptWithPassphrase(salt, iv, ciphertext) {{
+    const form = document.getElementById('passphrase-form');
+    const input = document.getElementById('passphrase-input');
+    const status = document.getElementById('passphrase-status');
+    document.getElementById('loading').style.display = 'none';
+    form.style.display = 'flex';
+    input.focus();
+    while (true) {{
+        const passphrase = await new Promise(resolve => {{
+            form.onsubmit = (e) => {{ e.preventDefault(); resolve(input.value); }};
+        }});
+        if (!passphrase) continue;
+        status.textContent = '';
+        const material = await crypto.subtle.importKey("raw", new TextEncoder().encode(passphrase), "PBKDF2", false, ["deriveKey"]);
+        const key = await crypto.subtle.deriveKey(
+            {{ name: "PBKDF2", hash: "SHA-256", salt, iterations: PASSPHRASE_ITERATIONS }},
+            material, {{ name: "AES-GCM", length: 256 }}, false, ["decrypt"]);
+        try {{
+            const compressed = await crypto.subtle.decrypt({{ name: "AES-GCM", iv }}, key, ciphertext);
+            form.style.display = 'none';
+            document.getElementById('loading').style.display = 'flex';
+            return compressed;
+        }} catch (_) {{
+            status.textContent = 'Wrong passphrase';
+            input.select();
+        }}
+    }}
+}}
+
 function base64UrlDecode(str) {{
     const pad = str.length % 4;
     if (pad) str += '='.repeat(4 - pad);
@@ -1515,6 +3207,33 @@ async function decompress(data) {{
 main();
 "#,
         blob_id = blob_id,
+        passphrase_prefix = PASSPHRASE_PREFIX,
+        passphrase_iterations = PASSPHRASE_ITERATIONS,
+        chunk_count_header = CHUNK_COUNT_HEADER,
+        common = VIEWER_JS_COMMON
+    )
+}
+
+fn plaintext_viewer_js() -> String {
+    format!(
+        r#"
+{common}
+
+function main() {{
+    try {{
+        const data = JSON.parse(document.getElementById('payload').textContent);
+        document.getElementById('loading').style.display = 'none';
+        document.getElementById('app').style.display = 'block';
+        render(data);
+    }} catch (err) {{
+        document.getElementById('loading').style.display = 'none';
+        document.getElementById('error').style.display = 'flex';
+        document.getElementById('error-message').textContent = err.message;
+    }}
+}}
+
+main();
+"#,
         common = VIEWER_JS_COMMON
     )
 }
@@ -1541,6 +3260,19 @@ function parseMarkdownTranscript(text) {{
         if (parts.length > 2) data.shared_at = parts[2];
     }}
 
+    // Extract series navigation line
+    const seriesMatch = text.match(/^\*Part (\d+) · \[previous part\]\(([^)]+)\)\*$/m);
+    if (seriesMatch) {{
+        data.series = {{ part: parseInt(seriesMatch[1]), previous_url: seriesMatch[2] }};
+    }}
+
+    // Extract session outcome line ("*Session errored* — error; error")
+    const statusMatch = text.match(/^\*Session (interrupted|errored)\*(?: — (.+))?$/m);
+    if (statusMatch) {{
+        data.status = statusMatch[1];
+        data.errors = statusMatch[2] ? statusMatch[2].split('; ') : [];
+    }}
+
     // Split by message headers (### Role)
     // Note: We use \z for end-of-string since $ matches end-of-line in multiline mode
     // But JS doesn't support \z, so we use a two-pass approach or negative lookahead
@@ -1557,6 +3289,8 @@ function parseMarkdownTranscript(text) {{
         else if (header.includes('Tool')) role = 'tool';
         else if (header.includes('Thinking')) role = 'thinking';
         else if (header.includes('System')) role = 'system';
+        else if (header.includes('Command')) role = 'command';
+        else if (header.includes('Plan')) role = 'plan';
 
         // Extract model if present
         const modelMatch = header.match(/\(([^)]+)\)/);
@@ -1571,6 +3305,9 @@ function parseMarkdownTranscript(text) {{
             raw = detailsMatch[2].trim();
             content = content.replace(detailsMatch[0], '').trim();
         }}
+        // Inline code: any fence length, minus the padding around backticks
+        if (role === 'command') content = content.replace(/^(`+)([\s\S]*)\1$/, (_, fence, code) =>
+            code.length > 1 && code.startsWith(' ') && code.endsWith(' ') && code.trim() ? code.slice(1, -1) : code);
 
         data.messages.push({{ role, content, model, raw, raw_label: rawLabel }});
     }}
@@ -1588,6 +3325,12 @@ function parseMarkdownTranscript(text) {{
         if (cacheCreateMatch) data.total_cache_creation_tokens = parseInt(cacheCreateMatch[1]);
     }}
 
+    // Extract publisher attribution from footer
+    const publisherMatch = text.match(/^\*Shared by (.+?)(?: \(([^)]+)\))?(?: from (.+))?\*$/m);
+    if (publisherMatch) {{
+        data.publisher = {{ name: publisherMatch[1], email: publisherMatch[2], host: publisherMatch[3] }};
+    }}
+
     return data;
 }}
 